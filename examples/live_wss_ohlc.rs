@@ -1,6 +1,6 @@
 use kraken_async_rs::test_support::set_up_logging;
 use kraken_async_rs::wss::{KrakenWSSClient, WS_KRAKEN, WS_KRAKEN_AUTH};
-use kraken_async_rs::wss::{Message, OhlcSubscription, WssMessage};
+use kraken_async_rs::wss::{Message, OhlcInterval, OhlcSubscription, WssMessage};
 use std::time::Duration;
 use tokio::time::timeout;
 use tokio_stream::StreamExt;
@@ -13,7 +13,7 @@ async fn main() {
     let mut client = KrakenWSSClient::new_with_tracing(WS_KRAKEN, WS_KRAKEN_AUTH, true, true);
     let mut kraken_stream = client.connect::<WssMessage>().await.unwrap();
 
-    let ohlc_params = OhlcSubscription::new(vec!["ETH/USD".into()], 60);
+    let ohlc_params = OhlcSubscription::new(vec!["ETH/USD".into()], OhlcInterval::Hour);
 
     let subscription = Message::new_subscription(ohlc_params, 0);
 
@@ -0,0 +1,205 @@
+//! Caching and refreshing of tradable asset pairs
+use crate::clients::errors::ClientError;
+use crate::clients::kraken_client::KrakenClient;
+use crate::request_types::TradableAssetPairsRequest;
+use crate::response_types::TradableAssetPair;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// The default time tradable asset pairs are cached before being requested again.
+pub const DEFAULT_ASSET_PAIRS_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// Holds the tradable asset pairs from a [KrakenClient], requesting them again once they're older
+/// than the ttl.
+///
+/// Pairs are looked up by Kraken's name (e.g. `XXBTZUSD`), alt name (`XBTUSD`) or websocket name
+/// (`XBT/USD`). All pairs are requested at once and expire together, so looking up an unknown pair
+/// does not cause another request until the cache is stale. Requests go through the given client,
+/// so a [RateLimitedKrakenClient](crate::clients::rate_limited_kraken_client::RateLimitedKrakenClient)
+/// applies its public rate limit to each refresh.
+#[derive(Debug)]
+pub struct AssetPairsCache<C>
+where
+    C: KrakenClient,
+{
+    client: C,
+    request: TradableAssetPairsRequest,
+    ttl: Duration,
+    fetched_at: Option<Instant>,
+    pairs: HashMap<String, TradableAssetPair>,
+    names: HashMap<String, String>,
+}
+
+impl<C> AssetPairsCache<C>
+where
+    C: KrakenClient,
+{
+    /// Create a cache of all tradable asset pairs, refreshed after [DEFAULT_ASSET_PAIRS_TTL].
+    pub fn new(client: C) -> Self {
+        Self::new_with_ttl(client, DEFAULT_ASSET_PAIRS_TTL)
+    }
+
+    /// Create a cache of all tradable asset pairs, refreshed after `ttl`.
+    pub fn new_with_ttl(client: C, ttl: Duration) -> Self {
+        Self::new_with_request(client, TradableAssetPairsRequest::builder().build(), ttl)
+    }
+
+    /// Create a cache of the pairs returned for `request`, refreshed after `ttl`.
+    pub fn new_with_request(client: C, request: TradableAssetPairsRequest, ttl: Duration) -> Self {
+        AssetPairsCache {
+            client,
+            request,
+            ttl,
+            fetched_at: None,
+            pairs: HashMap::new(),
+            names: HashMap::new(),
+        }
+    }
+
+    /// Get a pair by any of its names, requesting all pairs first if they were never requested or
+    /// are stale.
+    ///
+    /// Returns None if the pair is not known to Kraken.
+    pub async fn pair(&mut self, name: &str) -> Result<Option<TradableAssetPair>, ClientError> {
+        if self.is_stale() {
+            self.refresh().await?;
+        }
+
+        let Some(key) = self.names.get(name) else {
+            return Ok(None);
+        };
+
+        Ok(self.pairs.get(key).cloned())
+    }
+
+    /// Request all pairs regardless of the cache's age, replacing any cached pairs.
+    pub async fn refresh(&mut self) -> Result<(), ClientError> {
+        let pairs = self
+            .client
+            .get_tradable_asset_pairs(&self.request)
+            .await?
+            .result
+            .ok_or(ClientError::Parse(
+                "Missing result for tradable asset pairs",
+            ))?;
+
+        self.pairs.clear();
+        self.names.clear();
+
+        for (key, pair) in pairs {
+            self.names.insert(pair.alt_name.clone(), key.clone());
+            self.names.insert(pair.ws_name.clone(), key.clone());
+            self.names.insert(key.clone(), key.clone());
+            self.pairs.insert(key, pair);
+        }
+
+        self.fetched_at = Some(Instant::now());
+
+        Ok(())
+    }
+
+    fn is_stale(&self) -> bool {
+        self.fetched_at
+            .is_none_or(|fetched_at| fetched_at.elapsed() >= self.ttl)
+    }
+
+    /// Mutable access to the underlying client, for making other requests.
+    pub fn client_mut(&mut self) -> &mut C {
+        &mut self.client
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clients::core_kraken_client::CoreKrakenClient;
+    use crate::crypto::nonce_provider::{IncreasingNonceProvider, NonceProvider};
+    use crate::test_data::get_null_secrets_provider;
+    use crate::test_data::public_response_json::get_tradable_asset_pairs_json;
+    use std::sync::Arc;
+    use tokio::sync::Mutex;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn get_client(mock_server: &MockServer) -> CoreKrakenClient {
+        let nonce_provider: Box<Arc<Mutex<dyn NonceProvider>>> =
+            Box::new(Arc::new(Mutex::new(IncreasingNonceProvider::new())));
+        CoreKrakenClient::new_with_url(
+            get_null_secrets_provider(),
+            nonce_provider,
+            mock_server.uri(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_pairs_are_cached_until_stale() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/0/public/AssetPairs"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(get_tradable_asset_pairs_json()))
+            .expect(2)
+            .mount(&mock_server)
+            .await;
+
+        let mut cache =
+            AssetPairsCache::new_with_ttl(get_client(&mock_server), Duration::from_millis(200));
+
+        let by_key = cache.pair("XXBTZUSD").await.unwrap().unwrap();
+        let by_alt_name = cache.pair("XBTUSD").await.unwrap().unwrap();
+        let by_ws_name = cache.pair("XBT/USD").await.unwrap().unwrap();
+        assert_eq!(by_key, by_alt_name);
+        assert_eq!(by_key, by_ws_name);
+        assert!(cache.pair("UNKNOWN").await.unwrap().is_none());
+
+        tokio::time::sleep(Duration::from_millis(250)).await;
+        assert!(cache.pair("XBTUSD").await.unwrap().is_some());
+
+        mock_server.verify().await;
+    }
+
+    #[tokio::test]
+    async fn test_empty_pairs_are_cached_until_stale() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/0/public/AssetPairs"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"error":[],"result":{}}"#))
+            .expect(2)
+            .mount(&mock_server)
+            .await;
+
+        let mut cache =
+            AssetPairsCache::new_with_ttl(get_client(&mock_server), Duration::from_millis(200));
+
+        assert!(cache.pair("XBTUSD").await.unwrap().is_none());
+        assert!(cache.pair("XBTUSD").await.unwrap().is_none());
+        assert!(cache.pair("ETHUSD").await.unwrap().is_none());
+
+        tokio::time::sleep(Duration::from_millis(250)).await;
+        assert!(cache.pair("XBTUSD").await.unwrap().is_none());
+
+        mock_server.verify().await;
+    }
+
+    #[tokio::test]
+    async fn test_pairs_error() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/0/public/AssetPairs"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_string(r#"{"error":["EGeneral:Internal error"]}"#),
+            )
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let mut cache = AssetPairsCache::new(get_client(&mock_server));
+
+        let resp = cache.pair("XBTUSD").await;
+        assert!(matches!(resp, Err(ClientError::Kraken(_))));
+        mock_server.verify().await;
+    }
+}
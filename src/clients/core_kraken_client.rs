@@ -1,20 +1,21 @@
 //! A base implementation of [KrakenClient]
 use crate::clients::errors::ClientError;
-use crate::clients::errors::KrakenError;
 use crate::clients::http_response_types::ResultErrorResponse;
 use crate::clients::kraken_client::endpoints::*;
 use crate::clients::kraken_client::KrakenClient;
-use crate::crypto::nonce_provider::NonceProvider;
+use crate::crypto::nonce_provider::{IncreasingNonceProvider, NonceProvider};
 use crate::crypto::nonce_request::NonceRequest;
 use crate::crypto::signatures::{generate_signature, Signature};
+use crate::endpoints::Endpoints;
+use crate::metrics::{Metrics, NoopMetrics};
 use crate::request_types::*;
 use crate::response_types::*;
 use crate::secrets::secrets_provider::SecretsProvider;
-#[allow(unused)]
 use crate::secrets::secrets_provider::StaticSecretsProvider;
 use http_body_util::BodyExt;
+use hyper::body::Bytes;
 use hyper::http::request::Builder;
-use hyper::{Method, Request, Uri};
+use hyper::{Method, Request, StatusCode, Uri};
 use hyper_tls::HttpsConnector;
 use hyper_util::client::legacy::connect::HttpConnector;
 use hyper_util::client::legacy::Client;
@@ -22,13 +23,45 @@ use hyper_util::rt::TokioExecutor;
 use secrecy::ExposeSecret;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::fmt::{Debug, Formatter};
 use std::str::FromStr;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use to_query_params::{QueryParams, ToQueryParams};
 use tokio::sync::Mutex;
 use tracing::trace;
 use url::{form_urlencoded, Url};
 
+/// The HTTP client used by [CoreKrakenClient]. Clones share one connection pool, see
+/// [CoreKrakenClient::with_http_client].
+pub type HttpClient = Client<HttpsConnector<HttpConnector>, String>;
+
+/// Produces the body of every response for a [CoreKrakenClient] in offline mode, see
+/// [CoreKrakenClient::new_offline].
+///
+/// It's called with the path of each request (e.g. `/0/private/AddOrder`) and the request body,
+/// which is empty for public requests, and is answered with an HTTP 200.
+#[derive(Clone)]
+pub struct OfflineResponder(Arc<RespondFn>);
+
+type RespondFn = dyn Fn(&str, &str) -> String + Send + Sync;
+
+impl OfflineResponder {
+    pub fn new(respond: impl Fn(&str, &str) -> String + Send + Sync + 'static) -> Self {
+        OfflineResponder(Arc::new(respond))
+    }
+
+    fn respond(&self, endpoint: &str, body: &str) -> String {
+        (self.0)(endpoint, body)
+    }
+}
+
+impl Debug for OfflineResponder {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("OfflineResponder")
+    }
+}
+
 #[derive(QueryParams, Default)]
 struct EmptyRequest {}
 
@@ -89,9 +122,12 @@ pub struct CoreKrakenClient {
     pub api_url: String,
     secrets_provider: Box<Arc<Mutex<dyn SecretsProvider>>>,
     nonce_provider: Box<Arc<Mutex<dyn NonceProvider>>>,
-    http_client: Client<HttpsConnector<HttpConnector>, String>,
+    http_client: HttpClient,
     user_agent: Option<String>,
     trace_inbound: bool,
+    timeout: Option<Duration>,
+    metrics: Arc<dyn Metrics>,
+    offline: Option<OfflineResponder>,
 }
 
 impl KrakenClient for CoreKrakenClient {
@@ -99,9 +135,7 @@ impl KrakenClient for CoreKrakenClient {
         secrets_provider: Box<Arc<Mutex<dyn SecretsProvider>>>,
         nonce_provider: Box<Arc<Mutex<dyn NonceProvider>>>,
     ) -> Self {
-        let https = HttpsConnector::new();
-        let http_client: Client<HttpsConnector<HttpConnector>, String> =
-            Client::builder(TokioExecutor::new()).build(https);
+        let http_client = CoreKrakenClient::new_http_client();
         CoreKrakenClient {
             api_url: KRAKEN_BASE_URL.into(),
             secrets_provider,
@@ -109,6 +143,9 @@ impl KrakenClient for CoreKrakenClient {
             http_client,
             user_agent: None,
             trace_inbound: false,
+            timeout: None,
+            metrics: Arc::new(NoopMetrics),
+            offline: None,
         }
     }
 
@@ -117,8 +154,7 @@ impl KrakenClient for CoreKrakenClient {
         nonce_provider: Box<Arc<Mutex<dyn NonceProvider>>>,
         url: impl ToString,
     ) -> Self {
-        let https = HttpsConnector::new();
-        let http_client = Client::builder(TokioExecutor::new()).build(https);
+        let http_client = CoreKrakenClient::new_http_client();
         CoreKrakenClient {
             api_url: url.to_string(),
             secrets_provider,
@@ -126,6 +162,9 @@ impl KrakenClient for CoreKrakenClient {
             http_client,
             user_agent: None,
             trace_inbound: false,
+            timeout: None,
+            metrics: Arc::new(NoopMetrics),
+            offline: None,
         }
     }
 
@@ -134,8 +173,7 @@ impl KrakenClient for CoreKrakenClient {
         nonce_provider: Box<Arc<Mutex<dyn NonceProvider>>>,
         trace_inbound: bool,
     ) -> Self {
-        let https = HttpsConnector::new();
-        let http_client = Client::builder(TokioExecutor::new()).build(https);
+        let http_client = CoreKrakenClient::new_http_client();
         CoreKrakenClient {
             api_url: KRAKEN_BASE_URL.to_string(),
             secrets_provider,
@@ -143,6 +181,9 @@ impl KrakenClient for CoreKrakenClient {
             http_client,
             user_agent: None,
             trace_inbound,
+            timeout: None,
+            metrics: Arc::new(NoopMetrics),
+            offline: None,
         }
     }
 
@@ -154,7 +195,7 @@ impl KrakenClient for CoreKrakenClient {
     async fn get_server_time(&mut self) -> Result<ResultErrorResponse<SystemTime>, ClientError> {
         let url = Url::from_str(&self.api_url(TIME_ENDPOINT))?;
         let body = self.body_from_url(Method::GET, &url, "".into()).await?;
-        Ok(serde_json::from_str(&body)?)
+        self.report_deserialize_error(serde_json::from_str(&body).map_err(ClientError::from))
     }
 
     #[tracing::instrument(ret, err(Debug), skip(self))]
@@ -163,7 +204,7 @@ impl KrakenClient for CoreKrakenClient {
     ) -> Result<ResultErrorResponse<SystemStatusInfo>, ClientError> {
         let url = Url::from_str(&self.api_url(STATUS_ENDPOINT))?;
         let body = self.body_from_url(Method::GET, &url, "".into()).await?;
-        Ok(serde_json::from_str(&body)?)
+        self.report_deserialize_error(serde_json::from_str(&body).map_err(ClientError::from))
     }
 
     #[tracing::instrument(err(Debug), skip(self))]
@@ -191,7 +232,7 @@ impl KrakenClient for CoreKrakenClient {
         self.public_get(TICKER_INFO_ENDPOINT, request).await
     }
 
-    #[tracing::instrument(err(Debug), skip(self))]
+    #[tracing::instrument(err(Debug), skip(self), fields(symbol = %request.pair))]
     async fn get_ohlc(
         &mut self,
         request: &OHLCRequest,
@@ -199,7 +240,7 @@ impl KrakenClient for CoreKrakenClient {
         self.public_get(OHLC_ENDPOINT, request).await
     }
 
-    #[tracing::instrument(err(Debug), skip(self))]
+    #[tracing::instrument(err(Debug), skip(self), fields(symbol = %request.pair))]
     async fn get_orderbook(
         &mut self,
         request: &OrderbookRequest,
@@ -207,7 +248,7 @@ impl KrakenClient for CoreKrakenClient {
         self.public_get(ORDER_BOOK_ENDPOINT, request).await
     }
 
-    #[tracing::instrument(err(Debug), skip(self))]
+    #[tracing::instrument(err(Debug), skip(self), fields(symbol = %request.pair))]
     async fn get_recent_trades(
         &mut self,
         request: &RecentTradesRequest,
@@ -215,7 +256,7 @@ impl KrakenClient for CoreKrakenClient {
         self.public_get(RECENT_TRADES_ENDPOINT, request).await
     }
 
-    #[tracing::instrument(err(Debug), skip(self))]
+    #[tracing::instrument(err(Debug), skip(self), fields(symbol = %request.pair))]
     async fn get_recent_spreads(
         &mut self,
         request: &RecentSpreadsRequest,
@@ -367,7 +408,7 @@ impl KrakenClient for CoreKrakenClient {
             .await
     }
 
-    #[tracing::instrument(ret, err(Debug), skip(self))]
+    #[tracing::instrument(ret, err(Debug), skip(self), fields(symbol = %request.pair, user_ref = ?request.user_ref, client_order_id = ?request.client_order_id))]
     async fn add_order(
         &mut self,
         request: &AddOrderRequest,
@@ -375,7 +416,7 @@ impl KrakenClient for CoreKrakenClient {
         self.private_form_post(ADD_ORDER_ENDPOINT, request).await
     }
 
-    #[tracing::instrument(ret, err(Debug), skip(self))]
+    #[tracing::instrument(ret, err(Debug), skip(self), fields(symbol = %request.pair))]
     async fn add_order_batch(
         &mut self,
         request: &AddBatchedOrderRequest,
@@ -384,7 +425,7 @@ impl KrakenClient for CoreKrakenClient {
             .await
     }
 
-    #[tracing::instrument(ret, err(Debug), skip(self))]
+    #[tracing::instrument(ret, err(Debug), skip(self), fields(order_id = ?request.tx_id, client_order_id = ?request.client_order_id))]
     async fn amend_order(
         &mut self,
         request: &AmendOrderRequest,
@@ -392,7 +433,7 @@ impl KrakenClient for CoreKrakenClient {
         self.private_json_post(AMEND_ORDER_ENDPOINT, request).await
     }
 
-    #[tracing::instrument(ret, err(Debug), skip(self))]
+    #[tracing::instrument(ret, err(Debug), skip(self), fields(symbol = %request.pair, order_id = %request.tx_id, user_ref = ?request.user_ref))]
     async fn edit_order(
         &mut self,
         request: &EditOrderRequest,
@@ -400,7 +441,7 @@ impl KrakenClient for CoreKrakenClient {
         self.private_form_post(EDIT_ORDER_ENDPOINT, request).await
     }
 
-    #[tracing::instrument(ret, err(Debug), skip(self))]
+    #[tracing::instrument(ret, err(Debug), skip(self), fields(order_id = ?request.tx_id, client_order_id = ?request.client_order_id))]
     async fn cancel_order(
         &mut self,
         request: &CancelOrderRequest,
@@ -606,11 +647,122 @@ impl KrakenClient for CoreKrakenClient {
             .body_from_url_and_form_with_auth(Method::POST, &url, signature)
             .await?;
 
-        Ok(serde_json::from_str(&response_body)?)
+        self.report_deserialize_error(Self::parse_body_and_errors(&response_body))
     }
 }
 
 impl CoreKrakenClient {
+    /// Create a client with the given User-Agent, instead of the default `KrakenAsyncRsClient`.
+    pub fn new_with_user_agent(
+        secrets_provider: Box<Arc<Mutex<dyn SecretsProvider>>>,
+        nonce_provider: Box<Arc<Mutex<dyn NonceProvider>>>,
+        user_agent: impl ToString,
+    ) -> Self {
+        CoreKrakenClient::new(secrets_provider, nonce_provider).with_user_agent(user_agent)
+    }
+
+    /// Set the User-Agent while building a client, e.g. after `new_with_url`.
+    ///
+    /// Use `set_user_agent` to change it once the client is in use.
+    pub fn with_user_agent(mut self, user_agent: impl ToString) -> Self {
+        self.user_agent = Some(user_agent.to_string());
+        self
+    }
+
+    /// Create a client that sends requests through an existing [HttpClient], e.g. one taken from
+    /// another client with [CoreKrakenClient::http_client].
+    pub fn new_with_http_client(
+        secrets_provider: Box<Arc<Mutex<dyn SecretsProvider>>>,
+        nonce_provider: Box<Arc<Mutex<dyn NonceProvider>>>,
+        http_client: HttpClient,
+    ) -> Self {
+        CoreKrakenClient::builder(secrets_provider)
+            .nonce_provider(nonce_provider)
+            .http_client(http_client)
+            .build()
+    }
+
+    /// Send requests through the given [HttpClient] while building a client.
+    ///
+    /// Clones of an [HttpClient] share its pool of connections, so clients built from the same one
+    /// (e.g. one per strategy, each with their own secrets) reuse connections and TLS sessions to
+    /// Kraken rather than opening their own.
+    pub fn with_http_client(mut self, http_client: HttpClient) -> Self {
+        self.http_client = http_client;
+        self
+    }
+
+    /// Fail any request not completed within `timeout` with [ClientError::Timeout], including
+    /// reading the response body. Requests have no timeout by default.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Report requests, responses and deserialization errors to `metrics`, see [Metrics].
+    pub fn with_metrics(mut self, metrics: Arc<dyn Metrics>) -> Self {
+        self.metrics = metrics;
+        self
+    }
+
+    /// Create a client that never sends requests, instead answering each one with the body
+    /// returned by `respond`, which is given the request's path and body.
+    ///
+    /// Requests are still built, encoded and signed (with an empty key and secret), and responses
+    /// are parsed as usual, so this can stand in for a mock server when testing code built on a
+    /// client:
+    ///
+    /// ```
+    /// # use kraken_async_rs::clients::core_kraken_client::CoreKrakenClient;
+    /// # use kraken_async_rs::clients::kraken_client::KrakenClient;
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let mut client = CoreKrakenClient::new_offline(|endpoint, _body| match endpoint {
+    ///     "/0/public/Time" => {
+    ///         r#"{"error":[],"result":{"unixtime":1712245767,"rfc1123":"Thu,  4 Apr 24 15:49:27 +0000"}}"#
+    ///             .to_string()
+    ///     }
+    ///     _ => r#"{"error":["EGeneral:Unknown Method"]}"#.to_string(),
+    /// });
+    ///
+    /// let time = client.get_server_time().await.unwrap().result.unwrap();
+    /// assert_eq!(1712245767, time.unix_time);
+    /// # }
+    /// ```
+    pub fn new_offline(respond: impl Fn(&str, &str) -> String + Send + Sync + 'static) -> Self {
+        let secrets_provider: Box<Arc<Mutex<dyn SecretsProvider>>> =
+            Box::new(Arc::new(Mutex::new(StaticSecretsProvider::new("", ""))));
+        let nonce_provider: Box<Arc<Mutex<dyn NonceProvider>>> =
+            Box::new(Arc::new(Mutex::new(IncreasingNonceProvider::new())));
+
+        CoreKrakenClient::new(secrets_provider, nonce_provider)
+            .with_offline_responder(OfflineResponder::new(respond))
+    }
+
+    /// Answer every request with `responder` instead of sending it, see
+    /// [CoreKrakenClient::new_offline].
+    pub fn with_offline_responder(mut self, responder: OfflineResponder) -> Self {
+        self.offline = Some(responder);
+        self
+    }
+
+    /// Build a client step by step, requiring only a [SecretsProvider], see
+    /// [CoreKrakenClientBuilder].
+    pub fn builder(
+        secrets_provider: Box<Arc<Mutex<dyn SecretsProvider>>>,
+    ) -> CoreKrakenClientBuilder {
+        CoreKrakenClientBuilder::new(secrets_provider)
+    }
+
+    /// A clone of this client's [HttpClient], sharing its connection pool.
+    pub fn http_client(&self) -> HttpClient {
+        self.http_client.clone()
+    }
+
+    fn new_http_client() -> HttpClient {
+        Client::builder(TokioExecutor::new()).build(HttpsConnector::new())
+    }
+
     fn api_url(&self, endpoint: &str) -> String {
         format!("{}{}", self.api_url, endpoint)
     }
@@ -645,7 +797,7 @@ impl CoreKrakenClient {
         Self::add_query_params(&mut url, request);
 
         let response_body = self.body_from_url(Method::GET, &url, "".into()).await?;
-        Self::parse_body_and_errors(&response_body)
+        self.report_deserialize_error(Self::parse_body_and_errors(&response_body))
     }
 
     async fn private_form_post<T, R>(
@@ -664,7 +816,7 @@ impl CoreKrakenClient {
             .body_from_url_and_form_with_auth(Method::POST, &url, signature)
             .await?;
 
-        Self::parse_body_and_errors(&response_body)
+        self.report_deserialize_error(Self::parse_body_and_errors(&response_body))
     }
 
     async fn private_json_post<T, R>(
@@ -683,7 +835,7 @@ impl CoreKrakenClient {
             .body_from_url_and_json_with_auth(Method::POST, &url, signature)
             .await?;
 
-        Self::parse_body_and_errors(&response_body)
+        self.report_deserialize_error(Self::parse_body_and_errors(&response_body))
     }
 
     async fn private_post_binary<R>(
@@ -701,19 +853,27 @@ impl CoreKrakenClient {
             .await
     }
 
+    fn report_deserialize_error<T>(
+        &self,
+        result: Result<T, ClientError>,
+    ) -> Result<T, ClientError> {
+        if let Err(ClientError::Serde(err)) = &result {
+            self.metrics.deserialize_error(err);
+        }
+
+        result
+    }
+
     fn parse_body_and_errors<T>(body: &str) -> Result<ResultErrorResponse<T>, ClientError>
     where
         T: for<'a> Deserialize<'a>,
     {
         let result: ResultErrorResponse<T> = serde_json::from_str(body)?;
 
-        if let Some(error) = result.error.first() {
-            error
-                .try_into()
-                .map(|err: KrakenError| Err(ClientError::Kraken(err)))
-                .unwrap_or(Ok(result))
-        } else {
-            Ok(result)
+        // warnings are prefixed with 'W' and don't indicate a failed request
+        match result.error.iter().find(|error| !error.starts_with('W')) {
+            Some(error) => Err(ClientError::Kraken(error.into())),
+            None => Ok(result),
         }
     }
 
@@ -770,10 +930,7 @@ impl CoreKrakenClient {
         signature: Signature,
     ) -> Result<Vec<u8>, ClientError> {
         let request = self.build_form_request(method, url, signature).await?;
-        let resp = self.http_client.request(request).await?;
-
-        let status = resp.status();
-        let bytes = resp.into_body().collect().await?.to_bytes();
+        let (status, bytes) = self.send_request(request).await?;
 
         if !status.is_success() {
             Err(ClientError::HttpStatus(format!(
@@ -786,10 +943,7 @@ impl CoreKrakenClient {
     }
 
     async fn body_from_request(&self, req: Request<String>) -> Result<String, ClientError> {
-        let resp = self.http_client.request(req).await?;
-
-        let status = resp.status();
-        let bytes = resp.into_body().collect().await?.to_bytes();
+        let (status, bytes) = self.send_request(req).await?;
         let text = String::from_utf8(bytes.to_vec()).or(Err(ClientError::Parse(
             "Failed to parse bytes from response body.",
         )))?;
@@ -798,13 +952,46 @@ impl CoreKrakenClient {
             Err(ClientError::HttpStatus(text))
         } else {
             if self.trace_inbound {
-                trace!("Received: {}", text);
+                trace!(body = %text, "Received response");
             }
 
             Ok(text)
         }
     }
 
+    /// Send the request and read the full body, within the client's timeout if it has one.
+    async fn send_request(&self, req: Request<String>) -> Result<(StatusCode, Bytes), ClientError> {
+        let endpoint = req.uri().path().to_string();
+        self.metrics.request_sent(&endpoint);
+        let start = Instant::now();
+
+        let response = async {
+            if let Some(offline) = &self.offline {
+                let body = offline.respond(&endpoint, req.body());
+                return Ok((StatusCode::OK, Bytes::from(body)));
+            }
+
+            let resp = self.http_client.request(req).await?;
+            let status = resp.status();
+            let bytes = resp.into_body().collect().await?.to_bytes();
+            Ok((status, bytes))
+        };
+
+        let result = match self.timeout {
+            Some(timeout) => tokio::time::timeout(timeout, response)
+                .await
+                .map_err(|_| ClientError::Timeout(timeout))
+                .and_then(|result| result),
+            None => response.await,
+        };
+
+        let status = result.as_ref().ok().map(|(status, _)| status.as_u16());
+        self.metrics
+            .response_received(&endpoint, status, start.elapsed());
+
+        result
+    }
+
     async fn build_form_request(
         &mut self,
         method: Method,
@@ -861,6 +1048,8 @@ impl CoreKrakenClient {
         ))
     }
 
+    // Kraken signs the exact body, so both encodings must be deterministic: the signature is
+    //  generated over the returned string, which is then sent as-is in `Signature::body_data`
     fn encode_json_request<R>(&self, nonce: u64, request: &R) -> Result<String, ClientError>
     where
         R: Serialize,
@@ -896,6 +1085,134 @@ macro_rules! test_parse_error_matches_pattern {
     };
 }
 
+/// Builder for a [CoreKrakenClient], created with [CoreKrakenClient::builder].
+///
+/// Only the [SecretsProvider] is required. The nonce provider defaults to a new
+/// [IncreasingNonceProvider] and the url to [KRAKEN_BASE_URL], while every other option defaults to
+/// what [CoreKrakenClient::new] uses.
+///
+/// ```
+/// # use kraken_async_rs::clients::core_kraken_client::CoreKrakenClient;
+/// # use kraken_async_rs::secrets::secrets_provider::{SecretsProvider, StaticSecretsProvider};
+/// # use std::sync::Arc;
+/// # use std::time::Duration;
+/// # use tokio::sync::Mutex;
+/// let secrets_provider: Box<Arc<Mutex<dyn SecretsProvider>>> =
+///     Box::new(Arc::new(Mutex::new(StaticSecretsProvider::new("", ""))));
+///
+/// let client = CoreKrakenClient::builder(secrets_provider)
+///     .user_agent("Strategy#1")
+///     .timeout(Duration::from_secs(10))
+///     .build();
+/// ```
+pub struct CoreKrakenClientBuilder {
+    secrets_provider: Option<Box<Arc<Mutex<dyn SecretsProvider>>>>,
+    nonce_provider: Option<Box<Arc<Mutex<dyn NonceProvider>>>>,
+    url: Option<String>,
+    user_agent: Option<String>,
+    trace_inbound: Option<bool>,
+    timeout: Option<Duration>,
+    http_client: Option<HttpClient>,
+    metrics: Option<Arc<dyn Metrics>>,
+    offline: Option<OfflineResponder>,
+}
+
+impl CoreKrakenClientBuilder {
+    pub fn new(secrets_provider: Box<Arc<Mutex<dyn SecretsProvider>>>) -> Self {
+        CoreKrakenClientBuilder {
+            secrets_provider: Some(secrets_provider),
+            nonce_provider: None,
+            url: None,
+            user_agent: None,
+            trace_inbound: None,
+            timeout: None,
+            http_client: None,
+            metrics: None,
+            offline: None,
+        }
+    }
+
+    /// Use the given nonce provider, which must be shared by all clients using the same API key.
+    pub fn nonce_provider(
+        &mut self,
+        nonce_provider: Box<Arc<Mutex<dyn NonceProvider>>>,
+    ) -> &mut Self {
+        self.nonce_provider = Some(nonce_provider);
+        self
+    }
+
+    pub fn url(&mut self, url: impl ToString) -> &mut Self {
+        self.url = Some(url.to_string());
+        self
+    }
+
+    /// Use the REST URL of the given [Endpoints], the same as calling `url` with it.
+    pub fn endpoints(&mut self, endpoints: &Endpoints) -> &mut Self {
+        self.url(&endpoints.rest)
+    }
+
+    pub fn user_agent(&mut self, user_agent: impl ToString) -> &mut Self {
+        self.user_agent = Some(user_agent.to_string());
+        self
+    }
+
+    pub fn trace_inbound(&mut self, trace_inbound: bool) -> &mut Self {
+        self.trace_inbound = Some(trace_inbound);
+        self
+    }
+
+    /// See [CoreKrakenClient::with_timeout].
+    pub fn timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// See [CoreKrakenClient::with_http_client].
+    pub fn http_client(&mut self, http_client: HttpClient) -> &mut Self {
+        self.http_client = Some(http_client);
+        self
+    }
+
+    /// See [CoreKrakenClient::with_metrics].
+    pub fn metrics(&mut self, metrics: Arc<dyn Metrics>) -> &mut Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// See [CoreKrakenClient::with_offline_responder].
+    pub fn offline_responder(&mut self, responder: OfflineResponder) -> &mut Self {
+        self.offline = Some(responder);
+        self
+    }
+
+    pub fn build(&mut self) -> CoreKrakenClient {
+        let nonce_provider = self
+            .nonce_provider
+            .take()
+            .unwrap_or_else(|| Box::new(Arc::new(Mutex::new(IncreasingNonceProvider::new()))));
+
+        CoreKrakenClient {
+            api_url: self.url.take().unwrap_or(KRAKEN_BASE_URL.to_string()),
+            secrets_provider: self.secrets_provider.take().expect(
+                "secrets_provider must be set. Builder may have already been consumed by calling `build`",
+            ),
+            nonce_provider,
+            http_client: self
+                .http_client
+                .take()
+                .unwrap_or_else(CoreKrakenClient::new_http_client),
+            user_agent: self.user_agent.take(),
+            trace_inbound: self.trace_inbound.take().unwrap_or(false),
+            timeout: self.timeout.take(),
+            metrics: self
+                .metrics
+                .take()
+                .unwrap_or_else(|| Arc::new(NoopMetrics)),
+            offline: self.offline.take(),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -917,7 +1234,8 @@ mod tests {
     use crate::test_data::earn_json::{
         get_allocate_earn_funds_json, get_allocation_status_json, get_deallocate_earn_funds_json,
         get_deallocation_status_json, get_list_earn_allocations_json,
-        get_list_earn_strategies_json,
+        get_list_earn_strategies_first_page_json, get_list_earn_strategies_json,
+        get_list_earn_strategies_last_page_json,
     };
     use crate::test_data::funding::{
         get_deposit_addresses_json, get_deposit_methods_json, get_request_wallet_transfer_json,
@@ -926,6 +1244,9 @@ mod tests {
         get_withdrawal_addresses_json, get_withdrawal_info_json, get_withdrawal_methods_json,
     };
     use crate::test_data::get_null_secrets_provider;
+    use crate::test_data::paginated_closed_orders::{
+        get_closed_orders_page_1_json, get_closed_orders_page_2_json, get_closed_orders_page_3_json,
+    };
     use crate::test_data::public_response_json::{
         get_asset_info_json, get_ohlc_data_json, get_orderbook_json, get_recent_spreads_json,
         get_recent_trades_json, get_server_time_json, get_system_status_json,
@@ -940,8 +1261,16 @@ mod tests {
         get_cancel_order_json, get_edit_order_json,
     };
     use crate::test_data::websockets_json::get_websockets_token_json;
+    use crate::test_data::RecordingMetrics;
+    use futures_util::{StreamExt, TryStreamExt};
+    use hyper::server::conn::http1;
+    use hyper::service::service_fn;
+    use hyper_util::rt::TokioIo;
     use rust_decimal_macros::dec;
     use serde_json::json;
+    use std::convert::Infallible;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tokio::net::TcpListener;
     use tracing_test::traced_test;
     use wiremock::matchers::{
         body_partial_json, body_string_contains, header, header_exists, method, path, query_param,
@@ -979,6 +1308,75 @@ mod tests {
         assert_eq!(client.api_url, KRAKEN_BASE_URL);
     }
 
+    #[tokio::test]
+    async fn test_offline_client() {
+        let requests = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let recorded = requests.clone();
+
+        let mut client = CoreKrakenClient::new_offline(move |endpoint, body| {
+            recorded
+                .lock()
+                .unwrap()
+                .push((endpoint.to_string(), body.to_string()));
+            match endpoint {
+                TIME_ENDPOINT => get_server_time_json().to_string(),
+                ADD_ORDER_ENDPOINT => get_add_order_json().to_string(),
+                _ => ERROR_UNKNOWN_METHOD.to_string(),
+            }
+        });
+
+        let time = client.get_server_time().await.unwrap().result.unwrap();
+        assert_eq!(1688669448, time.unix_time);
+
+        let request = AddOrderRequest::builder(
+            OrderType::Limit,
+            BuySell::Buy,
+            dec!(5.0),
+            "USDCUSD".to_string(),
+        )
+        .price(dec!(0.95))
+        .build();
+        let order = client.add_order(&request).await.unwrap().result.unwrap();
+        assert_eq!(1, order.tx_id.len());
+
+        let err = client.get_account_balance().await.unwrap_err();
+        assert!(matches!(
+            err,
+            ClientError::Kraken(KrakenError::UnknownMethod)
+        ));
+
+        let requests = requests.lock().unwrap();
+        assert_eq!(3, requests.len());
+        assert_eq!((TIME_ENDPOINT.to_string(), String::new()), requests[0]);
+        assert_eq!(ADD_ORDER_ENDPOINT, requests[1].0);
+        assert!(requests[1].1.starts_with("nonce="));
+        assert!(requests[1].1.contains("pair=USDCUSD"));
+    }
+
+    #[test]
+    fn client_creates_with_endpoints() {
+        let endpoints = Endpoints::new("http://proxy", "wss://proxy/v2", "wss://proxy-auth/v2");
+
+        let client = CoreKrakenClient::new_with_endpoints(
+            get_null_secrets_provider(),
+            Box::new(Arc::new(Mutex::new(IncreasingNonceProvider::new()))),
+            &endpoints,
+        );
+        assert_eq!("http://proxy", client.api_url);
+
+        let client = CoreKrakenClientBuilder::new(get_null_secrets_provider())
+            .endpoints(&endpoints)
+            .build();
+        assert_eq!("http://proxy", client.api_url);
+
+        let client = CoreKrakenClient::new_with_endpoints(
+            get_null_secrets_provider(),
+            Box::new(Arc::new(Mutex::new(IncreasingNonceProvider::new()))),
+            &Endpoints::default(),
+        );
+        assert_eq!(KRAKEN_BASE_URL, client.api_url);
+    }
+
     #[tokio::test]
     async fn client_user_agent() {
         let secrets_provider = get_null_secrets_provider();
@@ -1013,6 +1411,187 @@ mod tests {
         mock_server.verify().await;
     }
 
+    #[tokio::test]
+    async fn client_builder() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/0/public/Time"))
+            .and(header("user-agent", "Strategy#3"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(get_server_time_json()))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let mut client = CoreKrakenClient::builder(get_null_secrets_provider())
+            .url(mock_server.uri())
+            .user_agent("Strategy#3")
+            .timeout(Duration::from_secs(5))
+            .build();
+
+        assert_eq!(mock_server.uri(), client.api_url);
+        assert!(client.get_server_time().await.is_ok());
+        mock_server.verify().await;
+
+        let default = CoreKrakenClient::builder(get_null_secrets_provider()).build();
+        assert_eq!(KRAKEN_BASE_URL, default.api_url);
+        assert_eq!("KrakenAsyncRsClient", default.get_user_agent());
+    }
+
+    #[tokio::test]
+    async fn client_timeout() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/0/public/Time"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(get_server_time_json())
+                    .set_delay(Duration::from_millis(500)),
+            )
+            .mount(&mock_server)
+            .await;
+
+        let mut client = CoreKrakenClient::builder(get_null_secrets_provider())
+            .url(mock_server.uri())
+            .timeout(Duration::from_millis(50))
+            .build();
+
+        let result = client.get_server_time().await;
+        assert!(
+            matches!(result, Err(ClientError::Timeout(timeout)) if timeout == Duration::from_millis(50))
+        );
+    }
+
+    #[tokio::test]
+    async fn client_metrics() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/0/public/Time"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(get_server_time_json()))
+            .mount(&mock_server)
+            .await;
+
+        Mock::given(method("GET"))
+            .and(path("/0/public/Assets"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"result":"#))
+            .mount(&mock_server)
+            .await;
+
+        let metrics = Arc::new(RecordingMetrics::default());
+        let mut client = CoreKrakenClient::builder(get_null_secrets_provider())
+            .url(mock_server.uri())
+            .metrics(metrics.clone())
+            .build();
+
+        assert!(client.get_server_time().await.is_ok());
+        let request = AssetInfoRequest::builder().build();
+        assert!(matches!(
+            client.get_asset_info(&request).await,
+            Err(ClientError::Serde(_))
+        ));
+
+        assert_eq!(
+            vec!["/0/public/Time", "/0/public/Assets"],
+            *metrics.requests.lock().unwrap()
+        );
+        assert_eq!(
+            vec![
+                ("/0/public/Time".to_string(), Some(200)),
+                ("/0/public/Assets".to_string(), Some(200))
+            ],
+            *metrics.responses.lock().unwrap()
+        );
+        assert_eq!(1, metrics.deserialize_errors.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn client_shares_http_client() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let connections = Arc::new(AtomicUsize::new(0));
+
+        // counts each connection, serving any number of keep-alive requests on it
+        let counter = connections.clone();
+        tokio::spawn(async move {
+            loop {
+                let (tcp, _) = listener.accept().await.unwrap();
+                counter.fetch_add(1, Ordering::SeqCst);
+                tokio::spawn(async move {
+                    let service = service_fn(|_request| async {
+                        Ok::<_, Infallible>(hyper::Response::new(
+                            get_server_time_json().to_string(),
+                        ))
+                    });
+                    let _ = http1::Builder::new()
+                        .serve_connection(TokioIo::new(tcp), service)
+                        .await;
+                });
+            }
+        });
+
+        let nonce_provider: Box<Arc<Mutex<dyn NonceProvider>>> =
+            Box::new(Arc::new(Mutex::new(IncreasingNonceProvider::new())));
+        let mut first = CoreKrakenClient::new_with_url(
+            get_null_secrets_provider(),
+            nonce_provider.clone(),
+            &url,
+        );
+
+        let mut second = CoreKrakenClient::new_with_http_client(
+            get_null_secrets_provider(),
+            nonce_provider.clone(),
+            first.http_client(),
+        );
+        second.api_url = url.clone();
+
+        assert!(first.get_server_time().await.is_ok());
+        assert!(second.get_server_time().await.is_ok());
+        assert_eq!(1, connections.load(Ordering::SeqCst));
+
+        // a client with its own HttpClient opens its own connection
+        let mut unshared =
+            CoreKrakenClient::new_with_url(get_null_secrets_provider(), nonce_provider, &url);
+        assert!(unshared.get_server_time().await.is_ok());
+        assert_eq!(2, connections.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn client_user_agent_at_construction() {
+        let mock_server = MockServer::start().await;
+        let nonce_provider: Box<Arc<Mutex<dyn NonceProvider>>> =
+            Box::new(Arc::new(Mutex::new(IncreasingNonceProvider::new())));
+        let mut client = CoreKrakenClient::new_with_url(
+            get_null_secrets_provider(),
+            nonce_provider,
+            mock_server.uri(),
+        )
+        .with_user_agent("Strategy#2");
+
+        Mock::given(method("GET"))
+            .and(path("/0/public/Time"))
+            .and(header("user-agent", "Strategy#2"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(get_server_time_json()))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let _resp = client.get_server_time().await;
+        mock_server.verify().await;
+
+        let nonce_provider: Box<Arc<Mutex<dyn NonceProvider>>> =
+            Box::new(Arc::new(Mutex::new(IncreasingNonceProvider::new())));
+        let client = CoreKrakenClient::new_with_user_agent(
+            get_null_secrets_provider(),
+            nonce_provider,
+            "Strategy#3",
+        );
+
+        assert_eq!("Strategy#3", client.get_user_agent());
+        assert_eq!(KRAKEN_BASE_URL, client.api_url);
+    }
+
     #[tokio::test]
     async fn test_get_server_time() {
         let secrets_provider = get_null_secrets_provider();
@@ -1040,7 +1619,117 @@ mod tests {
             .mount(&mock_server)
             .await;
 
-        test_core_endpoint!(secrets_provider, mock_server, get_system_status);
+        test_core_endpoint!(secrets_provider, mock_server, get_system_status);
+    }
+
+    #[tokio::test]
+    async fn test_poll_system_status() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("0/public/SystemStatus"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(get_system_status_json()))
+            .up_to_n_times(2)
+            .with_priority(1)
+            .expect(2)
+            .mount(&mock_server)
+            .await;
+
+        Mock::given(method("GET"))
+            .and(path("0/public/SystemStatus"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "error": [],
+                "result": {"status": "maintenance", "timestamp": "2024-01-24T11:46:46Z"}
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let mut client = CoreKrakenClient::builder(get_null_secrets_provider())
+            .url(mock_server.uri())
+            .build();
+        let mut statuses = Box::pin(client.poll_system_status(Duration::from_millis(10)));
+
+        let first = statuses.next().await.unwrap().unwrap();
+        assert_eq!(SystemStatus::Online, first.status);
+
+        let second = statuses.next().await.unwrap().unwrap();
+        assert_eq!(SystemStatus::Maintenance, second.status);
+        assert_eq!("2024-01-24T11:46:46Z", second.timestamp);
+
+        drop(statuses);
+        mock_server.verify().await;
+    }
+
+    #[tokio::test]
+    async fn test_recent_trades_stream() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("0/public/Trades"))
+            .and(query_param("pair", "XBTUSD"))
+            .and(query_param("since", "100"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "error": [],
+                "result": {
+                    "XXBTZUSD": [
+                        ["37247.00000","0.00026451",1701019771.0383663,"b","l","",64754585],
+                        ["37248.50000","0.05045233",1701019790.7240255,"s","m","",64754586]
+                    ],
+                    "last": "1701019790724025500"
+                }
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        Mock::given(method("GET"))
+            .and(path("0/public/Trades"))
+            .and(query_param("since", "1701019790724025500"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "error": [],
+                "result": {
+                    "XXBTZUSD": [
+                        ["37250.20000","0.00025000",1701019800.0491319,"b","l","",64754587]
+                    ],
+                    "last": "1701019800049131900"
+                }
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        Mock::given(method("GET"))
+            .and(path("0/public/Trades"))
+            .and(query_param("since", "1701019800049131900"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "error": [],
+                "result": {"XXBTZUSD": [], "last": "1701019800049131900"}
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let mut client = CoreKrakenClient::builder(get_null_secrets_provider())
+            .url(mock_server.uri())
+            .build();
+        let request = RecentTradesRequest::builder("XBTUSD".to_string())
+            .since(100)
+            .build();
+        let mut trades = Box::pin(client.recent_trades_stream(request, Duration::from_millis(10)));
+
+        let mut trade_ids = Vec::new();
+        for _ in 0..3 {
+            trade_ids.push(trades.next().await.unwrap().unwrap().trade_id);
+        }
+
+        assert_eq!(vec![64754585, 64754586, 64754587], trade_ids);
+        assert!(
+            tokio::time::timeout(Duration::from_millis(50), trades.next())
+                .await
+                .is_err()
+        );
+
+        drop(trades);
+        mock_server.verify().await;
     }
 
     #[tokio::test]
@@ -1147,6 +1836,52 @@ mod tests {
         test_core_endpoint!(secrets_provider, mock_server, get_ohlc, &request);
     }
 
+    #[tokio::test]
+    async fn test_get_ohlc_since() {
+        let secrets_provider = get_null_secrets_provider();
+        let nonce_provider: Box<Arc<Mutex<dyn NonceProvider>>> =
+            Box::new(Arc::new(Mutex::new(IncreasingNonceProvider::new())));
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("0/public/OHLC"))
+            .and(query_param("pair", "XXBTZUSD"))
+            .and(query_param("interval", "1"))
+            .and(query_param("since", "1701023340"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(get_ohlc_data_json()))
+            .with_priority(1)
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        Mock::given(method("GET"))
+            .and(path("0/public/OHLC"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(get_ohlc_data_json()))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let mut client =
+            CoreKrakenClient::new_with_url(secrets_provider, nonce_provider, mock_server.uri());
+
+        let first = client
+            .get_ohlc_since("XXBTZUSD", CandlestickInterval::Minute, None)
+            .await
+            .unwrap()
+            .result
+            .unwrap();
+        assert_eq!(1701023340, first.last);
+        assert!(!first.ohlc["XXBTZUSD"].is_empty());
+        assert!(!first.ohlc.contains_key("last"));
+
+        client
+            .get_ohlc_since("XXBTZUSD", CandlestickInterval::Minute, Some(first.last))
+            .await
+            .unwrap();
+
+        mock_server.verify().await;
+    }
+
     #[tokio::test]
     async fn test_get_orderbook() {
         let secrets_provider = get_null_secrets_provider();
@@ -1295,7 +2030,9 @@ mod tests {
     async fn test_client_tracing_enabled() {
         get_time_with_tracing_flag(true).await;
 
-        assert!(logs_contain(r#"Received: {"error":[],"result":{"rfc1123""#));
+        assert!(logs_contain(
+            r#"Received response body={"error":[],"result":{"rfc1123""#
+        ));
     }
 
     #[traced_test]
@@ -1303,7 +2040,7 @@ mod tests {
     async fn test_client_tracing_disabled() {
         get_time_with_tracing_flag(false).await;
 
-        assert!(!logs_contain("Received:"));
+        assert!(!logs_contain("Received response"));
     }
 
     async fn get_time_with_tracing_flag(trace_inbound: bool) {
@@ -1444,6 +2181,56 @@ mod tests {
         test_core_endpoint!(secrets_provider, mock_server, get_closed_orders, &request);
     }
 
+    #[tokio::test]
+    async fn test_get_closed_orders_all() {
+        let secrets_provider = get_null_secrets_provider();
+        let nonce_provider: Box<Arc<Mutex<dyn NonceProvider>>> =
+            Box::new(Arc::new(Mutex::new(IncreasingNonceProvider::new())));
+        let request = ClosedOrdersRequest::builder()
+            .trades(true)
+            .consolidate_taker(true)
+            .build();
+
+        let mock_server = MockServer::start().await;
+
+        for (offset, page) in [
+            ("ofs=50", get_closed_orders_page_2_json()),
+            ("ofs=100", get_closed_orders_page_3_json()),
+        ] {
+            Mock::given(method("POST"))
+                .and(path("/0/private/ClosedOrders"))
+                .and(body_string_contains(offset))
+                .and(body_string_contains("consolidate_taker=true"))
+                .respond_with(ResponseTemplate::new(200).set_body_json(page))
+                .with_priority(1)
+                .expect(1)
+                .mount(&mock_server)
+                .await;
+        }
+
+        Mock::given(method("POST"))
+            .and(path("/0/private/ClosedOrders"))
+            .and(body_string_contains("consolidate_taker=true"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(get_closed_orders_page_1_json()))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let mut client =
+            CoreKrakenClient::new_with_url(secrets_provider, nonce_provider, mock_server.uri());
+
+        let orders: Vec<(String, ClosedOrder)> = client
+            .get_closed_orders_all(&request)
+            .try_collect()
+            .await
+            .unwrap();
+
+        mock_server.verify().await;
+
+        assert_eq!(103, orders.len());
+        assert!(orders.iter().any(|(id, _)| id == "RUZCU5-USN56-DNTXMT"));
+    }
+
     #[tokio::test]
     async fn test_query_orders_info() {
         let secrets_provider = get_null_secrets_provider();
@@ -1802,6 +2589,7 @@ mod tests {
         );
     }
 
+    #[traced_test]
     #[tokio::test]
     async fn test_add_order() {
         let secrets_provider = get_null_secrets_provider();
@@ -1837,6 +2625,66 @@ mod tests {
             .await;
 
         test_core_endpoint!(secrets_provider, mock_server, add_order, &request);
+
+        assert!(logs_contain(
+            "symbol=USDCUSD user_ref=None client_order_id=None"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_dry_run() {
+        let request =
+            AddOrderRequest::limit(BuySell::Buy, dec!(5.0), "USDCUSD".to_string(), dec!(0.90))
+                .build();
+        let validated = json!({"error":[],"result":{"descr":{"order":"buy 5.00000000 USDCUSD @ limit 0.9000"}}});
+
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/0/private/AddOrder"))
+            .and(body_string_contains("validate=true"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(validated))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let mut client = CoreKrakenClient::new_with_url(
+            get_null_secrets_provider(),
+            Box::new(Arc::new(Mutex::new(IncreasingNonceProvider::new()))),
+            mock_server.uri(),
+        );
+
+        client.dry_run(&request).await.unwrap();
+        mock_server.verify().await;
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_rejected() {
+        let request =
+            AddOrderRequest::market(BuySell::Buy, dec!(5.0), "USDCUSD".to_string()).build();
+        let rejected = json!({"error":["EOrder:Insufficient funds"]});
+
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/0/private/AddOrder"))
+            .and(body_string_contains("validate=true"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(rejected))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let mut client = CoreKrakenClient::new_with_url(
+            get_null_secrets_provider(),
+            Box::new(Arc::new(Mutex::new(IncreasingNonceProvider::new()))),
+            mock_server.uri(),
+        );
+
+        let result = client.dry_run(&request).await;
+        assert!(matches!(
+            result,
+            Err(ClientError::Kraken(KrakenError::Unknown(_)))
+        ));
     }
 
     #[tokio::test]
@@ -1910,6 +2758,65 @@ mod tests {
         test_core_endpoint!(secrets_provider, mock_server, amend_order, &amend_request);
     }
 
+    #[test]
+    fn test_encode_form_request_is_stable() {
+        let client = CoreKrakenClient::new(
+            get_null_secrets_provider(),
+            Box::new(Arc::new(Mutex::new(IncreasingNonceProvider::new()))),
+        );
+
+        let request =
+            AddOrderRequest::limit(BuySell::Buy, dec!(1.25), "XBTUSD".into(), dec!(37500))
+                .user_ref(42)
+                .client_order_id("order-1".to_string())
+                .order_flags(OrderFlags::new(vec![
+                    OrderFlag::Post,
+                    OrderFlag::FeesInQuote,
+                ]))
+                .validate(true)
+                .build();
+
+        // required parameters are always encoded first, then optional ones in field order
+        let expected = "nonce=1616492376594&ordertype=limit&type=buy&volume=1.25&pair=XBTUSD&userref=42&cl_ord_id=order-1&price=37500&oflags=post%2Cfciq&validate=true";
+
+        assert_eq!(
+            expected,
+            client.encode_form_request(1616492376594, &request)
+        );
+        assert_eq!(
+            expected,
+            client.encode_form_request(1616492376594, &request.clone())
+        );
+    }
+
+    #[test]
+    fn test_encode_json_request_is_stable() {
+        let client = CoreKrakenClient::new(
+            get_null_secrets_provider(),
+            Box::new(Arc::new(Mutex::new(IncreasingNonceProvider::new()))),
+        );
+
+        let request = AmendOrderRequest::builder()
+            .tx_id("OK7HDB-KCKIZ-3D5HCN".to_string())
+            .order_quantity(dec!(5.25))
+            .limit_price(dec!(0.96).to_string())
+            .post_only(true)
+            .build();
+
+        let expected = r#"{"nonce":1616492376594,"txid":"OK7HDB-KCKIZ-3D5HCN","order_qty":"5.25","limit_price":"0.96","post_only":true}"#;
+
+        assert_eq!(
+            expected,
+            client.encode_json_request(1616492376594, &request).unwrap()
+        );
+        assert_eq!(
+            expected,
+            client
+                .encode_json_request(1616492376594, &request.clone())
+                .unwrap()
+        );
+    }
+
     #[tokio::test]
     async fn test_edit_order() {
         let secrets_provider = get_null_secrets_provider();
@@ -2206,6 +3113,49 @@ mod tests {
         test_core_endpoint!(secrets_provider, mock_server, get_withdrawal_info, &request);
     }
 
+    #[tokio::test]
+    async fn test_preview_withdrawal_fees() {
+        let secrets_provider = get_null_secrets_provider();
+        let nonce_provider: Box<Arc<Mutex<dyn NonceProvider>>> =
+            Box::new(Arc::new(Mutex::new(IncreasingNonceProvider::new())));
+        let mock_server = MockServer::start().await;
+        let mut client =
+            CoreKrakenClient::new_with_url(secrets_provider, nonce_provider, mock_server.uri());
+
+        for (amount, fee) in [("0.5", "0.0002"), ("2", "0.0004")] {
+            Mock::given(method("POST"))
+                .and(path("/0/private/WithdrawInfo"))
+                .and(body_string_contains("asset=XBT"))
+                .and(body_string_contains(format!("amount={amount}")))
+                .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                    "error": [],
+                    "result": {
+                        "method": "Bitcoin",
+                        "limit": "332.00956139",
+                        "amount": amount,
+                        "fee": fee
+                    }
+                })))
+                .expect(1)
+                .mount(&mock_server)
+                .await;
+        }
+
+        let fees = client
+            .preview_withdrawal_fees("XBT", "Greenlisted Address", &[dec!(2), dec!(0.5)])
+            .await
+            .unwrap();
+
+        assert_eq!(vec![&dec!(0.5), &dec!(2)], fees.keys().collect::<Vec<_>>());
+        assert_eq!(dec!(0.0002), fees[&dec!(0.5)].fee);
+        assert_eq!(dec!(0.0004), fees[&dec!(2)].fee);
+
+        let result = client
+            .preview_withdrawal_fees("XBT", "Greenlisted Address", &[dec!(3)])
+            .await;
+        assert!(result.is_err());
+    }
+
     #[tokio::test]
     async fn test_withdraw_funds() {
         let secrets_provider = get_null_secrets_provider();
@@ -2526,6 +3476,155 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_list_earn_strategies_all() {
+        let secrets_provider = get_null_secrets_provider();
+        let nonce_provider: Box<Arc<Mutex<dyn NonceProvider>>> =
+            Box::new(Arc::new(Mutex::new(IncreasingNonceProvider::new())));
+        let request = ListEarnStrategiesRequest::builder().limit(2).build();
+
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/0/private/Earn/Strategies"))
+            .and(body_string_contains("cursor=2"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(get_list_earn_strategies_last_page_json()),
+            )
+            .with_priority(1)
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        Mock::given(method("POST"))
+            .and(path("/0/private/Earn/Strategies"))
+            .and(body_string_contains("limit=2"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(get_list_earn_strategies_first_page_json()),
+            )
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let mut client =
+            CoreKrakenClient::new_with_url(secrets_provider, nonce_provider, mock_server.uri());
+
+        let strategies: Vec<EarnStrategy> = client
+            .list_earn_strategies_all(&request)
+            .try_collect()
+            .await
+            .unwrap();
+
+        mock_server.verify().await;
+
+        let ids: Vec<&str> = strategies.iter().map(|s| s.id.as_str()).collect();
+        assert_eq!(
+            vec![
+                "ESQEFMZ-JPPIY-3KXFJ2",
+                "ESSLTY3-TM6GC-G5BWW5",
+                "ES2QQD3-N4GX4-DPB622"
+            ],
+            ids
+        );
+    }
+
+    #[tokio::test]
+    async fn test_list_earn_strategies_all_repeated_cursor() {
+        let secrets_provider = get_null_secrets_provider();
+        let nonce_provider: Box<Arc<Mutex<dyn NonceProvider>>> =
+            Box::new(Arc::new(Mutex::new(IncreasingNonceProvider::new())));
+        let request = ListEarnStrategiesRequest::builder().build();
+
+        let mock_server = MockServer::start().await;
+
+        // every page, including the one for cursor 2, points at cursor 2 again
+        Mock::given(method("POST"))
+            .and(path("/0/private/Earn/Strategies"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(get_list_earn_strategies_first_page_json()),
+            )
+            .expect(2)
+            .mount(&mock_server)
+            .await;
+
+        let mut client =
+            CoreKrakenClient::new_with_url(secrets_provider, nonce_provider, mock_server.uri());
+
+        let strategies: Vec<EarnStrategy> = client
+            .list_earn_strategies_all(&request)
+            .try_collect()
+            .await
+            .unwrap();
+
+        mock_server.verify().await;
+        assert_eq!(4, strategies.len());
+    }
+
+    #[tokio::test]
+    async fn test_list_earn_strategies_all_empty_page() {
+        let secrets_provider = get_null_secrets_provider();
+        let nonce_provider: Box<Arc<Mutex<dyn NonceProvider>>> =
+            Box::new(Arc::new(Mutex::new(IncreasingNonceProvider::new())));
+        let request = ListEarnStrategiesRequest::builder().build();
+
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/0/private/Earn/Strategies"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "error": [],
+                "result": {"next_cursor": "3", "items": []}
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let mut client =
+            CoreKrakenClient::new_with_url(secrets_provider, nonce_provider, mock_server.uri());
+
+        let strategies: Vec<EarnStrategy> = client
+            .list_earn_strategies_all(&request)
+            .try_collect()
+            .await
+            .unwrap();
+
+        mock_server.verify().await;
+        assert!(strategies.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_list_earn_strategies_all_error() {
+        let secrets_provider = get_null_secrets_provider();
+        let nonce_provider: Box<Arc<Mutex<dyn NonceProvider>>> =
+            Box::new(Arc::new(Mutex::new(IncreasingNonceProvider::new())));
+        let request = ListEarnStrategiesRequest::builder().build();
+
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/0/private/Earn/Strategies"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(ERROR_PERMISSION_DENIED))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let mut client =
+            CoreKrakenClient::new_with_url(secrets_provider, nonce_provider, mock_server.uri());
+
+        let results: Vec<Result<EarnStrategy, ClientError>> =
+            client.list_earn_strategies_all(&request).collect().await;
+
+        mock_server.verify().await;
+
+        assert_eq!(1, results.len());
+        assert!(matches!(
+            results[0],
+            Err(ClientError::Kraken(KrakenError::PermissionDenied))
+        ));
+    }
+
     #[tokio::test]
     async fn test_list_earn_allocations() {
         let secrets_provider = get_null_secrets_provider();
@@ -2659,6 +3758,21 @@ mod tests {
             ERROR_FEATURE_DISABLED,
             Err(ClientError::Kraken(KrakenError::FeatureDisabled))
         );
+
+        test_parse_error_matches_pattern!(
+            r#"{"error":["EFake:Made up error"]}"#,
+            Err(ClientError::Kraken(KrakenError::Unknown(_)))
+        );
+    }
+
+    #[test]
+    fn test_parse_body_and_errors_ignores_warnings() {
+        let body = r#"{"error":["WGeneral:Made up warning"],"result":{"ZUSD":"1.0"}}"#;
+
+        let parsed = CoreKrakenClient::parse_body_and_errors::<AccountBalances>(body);
+
+        assert!(parsed.is_ok());
+        assert!(parsed.unwrap().result.is_some());
     }
 
     #[tokio::test]
@@ -2670,7 +3784,10 @@ mod tests {
             CoreKrakenClient::new_with_url(secrets_provider, nonce_provider, "badUrl".to_string());
 
         let resp = client.get_websockets_token().await;
-        assert_eq!("relative URL without a base", resp.unwrap_err().to_string());
+        assert_eq!(
+            "Invalid URL: relative URL without a base",
+            resp.unwrap_err().to_string()
+        );
     }
 
     #[tokio::test]
@@ -2691,7 +3808,7 @@ mod tests {
 
         let resp = client.get_websockets_token().await;
         assert_eq!(
-            "invalid type: string \"\", expected struct ResultErrorResponse at line 1 column 2",
+            "Failed to deserialize response: invalid type: string \"\", expected struct ResultErrorResponse at line 1 column 2",
             resp.unwrap_err().to_string()
         );
     }
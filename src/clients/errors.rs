@@ -7,11 +7,12 @@ use serde::Deserialize;
 use serde_json::Error as SerdeError;
 use std::error::Error;
 use std::fmt::{Display, Formatter};
+use std::time::Duration;
 use url::ParseError as UrlParseError;
 
 /// `ClientError::Kraken` contains all parsed error messages like `PermissionDenied` and
-/// `UnknownMethod`, but is not inclusive of all potential error messages that can be present in
-/// the `error` field of a response.
+/// `UnknownMethod`, and passes any other error message in the `error` field of a response through
+/// as `KrakenError::Unknown`.
 ///
 /// Various dependency errors are exposed, but may be of limited use aside from triggering retries.
 #[derive(Debug)]
@@ -24,6 +25,13 @@ pub enum ClientError {
     HttpStatus(String),
     UrlParse(UrlParseError),
     Parse(&'static str),
+    /// A request that was rejected before sending, since Kraken would not accept it
+    InvalidRequest(&'static str),
+    /// A request was not completed within the client's timeout
+    Timeout(Duration),
+    /// An order was not sent, since an order with the same client order id was already sent within
+    /// the client's deduplication window
+    DuplicateClientOrderId(String),
     Kraken(KrakenError),
 }
 
@@ -66,15 +74,20 @@ impl From<SerdeError> for ClientError {
 impl Display for ClientError {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
-            ClientError::Serde(err) => write!(f, "{}", err),
-            ClientError::Hyper(err) => write!(f, "{}", err),
-            ClientError::HyperClient(err) => write!(f, "{}", err),
-            ClientError::HyperHttp(err) => write!(f, "{}", err),
-            ClientError::HyperUri(err) => write!(f, "{}", err),
+            ClientError::Serde(err) => write!(f, "Failed to deserialize response: {}", err),
+            ClientError::Hyper(err) => write!(f, "HTTP error: {}", err),
+            ClientError::HyperClient(err) => write!(f, "HTTP client error: {}", err),
+            ClientError::HyperHttp(err) => write!(f, "Failed to build HTTP request: {}", err),
+            ClientError::HyperUri(err) => write!(f, "Invalid URI: {}", err),
             ClientError::HttpStatus(body) => write!(f, "Non-successful status with body: {}", body),
-            ClientError::UrlParse(err) => write!(f, "{}", err),
+            ClientError::UrlParse(err) => write!(f, "Invalid URL: {}", err),
             ClientError::Parse(err) => write!(f, "{}", err),
-            ClientError::Kraken(err) => write!(f, "{}", err),
+            ClientError::InvalidRequest(err) => write!(f, "Invalid request: {}", err),
+            ClientError::Timeout(timeout) => write!(f, "Request timed out after {:?}", timeout),
+            ClientError::DuplicateClientOrderId(id) => {
+                write!(f, "Order with client order id {} was already sent", id)
+            }
+            ClientError::Kraken(err) => write!(f, "Kraken error: {}", err),
         }
     }
 }
@@ -90,6 +103,9 @@ impl Error for ClientError {
             ClientError::HttpStatus(_) => None,
             ClientError::UrlParse(e) => Some(e),
             ClientError::Parse(_) => None,
+            ClientError::InvalidRequest(_) => None,
+            ClientError::Timeout(_) => None,
+            ClientError::DuplicateClientOrderId(_) => None,
             ClientError::Kraken(e) => Some(e),
         }
     }
@@ -126,75 +142,168 @@ pub enum KrakenError {
     InternalError,
     TradeLocked,
     FeatureDisabled,
+    /// Any error message not parsed into one of the other variants, passed through as-is
+    Unknown(String),
 }
 
 impl Error for KrakenError {}
 
 impl Display for KrakenError {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        match self {
-            KrakenError::PermissionDenied => write!(f, "PermissionDenied"),
-            KrakenError::InvalidKey => write!(f, "InvalidKey"),
-            KrakenError::UnknownAssetPair => write!(f, "UnknownAssetPair"),
-            KrakenError::InvalidArguments(s) => write!(f, "{s}"),
-            KrakenError::InvalidSignature => write!(f, "InvalidSignature"),
-            KrakenError::InvalidNonce => write!(f, "InvalidNonce"),
-            KrakenError::InvalidSession => write!(f, "InvalidSession"),
-            KrakenError::BadRequest => write!(f, "BadRequest"),
-            KrakenError::UnknownMethod => write!(f, "UnknownMethod"),
-            KrakenError::RateLimitExceeded => write!(f, "RateLimitExceeded"),
-            KrakenError::TradingRateLimitExceeded => write!(f, "TradingRateLimitExceeded"),
-            KrakenError::TemporaryLockout => write!(f, "TemporaryLockout"),
-            KrakenError::ServiceUnavailable => write!(f, "ServiceUnavailable"),
-            KrakenError::ServiceBusy => write!(f, "ServiceBusy"),
-            KrakenError::InternalError => write!(f, "InternalError"),
-            KrakenError::TradeLocked => write!(f, "TradeLocked"),
-            KrakenError::FeatureDisabled => write!(f, "FeatureDisabled"),
-        }
+        let message = match self {
+            KrakenError::InvalidArguments(s) => {
+                return write!(
+                    f,
+                    "InvalidArguments: check the request parameters, Kraken sent {s}"
+                );
+            }
+            KrakenError::Unknown(s) => return write!(f, "Unknown: Kraken sent {s}"),
+            KrakenError::PermissionDenied => {
+                "PermissionDenied: the API key does not have permission for this request, check \
+                the key's permissions"
+            }
+            KrakenError::InvalidKey => {
+                "InvalidKey: the API key was not recognized, check it was copied correctly and has \
+                not been revoked"
+            }
+            KrakenError::UnknownAssetPair => {
+                "UnknownAssetPair: the asset pair was not recognized, check the pair name against \
+                AssetPairs"
+            }
+            KrakenError::InvalidSignature => {
+                "InvalidSignature: the request signature was rejected, check the API secret \
+                matches the key"
+            }
+            KrakenError::InvalidNonce => {
+                "InvalidNonce: the nonce was not greater than the last one used, ensure your \
+                NonceProvider is monotonic across restarts and clients sharing a key"
+            }
+            KrakenError::InvalidSession => {
+                "InvalidSession: the session is invalid, request a new websockets token"
+            }
+            KrakenError::BadRequest => {
+                "BadRequest: the request was malformed, check the endpoint and its parameters"
+            }
+            KrakenError::UnknownMethod => {
+                "UnknownMethod: the endpoint was not recognized by Kraken"
+            }
+            KrakenError::RateLimitExceeded => {
+                "RateLimitExceeded: the API rate limit was exceeded, reduce request frequency or \
+                use a RateLimitedKrakenClient"
+            }
+            KrakenError::TradingRateLimitExceeded => {
+                "TradingRateLimitExceeded: the trading rate limit was exceeded, reduce order \
+                placement, edit and cancel frequency"
+            }
+            KrakenError::TemporaryLockout => {
+                "TemporaryLockout: the API key is temporarily locked out after exceeding rate \
+                limits, wait before retrying"
+            }
+            KrakenError::ServiceUnavailable => {
+                "ServiceUnavailable: Kraken is unavailable, retry later"
+            }
+            KrakenError::ServiceBusy => "ServiceBusy: Kraken is busy, retry with backoff",
+            KrakenError::InternalError => {
+                "InternalError: Kraken encountered an internal error, retry later"
+            }
+            KrakenError::TradeLocked => {
+                "TradeLocked: trading is locked for this account, contact Kraken support"
+            }
+            KrakenError::FeatureDisabled => {
+                "FeatureDisabled: the feature is disabled for this account or API key"
+            }
+        };
+
+        write!(f, "{message}")
     }
 }
 
 /// Parsing for all supported error types from the raw messages in `ResultErrorResponse.error`.
-impl TryFrom<&String> for KrakenError {
-    type Error = ();
-
-    fn try_from(value: &String) -> Result<Self, Self::Error> {
+///
+/// Messages that aren't recognized are passed through as [KrakenError::Unknown].
+impl From<&String> for KrakenError {
+    fn from(value: &String) -> Self {
         if value.starts_with("EGeneral:Permission denied") {
-            Ok(KrakenError::PermissionDenied)
+            KrakenError::PermissionDenied
         } else if value.starts_with("EAPI:Invalid key") {
-            Ok(KrakenError::InvalidKey)
+            KrakenError::InvalidKey
         } else if value.starts_with("EQuery:Unknown asset pair") {
-            Ok(KrakenError::UnknownAssetPair)
+            KrakenError::UnknownAssetPair
         } else if value.starts_with("EGeneral:Invalid arguments") {
-            Ok(KrakenError::InvalidArguments(value.clone()))
+            KrakenError::InvalidArguments(value.clone())
         } else if value.starts_with("EAPI:Invalid signature") {
-            Ok(KrakenError::InvalidSignature)
+            KrakenError::InvalidSignature
         } else if value.starts_with("EAPI:Invalid nonce") {
-            Ok(KrakenError::InvalidNonce)
+            KrakenError::InvalidNonce
         } else if value.starts_with("ESession:Invalid session") {
-            Ok(KrakenError::InvalidSession)
+            KrakenError::InvalidSession
         } else if value.starts_with("EAPI:Bad request") {
-            Ok(KrakenError::BadRequest)
+            KrakenError::BadRequest
         } else if value.starts_with("EGeneral:Unknown Method") {
-            Ok(KrakenError::UnknownMethod)
+            KrakenError::UnknownMethod
         } else if value.starts_with("EAPI:Rate limit exceeded") {
-            Ok(KrakenError::RateLimitExceeded)
+            KrakenError::RateLimitExceeded
         } else if value.starts_with("EOrder:Rate limit exceeded") {
-            Ok(KrakenError::TradingRateLimitExceeded)
+            KrakenError::TradingRateLimitExceeded
         } else if value.starts_with("EGeneral:Temporary lockout") {
-            Ok(KrakenError::TemporaryLockout)
+            KrakenError::TemporaryLockout
         } else if value.starts_with("EService:Unavailable") {
-            Ok(KrakenError::ServiceUnavailable)
+            KrakenError::ServiceUnavailable
         } else if value.starts_with("EService:Busy") {
-            Ok(KrakenError::ServiceBusy)
+            KrakenError::ServiceBusy
         } else if value.starts_with("EGeneral:Internal error") {
-            Ok(KrakenError::InternalError)
+            KrakenError::InternalError
         } else if value.starts_with("ETrade:Locked") {
-            Ok(KrakenError::TradeLocked)
+            KrakenError::TradeLocked
         } else if value.starts_with("EAPI:Feature disabled") {
-            Ok(KrakenError::FeatureDisabled)
+            KrakenError::FeatureDisabled
         } else {
-            Err(())
+            KrakenError::Unknown(value.clone())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::clients::errors::KrakenError;
+
+    #[test]
+    fn test_kraken_error_mapping_completeness() {
+        let errors = [
+            ("EGeneral:Permission denied", KrakenError::PermissionDenied),
+            ("EAPI:Invalid key", KrakenError::InvalidKey),
+            ("EQuery:Unknown asset pair", KrakenError::UnknownAssetPair),
+            (
+                "EGeneral:Invalid arguments:type",
+                KrakenError::InvalidArguments("EGeneral:Invalid arguments:type".to_string()),
+            ),
+            ("EAPI:Invalid signature", KrakenError::InvalidSignature),
+            ("EAPI:Invalid nonce", KrakenError::InvalidNonce),
+            ("ESession:Invalid session", KrakenError::InvalidSession),
+            ("EAPI:Bad request", KrakenError::BadRequest),
+            ("EGeneral:Unknown Method", KrakenError::UnknownMethod),
+            ("EAPI:Rate limit exceeded", KrakenError::RateLimitExceeded),
+            (
+                "EOrder:Rate limit exceeded",
+                KrakenError::TradingRateLimitExceeded,
+            ),
+            ("EGeneral:Temporary lockout", KrakenError::TemporaryLockout),
+            ("EService:Unavailable", KrakenError::ServiceUnavailable),
+            ("EService:Busy", KrakenError::ServiceBusy),
+            ("EGeneral:Internal error", KrakenError::InternalError),
+            ("ETrade:Locked", KrakenError::TradeLocked),
+            ("EAPI:Feature disabled", KrakenError::FeatureDisabled),
+        ];
+
+        for (raw, expected) in errors {
+            assert_eq!(expected, KrakenError::from(&raw.to_string()));
         }
     }
+
+    #[test]
+    fn test_unknown_kraken_error_passthrough() {
+        let raw = "EOrder:Made up error".to_string();
+
+        assert_eq!(KrakenError::Unknown(raw.clone()), KrakenError::from(&raw));
+    }
 }
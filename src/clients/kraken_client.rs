@@ -6,13 +6,18 @@ use crate::clients::http_response_types::ResultErrorResponse;
 #[allow(unused)]
 use crate::clients::rate_limited_kraken_client::RateLimitedKrakenClient;
 use crate::crypto::nonce_provider::NonceProvider;
+use crate::endpoints::Endpoints;
 use crate::request_types::*;
 use crate::response_types::*;
 use crate::secrets::secrets_provider::SecretsProvider;
-use std::collections::HashMap;
+use futures_util::{stream, Stream, TryStreamExt};
+use rust_decimal::Decimal;
+use std::collections::{BTreeMap, HashMap, VecDeque};
 use std::future::Future;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::Mutex;
+use tokio::time::MissedTickBehavior;
 
 pub mod endpoints {
     pub const KRAKEN_BASE_URL: &str = "https://api.kraken.com";
@@ -99,6 +104,18 @@ pub trait KrakenClient: Send + Sync {
         url: impl ToString,
     ) -> Self;
 
+    /// Creates a new instance using the REST URL of the given [Endpoints].
+    fn new_with_endpoints(
+        secrets_provider: Box<Arc<Mutex<dyn SecretsProvider>>>,
+        nonce_provider: Box<Arc<Mutex<dyn NonceProvider>>>,
+        endpoints: &Endpoints,
+    ) -> Self
+    where
+        Self: Sized,
+    {
+        Self::new_with_url(secrets_provider, nonce_provider, &endpoints.rest)
+    }
+
     /// Creates a new instance with the given [SecretsProvider] and [NonceProvider], optionally
     /// enabling tracing for inbound messages.
     fn new_with_tracing(
@@ -121,6 +138,50 @@ pub trait KrakenClient: Send + Sync {
         &mut self,
     ) -> impl Future<Output = Result<ResultErrorResponse<SystemStatusInfo>, ClientError>>;
 
+    /// Poll the system status every `period`, yielding the first status and then only changes to
+    /// it, e.g. from `online` to `maintenance`.
+    ///
+    /// Errors are yielded without ending the stream, and polling continues at the same period.
+    /// Requests go through this client, so a [RateLimitedKrakenClient] applies its public rate
+    /// limit to each poll.
+    ///
+    /// # Panics
+    /// If `period` is zero.
+    fn poll_system_status(
+        &mut self,
+        period: Duration,
+    ) -> impl Stream<Item = Result<SystemStatusInfo, ClientError>>
+    where
+        Self: Sized,
+    {
+        let mut interval = tokio::time::interval(period);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        stream::unfold(
+            (self, interval, None),
+            |(client, mut interval, last)| async move {
+                loop {
+                    interval.tick().await;
+
+                    let info = client.get_system_status().await.and_then(|response| {
+                        response
+                            .result
+                            .ok_or(ClientError::Parse("Missing result for system status"))
+                    });
+
+                    match info {
+                        Ok(info) if Some(info.status) == last => continue,
+                        Ok(info) => {
+                            let status = Some(info.status);
+                            return Some((Ok(info), (client, interval, status)));
+                        }
+                        Err(err) => return Some((Err(err), (client, interval, last))),
+                    }
+                }
+            },
+        )
+    }
+
     /// Get info about a particular asset, e.g. "XBT" or "ETH".
     fn get_asset_info(
         &mut self,
@@ -151,6 +212,29 @@ pub trait KrakenClient: Send + Sync {
         request: &OHLCRequest,
     ) -> impl Future<Output = Result<ResultErrorResponse<OhlcResponse>, ClientError>>;
 
+    /// Get the OHLC candles of `pair` since `last`, the [OhlcResponse::last] of a previous call, or
+    /// all available candles if `None`.
+    ///
+    /// Passing each response's `last` to the next call polls for only new candles. The most recent
+    /// candle is still in progress and is returned again, updated, by the next call.
+    fn get_ohlc_since(
+        &mut self,
+        pair: &str,
+        interval: CandlestickInterval,
+        last: Option<i64>,
+    ) -> impl Future<Output = Result<ResultErrorResponse<OhlcResponse>, ClientError>>
+    where
+        Self: Sized,
+    {
+        let request = OHLCRequest {
+            pair: pair.to_string(),
+            interval: Some(interval),
+            since: last,
+        };
+
+        async move { self.get_ohlc(&request).await }
+    }
+
     /// Get a snapshot of the orderbook for the requested pair and depth-of-book.
     fn get_orderbook(
         &mut self,
@@ -170,6 +254,57 @@ pub trait KrakenClient: Send + Sync {
         request: &RecentTradesRequest,
     ) -> impl Future<Output = Result<ResultErrorResponse<RecentTrades>, ClientError>>;
 
+    /// Poll recent trades every `period`, starting from `request` and continuing from the `last`
+    /// cursor of each response, yielding each trade once in the order Kraken returns them.
+    ///
+    /// Errors are yielded without ending the stream, and the failed page is requested again at the
+    /// next period. Requests go through this client, so a [RateLimitedKrakenClient] applies its
+    /// per-pair rate limit to each poll.
+    ///
+    /// # Panics
+    /// If `period` is zero.
+    fn recent_trades_stream(
+        &mut self,
+        request: RecentTradesRequest,
+        period: Duration,
+    ) -> impl Stream<Item = Result<RecentTrade, ClientError>>
+    where
+        Self: Sized,
+    {
+        let mut interval = tokio::time::interval(period);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        stream::unfold(
+            (self, interval, request, VecDeque::new()),
+            |(client, mut interval, mut request, mut trades)| async move {
+                loop {
+                    if let Some(trade) = trades.pop_front() {
+                        return Some((Ok(trade), (client, interval, request, trades)));
+                    }
+
+                    interval.tick().await;
+
+                    let page = client
+                        .get_recent_trades(&request)
+                        .await
+                        .and_then(|response| {
+                            response
+                                .result
+                                .ok_or(ClientError::Parse("Missing result for recent trades"))
+                        });
+
+                    match page {
+                        Ok(page) => {
+                            request = page.next_request(&request);
+                            trades.extend(page.trades.into_values().flatten());
+                        }
+                        Err(err) => return Some((Err(err), (client, interval, request, trades))),
+                    }
+                }
+            },
+        )
+    }
+
     /// Get the last ~200 spread values for the requested pair.
     ///
     /// The `since` parameter allows getting incremental updates, but does not paginate the request
@@ -210,6 +345,50 @@ pub trait KrakenClient: Send + Sync {
         request: &ClosedOrdersRequest,
     ) -> impl Future<Output = Result<ResultErrorResponse<ClosedOrders>, ClientError>>;
 
+    /// Get all closed orders matching the request as a stream, following `ofs` until `count` orders
+    /// have been returned.
+    ///
+    /// The `offset` of the given request is used for the first page and advanced for each following
+    /// page, while all other parameters (e.g. `trades` and `consolidate_taker`) are sent with every
+    /// page. Orders within a page are yielded in no particular order. Any error ends the stream
+    /// after it's yielded.
+    fn get_closed_orders_all(
+        &mut self,
+        request: &ClosedOrdersRequest,
+    ) -> impl Stream<Item = Result<(String, ClosedOrder), ClientError>>
+    where
+        Self: Sized,
+    {
+        let state = (self, Some(request.clone()));
+
+        stream::try_unfold(state, |(client, request)| async move {
+            let Some(request) = request else {
+                return Ok::<_, ClientError>(None);
+            };
+
+            let closed_orders = client
+                .get_closed_orders(&request)
+                .await?
+                .result
+                .ok_or(ClientError::Parse("Missing result for closed orders"))?;
+
+            let offset = request.offset.unwrap_or(0) + closed_orders.closed.len() as i64;
+            let next_request = if !closed_orders.closed.is_empty() && offset < closed_orders.count {
+                Some(ClosedOrdersRequest {
+                    offset: Some(offset),
+                    ..request
+                })
+            } else {
+                None
+            };
+
+            let orders = stream::iter(closed_orders.closed.into_iter().map(Ok));
+
+            Ok(Some((orders, (client, next_request))))
+        })
+        .try_flatten()
+    }
+
     /// Get the information for up to 50 orders at a time.
     fn query_orders_info(
         &mut self,
@@ -291,6 +470,29 @@ pub trait KrakenClient: Send + Sync {
         request: &AddOrderRequest,
     ) -> impl Future<Output = Result<ResultErrorResponse<AddOrder>, ClientError>>;
 
+    /// Check that Kraken would accept an order without placing it, by sending it with
+    /// `validate = true`.
+    ///
+    /// Returns `Ok(())` if the order passed validation, or the error Kraken rejected it with.
+    fn dry_run(
+        &mut self,
+        request: &AddOrderRequest,
+    ) -> impl Future<Output = Result<(), ClientError>> {
+        let request = AddOrderRequest {
+            validate: Some(true),
+            ..request.clone()
+        };
+
+        async move {
+            self.add_order(&request)
+                .await?
+                .result
+                .ok_or(ClientError::Parse("Missing result for validated order"))?;
+
+            Ok(())
+        }
+    }
+
     /// Add up to 15 orders *for a single pair* at once. Orders that fail to place are dropped from
     /// processing and will be returned with errors in the response's `Vec`.
     fn add_order_batch(
@@ -370,6 +572,40 @@ pub trait KrakenClient: Send + Sync {
         request: &WithdrawalInfoRequest,
     ) -> impl Future<Output = Result<ResultErrorResponse<Withdrawal>, ClientError>>;
 
+    /// Get the fee and limit for withdrawing each of `amounts` of `asset` to `key`, keyed by amount,
+    /// e.g. to preview fees for several amounts at once.
+    ///
+    /// Amounts are requested one at a time with [KrakenClient::get_withdrawal_info], so a
+    /// [RateLimitedKrakenClient] waits between them as usual. The first error is returned.
+    fn preview_withdrawal_fees(
+        &mut self,
+        asset: &str,
+        key: &str,
+        amounts: &[Decimal],
+    ) -> impl Future<Output = Result<BTreeMap<Decimal, Withdrawal>, ClientError>> {
+        let requests: Vec<WithdrawalInfoRequest> = amounts
+            .iter()
+            .map(|amount| {
+                WithdrawalInfoRequest::builder(asset.to_string(), key.to_string(), *amount).build()
+            })
+            .collect();
+
+        async move {
+            let mut withdrawals = BTreeMap::new();
+
+            for request in requests {
+                let withdrawal = self
+                    .get_withdrawal_info(&request)
+                    .await?
+                    .result
+                    .ok_or(ClientError::Parse("Missing result for withdrawal info"))?;
+                withdrawals.insert(request.amount, withdrawal);
+            }
+
+            Ok(withdrawals)
+        }
+    }
+
     /// Request a withdrawal for the provided asset and key.
     fn withdraw_funds(
         &mut self,
@@ -440,6 +676,50 @@ pub trait KrakenClient: Send + Sync {
         request: &ListEarnStrategiesRequest,
     ) -> impl Future<Output = Result<ResultErrorResponse<EarnStrategies>, ClientError>>;
 
+    /// List all earn strategies as a stream, following `next_cursor` until all pages are consumed.
+    ///
+    /// The `cursor` of the given request is used for the first page, and replaced for each
+    /// following page. The stream also ends on a page without items, or one that returns the
+    /// cursor it was requested with, so a misbehaving cursor cannot loop forever. Any error ends
+    /// the stream after it's yielded.
+    fn list_earn_strategies_all(
+        &mut self,
+        request: &ListEarnStrategiesRequest,
+    ) -> impl Stream<Item = Result<EarnStrategy, ClientError>>
+    where
+        Self: Sized,
+    {
+        let state = (self, Some(request.clone()));
+
+        stream::try_unfold(state, |(client, request)| async move {
+            let Some(request) = request else {
+                return Ok::<_, ClientError>(None);
+            };
+
+            let strategies = client
+                .list_earn_strategies(&request)
+                .await?
+                .result
+                .ok_or(ClientError::Parse("Missing result for earn strategies"))?;
+
+            let is_last_page = strategies.items.is_empty();
+            let next_request = strategies
+                .next_cursor
+                .filter(|cursor| {
+                    !is_last_page && !cursor.is_empty() && Some(cursor) != request.cursor.as_ref()
+                })
+                .map(|cursor| ListEarnStrategiesRequest {
+                    cursor: Some(cursor),
+                    ..request
+                });
+
+            let items = stream::iter(strategies.items.into_iter().map(Ok));
+
+            Ok(Some((items, (client, next_request))))
+        })
+        .try_flatten()
+    }
+
     /// List all current earn allocations.
     fn list_earn_allocations(
         &mut self,
@@ -7,15 +7,25 @@
 //! - [CoreKrakenClient]: Basic impl of REST calls with no rate limiting or additional behavior
 //! - [RateLimitedKrakenClient]: Rate-limited decorator of arbitrary [KrakenClient] implementations
 //!
+//! Helpers:
+//! - [TokenManager]: Caches websocket tokens from a [KrakenClient], refreshing them before expiry
+//! - [AssetPairsCache]: Caches tradable asset pairs from a [KrakenClient], refreshing them when stale
+//!
+#[allow(unused)]
+use crate::clients::asset_pairs_cache::AssetPairsCache;
 #[allow(unused)]
 use crate::clients::core_kraken_client::CoreKrakenClient;
 #[allow(unused)]
 use crate::clients::kraken_client::KrakenClient;
 #[allow(unused)]
 use crate::clients::rate_limited_kraken_client::RateLimitedKrakenClient;
+#[allow(unused)]
+use crate::clients::token_manager::TokenManager;
 
+pub mod asset_pairs_cache;
 pub mod core_kraken_client;
 pub mod errors;
 pub mod http_response_types;
 pub mod kraken_client;
 pub mod rate_limited_kraken_client;
+pub mod token_manager;
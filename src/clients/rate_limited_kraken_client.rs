@@ -1,10 +1,17 @@
 //! A rate-limited [KrakenClient]
-use crate::clients::errors::ClientError;
+use crate::clients::errors::{ClientError, KrakenError};
 use crate::clients::http_response_types::ResultErrorResponse;
+use crate::clients::kraken_client::endpoints::*;
 use crate::clients::kraken_client::KrakenClient;
 use crate::crypto::nonce_provider::NonceProvider;
+use crate::metrics::{Metrics, NoopMetrics};
 use crate::rate_limiting::keyed_rate_limits::KeyedRateLimiter;
-use crate::rate_limiting::trading_rate_limits::KrakenTradingRateLimiter;
+use crate::rate_limiting::private_costs::private_endpoint_cost;
+use crate::rate_limiting::token_bucket_mirror::TokenBucketMirror;
+use crate::rate_limiting::trading_rate_limits::{
+    KrakenTradingRateLimiter, OrderPenalties, TradingPenalties,
+};
+use crate::rate_limiting::ttl_cache::{TtlCache, TtlEntry};
 use crate::request_types::*;
 use crate::response_types::*;
 use crate::secrets::secrets_provider::SecretsProvider;
@@ -16,12 +23,20 @@ use std::sync::Arc;
 use std::time::Duration;
 use time::OffsetDateTime;
 use tokio::sync::Mutex;
+use tokio::time::Instant;
+use tracing::debug;
+
+/// Waits on a rate limiter longer than this are logged, while shorter ones only acquired a token
+/// that was already available
+const WAIT_LOG_THRESHOLD: Duration = Duration::from_millis(1);
 
 /// A [KrakenClient] implementation that decorates a provided client, and applies rate limiting
 /// according to the Kraken API specs.
 ///
 /// Loosely, this is:
 /// - public endpoints are limited to 1 call per second
+///     - OHLC and recent trades are also limited to 1 call per second *per pair*, in addition to the
+///       limit shared with other public endpoints
 /// - private endpoints follow a token-bucket rate limiting scheme, with some endpoints having higher costs
 /// - trading endpoints implement the Advanced version of Kraken's rate limiting scheme
 ///     - this includes tracking order lifetimes and applying penalties to rapid cancels and edits of orders
@@ -29,7 +44,8 @@ use tokio::sync::Mutex;
 /// The exact rate limit values and replenishment schedule are determined by a user's
 /// verification tier. Default new methods assume an `Intermediate` verification, so `Pro` users will
 /// want to rely on methods that allow providing a custom verification tier if they want to take full
-/// advantage of their increased rate limits (e.g. `new_with_verification_tier`).
+/// advantage of their increased rate limits (e.g. `new_with_verification_tier`), while `Starter`
+/// users need them to avoid exceeding their lower limits.
 ///
 /// Calls made that violate the rate limiting policy are made to wait asynchronously, but no error handling
 /// is in place for receiving rate limit errors, these are to be handled/backed-off by the user.
@@ -61,9 +77,19 @@ where
 {
     core_client: C,
     private_rate_limiter: TokenBucketRateLimiter,
+    private_token_mirror: TokenBucketMirror,
     public_rate_limiter: SlidingWindowRateLimiter,
     trading_rate_limiter: KrakenTradingRateLimiter,
     pair_rate_limiter: KeyedRateLimiter<String>,
+    metrics: Arc<dyn Metrics>,
+    client_order_ids: Option<ClientOrderIdDedup>,
+}
+
+/// The client order ids sent by a client within its deduplication window, shared by its clones.
+#[derive(Debug, Clone)]
+struct ClientOrderIdDedup {
+    window_us: i128,
+    sent: Arc<Mutex<TtlCache<String, ()>>>,
 }
 
 impl<C> KrakenClient for RateLimitedKrakenClient<C>
@@ -77,9 +103,12 @@ where
         RateLimitedKrakenClient {
             core_client: C::new(secrets_provider, nonce_provider),
             private_rate_limiter: Self::get_private_rate_limiter(VerificationTier::Intermediate),
+            private_token_mirror: Self::get_private_token_mirror(VerificationTier::Intermediate),
             public_rate_limiter: Self::get_public_rate_limiter(),
             trading_rate_limiter: KrakenTradingRateLimiter::new(VerificationTier::Intermediate),
             pair_rate_limiter: KeyedRateLimiter::new(),
+            metrics: Arc::new(NoopMetrics),
+            client_order_ids: None,
         }
     }
 
@@ -91,9 +120,12 @@ where
         RateLimitedKrakenClient {
             core_client: C::new_with_url(secrets_provider, nonce_provider, url),
             private_rate_limiter: Self::get_private_rate_limiter(VerificationTier::Intermediate),
+            private_token_mirror: Self::get_private_token_mirror(VerificationTier::Intermediate),
             public_rate_limiter: Self::get_public_rate_limiter(),
             trading_rate_limiter: KrakenTradingRateLimiter::new(VerificationTier::Intermediate),
             pair_rate_limiter: KeyedRateLimiter::new(),
+            metrics: Arc::new(NoopMetrics),
+            client_order_ids: None,
         }
     }
 
@@ -105,9 +137,12 @@ where
         RateLimitedKrakenClient {
             core_client: C::new_with_tracing(secrets_provider, nonce_provider, trace_inbound),
             private_rate_limiter: Self::get_private_rate_limiter(VerificationTier::Intermediate),
+            private_token_mirror: Self::get_private_token_mirror(VerificationTier::Intermediate),
             public_rate_limiter: Self::get_public_rate_limiter(),
             trading_rate_limiter: KrakenTradingRateLimiter::new(VerificationTier::Intermediate),
             pair_rate_limiter: KeyedRateLimiter::new(),
+            metrics: Arc::new(NoopMetrics),
+            client_order_ids: None,
         }
     }
 
@@ -116,14 +151,18 @@ where
     }
 
     async fn get_server_time(&mut self) -> Result<ResultErrorResponse<SystemTime>, ClientError> {
+        let waiting = Instant::now();
         self.public_rate_limiter.wait_until_ready().await;
+        self.report_wait("public", TIME_ENDPOINT, waiting);
         self.core_client.get_server_time().await
     }
 
     async fn get_system_status(
         &mut self,
     ) -> Result<ResultErrorResponse<SystemStatusInfo>, ClientError> {
+        let waiting = Instant::now();
         self.public_rate_limiter.wait_until_ready().await;
+        self.report_wait("public", STATUS_ENDPOINT, waiting);
         self.core_client.get_system_status().await
     }
 
@@ -131,7 +170,9 @@ where
         &mut self,
         request: &AssetInfoRequest,
     ) -> Result<ResultErrorResponse<HashMap<String, AssetInfo>>, ClientError> {
+        let waiting = Instant::now();
         self.public_rate_limiter.wait_until_ready().await;
+        self.report_wait("public", ASSET_INFO_ENDPOINT, waiting);
         self.core_client.get_asset_info(request).await
     }
 
@@ -139,7 +180,9 @@ where
         &mut self,
         request: &TradableAssetPairsRequest,
     ) -> Result<ResultErrorResponse<HashMap<String, TradableAssetPair>>, ClientError> {
+        let waiting = Instant::now();
         self.public_rate_limiter.wait_until_ready().await;
+        self.report_wait("public", TRADABLE_ASSET_PAIRS_ENDPOINT, waiting);
         self.core_client.get_tradable_asset_pairs(request).await
     }
 
@@ -147,7 +190,9 @@ where
         &mut self,
         request: &TickerRequest,
     ) -> Result<ResultErrorResponse<HashMap<String, RestTickerInfo>>, ClientError> {
+        let waiting = Instant::now();
         self.public_rate_limiter.wait_until_ready().await;
+        self.report_wait("public", TICKER_INFO_ENDPOINT, waiting);
         self.core_client.get_ticker_information(request).await
     }
 
@@ -155,9 +200,12 @@ where
         &mut self,
         request: &OHLCRequest,
     ) -> Result<ResultErrorResponse<OhlcResponse>, ClientError> {
+        let waiting = Instant::now();
         self.pair_rate_limiter
             .wait_until_ready(request.pair.clone())
             .await;
+        self.public_rate_limiter.wait_until_ready().await;
+        self.report_wait("public", OHLC_ENDPOINT, waiting);
         self.core_client.get_ohlc(request).await
     }
 
@@ -165,7 +213,9 @@ where
         &mut self,
         request: &OrderbookRequest,
     ) -> Result<ResultErrorResponse<HashMap<String, Orderbook>>, ClientError> {
+        let waiting = Instant::now();
         self.public_rate_limiter.wait_until_ready().await;
+        self.report_wait("public", ORDER_BOOK_ENDPOINT, waiting);
         self.core_client.get_orderbook(request).await
     }
 
@@ -173,10 +223,12 @@ where
         &mut self,
         request: &RecentTradesRequest,
     ) -> Result<ResultErrorResponse<RecentTrades>, ClientError> {
+        let waiting = Instant::now();
         self.pair_rate_limiter
             .wait_until_ready(request.pair.clone())
             .await;
         self.public_rate_limiter.wait_until_ready().await;
+        self.report_wait("public", RECENT_TRADES_ENDPOINT, waiting);
         self.core_client.get_recent_trades(request).await
     }
 
@@ -184,21 +236,24 @@ where
         &mut self,
         request: &RecentSpreadsRequest,
     ) -> Result<ResultErrorResponse<RecentSpreads>, ClientError> {
+        let waiting = Instant::now();
         self.public_rate_limiter.wait_until_ready().await;
+        self.report_wait("public", RECENT_SPREADS_ENDPOINT, waiting);
         self.core_client.get_recent_spreads(request).await
     }
 
     async fn get_account_balance(
         &mut self,
     ) -> Result<ResultErrorResponse<AccountBalances>, ClientError> {
-        self.private_rate_limit(100).await;
+        self.private_rate_limit(ACCOUNT_BALANCE_ENDPOINT).await;
         self.core_client.get_account_balance().await
     }
 
     async fn get_extended_balances(
         &mut self,
     ) -> Result<ResultErrorResponse<ExtendedBalances>, ClientError> {
-        self.private_rate_limit(100).await;
+        self.private_rate_limit(ACCOUNT_BALANCE_EXTENDED_ENDPOINT)
+            .await;
         self.core_client.get_extended_balances().await
     }
 
@@ -206,7 +261,7 @@ where
         &mut self,
         request: &TradeBalanceRequest,
     ) -> Result<ResultErrorResponse<TradeBalances>, ClientError> {
-        self.private_rate_limit(100).await;
+        self.private_rate_limit(TRADE_BALANCE_ENDPOINT).await;
         self.core_client.get_trade_balances(request).await
     }
 
@@ -214,7 +269,7 @@ where
         &mut self,
         request: &OpenOrdersRequest,
     ) -> Result<ResultErrorResponse<OpenOrders>, ClientError> {
-        self.private_rate_limit(100).await;
+        self.private_rate_limit(OPEN_ORDERS_ENDPOINT).await;
         self.core_client.get_open_orders(request).await
     }
 
@@ -222,7 +277,7 @@ where
         &mut self,
         request: &ClosedOrdersRequest,
     ) -> Result<ResultErrorResponse<ClosedOrders>, ClientError> {
-        self.private_rate_limit(200).await;
+        self.private_rate_limit(CLOSED_ORDERS_ENDPOINT).await;
         self.core_client.get_closed_orders(request).await
     }
 
@@ -230,7 +285,7 @@ where
         &mut self,
         request: &OrderRequest,
     ) -> Result<ResultErrorResponse<HashMap<String, Order>>, ClientError> {
-        self.private_rate_limit(100).await;
+        self.private_rate_limit(QUERY_ORDERS_ENDPOINT).await;
         self.core_client.query_orders_info(request).await
     }
 
@@ -238,7 +293,7 @@ where
         &mut self,
         request: &OrderAmendsRequest,
     ) -> Result<ResultErrorResponse<OrderAmends>, ClientError> {
-        self.private_rate_limiter.wait_with_cost(100).await;
+        self.private_rate_limit(ORDER_AMENDS_ENDPOINT).await;
         self.core_client.get_order_amends(request).await
     }
 
@@ -246,7 +301,7 @@ where
         &mut self,
         request: &TradesHistoryRequest,
     ) -> Result<ResultErrorResponse<TradesHistory>, ClientError> {
-        self.private_rate_limit(200).await;
+        self.private_rate_limit(TRADES_HISTORY_ENDPOINT).await;
         self.core_client.get_trades_history(request).await
     }
 
@@ -254,7 +309,7 @@ where
         &mut self,
         request: &TradeInfoRequest,
     ) -> Result<ResultErrorResponse<TradesInfo>, ClientError> {
-        self.private_rate_limit(100).await;
+        self.private_rate_limit(QUERY_TRADES_ENDPOINT).await;
         self.core_client.query_trades_info(request).await
     }
 
@@ -262,7 +317,7 @@ where
         &mut self,
         request: &OpenPositionsRequest,
     ) -> Result<ResultErrorResponse<OpenPositions>, ClientError> {
-        self.private_rate_limit(100).await;
+        self.private_rate_limit(OPEN_POSITIONS_ENDPOINT).await;
         self.core_client.get_open_positions(request).await
     }
 
@@ -270,7 +325,7 @@ where
         &mut self,
         request: &LedgersInfoRequest,
     ) -> Result<ResultErrorResponse<LedgerInfo>, ClientError> {
-        self.private_rate_limit(200).await;
+        self.private_rate_limit(LEDGERS_ENDPOINT).await;
         self.core_client.get_ledgers_info(request).await
     }
 
@@ -278,7 +333,7 @@ where
         &mut self,
         request: &QueryLedgerRequest,
     ) -> Result<ResultErrorResponse<QueryLedgerInfo>, ClientError> {
-        self.private_rate_limit(100).await;
+        self.private_rate_limit(QUERY_LEDGERS_ENDPOINT).await;
         self.core_client.query_ledgers(request).await
     }
 
@@ -286,7 +341,7 @@ where
         &mut self,
         request: &TradeVolumeRequest,
     ) -> Result<ResultErrorResponse<TradeVolume>, ClientError> {
-        self.private_rate_limit(100).await;
+        self.private_rate_limit(TRADE_VOLUME_ENDPOINT).await;
         self.core_client.get_trade_volume(request).await
     }
 
@@ -294,7 +349,7 @@ where
         &mut self,
         request: &ExportReportRequest,
     ) -> Result<ResultErrorResponse<ExportReport>, ClientError> {
-        self.private_rate_limit(100).await;
+        self.private_rate_limit(ADD_EXPORT_ENDPOINT).await;
         self.core_client.request_export_report(request).await
     }
 
@@ -302,7 +357,7 @@ where
         &mut self,
         request: &ExportReportStatusRequest,
     ) -> Result<ResultErrorResponse<Vec<ExportReportStatus>>, ClientError> {
-        self.private_rate_limit(100).await;
+        self.private_rate_limit(EXPORT_STATUS_ENDPOINT).await;
         self.core_client.get_export_report_status(request).await
     }
 
@@ -310,7 +365,7 @@ where
         &mut self,
         request: &RetrieveExportReportRequest,
     ) -> Result<Vec<u8>, ClientError> {
-        self.private_rate_limit(100).await;
+        self.private_rate_limit(RETRIEVE_EXPORT_ENDPOINT).await;
         self.core_client.retrieve_export_report(request).await
     }
 
@@ -318,7 +373,7 @@ where
         &mut self,
         request: &DeleteExportRequest,
     ) -> Result<ResultErrorResponse<DeleteExportReport>, ClientError> {
-        self.private_rate_limit(100).await;
+        self.private_rate_limit(REMOVE_EXPORT_ENDPOINT).await;
         self.core_client.delete_export_report(request).await
     }
 
@@ -326,11 +381,31 @@ where
         &mut self,
         request: &AddOrderRequest,
     ) -> Result<ResultErrorResponse<AddOrder>, ClientError> {
+        // validated orders are never placed, so they don't claim a client order id or create an
+        //  order for the trading rate limiter to track
+        if request.validate == Some(true) {
+            return self.core_client.add_order(request).await;
+        }
+
+        if let Some(client_order_id) = &request.client_order_id {
+            self.claim_client_order_id(client_order_id).await?;
+        }
+
+        let waiting = Instant::now();
         self.trading_rate_limiter.add_order().await;
+        self.report_wait("trading", ADD_ORDER_ENDPOINT, waiting);
         let response = self.core_client.add_order(request).await;
         self.notify_add_order(&response, request.user_ref, &request.client_order_id)
             .await;
 
+        if let (Some(client_order_id), Err(ClientError::Kraken(err))) =
+            (&request.client_order_id, &response)
+        {
+            if is_order_rejection(err) {
+                self.release_client_order_id(client_order_id).await;
+            }
+        }
+
         response
     }
 
@@ -338,7 +413,9 @@ where
         &mut self,
         request: &AddBatchedOrderRequest,
     ) -> Result<ResultErrorResponse<AddOrderBatch>, ClientError> {
+        let waiting = Instant::now();
         self.trading_rate_limiter.add_order_batch(request).await;
+        self.report_wait("trading", ADD_ORDER_BATCH_ENDPOINT, waiting);
         let response = self.core_client.add_order_batch(request).await;
         self.notify_add_order_batched(&response, request).await;
 
@@ -349,9 +426,11 @@ where
         &mut self,
         request: &AmendOrderRequest,
     ) -> Result<ResultErrorResponse<AmendOrder>, ClientError> {
+        let waiting = Instant::now();
         self.trading_rate_limiter
             .amend_order(&request.tx_id, &request.client_order_id)
             .await;
+        self.report_wait("trading", AMEND_ORDER_ENDPOINT, waiting);
         let response = self.core_client.amend_order(request).await;
         self.notify_amend_order(&request.tx_id, &request.client_order_id.clone())
             .await;
@@ -363,7 +442,9 @@ where
         &mut self,
         request: &EditOrderRequest,
     ) -> Result<ResultErrorResponse<OrderEdit>, ClientError> {
+        let waiting = Instant::now();
         self.trading_rate_limiter.edit_order(request).await;
+        self.report_wait("trading", EDIT_ORDER_ENDPOINT, waiting);
         let response = self.core_client.edit_order(request).await;
         self.notify_edit_order(&response, request.user_ref).await;
         response
@@ -373,6 +454,7 @@ where
         &mut self,
         request: &CancelOrderRequest,
     ) -> Result<ResultErrorResponse<CancelOrder>, ClientError> {
+        let waiting = Instant::now();
         match &request.tx_id {
             IntOrString::Int(i) => {
                 self.trading_rate_limiter.cancel_order_user_ref(i).await;
@@ -381,18 +463,40 @@ where
                 self.trading_rate_limiter.cancel_order_tx_id(s).await;
             }
         }
+        self.report_wait("trading", CANCEL_ORDER_ENDPOINT, waiting);
 
-        self.core_client.cancel_order(request).await
+        let response = self.core_client.cancel_order(request).await;
+        if is_success(&response) {
+            self.trading_rate_limiter
+                .notify_cancel_order(&request.tx_id)
+                .await;
+            if let Some(client_order_id) = &request.client_order_id {
+                self.trading_rate_limiter
+                    .notify_cancel_order(&IntOrString::String(client_order_id.clone()))
+                    .await;
+            }
+        }
+        response
     }
 
     async fn cancel_all_orders(&mut self) -> Result<ResultErrorResponse<CancelOrder>, ClientError> {
-        self.core_client.cancel_all_orders().await
+        let waiting = Instant::now();
+        self.trading_rate_limiter.cancel_all_orders().await;
+        self.report_wait("trading", CANCEL_ALL_ORDERS_ENDPOINT, waiting);
+        let response = self.core_client.cancel_all_orders().await;
+        if is_success(&response) {
+            self.trading_rate_limiter.notify_cancel_all_orders().await;
+        }
+        response
     }
 
     async fn cancel_all_orders_after(
         &mut self,
         request: &CancelAllOrdersAfterRequest,
     ) -> Result<ResultErrorResponse<CancelAllOrdersAfter>, ClientError> {
+        let waiting = Instant::now();
+        self.trading_rate_limiter.cancel_all_orders_after().await;
+        self.report_wait("trading", CANCEL_ALL_ORDERS_AFTER_ENDPOINT, waiting);
         self.core_client.cancel_all_orders_after(request).await
     }
 
@@ -402,6 +506,7 @@ where
         &mut self,
         request: &CancelBatchOrdersRequest,
     ) -> Result<ResultErrorResponse<CancelOrder>, ClientError> {
+        let waiting = Instant::now();
         for order in &request.orders {
             match order {
                 IntOrString::Int(user_ref) => {
@@ -414,15 +519,22 @@ where
                 }
             }
         }
+        self.report_wait("trading", CANCEL_ORDER_BATCH_ENDPOINT, waiting);
 
-        self.core_client.cancel_order_batch(request).await
+        let response = self.core_client.cancel_order_batch(request).await;
+        if is_success(&response) {
+            for order in &request.orders {
+                self.trading_rate_limiter.notify_cancel_order(order).await;
+            }
+        }
+        response
     }
 
     async fn get_deposit_methods(
         &mut self,
         request: &DepositMethodsRequest,
     ) -> Result<ResultErrorResponse<Vec<DepositMethod>>, ClientError> {
-        self.private_rate_limit(100).await;
+        self.private_rate_limit(DEPOSIT_METHODS_ENDPOINT).await;
         self.core_client.get_deposit_methods(request).await
     }
 
@@ -430,7 +542,7 @@ where
         &mut self,
         request: &DepositAddressesRequest,
     ) -> Result<ResultErrorResponse<Vec<DepositAddress>>, ClientError> {
-        self.private_rate_limit(100).await;
+        self.private_rate_limit(DEPOSIT_ADDRESSES_ENDPOINT).await;
         self.core_client.get_deposit_addresses(request).await
     }
 
@@ -438,7 +550,7 @@ where
         &mut self,
         request: &StatusOfDepositWithdrawRequest,
     ) -> Result<ResultErrorResponse<DepositWithdrawResponse>, ClientError> {
-        self.private_rate_limit(100).await;
+        self.private_rate_limit(DEPOSIT_STATUS_ENDPOINT).await;
         self.core_client
             .get_status_of_recent_deposits(request)
             .await
@@ -448,7 +560,7 @@ where
         &mut self,
         request: &WithdrawalMethodsRequest,
     ) -> Result<ResultErrorResponse<Vec<WithdrawMethod>>, ClientError> {
-        self.private_rate_limit(100).await;
+        self.private_rate_limit(WITHDRAW_METHODS_ENDPOINT).await;
         self.core_client.get_withdrawal_methods(request).await
     }
 
@@ -456,7 +568,7 @@ where
         &mut self,
         request: &WithdrawalAddressesRequest,
     ) -> Result<ResultErrorResponse<Vec<WithdrawalAddress>>, ClientError> {
-        self.private_rate_limit(100).await;
+        self.private_rate_limit(WITHDRAW_ADDRESSES_ENDPOINT).await;
         self.core_client.get_withdrawal_addresses(request).await
     }
 
@@ -464,7 +576,7 @@ where
         &mut self,
         request: &WithdrawalInfoRequest,
     ) -> Result<ResultErrorResponse<Withdrawal>, ClientError> {
-        self.private_rate_limit(100).await;
+        self.private_rate_limit(WITHDRAW_INFO_ENDPOINT).await;
         self.core_client.get_withdrawal_info(request).await
     }
 
@@ -472,7 +584,7 @@ where
         &mut self,
         request: &WithdrawFundsRequest,
     ) -> Result<ResultErrorResponse<ConfirmationRefId>, ClientError> {
-        self.private_rate_limit(100).await;
+        self.private_rate_limit(WITHDRAW_ENDPOINT).await;
         self.core_client.withdraw_funds(request).await
     }
 
@@ -480,7 +592,7 @@ where
         &mut self,
         request: &StatusOfDepositWithdrawRequest,
     ) -> Result<ResultErrorResponse<Vec<DepositWithdrawal>>, ClientError> {
-        self.private_rate_limit(100).await;
+        self.private_rate_limit(WITHDRAW_STATUS_ENDPOINT).await;
         self.core_client
             .get_status_of_recent_withdrawals(request)
             .await
@@ -490,7 +602,7 @@ where
         &mut self,
         request: &WithdrawCancelRequest,
     ) -> Result<ResultErrorResponse<bool>, ClientError> {
-        self.private_rate_limit(100).await;
+        self.private_rate_limit(WITHDRAW_CANCEL_ENDPOINT).await;
         self.core_client
             .request_withdrawal_cancellation(request)
             .await
@@ -500,7 +612,7 @@ where
         &mut self,
         request: &WalletTransferRequest,
     ) -> Result<ResultErrorResponse<ConfirmationRefId>, ClientError> {
-        self.private_rate_limit(100).await;
+        self.private_rate_limit(WALLET_TRANSFER_ENDPOINT).await;
         self.core_client.request_wallet_transfer(request).await
     }
 
@@ -508,7 +620,7 @@ where
         &mut self,
         request: &CreateSubAccountRequest,
     ) -> Result<ResultErrorResponse<bool>, ClientError> {
-        self.private_rate_limit(100).await;
+        self.private_rate_limit(CREATE_SUB_ACCOUNT_ENDPOINT).await;
         self.core_client.create_sub_account(request).await
     }
 
@@ -516,7 +628,7 @@ where
         &mut self,
         request: &AccountTransferRequest,
     ) -> Result<ResultErrorResponse<AccountTransfer>, ClientError> {
-        self.private_rate_limit(100).await;
+        self.private_rate_limit(ACCOUNT_TRANSFER_ENDPOINT).await;
         self.core_client.account_transfer(request).await
     }
 
@@ -524,7 +636,7 @@ where
         &mut self,
         request: &AllocateEarnFundsRequest,
     ) -> Result<ResultErrorResponse<bool>, ClientError> {
-        self.private_rate_limit(100).await;
+        self.private_rate_limit(EARN_ALLOCATE_ENDPOINT).await;
         self.core_client.allocate_earn_funds(request).await
     }
 
@@ -532,7 +644,7 @@ where
         &mut self,
         request: &AllocateEarnFundsRequest,
     ) -> Result<ResultErrorResponse<bool>, ClientError> {
-        self.private_rate_limit(100).await;
+        self.private_rate_limit(EARN_DEALLOCATE_ENDPOINT).await;
         self.core_client.deallocate_earn_funds(request).await
     }
 
@@ -540,7 +652,7 @@ where
         &mut self,
         request: &EarnAllocationStatusRequest,
     ) -> Result<ResultErrorResponse<AllocationStatus>, ClientError> {
-        self.private_rate_limit(100).await;
+        self.private_rate_limit(EARN_ALLOCATE_STATUS_ENDPOINT).await;
         self.core_client.get_earn_allocation_status(request).await
     }
 
@@ -548,7 +660,8 @@ where
         &mut self,
         request: &EarnAllocationStatusRequest,
     ) -> Result<ResultErrorResponse<AllocationStatus>, ClientError> {
-        self.private_rate_limit(100).await;
+        self.private_rate_limit(EARN_DEALLOCATE_STATUS_ENDPOINT)
+            .await;
         self.core_client.get_earn_deallocation_status(request).await
     }
 
@@ -556,7 +669,7 @@ where
         &mut self,
         request: &ListEarnStrategiesRequest,
     ) -> Result<ResultErrorResponse<EarnStrategies>, ClientError> {
-        self.private_rate_limit(100).await;
+        self.private_rate_limit(EARN_STRATEGIES_ENDPOINT).await;
         self.core_client.list_earn_strategies(request).await
     }
 
@@ -564,18 +677,37 @@ where
         &mut self,
         request: &ListEarnAllocationsRequest,
     ) -> Result<ResultErrorResponse<EarnAllocations>, ClientError> {
-        self.private_rate_limit(100).await;
+        self.private_rate_limit(EARN_ALLOCATIONS_ENDPOINT).await;
         self.core_client.list_earn_allocations(request).await
     }
 
     async fn get_websockets_token(
         &mut self,
     ) -> Result<ResultErrorResponse<WebsocketToken>, ClientError> {
-        self.private_rate_limit(100).await;
+        self.private_rate_limit(GET_WS_TOKEN_ENDPOINT).await;
         self.core_client.get_websockets_token().await
     }
 }
 
+/// Whether Kraken definitely rejected an order, as opposed to errors where it may have been placed.
+fn is_order_rejection(err: &KrakenError) -> bool {
+    match err {
+        KrakenError::InvalidArguments(_) | KrakenError::TradingRateLimitExceeded => true,
+        KrakenError::Unknown(message) => message.starts_with("EOrder:"),
+        _ => false,
+    }
+}
+
+fn is_success<T>(response: &Result<ResultErrorResponse<T>, ClientError>) -> bool {
+    matches!(
+        response,
+        Ok(ResultErrorResponse {
+            result: Some(_),
+            ..
+        })
+    )
+}
+
 impl<C> RateLimitedKrakenClient<C>
 where
     C: KrakenClient,
@@ -683,9 +815,12 @@ where
         RateLimitedKrakenClient {
             core_client: client,
             private_rate_limiter: Self::get_private_rate_limiter(verification),
+            private_token_mirror: Self::get_private_token_mirror(verification),
             public_rate_limiter: Self::get_public_rate_limiter(),
             trading_rate_limiter: KrakenTradingRateLimiter::new(verification),
             pair_rate_limiter: KeyedRateLimiter::new(),
+            metrics: Arc::new(NoopMetrics),
+            client_order_ids: None,
         }
     }
 
@@ -698,9 +833,12 @@ where
         RateLimitedKrakenClient {
             core_client: C::new(secrets_provider, nonce_provider),
             private_rate_limiter: Self::get_private_rate_limiter(verification),
+            private_token_mirror: Self::get_private_token_mirror(verification),
             public_rate_limiter: Self::get_public_rate_limiter(),
             trading_rate_limiter: KrakenTradingRateLimiter::new(verification),
             pair_rate_limiter: KeyedRateLimiter::new(),
+            metrics: Arc::new(NoopMetrics),
+            client_order_ids: None,
         }
     }
 
@@ -714,26 +852,58 @@ where
         RateLimitedKrakenClient {
             core_client: C::new_with_url(secrets_provider, nonce_provider, url),
             private_rate_limiter: Self::get_private_rate_limiter(verification),
+            private_token_mirror: Self::get_private_token_mirror(verification),
             public_rate_limiter: Self::get_public_rate_limiter(),
             trading_rate_limiter: KrakenTradingRateLimiter::new(verification),
             pair_rate_limiter: KeyedRateLimiter::new(),
+            metrics: Arc::new(NoopMetrics),
+            client_order_ids: None,
         }
     }
 
+    /// Report the time each request waits on the rate limiters to `metrics`, see [Metrics].
+    ///
+    /// Requests, responses and errors are reported by the wrapped client, e.g. with
+    /// [CoreKrakenClient::with_metrics](crate::clients::core_kraken_client::CoreKrakenClient::with_metrics).
+    pub fn with_metrics(mut self, metrics: Arc<dyn Metrics>) -> Self {
+        self.metrics = metrics;
+        self
+    }
+
+    /// The wrapped client, e.g. to read its configuration.
+    pub fn inner(&self) -> &C {
+        &self.core_client
+    }
+
+    /// Mutable access to the wrapped client. Calls made through it bypass all rate limiting and
+    /// are not counted against the limits of this client.
+    pub fn inner_mut(&mut self) -> &mut C {
+        &mut self.core_client
+    }
+
     /// Get a private endpoint rate limiter, depending on the user's verification level.
     ///
     /// This implements a more involved scheme.
     pub fn get_private_rate_limiter(user_verification: VerificationTier) -> TokenBucketRateLimiter {
+        let (max_tokens, replace_amount) = Self::private_token_bucket_size(user_verification);
+        let token_bucket_state =
+            TokenBucketState::new(max_tokens, replace_amount, Duration::from_secs(1));
+        TokenBucketRateLimiter::new(Arc::new(Mutex::new(token_bucket_state)))
+    }
+
+    /// Get a mirror of the private endpoint rate limiter's token bucket, for introspection.
+    pub fn get_private_token_mirror(user_verification: VerificationTier) -> TokenBucketMirror {
+        let (max_tokens, replace_amount) = Self::private_token_bucket_size(user_verification);
+        TokenBucketMirror::new(max_tokens, replace_amount, Duration::from_secs(1))
+    }
+
+    /// Max tokens and tokens replaced per second for private endpoints.
+    fn private_token_bucket_size(user_verification: VerificationTier) -> (usize, usize) {
         // tokens are scaled 100x from Kraken's floating-point method to keep as integers
         match user_verification {
-            VerificationTier::Intermediate => {
-                let token_bucket_state = TokenBucketState::new(2000, 50, Duration::from_secs(1));
-                TokenBucketRateLimiter::new(Arc::new(Mutex::new(token_bucket_state)))
-            }
-            VerificationTier::Pro => {
-                let token_bucket_state = TokenBucketState::new(2000, 100, Duration::from_secs(1));
-                TokenBucketRateLimiter::new(Arc::new(Mutex::new(token_bucket_state)))
-            }
+            VerificationTier::Starter => (1500, 33),
+            VerificationTier::Intermediate => (2000, 50),
+            VerificationTier::Pro => (2000, 100),
         }
     }
 
@@ -742,48 +912,164 @@ where
         SlidingWindowRateLimiter::new(Duration::from_secs(1), 1)
     }
 
-    async fn private_rate_limit(&mut self, cost: usize) {
-        self.private_rate_limiter.wait_with_cost(cost).await
+    /// The number of private endpoint tokens currently available, scaled 100x like call costs.
+    ///
+    /// e.g. a newly created client has 2000 tokens, and most private calls cost 100.
+    pub fn available_private_tokens(&self) -> usize {
+        self.private_token_mirror.available_tokens()
+    }
+
+    /// The time until a private call of `cost` tokens could proceed without waiting.
+    ///
+    /// This is zero if the call can be made immediately, or [Duration::MAX] if the cost exceeds the
+    /// maximum tokens available to the verification tier.
+    pub fn time_until_ready_for(&self, cost: usize) -> Duration {
+        self.private_token_mirror.time_until_ready_for(cost)
+    }
+
+    /// The time until a call to the private `endpoint` could proceed without waiting, using its
+    /// cost from [private_endpoint_cost].
+    pub fn time_until_ready_for_endpoint(&self, endpoint: &str) -> Duration {
+        self.time_until_ready_for(private_endpoint_cost(endpoint))
+    }
+
+    /// Set the (scaled) trading rate limit cost of `cancel_all_orders` and
+    /// `cancel_all_orders_after`, which defaults to 100, the same as a penalty-free cancel.
+    ///
+    /// This applies only to this client, not to clones made before calling it.
+    pub fn set_cancel_all_orders_cost(&mut self, cost: usize) {
+        self.trading_rate_limiter.set_cancel_all_cost(cost);
+    }
+
+    /// Set the [TradingPenalties] for amending, editing and cancelling orders, which default to
+    /// Kraken's documented schedule.
+    ///
+    /// This applies only to this client, not to clones made before calling it.
+    pub fn set_trading_penalties(&mut self, penalties: TradingPenalties) {
+        self.trading_rate_limiter.set_penalties(penalties);
+    }
+
+    /// The penalties that acting on the order with the given tx id, client order id or user ref
+    /// would currently incur, see [KrakenTradingRateLimiter::order_penalties].
+    pub async fn order_penalties(&self, id: &IntOrString) -> OrderPenalties {
+        self.trading_rate_limiter.order_penalties(id).await
+    }
+
+    /// Refuse to send an `add_order` request whose client order id was already sent by this client
+    /// (or its clones made after calling this) within `window`, returning
+    /// [ClientError::DuplicateClientOrderId] instead.
+    ///
+    /// This makes it safe to retry `add_order` after an error like [ClientError::Timeout], where the
+    /// order may or may not have been placed. Ids of orders Kraken definitely rejected (an `EOrder`
+    /// error or invalid arguments) can be sent again immediately, since no order was created, while
+    /// other errors like `EService:Unavailable` keep the id claimed. Requests without a client
+    /// order id are never deduplicated, and by default no requests are.
+    pub fn set_client_order_id_dedup(&mut self, window: Duration) {
+        self.client_order_ids = Some(ClientOrderIdDedup {
+            window_us: window.as_micros() as i128,
+            sent: Default::default(),
+        });
+    }
+
+    /// Notify the trading rate limiter that the order with the given tx id, client order id or user
+    /// ref was closed, e.g. when a fill is seen on the executions channel, so it stops tracking
+    /// its lifetime.
+    ///
+    /// Orders cancelled through this client are removed automatically.
+    pub async fn notify_order_closed(&mut self, id: &IntOrString) {
+        self.trading_rate_limiter.notify_cancel_order(id).await;
+    }
+
+    async fn claim_client_order_id(&mut self, client_order_id: &str) -> Result<(), ClientError> {
+        if let Some(dedup) = &self.client_order_ids {
+            let mut sent = dedup.sent.lock().await;
+            if sent.contains(&client_order_id.to_string()) {
+                return Err(ClientError::DuplicateClientOrderId(
+                    client_order_id.to_string(),
+                ));
+            }
+            sent.insert(TtlEntry::new(
+                client_order_id.to_string(),
+                dedup.window_us,
+                (),
+            ));
+        }
+
+        Ok(())
+    }
+
+    async fn release_client_order_id(&mut self, client_order_id: &str) {
+        if let Some(dedup) = &self.client_order_ids {
+            dedup
+                .sent
+                .lock()
+                .await
+                .remove_id(&client_order_id.to_string());
+        }
+    }
+
+    async fn private_rate_limit(&mut self, endpoint: &str) {
+        let cost = private_endpoint_cost(endpoint);
+        let waiting = Instant::now();
+        self.private_rate_limiter.wait_with_cost(cost).await;
+        self.report_wait("private", endpoint, waiting);
+        self.private_token_mirror.record(cost);
+    }
+
+    fn report_wait(&self, limiter: &str, endpoint: &str, waiting: Instant) {
+        let waited = waiting.elapsed();
+        if waited > WAIT_LOG_THRESHOLD {
+            debug!(limiter, endpoint, ?waited, "waited for rate limit");
+        }
+        self.metrics.rate_limit_wait(waited);
     }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::clients::core_kraken_client::CoreKrakenClient;
-    use crate::clients::kraken_client::endpoints::KRAKEN_BASE_URL;
+    use crate::clients::errors::{ClientError, KrakenError};
+    use crate::clients::kraken_client::endpoints::{
+        ACCOUNT_BALANCE_ENDPOINT, CLOSED_ORDERS_ENDPOINT, KRAKEN_BASE_URL,
+    };
     use crate::clients::kraken_client::KrakenClient;
     use crate::clients::rate_limited_kraken_client::RateLimitedKrakenClient;
     use crate::crypto::nonce_provider::{IncreasingNonceProvider, NonceProvider};
+    use crate::rate_limiting::trading_rate_limits::{PenaltySchedule, TradingPenalties};
     use crate::request_types::{
         AccountTransferRequest, AddBatchedOrderRequest, AddOrderRequest, AllocateEarnFundsRequest,
-        AmendOrderRequest, AssetInfoRequestBuilder, BatchedOrderRequest, CancelBatchOrdersRequest,
-        CancelOrderRequest, CandlestickInterval, ClosedOrdersRequestBuilder,
-        CreateSubAccountRequest, DeleteExportRequest, DeleteExportType, DepositAddressesRequest,
-        DepositMethodsRequest, EarnAllocationStatusRequest, EditOrderRequest, ExportReportRequest,
+        AmendOrderRequest, AssetInfoRequestBuilder, BatchedOrderRequest,
+        CancelAllOrdersAfterRequest, CancelBatchOrdersRequest, CancelOrderRequest,
+        CandlestickInterval, ClosedOrdersRequestBuilder, CreateSubAccountRequest,
+        DeleteExportRequest, DeleteExportType, DepositAddressesRequest, DepositMethodsRequest,
+        EarnAllocationStatusRequest, EditOrderRequest, ExportReportRequest,
         ExportReportStatusRequest, IntOrString, LedgersInfoRequest, ListEarnAllocationsRequest,
         ListEarnStrategiesRequest, OHLCRequest, OpenOrdersRequest, OpenPositionsRequest,
-        OrderFlags, OrderRequest, OrderbookRequest, QueryLedgerRequest, RecentSpreadsRequest,
-        RecentTradesRequest, ReportFormatType, ReportType, RetrieveExportReportRequest,
-        StatusOfDepositWithdrawRequest, StringCSV, TickerRequest, TradableAssetPairsRequest,
-        TradeBalanceRequest, TradeInfoRequest, TradeVolumeRequest, TradesHistoryRequest,
-        WalletTransferRequest, WithdrawCancelRequest, WithdrawFundsRequest,
+        OrderAmendsRequest, OrderFlags, OrderRequest, OrderbookRequest, QueryLedgerRequest,
+        RecentSpreadsRequest, RecentTradesRequest, ReportFormatType, ReportType,
+        RetrieveExportReportRequest, StatusOfDepositWithdrawRequest, StringCSV, TickerRequest,
+        TradableAssetPairsRequest, TradeBalanceRequest, TradeInfoRequest, TradeVolumeRequest,
+        TradesHistoryRequest, WalletTransferRequest, WithdrawCancelRequest, WithdrawFundsRequest,
         WithdrawalAddressesRequest, WithdrawalInfoRequest, WithdrawalMethodsRequest,
     };
-    use crate::response_types::VerificationTier::{Intermediate, Pro};
+    use crate::response_types::VerificationTier::{Intermediate, Pro, Starter};
     use crate::response_types::{AddOrder, BuySell, OrderFlag, OrderType, VerificationTier};
     use crate::secrets::secrets_provider::StaticSecretsProvider;
     use crate::test_data::public_response_json::get_server_time_json;
-    use crate::test_data::TestRateLimitedClient;
+    use crate::test_data::trading_response_json::get_add_order_json;
     use crate::test_data::{
         get_null_secrets_provider, get_rate_limit_test_client, get_rate_limit_test_client_err,
     };
+    use crate::test_data::{RecordingMetrics, TestRateLimitedClient};
     use crate::test_rate_limited_endpoint;
     use rust_decimal_macros::dec;
+    use std::sync::atomic::{AtomicUsize, Ordering};
     use std::sync::Arc;
     use std::time::Duration;
     use tokio::sync::Mutex;
     use tokio::time::pause;
     use tokio::time::Instant;
+    use tracing_test::traced_test;
     use wiremock::matchers::{header, method, path};
     use wiremock::{Mock, MockServer, ResponseTemplate};
 
@@ -838,6 +1124,50 @@ mod tests {
         mock_server.verify().await;
     }
 
+    #[tokio::test]
+    async fn test_inner_client() {
+        let mock_server = MockServer::start().await;
+        let secrets_provider = get_null_secrets_provider();
+        let nonce_provider: Box<Arc<Mutex<dyn NonceProvider>>> =
+            Box::new(Arc::new(Mutex::new(IncreasingNonceProvider::new())));
+        let mut client: RateLimitedKrakenClient<CoreKrakenClient> =
+            RateLimitedKrakenClient::new_with_verification_tier_and_url(
+                secrets_provider,
+                nonce_provider,
+                mock_server.uri(),
+                Intermediate,
+            );
+
+        assert_eq!(mock_server.uri(), client.inner().api_url);
+
+        Mock::given(method("GET"))
+            .and(path("/0/public/Time"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(get_server_time_json()))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        assert!(client.inner_mut().get_server_time().await.is_ok());
+        mock_server.verify().await;
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_metrics() {
+        pause();
+        let metrics = Arc::new(RecordingMetrics::default());
+        let mut client = get_rate_limit_test_client(Intermediate).with_metrics(metrics.clone());
+
+        for _ in 0..3 {
+            let _ = client.get_server_time().await;
+        }
+
+        let waits = metrics.waits.lock().unwrap().clone();
+        assert_eq!(3, waits.len());
+        assert!(waits[0] < Duration::from_millis(100));
+        assert!(waits[1] >= Duration::from_millis(900));
+        assert!(waits[2] >= Duration::from_millis(900));
+    }
+
     #[tokio::test]
     async fn test_system_public_endpoints() {
         pause();
@@ -948,7 +1278,7 @@ mod tests {
 
         let start = Instant::now();
 
-        // calling both in parallel should be fine, since they request different pairs
+        // both share the public rate limit, even though they request different pairs
         for _ in 0..n_calls {
             let _ = client.get_ohlc(&ohlc_request).await;
             let _ = client.get_recent_trades(&trades_request).await;
@@ -959,8 +1289,59 @@ mod tests {
 
         println!("{:?}", elapsed);
 
-        assert!(elapsed > Duration::from_secs(n_calls - 1));
-        assert!(elapsed < Duration::from_secs(n_calls));
+        assert!(elapsed > Duration::from_secs(2 * n_calls - 1));
+        assert!(elapsed < Duration::from_secs(2 * n_calls));
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn test_rate_limit_waits_are_logged() {
+        pause();
+        let mut client = get_rate_limit_test_client(Intermediate);
+
+        let _ = client.get_server_time().await;
+        assert!(!logs_contain("waited for rate limit"));
+
+        let _ = client.get_server_time().await;
+        assert!(logs_contain("waited for rate limit"));
+        assert!(logs_contain("limiter=\"public\""));
+        assert!(logs_contain("/0/public/Time"));
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn test_rate_limit_waits_not_logged_when_not_blocked() {
+        let mut client = get_rate_limit_test_client(Intermediate);
+
+        // in real time, acquiring an available token takes a nonzero but negligible time
+        let _ = client.get_server_time().await;
+        let _ = client.get_account_balance().await;
+        assert!(!logs_contain("waited for rate limit"));
+    }
+
+    #[tokio::test]
+    async fn test_get_ohlc_and_recent_trades_share_public_limit() {
+        pause();
+        let pairs = ["XXBTZUSD", "XETHZUSD", "USDCUSD"];
+
+        let mut client = get_rate_limit_test_client(Intermediate);
+
+        let start = Instant::now();
+
+        // unique pairs don't wait on the per-pair limit, but every call waits on the public limit
+        for pair in pairs {
+            let ohlc_request = OHLCRequest::builder(pair.to_string()).build();
+            let trades_request = RecentTradesRequest::builder(pair.to_string()).build();
+
+            let _ = client.get_ohlc(&ohlc_request).await;
+            let _ = client.get_recent_trades(&trades_request).await;
+        }
+        let _ = client.get_server_time().await;
+
+        let elapsed = Instant::now() - start;
+
+        assert!(elapsed > Duration::from_secs(6));
+        assert!(elapsed < Duration::from_secs(7));
     }
 
     #[tokio::test]
@@ -1030,6 +1411,89 @@ mod tests {
         test_rate_limited_endpoint!(get_account_balance, 22, 4, 5, Intermediate);
     }
 
+    #[tokio::test]
+    async fn test_get_account_balance_starter() {
+        pause();
+
+        // 16 calls costs 1600, requiring 4s to replenish 100 @ 33/s
+        test_rate_limited_endpoint!(get_account_balance, 16, 3, 5, Starter);
+    }
+
+    #[tokio::test]
+    async fn test_starter_private_limits_are_stricter() {
+        let starter = get_rate_limit_test_client(Starter);
+        let intermediate = get_rate_limit_test_client(Intermediate);
+
+        assert!(starter.available_private_tokens() < intermediate.available_private_tokens());
+    }
+
+    #[tokio::test]
+    async fn test_private_token_introspection() {
+        pause();
+
+        let mut client: TestRateLimitedClient = RateLimitedKrakenClient::new_with_verification_tier(
+            get_null_secrets_provider(),
+            Box::new(Arc::new(Mutex::new(IncreasingNonceProvider::new()))),
+            Intermediate,
+        );
+
+        assert_eq!(2000, client.available_private_tokens());
+        assert_eq!(Duration::ZERO, client.time_until_ready_for(100));
+
+        for _ in 0..20 {
+            let _ = client.get_account_balance().await;
+        }
+
+        assert_eq!(0, client.available_private_tokens());
+        // 100 tokens requires 2s to replenish @ 50/s
+        assert_eq!(Duration::from_secs(2), client.time_until_ready_for(100));
+        assert_eq!(Duration::MAX, client.time_until_ready_for(2100));
+
+        tokio::time::advance(Duration::from_secs(2)).await;
+
+        assert_eq!(100, client.available_private_tokens());
+        assert_eq!(Duration::ZERO, client.time_until_ready_for(100));
+    }
+
+    #[tokio::test]
+    async fn test_private_endpoint_costs_are_recorded() {
+        pause();
+
+        let mut client = get_rate_limit_test_client(Intermediate);
+
+        let _ = client.get_account_balance().await;
+        assert_eq!(1900, client.available_private_tokens());
+
+        let _ = client
+            .get_closed_orders(&ClosedOrdersRequestBuilder::new().build())
+            .await;
+        assert_eq!(1700, client.available_private_tokens());
+
+        let _ = client
+            .get_order_amends(&OrderAmendsRequest::builder("OQCLML-BW3P3-BUCMWZ".into()).build())
+            .await;
+        assert_eq!(1600, client.available_private_tokens());
+
+        let _ = client
+            .get_ledgers_info(&LedgersInfoRequest::builder().build())
+            .await;
+        assert_eq!(1400, client.available_private_tokens());
+
+        for _ in 0..14 {
+            let _ = client.get_account_balance().await;
+        }
+
+        // 100 tokens requires 2s to replenish @ 50/s, and 200 requires 4s
+        assert_eq!(
+            Duration::from_secs(2),
+            client.time_until_ready_for_endpoint(ACCOUNT_BALANCE_ENDPOINT)
+        );
+        assert_eq!(
+            Duration::from_secs(4),
+            client.time_until_ready_for_endpoint(CLOSED_ORDERS_ENDPOINT)
+        );
+    }
+
     #[tokio::test]
     async fn test_get_extended_balance() {
         pause();
@@ -1234,6 +1698,28 @@ mod tests {
         assert!(elapsed < Duration::from_secs(5));
     }
 
+    #[tokio::test]
+    async fn test_dry_run_does_not_limit_orders() {
+        pause();
+        let core_client =
+            CoreKrakenClient::new_offline(|_endpoint, _body| get_add_order_json().to_string());
+        let mut client = RateLimitedKrakenClient::new_with_client(core_client, Pro);
+        client.set_client_order_id_dedup(Duration::from_secs(60));
+
+        let mut request = get_add_order_request();
+        request.client_order_id = Some("order-1".to_string());
+
+        let start = Instant::now();
+
+        // more dry runs than the 180 orders that exhaust the trading limit, then the real order
+        for _ in 0..200 {
+            assert!(client.dry_run(&request).await.is_ok());
+        }
+        assert!(client.add_order(&request).await.is_ok());
+
+        assert!(Instant::now() - start < Duration::from_millis(1));
+    }
+
     #[tokio::test]
     async fn test_amend_order_max_penalty() {
         pause();
@@ -1257,7 +1743,7 @@ mod tests {
         assert!(amend_elapsed < Duration::from_secs(8));
     }
 
-    fn get_amend_for_order(orders: &Vec<AddOrder>, i: usize) -> AmendOrderRequest {
+    fn get_amend_for_order(orders: &[AddOrder], i: usize) -> AmendOrderRequest {
         AmendOrderRequest::builder()
             .tx_id(orders.get(i).unwrap().tx_id.first().unwrap().clone()) // TODO: cleanup
             .build()
@@ -1403,6 +1889,209 @@ mod tests {
         assert!(edit_elapsed < Duration::from_secs(19));
     }
 
+    #[tokio::test]
+    async fn test_order_penalties() {
+        let mut client = get_rate_limit_test_client(Intermediate);
+
+        let order = client
+            .add_order(&get_add_order_request())
+            .await
+            .unwrap()
+            .result
+            .unwrap();
+        let tx_id = &order.tx_id[0];
+
+        let penalties = client.order_penalties(&tx_id.as_str().into()).await;
+        assert!(penalties.lifetime.is_some());
+        assert_eq!(8, penalties.cancel);
+
+        client.set_trading_penalties(TradingPenalties {
+            cancel: PenaltySchedule::new(vec![]),
+            ..TradingPenalties::default()
+        });
+        assert_eq!(
+            0,
+            client.order_penalties(&tx_id.as_str().into()).await.cancel
+        );
+    }
+
+    #[tokio::test]
+    async fn test_client_order_id_dedup() {
+        let core_client =
+            CoreKrakenClient::new_offline(|_endpoint, _body| get_add_order_json().to_string());
+        let mut client = RateLimitedKrakenClient::new_with_client(core_client, Intermediate);
+
+        let mut request = get_add_order_request();
+        request.client_order_id = Some("order-1".to_string());
+
+        // ids aren't deduplicated by default
+        assert!(client.add_order(&request).await.is_ok());
+        assert!(client.add_order(&request).await.is_ok());
+
+        client.set_client_order_id_dedup(Duration::from_secs(60));
+        let mut clone = client.clone();
+
+        assert!(client.add_order(&request).await.is_ok());
+        let err = clone.add_order(&request).await.unwrap_err();
+        assert!(matches!(err, ClientError::DuplicateClientOrderId(id) if id == "order-1"));
+
+        request.client_order_id = Some("order-2".to_string());
+        assert!(clone.add_order(&request).await.is_ok());
+
+        request.client_order_id = None;
+        assert!(client.add_order(&request).await.is_ok());
+        assert!(client.add_order(&request).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_client_order_id_dedup_ignores_validated_orders() {
+        let core_client =
+            CoreKrakenClient::new_offline(|_endpoint, _body| get_add_order_json().to_string());
+        let mut client = RateLimitedKrakenClient::new_with_client(core_client, Intermediate);
+        client.set_client_order_id_dedup(Duration::from_secs(60));
+
+        let mut request = get_add_order_request();
+        request.client_order_id = Some("order-1".to_string());
+
+        assert!(client.dry_run(&request).await.is_ok());
+        assert!(client.add_order(&request).await.is_ok());
+        assert!(matches!(
+            client.add_order(&request).await,
+            Err(ClientError::DuplicateClientOrderId(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_client_order_id_dedup_allows_retry_after_kraken_error() {
+        let attempts = Arc::new(AtomicUsize::new(0));
+        let counter = attempts.clone();
+        let core_client = CoreKrakenClient::new_offline(move |_endpoint, _body| {
+            match counter.fetch_add(1, Ordering::Relaxed) {
+                0 => r#"{"error":["EOrder:Insufficient funds"]}"#.to_string(),
+                _ => get_add_order_json().to_string(),
+            }
+        });
+        let mut client = RateLimitedKrakenClient::new_with_client(core_client, Intermediate);
+        client.set_client_order_id_dedup(Duration::from_secs(60));
+
+        let mut request = get_add_order_request();
+        request.client_order_id = Some("order-1".to_string());
+
+        let err = client.add_order(&request).await.unwrap_err();
+        assert!(matches!(err, ClientError::Kraken(_)));
+        assert!(client.add_order(&request).await.is_ok());
+        assert!(matches!(
+            client.add_order(&request).await,
+            Err(ClientError::DuplicateClientOrderId(_))
+        ));
+        assert_eq!(2, attempts.load(Ordering::Relaxed));
+    }
+
+    #[tokio::test]
+    async fn test_client_order_id_dedup_keeps_id_after_service_error() {
+        let core_client = CoreKrakenClient::new_offline(|_endpoint, _body| {
+            r#"{"error":["EService:Unavailable"]}"#.to_string()
+        });
+        let mut client = RateLimitedKrakenClient::new_with_client(core_client, Intermediate);
+        client.set_client_order_id_dedup(Duration::from_secs(60));
+
+        let mut request = get_add_order_request();
+        request.client_order_id = Some("order-1".to_string());
+
+        // the order may have been placed, so retrying the same id is refused
+        let err = client.add_order(&request).await.unwrap_err();
+        assert!(matches!(
+            err,
+            ClientError::Kraken(KrakenError::ServiceUnavailable)
+        ));
+        assert!(matches!(
+            client.add_order(&request).await,
+            Err(ClientError::DuplicateClientOrderId(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_client_order_id_dedup_expires() {
+        let mut client = get_rate_limit_test_client(Intermediate);
+        client.set_client_order_id_dedup(Duration::from_millis(10));
+
+        let mut request = get_add_order_request();
+        request.client_order_id = Some("order-1".to_string());
+
+        assert!(client.add_order(&request).await.is_ok());
+        assert!(client.add_order(&request).await.is_err());
+
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(client.add_order(&request).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_cancel_order_stops_tracking_lifetime() {
+        let mut client = get_rate_limit_test_client(Intermediate);
+
+        let order = client
+            .add_order(&get_add_order_request())
+            .await
+            .unwrap()
+            .result
+            .unwrap();
+        let tx_id = order.tx_id[0].clone();
+        assert!(client
+            .order_penalties(&tx_id.clone().into())
+            .await
+            .lifetime
+            .is_some());
+
+        let request = CancelOrderRequest::builder(IntOrString::String(tx_id.clone())).build();
+        client.cancel_order(&request).await.unwrap();
+
+        let penalties = client.order_penalties(&tx_id.into()).await;
+        assert_eq!(None, penalties.lifetime);
+        assert_eq!(0, penalties.cancel);
+    }
+
+    #[tokio::test]
+    async fn test_cancel_all_orders() {
+        pause();
+        let verification = Intermediate;
+        let mut client = get_rate_limit_test_client(verification);
+
+        max_out_rate_limits(&mut client, verification).await;
+
+        let start = Instant::now();
+
+        // 300 tokens requires two replenishments @ 234/s
+        for _ in 0..3 {
+            let _ = client.cancel_all_orders().await;
+        }
+
+        let elapsed = Instant::now() - start;
+
+        assert!(elapsed > Duration::from_secs(2));
+        assert!(elapsed < Duration::from_secs(3));
+    }
+
+    #[tokio::test]
+    async fn test_cancel_all_orders_after() {
+        pause();
+        let verification = Pro;
+        let mut client = get_rate_limit_test_client(verification);
+        client.set_cancel_all_orders_cost(750);
+
+        max_out_rate_limits(&mut client, verification).await;
+
+        let start = Instant::now();
+        let request = CancelAllOrdersAfterRequest::builder(30).build();
+
+        // 750 tokens requires two replenishments @ 375/s
+        let _ = client.cancel_all_orders_after(&request).await;
+
+        let elapsed = Instant::now() - start;
+
+        assert!(elapsed > Duration::from_secs(1));
+        assert!(elapsed < Duration::from_secs(3));
+    }
+
     /// Depending on the verification tier, submit enough orders to empty the rate limit bucket and
     /// return the created orders. Also checks that it has not exceeded the limits (executes in < 10ms).
     async fn max_out_rate_limits(
@@ -1414,6 +2103,7 @@ mod tests {
         let request = get_add_order_request();
 
         let n_orders = match verification_tier {
+            Starter => 60,
             Intermediate => 125,
             Pro => 180,
         };
@@ -0,0 +1,151 @@
+//! Caching and refreshing of websocket tokens
+use crate::clients::errors::ClientError;
+use crate::clients::kraken_client::KrakenClient;
+use crate::crypto::secrets::Token;
+use crate::response_types::WebsocketToken;
+use std::time::Duration;
+
+/// The default time before a token's expiry at which it will be refreshed.
+pub const DEFAULT_REFRESH_MARGIN: Duration = Duration::from_secs(60);
+
+/// Holds the most recent [WebsocketToken] for a [KrakenClient], requesting a new one when the
+/// current token is expired or within the refresh margin of expiring.
+///
+/// Use [TokenManager::token] whenever a token is needed to connect or (re)subscribe to private
+/// channels, so long-running processes never use a stale token.
+#[derive(Debug)]
+pub struct TokenManager<C>
+where
+    C: KrakenClient,
+{
+    client: C,
+    refresh_margin: Duration,
+    token: Option<WebsocketToken>,
+}
+
+impl<C> TokenManager<C>
+where
+    C: KrakenClient,
+{
+    /// Create a manager that refreshes tokens [DEFAULT_REFRESH_MARGIN] before they expire.
+    pub fn new(client: C) -> Self {
+        Self::new_with_refresh_margin(client, DEFAULT_REFRESH_MARGIN)
+    }
+
+    /// Create a manager that refreshes tokens `refresh_margin` before they expire.
+    pub fn new_with_refresh_margin(client: C, refresh_margin: Duration) -> Self {
+        TokenManager {
+            client,
+            refresh_margin,
+            token: None,
+        }
+    }
+
+    /// Get a valid token, requesting a new one if there is none or the current one is near expiry.
+    pub async fn token(&mut self) -> Result<Token, ClientError> {
+        match &self.token {
+            Some(token) if !token.expires_within(self.refresh_margin) => Ok(token.token.clone()),
+            _ => self.refresh().await,
+        }
+    }
+
+    /// Request a new token regardless of the current token's expiry.
+    pub async fn refresh(&mut self) -> Result<Token, ClientError> {
+        let token = self
+            .client
+            .get_websockets_token()
+            .await?
+            .result
+            .ok_or(ClientError::Parse("Missing result for websockets token"))?;
+
+        let value = token.token.clone();
+        self.token = Some(token);
+
+        Ok(value)
+    }
+
+    /// The currently held token, if any, without refreshing it.
+    pub fn current(&self) -> Option<&WebsocketToken> {
+        self.token.as_ref()
+    }
+
+    /// Mutable access to the underlying client, for making other requests.
+    pub fn client_mut(&mut self) -> &mut C {
+        &mut self.client
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clients::core_kraken_client::CoreKrakenClient;
+    use crate::crypto::nonce_provider::{IncreasingNonceProvider, NonceProvider};
+    use crate::test_data::get_null_secrets_provider;
+    use crate::test_data::websockets_json::get_websockets_token_json;
+    use std::sync::Arc;
+    use tokio::sync::Mutex;
+    use tokio::time::{advance, pause};
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    async fn get_manager(mock_server: &MockServer) -> TokenManager<CoreKrakenClient> {
+        let nonce_provider: Box<Arc<Mutex<dyn NonceProvider>>> =
+            Box::new(Arc::new(Mutex::new(IncreasingNonceProvider::new())));
+        let client = CoreKrakenClient::new_with_url(
+            get_null_secrets_provider(),
+            nonce_provider,
+            mock_server.uri(),
+        );
+
+        TokenManager::new(client)
+    }
+
+    #[tokio::test]
+    async fn test_token_is_cached_until_near_expiry() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/0/private/GetWebSocketsToken"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(get_websockets_token_json()))
+            .expect(2)
+            .mount(&mock_server)
+            .await;
+
+        let mut manager = get_manager(&mock_server).await;
+        assert!(manager.current().is_none());
+
+        pause();
+        manager.token().await.unwrap();
+        manager.token().await.unwrap();
+
+        advance(Duration::from_secs(839)).await;
+        manager.token().await.unwrap();
+
+        advance(Duration::from_secs(2)).await;
+        manager.token().await.unwrap();
+
+        mock_server.verify().await;
+        assert!(manager.current().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_token_error() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/0/private/GetWebSocketsToken"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_string(r#"{"error":["EAPI:Invalid key"]}"#),
+            )
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let mut manager = get_manager(&mock_server).await;
+
+        let resp = manager.token().await;
+        assert!(matches!(resp, Err(ClientError::Kraken(_))));
+        assert!(manager.current().is_none());
+        mock_server.verify().await;
+    }
+}
@@ -8,6 +8,11 @@ use std::time::{SystemTime, UNIX_EPOCH};
 /// [KrakenClient].
 pub trait NonceProvider: Send + Sync + Debug {
     fn get_nonce(&mut self) -> u64;
+
+    /// Ensure every nonce returned after this call is greater than `floor`, e.g. the last nonce
+    /// used before a restart, to avoid `EAPI:Invalid nonce` errors. Lower floors have no effect, so
+    /// a provider is only ever moved forward.
+    fn set_floor(&mut self, floor: u64);
 }
 
 /// A nonce generator that gives the current epoch in milliseconds, except when called in the same
@@ -32,11 +37,19 @@ impl IncreasingNonceProvider {
     pub fn new() -> Self {
         IncreasingNonceProvider { last: 0 }
     }
+
+    /// Create a provider whose nonces are all greater than `floor`, see [NonceProvider::set_floor].
+    pub fn with_floor(floor: u64) -> Self {
+        IncreasingNonceProvider { last: floor }
+    }
 }
 
 impl NonceProvider for IncreasingNonceProvider {
     /// Returns the current time in milliseconds, or the last nonce + 1 if a duplicate would be
     /// generated.
+    ///
+    /// Nonces saturate at `u64::MAX` rather than overflowing, which only a floor set near it can
+    /// reach, and from then on every nonce is `u64::MAX`.
     fn get_nonce(&mut self) -> u64 {
         let nonce = SystemTime::now()
             .duration_since(UNIX_EPOCH)
@@ -44,13 +57,17 @@ impl NonceProvider for IncreasingNonceProvider {
             .as_millis() as u64;
 
         if nonce <= self.last {
-            self.last += 1;
+            self.last = self.last.saturating_add(1);
         } else {
             self.last = nonce;
         }
 
         self.last
     }
+
+    fn set_floor(&mut self, floor: u64) {
+        self.last = self.last.max(floor);
+    }
 }
 
 #[cfg(test)]
@@ -69,4 +86,21 @@ mod tests {
             last = nonce;
         }
     }
+
+    #[test]
+    fn test_increasing_nonce_provider_floor() {
+        let floor = u64::MAX - 10;
+        let mut provider = IncreasingNonceProvider::with_floor(floor);
+        assert_eq!(floor + 1, provider.get_nonce());
+
+        provider.set_floor(0);
+        assert_eq!(floor + 2, provider.get_nonce());
+
+        provider.set_floor(floor + 5);
+        assert_eq!(floor + 6, provider.get_nonce());
+
+        provider.set_floor(u64::MAX);
+        assert_eq!(u64::MAX, provider.get_nonce());
+        assert_eq!(u64::MAX, provider.get_nonce());
+    }
 }
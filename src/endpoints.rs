@@ -0,0 +1,33 @@
+//! Base URLs of the REST and websocket APIs
+use crate::clients::kraken_client::endpoints::KRAKEN_BASE_URL;
+use crate::wss::{WS_KRAKEN, WS_KRAKEN_AUTH};
+
+/// The base URLs of Kraken's REST API and public and private websockets, defaulting to Kraken's own.
+///
+/// One set of [Endpoints] can be given to both REST clients (see
+/// [KrakenClient::new_with_endpoints](crate::clients::kraken_client::KrakenClient::new_with_endpoints))
+/// and [KrakenWSSClient::new_with_endpoints](crate::wss::KrakenWSSClient::new_with_endpoints), e.g.
+/// to point all of them at a proxy or mock server. Request paths like `/0/public/Time` are appended
+/// to `rest` as-is.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Endpoints {
+    pub rest: String,
+    pub wss: String,
+    pub wss_auth: String,
+}
+
+impl Endpoints {
+    pub fn new(rest: impl ToString, wss: impl ToString, wss_auth: impl ToString) -> Endpoints {
+        Endpoints {
+            rest: rest.to_string(),
+            wss: wss.to_string(),
+            wss_auth: wss_auth.to_string(),
+        }
+    }
+}
+
+impl Default for Endpoints {
+    fn default() -> Self {
+        Endpoints::new(KRAKEN_BASE_URL, WS_KRAKEN, WS_KRAKEN_AUTH)
+    }
+}
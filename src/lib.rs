@@ -7,12 +7,15 @@
 //!
 pub mod clients;
 pub mod crypto;
+pub mod endpoints;
+pub mod metrics;
 pub mod rate_limiting;
 pub mod request_types;
 pub mod response_types;
 pub mod secrets;
 #[cfg(test)]
 pub mod test_data;
-#[cfg(feature = "test-support")]
+#[cfg(any(test, feature = "test-support"))]
 pub mod test_support;
+pub mod util;
 pub mod wss;
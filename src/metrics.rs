@@ -0,0 +1,61 @@
+//! Hooks for observing clients and websocket streams
+use serde_json::Error as SerdeError;
+use std::fmt::Debug;
+use std::time::Duration;
+
+/// Receives events from clients and streams, e.g. to export them to Prometheus or statsd.
+///
+/// Every method does nothing by default, so implementations only need to handle the events they
+/// record. Methods are called inline on the request or stream's task, so they should return quickly,
+/// for instance by incrementing counters rather than doing any I/O.
+///
+/// A [Metrics] implementation is given to each client as an `Arc<dyn Metrics>`, so one instance can
+/// be shared by all of them:
+/// - [CoreKrakenClient](crate::clients::core_kraken_client::CoreKrakenClient) reports each request,
+///   its response, and responses that failed to deserialize
+/// - [RateLimitedKrakenClient](crate::clients::rate_limited_kraken_client::RateLimitedKrakenClient)
+///   reports the time spent waiting on its rate limiters
+/// - [KrakenWSSClient](crate::wss::KrakenWSSClient) passes it to each stream it connects, which
+///   reports messages that failed to deserialize, and
+///   [WssConnectionPool](crate::wss::WssConnectionPool) reports each reconnection of a shard
+///
+/// # Example: Counting Requests
+/// ```
+/// use kraken_async_rs::metrics::Metrics;
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+///
+/// #[derive(Debug, Default)]
+/// struct RequestCounter {
+///     requests: AtomicUsize,
+/// }
+///
+/// impl Metrics for RequestCounter {
+///     fn request_sent(&self, _endpoint: &str) {
+///         self.requests.fetch_add(1, Ordering::Relaxed);
+///     }
+/// }
+/// ```
+pub trait Metrics: Debug + Send + Sync {
+    /// A REST request to `endpoint` (its path, e.g. `/0/public/Time`) is being sent.
+    fn request_sent(&self, _endpoint: &str) {}
+
+    /// A REST request to `endpoint` completed after `elapsed`, with the HTTP `status` of the
+    /// response, or None if no response was received, e.g. on a timeout or connection error.
+    fn response_received(&self, _endpoint: &str, _status: Option<u16>, _elapsed: Duration) {}
+
+    /// A request waited `waited` for a rate limiter before being sent, reported for every rate
+    /// limited request, even if it didn't have to wait.
+    fn rate_limit_wait(&self, _waited: Duration) {}
+
+    /// A websocket connection was reconnected after failing.
+    fn reconnect(&self) {}
+
+    /// A REST response or websocket message failed to deserialize.
+    fn deserialize_error(&self, _error: &SerdeError) {}
+}
+
+/// A [Metrics] implementation that ignores all events, used by all clients by default.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoopMetrics;
+
+impl Metrics for NoopMetrics {}
@@ -10,18 +10,28 @@ pub fn new_public_rate_limiter() -> SlidingWindowRateLimiter {
     SlidingWindowRateLimiter::new(Duration::from_secs(1), 1)
 }
 
+/// The default maximum number of keys of a [KeyedRateLimiter], see
+/// [KeyedRateLimiter::with_max_keys].
+pub const DEFAULT_MAX_KEYS: usize = 4096;
+
 /// A rate limiter that utilizes a [BTreeMap] to map K -> [SlidingWindowRateLimiter], allowing for
 /// a per-argument rate limiter.
 ///
 /// This is used for several endpoints that are rate limited by IP and trading pair, so each pair
 /// is given a unique rate limiter.
+///
+/// At most `max_keys` rate limiters are kept, evicting the least recently used when a new key
+/// would exceed it. Evicting a rate limiter forgets its recent requests, so the bound should be
+/// large enough that evicted keys haven't been used for at least their window.
 #[derive(Debug, Clone)]
 pub struct KeyedRateLimiter<K>
 where
     K: Ord,
 {
-    rate_limiters: BTreeMap<K, SlidingWindowRateLimiter>,
+    rate_limiters: BTreeMap<K, (SlidingWindowRateLimiter, u64)>,
     default: fn() -> SlidingWindowRateLimiter,
+    max_keys: usize,
+    uses: u64,
 }
 
 impl<K> Default for KeyedRateLimiter<K>
@@ -37,25 +47,59 @@ impl<K> KeyedRateLimiter<K>
 where
     K: Ord,
 {
-    /// Create an empty instance with no rate limiters.
+    /// Create an empty instance with no rate limiters, keeping at most [DEFAULT_MAX_KEYS].
     pub fn new() -> Self {
         KeyedRateLimiter {
             rate_limiters: Default::default(),
             default: new_public_rate_limiter,
+            max_keys: DEFAULT_MAX_KEYS,
+            uses: 0,
         }
     }
 
+    /// Keep at most `max_keys` rate limiters, evicting the least recently used beyond it.
+    ///
+    /// # Panics
+    /// If `max_keys` is zero.
+    pub fn with_max_keys(mut self, max_keys: usize) -> Self {
+        assert!(max_keys > 0, "max_keys must be positive!");
+        self.max_keys = max_keys;
+        self.evict_to(max_keys);
+        self
+    }
+
     /// Add a rate limiter implementation for a given key, such that `self.wait_until_ready(key)`
     /// will use this rate limiter.
     ///
     /// This can overwrite previous rate limiters if the key already exists and returns/follows the
-    /// same semantics as [BTreeMap]'s insert method.
+    /// same semantics as [BTreeMap]'s insert method. Adding a new key counts as using it, and may
+    /// evict the least recently used rate limiter.
     pub fn add_rate_limiter(
         &mut self,
         key: K,
         rate_limiter: SlidingWindowRateLimiter,
     ) -> Option<SlidingWindowRateLimiter> {
-        self.rate_limiters.insert(key, rate_limiter)
+        if !self.rate_limiters.contains_key(&key) {
+            self.evict_to(self.max_keys - 1);
+        }
+
+        self.uses += 1;
+        self.rate_limiters
+            .insert(key, (rate_limiter, self.uses))
+            .map(|(rate_limiter, _)| rate_limiter)
+    }
+
+    /// The number of keys with a rate limiter, including those created by default on first use.
+    ///
+    /// Rate limiters are kept until removed with `remove_rate_limiter`, or evicted to stay within
+    /// the max keys.
+    pub fn len(&self) -> usize {
+        self.rate_limiters.len()
+    }
+
+    /// Returns if there are no rate limiters.
+    pub fn is_empty(&self) -> bool {
+        self.rate_limiters.is_empty()
     }
 
     /// Remove a rate limiter from the internal map. This may result in subsequent usages of this
@@ -63,27 +107,47 @@ where
     ///
     /// This follows the same return semantics as [BTreeMap]'s remove method.
     pub fn remove_rate_limiter(&mut self, key: &K) -> Option<SlidingWindowRateLimiter> {
-        self.rate_limiters.remove(key)
+        self.rate_limiters
+            .remove(key)
+            .map(|(rate_limiter, _)| rate_limiter)
     }
 
     /// Follows the same semantics as [SlidingWindowRateLimiter], except it looks up a rate limiter
     /// by key, and creates a rate limiter if none is found.
     pub async fn wait_until_ready(&mut self, key: K) {
-        self.rate_limiters
-            .entry(key)
-            .or_insert((self.default)())
-            .wait_until_ready()
-            .await
+        self.get_or_insert(key).wait_until_ready().await
     }
 
     /// Follows the same semantics as [SlidingWindowRateLimiter], except it looks up a rate limiter
     /// by key, and creates a rate limiter if none is found.
     pub async fn wait_with_cost(&mut self, cost: usize, key: K) {
-        self.rate_limiters
+        self.get_or_insert(key).wait_with_cost(cost).await
+    }
+
+    fn get_or_insert(&mut self, key: K) -> &mut SlidingWindowRateLimiter {
+        if !self.rate_limiters.contains_key(&key) {
+            self.evict_to(self.max_keys - 1);
+        }
+
+        self.uses += 1;
+        let default = self.default;
+        let (rate_limiter, last_used) = self
+            .rate_limiters
             .entry(key)
-            .or_insert((self.default)())
-            .wait_with_cost(cost)
-            .await
+            .or_insert_with(|| (default(), 0));
+        *last_used = self.uses;
+
+        rate_limiter
+    }
+
+    /// Evict the least recently used rate limiters until at most `len` remain.
+    fn evict_to(&mut self, len: usize) {
+        while self.rate_limiters.len() > len {
+            let Some(oldest) = self.rate_limiters.values().map(|(_, used)| *used).min() else {
+                return;
+            };
+            self.rate_limiters.retain(|_, (_, used)| *used != oldest);
+        }
     }
 }
 
@@ -118,6 +182,36 @@ mod tests {
         assert_eq!(0, limiter.rate_limiters.len());
     }
 
+    #[tokio::test]
+    async fn test_max_keys_evicts_least_recently_used() {
+        pause();
+
+        let mut limiter = KeyedRateLimiter::new().with_max_keys(2);
+
+        limiter.wait_until_ready("k1").await;
+        limiter.wait_until_ready("k2").await;
+        limiter.wait_with_cost(1, "k1").await;
+        limiter.wait_until_ready("k3").await;
+
+        assert_eq!(2, limiter.len());
+        assert!(limiter.rate_limiters.contains_key("k1"));
+        assert!(limiter.rate_limiters.contains_key("k3"));
+
+        limiter.add_rate_limiter(
+            "k4",
+            SlidingWindowRateLimiter::new(Duration::from_secs(1), 1),
+        );
+        assert_eq!(2, limiter.len());
+        assert!(limiter.rate_limiters.contains_key("k3"));
+        assert!(limiter.rate_limiters.contains_key("k4"));
+    }
+
+    #[test]
+    #[should_panic(expected = "max_keys must be positive!")]
+    fn test_zero_max_keys() {
+        KeyedRateLimiter::<String>::new().with_max_keys(0);
+    }
+
     #[tokio::test]
     async fn test_waiting_separately() {
         pause();
@@ -7,5 +7,7 @@
 //! This was a simplification that allowed using Semaphore permits as the core rate limiting concept
 //! under the hood.
 pub mod keyed_rate_limits;
+pub mod private_costs;
+pub mod token_bucket_mirror;
 pub mod trading_rate_limits;
 pub mod ttl_cache;
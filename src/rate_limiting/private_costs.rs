@@ -0,0 +1,81 @@
+//! Costs of private REST endpoints against the private token bucket
+use crate::clients::kraken_client::endpoints::*;
+
+/// The (scaled) cost of most private endpoints, 1.0 in Kraken's documentation.
+pub const DEFAULT_PRIVATE_COST: usize = 100;
+
+/// The (scaled) cost of the private history endpoints, 2.0 in Kraken's documentation.
+pub const HISTORY_PRIVATE_COST: usize = 200;
+
+/// Private endpoints whose cost differs from [DEFAULT_PRIVATE_COST].
+pub const PRIVATE_ENDPOINT_COSTS: [(&str, usize); 3] = [
+    (CLOSED_ORDERS_ENDPOINT, HISTORY_PRIVATE_COST),
+    (TRADES_HISTORY_ENDPOINT, HISTORY_PRIVATE_COST),
+    (LEDGERS_ENDPOINT, HISTORY_PRIVATE_COST),
+];
+
+/// The (scaled) cost of calling a private `endpoint`, e.g. [CLOSED_ORDERS_ENDPOINT].
+///
+/// Trading endpoints like [ADD_ORDER_ENDPOINT] are limited separately by
+/// [KrakenTradingRateLimiter](crate::rate_limiting::trading_rate_limits::KrakenTradingRateLimiter),
+/// so the default returned for them is not used.
+pub fn private_endpoint_cost(endpoint: &str) -> usize {
+    PRIVATE_ENDPOINT_COSTS
+        .iter()
+        .find(|(path, _)| *path == endpoint)
+        .map_or(DEFAULT_PRIVATE_COST, |(_, cost)| *cost)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_private_endpoint_costs() {
+        for endpoint in [
+            CLOSED_ORDERS_ENDPOINT,
+            TRADES_HISTORY_ENDPOINT,
+            LEDGERS_ENDPOINT,
+        ] {
+            assert_eq!(200, private_endpoint_cost(endpoint), "{endpoint}");
+        }
+
+        for endpoint in [
+            ACCOUNT_BALANCE_ENDPOINT,
+            ACCOUNT_BALANCE_EXTENDED_ENDPOINT,
+            TRADE_BALANCE_ENDPOINT,
+            OPEN_ORDERS_ENDPOINT,
+            QUERY_ORDERS_ENDPOINT,
+            ORDER_AMENDS_ENDPOINT,
+            QUERY_TRADES_ENDPOINT,
+            OPEN_POSITIONS_ENDPOINT,
+            QUERY_LEDGERS_ENDPOINT,
+            TRADE_VOLUME_ENDPOINT,
+            ADD_EXPORT_ENDPOINT,
+            EXPORT_STATUS_ENDPOINT,
+            RETRIEVE_EXPORT_ENDPOINT,
+            REMOVE_EXPORT_ENDPOINT,
+            DEPOSIT_METHODS_ENDPOINT,
+            DEPOSIT_ADDRESSES_ENDPOINT,
+            DEPOSIT_STATUS_ENDPOINT,
+            WITHDRAW_METHODS_ENDPOINT,
+            WITHDRAW_ADDRESSES_ENDPOINT,
+            WITHDRAW_INFO_ENDPOINT,
+            WITHDRAW_ENDPOINT,
+            WITHDRAW_STATUS_ENDPOINT,
+            WITHDRAW_CANCEL_ENDPOINT,
+            WALLET_TRANSFER_ENDPOINT,
+            CREATE_SUB_ACCOUNT_ENDPOINT,
+            ACCOUNT_TRANSFER_ENDPOINT,
+            EARN_ALLOCATE_ENDPOINT,
+            EARN_DEALLOCATE_ENDPOINT,
+            EARN_ALLOCATE_STATUS_ENDPOINT,
+            EARN_DEALLOCATE_STATUS_ENDPOINT,
+            EARN_STRATEGIES_ENDPOINT,
+            EARN_ALLOCATIONS_ENDPOINT,
+            GET_WS_TOKEN_ENDPOINT,
+        ] {
+            assert_eq!(100, private_endpoint_cost(endpoint), "{endpoint}");
+        }
+    }
+}
@@ -0,0 +1,146 @@
+//! A read-only mirror of a token bucket's state, for introspection
+use std::cmp::min;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// A best-effort mirror of a [TokenBucketRateLimiter]'s state, which can be inspected without
+/// acquiring any tokens.
+///
+/// [TokenBucketRateLimiter] does not expose its available tokens, so this tracks the same bucket
+/// parameters and is updated with the cost of every call made through the real limiter. Tokens are
+/// replenished in whole periods of `replace_duration`, matching the real bucket's schedule closely
+/// but not exactly.
+///
+/// Like [TokenBucketRateLimiter], cloning results in a mirror that shares the same underlying state.
+///
+/// [TokenBucketRateLimiter]: async_rate_limit::token_bucket::TokenBucketRateLimiter
+#[derive(Debug, Clone)]
+pub struct TokenBucketMirror {
+    state: Arc<Mutex<MirrorState>>,
+}
+
+#[derive(Debug)]
+struct MirrorState {
+    max_tokens: usize,
+    replace_amount: usize,
+    replace_duration: Duration,
+    tokens: usize,
+    last_replenished: Instant,
+}
+
+impl MirrorState {
+    /// Count the tokens available at `now`, returning them and the start of the current period.
+    fn replenished(&self, now: Instant) -> (usize, Instant) {
+        if self.tokens >= self.max_tokens || self.replace_duration.is_zero() {
+            return (self.tokens, now);
+        }
+
+        let elapsed = now.saturating_duration_since(self.last_replenished);
+        let periods = (elapsed.as_nanos() / self.replace_duration.as_nanos()) as u32;
+        let tokens = self
+            .tokens
+            .saturating_add(self.replace_amount.saturating_mul(periods as usize));
+
+        (
+            min(tokens, self.max_tokens),
+            self.last_replenished + self.replace_duration * periods,
+        )
+    }
+}
+
+impl TokenBucketMirror {
+    /// Create a mirror of a full token bucket with the same parameters as the
+    /// [TokenBucketState](async_rate_limit::token_bucket::TokenBucketState) being mirrored.
+    pub fn new(max_tokens: usize, replace_amount: usize, replace_duration: Duration) -> Self {
+        TokenBucketMirror {
+            state: Arc::new(Mutex::new(MirrorState {
+                max_tokens,
+                replace_amount,
+                replace_duration,
+                tokens: max_tokens,
+                last_replenished: Instant::now(),
+            })),
+        }
+    }
+
+    /// Record that `cost` tokens were taken from the mirrored bucket.
+    pub fn record(&self, cost: usize) {
+        let mut state = self.state.lock().unwrap();
+        let (tokens, last_replenished) = state.replenished(Instant::now());
+        state.tokens = tokens.saturating_sub(cost);
+        state.last_replenished = last_replenished;
+    }
+
+    /// The number of tokens currently available, in the same (scaled) units as call costs.
+    pub fn available_tokens(&self) -> usize {
+        let state = self.state.lock().unwrap();
+        state.replenished(Instant::now()).0
+    }
+
+    /// The time until a call of `cost` could proceed without waiting, or zero if it can proceed now.
+    ///
+    /// Costs greater than the bucket's maximum can never proceed, and return [Duration::MAX].
+    pub fn time_until_ready_for(&self, cost: usize) -> Duration {
+        let state = self.state.lock().unwrap();
+
+        if cost > state.max_tokens || (state.replace_amount == 0 && cost > state.tokens) {
+            return Duration::MAX;
+        }
+
+        let now = Instant::now();
+        let (tokens, last_replenished) = state.replenished(now);
+
+        if tokens >= cost {
+            return Duration::ZERO;
+        }
+
+        let missing = cost - tokens;
+        let periods = missing.div_ceil(state.replace_amount) as u32;
+        let ready_at = last_replenished + state.replace_duration * periods;
+
+        ready_at.saturating_duration_since(now)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::time::{advance, pause};
+
+    #[tokio::test]
+    async fn test_full_bucket() {
+        let mirror = TokenBucketMirror::new(2000, 50, Duration::from_secs(1));
+
+        assert_eq!(2000, mirror.available_tokens());
+        assert_eq!(Duration::ZERO, mirror.time_until_ready_for(2000));
+        assert_eq!(Duration::MAX, mirror.time_until_ready_for(2001));
+    }
+
+    #[tokio::test]
+    async fn test_record_and_replenish() {
+        pause();
+        let mirror = TokenBucketMirror::new(2000, 50, Duration::from_secs(1));
+
+        mirror.record(2000);
+        assert_eq!(0, mirror.available_tokens());
+        assert_eq!(Duration::from_secs(2), mirror.time_until_ready_for(100));
+
+        advance(Duration::from_millis(1500)).await;
+        assert_eq!(50, mirror.available_tokens());
+        assert_eq!(Duration::from_millis(500), mirror.time_until_ready_for(100));
+
+        advance(Duration::from_secs(100)).await;
+        assert_eq!(2000, mirror.available_tokens());
+    }
+
+    #[tokio::test]
+    async fn test_clones_share_state() {
+        let mirror = TokenBucketMirror::new(2000, 50, Duration::from_secs(1));
+        let clone = mirror.clone();
+
+        clone.record(300);
+
+        assert_eq!(1700, mirror.available_tokens());
+    }
+}
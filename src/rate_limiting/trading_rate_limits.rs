@@ -1,5 +1,5 @@
 use crate::rate_limiting::ttl_cache::{TtlCache, TtlEntry};
-use crate::request_types::{AddBatchedOrderRequest, EditOrderRequest};
+use crate::request_types::{AddBatchedOrderRequest, EditOrderRequest, IntOrString};
 use crate::response_types::VerificationTier;
 use async_rate_limit::limiters::VariableCostRateLimiter;
 use async_rate_limit::token_bucket::{TokenBucketRateLimiter, TokenBucketState};
@@ -11,12 +11,90 @@ use tokio::sync::Mutex;
 // 300 seconds in microseconds
 const ORDER_TTL_US: i128 = 300_i128 * 10_i128.pow(6);
 
+/// Default cost of `cancel_all_orders` and `cancel_all_orders_after`, the same as a single
+/// penalty-free action
+pub const DEFAULT_CANCEL_ALL_COST: usize = 100;
+
+/// Penalties for acting on an order soon after it was placed (or last amended), as pairs of the
+/// order's lifetime in seconds that each applies below, and the penalty.
+///
+/// Orders older than the last lifetime have no penalty. Penalties are in Kraken's units, so a
+/// penalty of 1 costs 100 scaled tokens.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PenaltySchedule {
+    buckets: Vec<(i64, u32)>,
+}
+
+impl PenaltySchedule {
+    /// Create a schedule from `(lifetime, penalty)` pairs in any order, which are sorted by
+    /// ascending lifetime so each lifetime gets the penalty of the first bucket it's below.
+    pub fn new(mut buckets: Vec<(i64, u32)>) -> PenaltySchedule {
+        buckets.sort_by_key(|(below, _)| *below);
+        PenaltySchedule { buckets }
+    }
+
+    /// The `(lifetime, penalty)` pairs of the schedule, in ascending lifetime.
+    pub fn buckets(&self) -> &[(i64, u32)] {
+        &self.buckets
+    }
+
+    /// The penalty for an order that has been open for `lifetime_seconds`.
+    pub fn penalty(&self, lifetime_seconds: i64) -> u32 {
+        self.buckets
+            .iter()
+            .find(|(below, _)| lifetime_seconds < *below)
+            .map_or(0, |(_, penalty)| *penalty)
+    }
+}
+
+/// The [PenaltySchedule]s of the advanced trading rate limits, defaulting to Kraken's documented
+/// schedule.
+///
+/// Order lifetimes are only tracked for 300s, so longer lifetimes in a schedule never apply.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TradingPenalties {
+    pub amend: PenaltySchedule,
+    pub edit: PenaltySchedule,
+    pub cancel: PenaltySchedule,
+}
+
+impl Default for TradingPenalties {
+    fn default() -> Self {
+        TradingPenalties {
+            amend: PenaltySchedule::new(vec![(5, 3), (10, 2), (15, 1)]),
+            edit: PenaltySchedule::new(vec![(5, 6), (10, 5), (15, 4), (45, 3), (90, 2)]),
+            cancel: PenaltySchedule::new(vec![
+                (5, 8),
+                (10, 6),
+                (15, 5),
+                (45, 4),
+                (90, 2),
+                (300, 1),
+            ]),
+        }
+    }
+}
+
+/// The penalties that amending, editing or cancelling an order would currently incur, see
+/// [KrakenTradingRateLimiter::order_penalties].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OrderPenalties {
+    /// Seconds since the order was placed or last amended, or None if it isn't tracked, e.g. since
+    /// it's older than 300s.
+    pub lifetime: Option<i64>,
+    pub amend: u32,
+    pub edit: u32,
+    pub cancel: u32,
+}
+
 /// An implementation of the most accurate trading rate limits given by Kraken
 #[derive(Debug, Clone)]
 pub struct KrakenTradingRateLimiter {
     ttl_ref_id_cache: Arc<Mutex<TtlCache<String, i64>>>,
     ttl_user_ref_cache: Arc<Mutex<TtlCache<i64, i64>>>,
     rate_limiter: TokenBucketRateLimiter,
+    cancel_all_cost: usize,
+    penalties: TradingPenalties,
 }
 
 /// Implements the Advanced rate limiting scheme that requires knowing each order's lifetime.
@@ -34,9 +112,57 @@ impl KrakenTradingRateLimiter {
             ttl_ref_id_cache: Default::default(),
             ttl_user_ref_cache: Default::default(),
             rate_limiter: Self::get_rate_limiter(user_verification),
+            cancel_all_cost: DEFAULT_CANCEL_ALL_COST,
+            penalties: TradingPenalties::default(),
         }
     }
 
+    /// Set the [TradingPenalties] for amending, editing and cancelling orders, which default to
+    /// Kraken's documented schedule.
+    pub fn set_penalties(&mut self, penalties: TradingPenalties) {
+        self.penalties = penalties;
+    }
+
+    pub fn penalties(&self) -> &TradingPenalties {
+        &self.penalties
+    }
+
+    /// The penalties that acting on the order with the given tx id, client order id or user ref
+    /// would currently incur, all zero if the order isn't tracked.
+    pub async fn order_penalties(&self, id: &IntOrString) -> OrderPenalties {
+        let now_seconds = OffsetDateTime::now_utc().unix_timestamp();
+
+        let placement_time = match id {
+            IntOrString::Int(user_ref) => self
+                .ttl_user_ref_cache
+                .lock()
+                .await
+                .get(user_ref)
+                .map(|ttl_entry| ttl_entry.data),
+            IntOrString::String(id) => self
+                .ttl_ref_id_cache
+                .lock()
+                .await
+                .get(id)
+                .map(|ttl_entry| ttl_entry.data),
+        };
+        let lifetime = placement_time.map(|placement_time| now_seconds - placement_time);
+        let lifetime_seconds = lifetime.unwrap_or(i64::MAX);
+
+        OrderPenalties {
+            lifetime,
+            amend: self.penalties.amend.penalty(lifetime_seconds),
+            edit: self.penalties.edit.penalty(lifetime_seconds),
+            cancel: self.penalties.cancel.penalty(lifetime_seconds),
+        }
+    }
+
+    /// Set the (scaled) cost of `cancel_all_orders` and `cancel_all_orders_after` calls, which
+    /// defaults to [DEFAULT_CANCEL_ALL_COST].
+    pub fn set_cancel_all_cost(&mut self, cost: usize) {
+        self.cancel_all_cost = cost;
+    }
+
     /// Wait for the fixed cost of placing an order
     pub async fn add_order(&mut self) {
         self.rate_limiter.wait_with_cost(100).await;
@@ -74,10 +200,10 @@ impl KrakenTradingRateLimiter {
             .map(|ttl_entry| now_seconds - ttl_entry.data)
             .unwrap_or(i64::MAX);
 
-        let penalty = Self::amend_order_penalty(order_lifetime);
-        let cost = (penalty + 1) * 100;
+        let penalty = self.penalties.amend.penalty(order_lifetime);
+        let cost = (penalty as usize + 1) * 100;
 
-        self.rate_limiter.wait_with_cost(cost as usize).await
+        self.rate_limiter.wait_with_cost(cost).await
     }
 
     /// Determine the cost of editing an order and wait if necessary
@@ -95,10 +221,10 @@ impl KrakenTradingRateLimiter {
             .map(|ttl_entry| now_seconds - ttl_entry.data)
             .unwrap_or(i64::MAX);
 
-        let penalty = Self::edit_order_penalty(order_lifetime);
-        let cost = (penalty + 1) * 100;
+        let penalty = self.penalties.edit.penalty(order_lifetime);
+        let cost = (penalty as usize + 1) * 100;
 
-        self.rate_limiter.wait_with_cost(cost as usize).await
+        self.rate_limiter.wait_with_cost(cost).await
     }
 
     /// Determine the cost of cancelling the provided order id and wait appropriately
@@ -134,11 +260,22 @@ impl KrakenTradingRateLimiter {
         self.cancel_with_penalty(order_lifetime).await;
     }
 
+    /// Wait for the configurable fixed cost of cancelling all orders
+    pub async fn cancel_all_orders(&mut self) {
+        self.rate_limiter.wait_with_cost(self.cancel_all_cost).await
+    }
+
+    /// Wait for the configurable fixed cost of setting or resetting the cancel-all-orders-after
+    /// timer, which shares its cost with `cancel_all_orders`
+    pub async fn cancel_all_orders_after(&mut self) {
+        self.rate_limiter.wait_with_cost(self.cancel_all_cost).await
+    }
+
     async fn cancel_with_penalty(&mut self, order_lifetime: i64) {
-        let penalty = Self::cancel_order_penalty(order_lifetime);
-        let cost = penalty * 100;
+        let penalty = self.penalties.cancel.penalty(order_lifetime);
+        let cost = penalty as usize * 100;
 
-        self.rate_limiter.wait_with_cost(cost as usize).await
+        self.rate_limiter.wait_with_cost(cost).await
     }
 
     /// Notify the rate limiter of a new order being created -- this is essential to the rate limiting scheme!
@@ -170,6 +307,25 @@ impl KrakenTradingRateLimiter {
         }
     }
 
+    /// Notify the rate limiter that the order with the given tx id, client order id or user ref
+    /// was cancelled or filled, so its lifetime no longer needs to be tracked.
+    pub async fn notify_cancel_order(&mut self, id: &IntOrString) {
+        match id {
+            IntOrString::Int(user_ref) => {
+                self.ttl_user_ref_cache.lock().await.remove_id(user_ref);
+            }
+            IntOrString::String(id) => {
+                self.ttl_ref_id_cache.lock().await.remove_id(id);
+            }
+        }
+    }
+
+    /// Notify the rate limiter that all orders were cancelled, so no lifetimes need to be tracked.
+    pub async fn notify_cancel_all_orders(&mut self) {
+        self.ttl_ref_id_cache.lock().await.clear();
+        self.ttl_user_ref_cache.lock().await.clear();
+    }
+
     /// Notify the cache that an order was amended at the given time -- this is essential to the rate limiting scheme!
     pub async fn notify_amend_order(
         &mut self,
@@ -187,55 +343,13 @@ impl KrakenTradingRateLimiter {
         }
     }
 
-    fn amend_order_penalty(lifetime_seconds: i64) -> i64 {
-        if lifetime_seconds < 5 {
-            3
-        } else if lifetime_seconds < 10 {
-            2
-        } else if lifetime_seconds < 15 {
-            1
-        } else {
-            0
-        }
-    }
-
-    fn edit_order_penalty(lifetime_seconds: i64) -> i64 {
-        if lifetime_seconds < 5 {
-            6
-        } else if lifetime_seconds < 10 {
-            5
-        } else if lifetime_seconds < 15 {
-            4
-        } else if lifetime_seconds < 45 {
-            3
-        } else if lifetime_seconds < 90 {
-            2
-        } else {
-            0
-        }
-    }
-
-    fn cancel_order_penalty(lifetime_seconds: i64) -> i64 {
-        if lifetime_seconds < 5 {
-            8
-        } else if lifetime_seconds < 10 {
-            6
-        } else if lifetime_seconds < 15 {
-            5
-        } else if lifetime_seconds < 45 {
-            4
-        } else if lifetime_seconds < 90 {
-            2
-        } else if lifetime_seconds < 300 {
-            1
-        } else {
-            0
-        }
-    }
-
     fn get_rate_limiter(user_verification: VerificationTier) -> TokenBucketRateLimiter {
         // tokens are scaled 100x from Kraken's floating-point method to keep as integers
         match user_verification {
+            VerificationTier::Starter => {
+                let token_bucket_state = TokenBucketState::new(6000, 100, Duration::from_secs(1));
+                TokenBucketRateLimiter::new(Arc::new(Mutex::new(token_bucket_state)))
+            }
             VerificationTier::Intermediate => {
                 let token_bucket_state = TokenBucketState::new(12500, 234, Duration::from_secs(1));
                 TokenBucketRateLimiter::new(Arc::new(Mutex::new(token_bucket_state)))
@@ -250,14 +364,17 @@ impl KrakenTradingRateLimiter {
 
 #[cfg(test)]
 mod tests {
-    use crate::rate_limiting::trading_rate_limits::KrakenTradingRateLimiter;
+    use crate::rate_limiting::trading_rate_limits::{
+        KrakenTradingRateLimiter, OrderPenalties, PenaltySchedule, TradingPenalties,
+    };
     /// Tests use Tokio's pause() functionality to have instantaneous testing that relies on Tokio
     /// keeping track of time elapsed by fast-forwarding when there are no pending tasks on the
     /// event loop.
     ///
     /// Tests are done at a high enough level that execution time of Rust is thought to be negligible.
     ///
-    use crate::response_types::VerificationTier::{Intermediate, Pro};
+    use crate::request_types::IntOrString;
+    use crate::response_types::VerificationTier::{Intermediate, Pro, Starter};
     use std::time::Duration;
     use tokio::time::{pause, Instant};
 
@@ -280,6 +397,30 @@ mod tests {
         assert!(elapsed < Duration::from_secs(2));
     }
 
+    #[tokio::test]
+    async fn test_trading_rate_limiter_starter_add_order_limit() {
+        pause();
+
+        let mut starter = KrakenTradingRateLimiter::new(Starter);
+        let mut intermediate = KrakenTradingRateLimiter::new(Intermediate);
+
+        let start = Instant::now();
+        // 61 calls should push the starter limiter over the 6000 limit, requiring waiting 1s
+        for _ in 0..61 {
+            intermediate.add_order().await;
+        }
+        assert_eq!(Duration::ZERO, Instant::now() - start);
+
+        for _ in 0..61 {
+            starter.add_order().await;
+        }
+
+        let elapsed = Instant::now() - start;
+
+        assert!(elapsed > Duration::from_secs(1));
+        assert!(elapsed < Duration::from_secs(2));
+    }
+
     #[tokio::test]
     async fn test_trading_rate_limiter_pro_add_order_limit() {
         pause();
@@ -345,6 +486,44 @@ mod tests {
         assert!(elapsed < Duration::from_secs(5));
     }
 
+    #[tokio::test]
+    async fn test_trading_rate_limiter_cancel_all_orders() {
+        pause();
+
+        let mut limiter = KrakenTradingRateLimiter::new(Intermediate);
+
+        let start = Instant::now();
+        // 125 cancels empty the 12500 limit, and the 126th waits 1s for replenishment
+        for _ in 0..63 {
+            limiter.cancel_all_orders().await;
+            limiter.cancel_all_orders_after().await;
+        }
+
+        let elapsed = Instant::now() - start;
+
+        assert!(elapsed > Duration::from_secs(1));
+        assert!(elapsed < Duration::from_secs(2));
+    }
+
+    #[tokio::test]
+    async fn test_trading_rate_limiter_cancel_all_orders_custom_cost() {
+        pause();
+
+        let mut limiter = KrakenTradingRateLimiter::new(Pro);
+        limiter.set_cancel_all_cost(1800);
+
+        let start = Instant::now();
+        // 10 cancels empty the 18000 limit, and the 11th waits 5s to replenish 1800 @ 375/s
+        for _ in 0..11 {
+            limiter.cancel_all_orders().await;
+        }
+
+        let elapsed = Instant::now() - start;
+
+        assert!(elapsed > Duration::from_secs(4));
+        assert!(elapsed < Duration::from_secs(6));
+    }
+
     #[test]
     fn test_amend_order_penalties() {
         let cases = vec![
@@ -361,7 +540,7 @@ mod tests {
         for (lifetime, expected) in cases {
             assert_eq!(
                 expected,
-                KrakenTradingRateLimiter::amend_order_penalty(lifetime)
+                TradingPenalties::default().amend.penalty(lifetime)
             );
         }
     }
@@ -384,10 +563,7 @@ mod tests {
         ];
 
         for (lifetime, expected) in cases {
-            assert_eq!(
-                expected,
-                KrakenTradingRateLimiter::edit_order_penalty(lifetime)
-            );
+            assert_eq!(expected, TradingPenalties::default().edit.penalty(lifetime));
         }
     }
 
@@ -413,8 +589,143 @@ mod tests {
         for (lifetime, expected) in cases {
             assert_eq!(
                 expected,
-                KrakenTradingRateLimiter::cancel_order_penalty(lifetime)
+                TradingPenalties::default().cancel.penalty(lifetime)
             );
         }
     }
+
+    #[tokio::test]
+    async fn test_order_penalties() {
+        let mut limiter = KrakenTradingRateLimiter::new(Intermediate);
+        let now = time::OffsetDateTime::now_utc().unix_timestamp();
+
+        limiter
+            .notify_add_order("OQCLML-BW3P3-BUCMWZ".to_string(), now - 20, None, &None)
+            .await;
+
+        let expected = OrderPenalties {
+            lifetime: Some(20),
+            amend: 0,
+            edit: 3,
+            cancel: 4,
+        };
+        assert_eq!(
+            expected,
+            limiter.order_penalties(&"OQCLML-BW3P3-BUCMWZ".into()).await
+        );
+
+        let untracked = limiter.order_penalties(&"unknown".into()).await;
+        assert_eq!(None, untracked.lifetime);
+        assert_eq!(0, untracked.cancel);
+
+        limiter.set_penalties(TradingPenalties {
+            cancel: PenaltySchedule::new(vec![(30, 10)]),
+            ..TradingPenalties::default()
+        });
+
+        let penalties = limiter.order_penalties(&"OQCLML-BW3P3-BUCMWZ".into()).await;
+        assert_eq!(10, penalties.cancel);
+        assert_eq!(3, penalties.edit);
+        assert_eq!(
+            PenaltySchedule::new(vec![(30, 10)]),
+            limiter.penalties().cancel
+        );
+    }
+
+    #[tokio::test]
+    async fn test_order_penalties_by_user_ref() {
+        let mut limiter = KrakenTradingRateLimiter::new(Intermediate);
+        let now = time::OffsetDateTime::now_utc().unix_timestamp();
+
+        limiter
+            .notify_add_order("OQCLML-BW3P3-BUCMWZ".to_string(), now - 20, Some(42), &None)
+            .await;
+
+        let penalties = limiter.order_penalties(&IntOrString::Int(42)).await;
+        assert_eq!(Some(20), penalties.lifetime);
+        assert_eq!(4, penalties.cancel);
+
+        let untracked = limiter.order_penalties(&IntOrString::Int(7)).await;
+        assert_eq!(None, untracked.lifetime);
+    }
+
+    #[test]
+    fn test_penalty_schedule_sorts_buckets() {
+        let schedule = PenaltySchedule::new(vec![(15, 1), (5, 3), (10, 2)]);
+
+        assert_eq!(&[(5, 3), (10, 2), (15, 1)], schedule.buckets());
+        assert_eq!(3, schedule.penalty(0));
+        assert_eq!(2, schedule.penalty(7));
+        assert_eq!(0, schedule.penalty(15));
+    }
+
+    #[tokio::test]
+    async fn test_notify_cancel_order() {
+        let mut limiter = KrakenTradingRateLimiter::new(Intermediate);
+        let now = time::OffsetDateTime::now_utc().unix_timestamp();
+
+        limiter
+            .notify_add_order(
+                "OQCLML-BW3P3-BUCMWZ".to_string(),
+                now,
+                Some(42),
+                &Some("client-id".to_string()),
+            )
+            .await;
+        limiter
+            .notify_add_order("OB5VMB-B4U2U-DK2WRW".to_string(), now, None, &None)
+            .await;
+
+        limiter
+            .notify_cancel_order(&IntOrString::String("OQCLML-BW3P3-BUCMWZ".to_string()))
+            .await;
+        limiter
+            .notify_cancel_order(&IntOrString::String("client-id".to_string()))
+            .await;
+        limiter.notify_cancel_order(&IntOrString::Int(42)).await;
+
+        assert_eq!(1, limiter.ttl_ref_id_cache.lock().await.len());
+        assert!(limiter.ttl_user_ref_cache.lock().await.is_empty());
+        assert_eq!(
+            None,
+            limiter
+                .order_penalties(&"OQCLML-BW3P3-BUCMWZ".into())
+                .await
+                .lifetime
+        );
+        assert_eq!(
+            Some(0),
+            limiter
+                .order_penalties(&"OB5VMB-B4U2U-DK2WRW".into())
+                .await
+                .lifetime
+        );
+
+        limiter.notify_cancel_all_orders().await;
+        assert!(limiter.ttl_ref_id_cache.lock().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_custom_cancel_penalty_applies() {
+        pause();
+
+        let mut limiter = KrakenTradingRateLimiter::new(Intermediate);
+        limiter.set_penalties(TradingPenalties {
+            cancel: PenaltySchedule::new(vec![(300, 25)]),
+            ..TradingPenalties::default()
+        });
+
+        let now = time::OffsetDateTime::now_utc().unix_timestamp();
+        let start = Instant::now();
+        // each cancel costs 2500, so 6 cancels exceed the 12500 limit by 2500, requiring ~11s
+        for i in 0..6 {
+            let id = format!("order-{i}");
+            limiter.notify_add_order(id.clone(), now, None, &None).await;
+            limiter.cancel_order_tx_id(&id).await;
+        }
+
+        let elapsed = Instant::now() - start;
+        assert!(elapsed > Duration::from_secs(10));
+        assert!(elapsed < Duration::from_secs(12));
+    }
 }
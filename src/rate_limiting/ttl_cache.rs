@@ -59,7 +59,7 @@ where
     T: Clone,
 {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.ttl.cmp(&other.ttl))
+        Some(self.cmp(other))
     }
 }
 
@@ -69,12 +69,20 @@ where
     T: Clone,
 {
     fn cmp(&self, other: &Self) -> Ordering {
-        self.ttl.cmp(&other.ttl)
+        self.ttl
+            .cmp(&other.ttl)
+            .then_with(|| self.id.cmp(&other.id))
     }
 }
 
 /// A time-to-live cache that removes values when they expire. This is used to store and look up
 /// orders, to determine how old they are for rate limiting penalties when editing or cancelling.
+///
+/// A cache can optionally be given a max capacity, in which case inserting a new id into a full
+/// cache evicts the entry closest to expiring. Entries are ordered by expiry already, and when they
+/// share a ttl (like the order lifetimes of the trading rate limiter) the entry closest to
+/// expiring is the least recently inserted, so this is LRU by insertion without tracking reads.
+/// Evictions are counted and available via `evictions`.
 #[derive(Debug)]
 pub struct TtlCache<K, T>
 where
@@ -83,6 +91,8 @@ where
 {
     ids: BTreeMap<K, TtlEntry<K, T>>,
     ttls: BTreeSet<TtlEntry<K, T>>,
+    max_capacity: Option<usize>,
+    evictions: u64,
 }
 
 impl<K, T> Default for TtlCache<K, T>
@@ -105,16 +115,68 @@ where
         TtlCache {
             ids: Default::default(),
             ttls: Default::default(),
+            max_capacity: None,
+            evictions: 0,
+        }
+    }
+
+    /// Create a new, empty cache that holds at most `max_capacity` entries.
+    ///
+    /// # Panics
+    /// If `max_capacity` is zero, since such a cache could never hold an entry.
+    pub fn with_max_capacity(max_capacity: usize) -> TtlCache<K, T> {
+        assert!(max_capacity > 0, "max_capacity must be positive!");
+
+        TtlCache {
+            max_capacity: Some(max_capacity),
+            ..TtlCache::new()
         }
     }
 
     /// Insert the provided [TtlEntry] by it's id for future lookup. Entries beyond their ttl are
     /// removed automatically any time the `remove`, `get`, or `contains` methods are called.
+    ///
+    /// If the cache is at its max capacity, expired values are removed, then the entry closest to
+    /// expiring is evicted if there's still no room.
     pub fn insert(&mut self, ttl_entry: TtlEntry<K, T>) -> Option<TtlEntry<K, T>> {
+        if let Some(previous) = self.ids.get(&ttl_entry.id) {
+            self.ttls.remove(previous);
+        } else if let Some(max_capacity) = self.max_capacity {
+            if self.ids.len() >= max_capacity {
+                self.purge_expired();
+            }
+
+            while self.ids.len() >= max_capacity {
+                match self.ttls.pop_first() {
+                    Some(evicted) => {
+                        self.ids.remove(&evicted.id);
+                        self.evictions += 1;
+                    }
+                    None => return None,
+                }
+            }
+        }
+
         self.ttls.insert(ttl_entry.clone());
         self.ids.insert(ttl_entry.id.clone(), ttl_entry)
     }
 
+    /// The number of entries in the cache, which may include expired entries that have not yet
+    /// been removed.
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    /// Returns if the cache has no entries, expired or not.
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    /// The number of unexpired entries evicted to stay within the max capacity since creation.
+    pub fn evictions(&self) -> u64 {
+        self.evictions
+    }
+
     /// Removes an entry manually, returning if the entry was removed.
     ///
     /// The cache is cleaned of any expired values after checking if this value was removed.
@@ -123,37 +185,61 @@ where
     pub fn remove(&mut self, ttl_entry: &TtlEntry<K, T>) -> bool {
         self.ids.remove(&ttl_entry.id);
         let removed = self.ttls.remove(ttl_entry);
-        self.remove_expired_values();
+        self.purge_expired();
 
         removed
     }
 
+    /// Removes the entry with the given id, returning it if it was present.
+    ///
+    /// The cache is cleaned of any expired values after the entry is removed.
+    pub fn remove_id(&mut self, id: &K) -> Option<TtlEntry<K, T>> {
+        let removed = self.ids.remove(id);
+        if let Some(entry) = &removed {
+            self.ttls.remove(entry);
+        }
+        self.purge_expired();
+
+        removed
+    }
+
+    /// Removes all entries.
+    pub fn clear(&mut self) {
+        self.ids.clear();
+        self.ttls.clear();
+    }
+
     /// Returns if the provided key is in the cache, after removing any expired values.
     pub fn contains(&mut self, id: &K) -> bool {
-        self.remove_expired_values();
+        self.purge_expired();
         self.ids.contains_key(id)
     }
 
     /// Gets a [TtlEntry] by id after removing any expired values.
     pub fn get(&mut self, id: &K) -> Option<&TtlEntry<K, T>> {
-        self.remove_expired_values();
+        self.purge_expired();
         self.ids.get(id)
     }
 
-    fn remove_expired_values(&mut self) {
+    /// Remove all expired values, returning the number removed.
+    pub fn purge_expired(&mut self) -> usize {
         let now = now();
         let mut to_remove = Vec::new();
 
         for entry in &self.ttls {
             if entry.ttl < now {
                 to_remove.push(entry.clone());
+            } else {
+                break;
             }
         }
 
-        for entry in to_remove {
+        for entry in &to_remove {
             self.ids.remove(&entry.id);
-            self.ttls.remove(&entry);
+            self.ttls.remove(entry);
         }
+
+        to_remove.len()
     }
 }
 
@@ -258,6 +344,22 @@ mod tests {
         assert_eq!(entry_1, *result.unwrap())
     }
 
+    #[test]
+    fn test_ttl_cache_remove_id_and_clear() {
+        let mut ttl_cache: TtlCache<String, i64> = TtlCache::new();
+
+        ttl_cache.insert(TtlEntry::new("a".to_string(), 1_000_000, 1));
+        ttl_cache.insert(TtlEntry::new("b".to_string(), 1_000_000, 2));
+
+        assert_eq!(1, ttl_cache.remove_id(&"a".to_string()).unwrap().data);
+        assert!(ttl_cache.remove_id(&"a".to_string()).is_none());
+        assert!(!ttl_cache.contains(&"a".to_string()));
+        assert_eq!(1, ttl_cache.len());
+
+        ttl_cache.clear();
+        assert!(ttl_cache.is_empty());
+    }
+
     #[test]
     fn test_ttl_cache_expiry() {
         let entry_1 = TtlEntry::new(
@@ -289,4 +391,91 @@ mod tests {
         assert!(!cache.contains(&entry_1.id));
         assert!(!cache.contains(&entry_2.id));
     }
+
+    #[test]
+    fn test_ttl_cache_reinsert_keeps_newest_ttl() {
+        let short = Duration::milliseconds(100).whole_microseconds();
+        let long = Duration::seconds(10).whole_microseconds();
+
+        let mut cache = TtlCache::new();
+
+        cache.insert(TtlEntry::new("0x1".to_string(), short, 0));
+        cache.insert(TtlEntry::new("0x1".to_string(), long, 1));
+
+        assert_eq!(1, cache.len());
+
+        // expiry of the replaced entry must not remove the new one
+        sleep(StdDuration::from_millis(150));
+        assert_eq!(1, cache.get(&"0x1".to_string()).unwrap().data);
+    }
+
+    #[test]
+    fn test_ttl_cache_len_and_purge_expired() {
+        let short = Duration::milliseconds(100).whole_microseconds();
+        let long = Duration::seconds(10).whole_microseconds();
+
+        let mut cache = TtlCache::new();
+        assert!(cache.is_empty());
+
+        cache.insert(TtlEntry::new("0x1".to_string(), short, 0));
+        cache.insert(TtlEntry::new("0x2".to_string(), short, 0));
+        cache.insert(TtlEntry::new("0x3".to_string(), long, 0));
+
+        assert_eq!(3, cache.len());
+        assert_eq!(0, cache.purge_expired());
+
+        sleep(StdDuration::from_millis(150));
+
+        // expired entries are counted until purged
+        assert_eq!(3, cache.len());
+        assert_eq!(2, cache.purge_expired());
+        assert_eq!(1, cache.len());
+        assert!(cache.contains(&"0x3".to_string()));
+    }
+
+    #[test]
+    fn test_ttl_cache_max_capacity_evicts_closest_to_expiry() {
+        let ttl = Duration::seconds(10).whole_microseconds();
+
+        let mut cache = TtlCache::with_max_capacity(2);
+
+        cache.insert(TtlEntry::new("0x1".to_string(), ttl, 0));
+        cache.insert(TtlEntry::new("0x2".to_string(), ttl, 0));
+        // re-inserting an existing id does not evict
+        cache.insert(TtlEntry::new("0x1".to_string(), ttl, 1));
+
+        assert_eq!(2, cache.len());
+        assert_eq!(0, cache.evictions());
+
+        cache.insert(TtlEntry::new("0x3".to_string(), ttl, 0));
+
+        assert_eq!(2, cache.len());
+        assert_eq!(1, cache.evictions());
+        assert!(!cache.contains(&"0x2".to_string()));
+        assert!(cache.contains(&"0x1".to_string()));
+        assert!(cache.contains(&"0x3".to_string()));
+    }
+
+    #[test]
+    #[should_panic(expected = "max_capacity must be positive!")]
+    fn test_ttl_cache_zero_max_capacity() {
+        TtlCache::<String, ()>::with_max_capacity(0);
+    }
+
+    #[test]
+    fn test_ttl_cache_max_capacity_prefers_purging_expired() {
+        let short = Duration::milliseconds(100).whole_microseconds();
+        let long = Duration::seconds(10).whole_microseconds();
+
+        let mut cache = TtlCache::with_max_capacity(2);
+
+        cache.insert(TtlEntry::new("0x1".to_string(), short, 0));
+        cache.insert(TtlEntry::new("0x2".to_string(), long, 0));
+
+        sleep(StdDuration::from_millis(150));
+        cache.insert(TtlEntry::new("0x3".to_string(), long, 0));
+
+        assert_eq!(2, cache.len());
+        assert_eq!(0, cache.evictions());
+    }
 }
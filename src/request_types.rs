@@ -1,5 +1,6 @@
 //! REST request types
 //!
+use crate::clients::errors::ClientError;
 use crate::response_types::{BuySell, LedgerEntryType, OrderFlag, OrderType};
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
@@ -8,6 +9,9 @@ use serde_with::StringWithSeparator;
 use serde_with::{serde_as, skip_serializing_none};
 use simple_builder::Builder;
 use std::fmt::{Display, Formatter};
+use std::time::Duration;
+use time::format_description::well_known::Rfc3339;
+use time::{OffsetDateTime, UtcOffset};
 use to_query_params::{QueryParams, ToQueryParams};
 
 /// Wrapper type for submitting order cancels by Kraken id (String) or user-ref (Int).
@@ -141,10 +145,50 @@ impl Display for TradeType {
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct OrderFlags(Vec<OrderFlag>);
 
+/// Pairs of flags that Kraken rejects when given together.
+const INCOMPATIBLE_ORDER_FLAGS: [(OrderFlag, OrderFlag, &str); 2] = [
+    (
+        OrderFlag::FeesInBase,
+        OrderFlag::FeesInQuote,
+        "fees can't be taken in both the base (fcib) and quote (fciq) currency",
+    ),
+    (
+        OrderFlag::Post,
+        OrderFlag::OrderVolumeInQuote,
+        "post only (post) is for limit orders, while volume in quote (viqc) is for market orders",
+    ),
+];
+
 impl OrderFlags {
     pub fn new(order_flags: Vec<OrderFlag>) -> OrderFlags {
         OrderFlags(order_flags)
     }
+
+    /// Create flags, rejecting combinations Kraken is known not to accept, like `post` and `viqc`.
+    pub fn try_new(order_flags: Vec<OrderFlag>) -> Result<OrderFlags, ClientError> {
+        let order_flags = OrderFlags(order_flags);
+        order_flags.validate()?;
+        Ok(order_flags)
+    }
+
+    /// Check that no two flags are known to be incompatible.
+    pub fn validate(&self) -> Result<(), ClientError> {
+        for (a, b, reason) in INCOMPATIBLE_ORDER_FLAGS {
+            if self.contains(a) && self.contains(b) {
+                return Err(ClientError::InvalidRequest(reason));
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn contains(&self, flag: OrderFlag) -> bool {
+        self.0.contains(&flag)
+    }
+
+    pub fn flags(&self) -> &[OrderFlag] {
+        &self.0
+    }
 }
 
 impl From<OrderFlag> for OrderFlags {
@@ -161,7 +205,7 @@ impl Display for OrderFlags {
 }
 
 /// Type of report to request generation for.
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ReportType {
     Trades,
     Ledgers,
@@ -177,7 +221,7 @@ impl Display for ReportType {
 }
 
 /// Format of report, either comma or tab separated values.
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ReportFormatType {
     Csv,
     Tsv,
@@ -193,7 +237,7 @@ impl Display for ReportFormatType {
 }
 
 /// Whether to cancel or delete a requested export report.
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum DeleteExportType {
     Cancel,
     Delete,
@@ -272,6 +316,53 @@ pub enum TimeInForceV2 {
     GTD,
 }
 
+impl TimeInForce {
+    /// Check `expire_time` is set for GTD orders, which Kraken requires, and not for IOC orders,
+    /// which can't rest on the book until then.
+    ///
+    /// An `expire_time` of "0" means no expiry, so it counts as unset.
+    pub fn validate_expire_time(&self, expire_time: Option<&str>) -> Result<(), ClientError> {
+        let has_expiry = expire_time.is_some_and(|time| time != "0");
+
+        match (self, has_expiry) {
+            (TimeInForce::GTD, false) => Err(ClientError::InvalidRequest(
+                "GTD orders require an expire_time",
+            )),
+            (TimeInForce::IOC, true) => Err(ClientError::InvalidRequest(
+                "IOC orders cannot have an expire_time",
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl TimeInForceV2 {
+    /// See [TimeInForce::validate_expire_time].
+    pub fn validate_expire_time(&self, expire_time: Option<&str>) -> Result<(), ClientError> {
+        TimeInForce::from(*self).validate_expire_time(expire_time)
+    }
+}
+
+impl From<TimeInForceV2> for TimeInForce {
+    fn from(value: TimeInForceV2) -> Self {
+        match value {
+            TimeInForceV2::GTC => TimeInForce::GTC,
+            TimeInForceV2::IOC => TimeInForce::IOC,
+            TimeInForceV2::GTD => TimeInForce::GTD,
+        }
+    }
+}
+
+impl From<TimeInForce> for TimeInForceV2 {
+    fn from(value: TimeInForce) -> Self {
+        match value {
+            TimeInForce::GTC => TimeInForceV2::GTC,
+            TimeInForce::IOC => TimeInForceV2::IOC,
+            TimeInForce::GTD => TimeInForceV2::GTD,
+        }
+    }
+}
+
 impl Display for TimeInForce {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -282,6 +373,76 @@ impl Display for TimeInForce {
     }
 }
 
+/// A point in time for an order's start, expiry or deadline, given either absolutely or as a
+/// duration from now.
+///
+/// REST order start and expire times are formatted as a unix timestamp, or Kraken's relative
+/// `+<seconds>` syntax, with [Deadline::to_order_time]. Deadlines and all websocket order times are
+/// RFC3339, formatted with [Deadline::to_rfc3339].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Deadline {
+    Absolute(OffsetDateTime),
+    Relative(Duration),
+}
+
+impl Deadline {
+    /// The time `duration` after now.
+    pub fn from_now(duration: Duration) -> Self {
+        Deadline::Relative(duration)
+    }
+
+    /// The absolute time of this deadline if `now` is the current time, or
+    /// [ClientError::InvalidRequest] if a relative deadline is too far away to represent.
+    pub fn resolve(&self, now: OffsetDateTime) -> Result<OffsetDateTime, ClientError> {
+        match self {
+            Deadline::Absolute(time) => Ok(*time),
+            Deadline::Relative(duration) => time::Duration::try_from(*duration)
+                .ok()
+                .and_then(|duration| now.checked_add(duration))
+                .ok_or(ClientError::InvalidRequest(
+                    "deadline is too far in the future",
+                )),
+        }
+    }
+
+    /// Format the time as RFC3339 in UTC, resolving relative times against the current time.
+    pub fn to_rfc3339(&self) -> Result<String, ClientError> {
+        self.resolve(OffsetDateTime::now_utc())?
+            .to_offset(UtcOffset::UTC)
+            .format(&Rfc3339)
+            .map_err(|_| ClientError::InvalidRequest("deadline cannot be formatted as RFC3339"))
+    }
+
+    /// Format the time for REST `starttm` and `expiretm`, a unix timestamp when absolute or
+    /// `+<seconds>` from when Kraken receives the order when relative.
+    ///
+    /// Relative times are rounded up to whole seconds, so sub-second durations aren't truncated to
+    /// `+0`.
+    pub fn to_order_time(&self) -> String {
+        match self {
+            Deadline::Absolute(time) => time.unix_timestamp().to_string(),
+            Deadline::Relative(duration) => {
+                let seconds = duration
+                    .as_secs()
+                    .saturating_add(u64::from(duration.subsec_nanos() > 0));
+                format!("+{seconds}")
+            }
+        }
+    }
+}
+
+impl From<OffsetDateTime> for Deadline {
+    fn from(value: OffsetDateTime) -> Self {
+        Deadline::Absolute(value)
+    }
+}
+
+impl From<Duration> for Deadline {
+    fn from(value: Duration) -> Self {
+        Deadline::Relative(value)
+    }
+}
+
 /// Type of lock-up for a given Earn strategy.
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
 pub enum LockType {
@@ -303,7 +464,7 @@ impl Display for LockType {
 }
 
 /// Wrapper type for a `Vec<String>` that serializes to comma-separated.
-#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
 pub struct StringCSV(pub Vec<String>);
 
 impl StringCSV {
@@ -339,7 +500,7 @@ impl Display for StringCSV {
 /// A request for details on a particular asset, such as "BTC", "ETH", or "USDC".
 ///
 /// [StringCSV] takes a `Vec<String>` and formats them in queries as comma-separated.
-#[derive(Debug, Clone, QueryParams, Builder)]
+#[derive(Debug, Clone, QueryParams, Builder, PartialEq, Eq)]
 pub struct AssetInfoRequest {
     pub asset: Option<StringCSV>,
     #[query(rename = "aclass")]
@@ -349,7 +510,7 @@ pub struct AssetInfoRequest {
 /// A request for details on a particular trading pair, such as "BTCUSD", "DOGEUSDT", or "ETHUSD".
 ///
 /// [StringCSV] takes a `Vec<String>` and formats them in queries as comma-separated.
-#[derive(Debug, Clone, QueryParams, Builder)]
+#[derive(Debug, Clone, QueryParams, Builder, PartialEq, Eq)]
 pub struct TradableAssetPairsRequest {
     pub pair: Option<StringCSV>,
     pub info: Option<AssetPairInfo>,
@@ -359,14 +520,14 @@ pub struct TradableAssetPairsRequest {
 /// A request for common ticker info for one or many pairs.
 ///
 /// [StringCSV] takes a `Vec<String>` and formats them in queries as comma-separated.
-#[derive(Debug, Clone, QueryParams, Builder)]
+#[derive(Debug, Clone, QueryParams, Builder, PartialEq, Eq)]
 pub struct TickerRequest {
     pub pair: Option<StringCSV>,
 }
 
 /// A request for OHLC data for a single pair, optionally providing a `since` to retrieve
 /// incremental updates.
-#[derive(Debug, Clone, QueryParams, Builder)]
+#[derive(Debug, Clone, QueryParams, Builder, PartialEq, Eq)]
 pub struct OHLCRequest {
     #[query(required)]
     #[builder(required)]
@@ -377,7 +538,7 @@ pub struct OHLCRequest {
 
 /// A request for the orderbook of a pair, optionally at a given depth of bids and asks
 /// (`count` parameter).
-#[derive(Debug, Clone, QueryParams, Builder)]
+#[derive(Debug, Clone, QueryParams, Builder, PartialEq, Eq)]
 pub struct OrderbookRequest {
     #[query(required)]
     #[builder(required)]
@@ -391,7 +552,7 @@ pub struct OrderbookRequest {
 /// value provided in the response for full pagination.
 ///
 /// See examples/live_retrieving_recent_traders.rs for an example of completing a paginated request.
-#[derive(Debug, Clone, QueryParams, Builder)]
+#[derive(Debug, Clone, QueryParams, Builder, PartialEq, Eq)]
 pub struct RecentTradesRequest {
     #[query(required)]
     #[builder(required)]
@@ -402,7 +563,7 @@ pub struct RecentTradesRequest {
 
 /// Retrieve the most recent bid/ask spreads for a given pair, optionally with a `since` parameter
 /// to receive only incremental updates.
-#[derive(Debug, Clone, QueryParams, Builder)]
+#[derive(Debug, Clone, QueryParams, Builder, PartialEq, Eq)]
 pub struct RecentSpreadsRequest {
     #[query(required)]
     #[builder(required)]
@@ -411,7 +572,7 @@ pub struct RecentSpreadsRequest {
 }
 
 /// A request for margin trading data, optionally only for a specific pair.
-#[derive(Debug, Clone, QueryParams, Builder)]
+#[derive(Debug, Clone, QueryParams, Builder, PartialEq, Eq)]
 pub struct TradeBalanceRequest {
     pub asset: Option<String>,
 }
@@ -420,7 +581,7 @@ pub struct TradeBalanceRequest {
 ///
 /// Optionally returns trades associated with each order if `trades` is true, and can be filtered by
 /// a provided user ref value.
-#[derive(Debug, Clone, QueryParams, Builder)]
+#[derive(Debug, Clone, QueryParams, Builder, PartialEq, Eq)]
 pub struct OpenOrdersRequest {
     pub trades: Option<bool>,
     pub userref: Option<i64>,
@@ -432,7 +593,7 @@ pub struct OpenOrdersRequest {
 ///
 /// `start` and `end` provide epoch-time bounds to query, while offset provides pagination within
 /// that window.
-#[derive(Debug, Clone, QueryParams, Builder)]
+#[derive(Debug, Clone, QueryParams, Builder, PartialEq, Eq)]
 pub struct ClosedOrdersRequest {
     pub trades: Option<bool>,
     pub userref: Option<i64>,
@@ -444,12 +605,13 @@ pub struct ClosedOrdersRequest {
     pub close_time: Option<CloseTime>,
     #[query(rename = "cl_ord_id")]
     pub client_order_id: Option<String>,
+    pub consolidate_taker: Option<bool>,
 }
 
 /// A request for the details of up to 50 orders by id.
 ///
 /// Optionally including trade ids, filtering by user-ref, and consolidating trades by taker.
-#[derive(Debug, Clone, QueryParams, Builder)]
+#[derive(Debug, Clone, QueryParams, Builder, PartialEq, Eq)]
 pub struct OrderRequest {
     #[builder(required)]
     #[query(required, rename = "txid")]
@@ -459,7 +621,7 @@ pub struct OrderRequest {
     pub consolidate_taker: Option<bool>,
 }
 
-#[derive(Debug, Clone, Serialize, Builder)]
+#[derive(Debug, Clone, Serialize, Builder, PartialEq, Eq)]
 pub struct OrderAmendsRequest {
     #[builder(required)]
     order_id: String,
@@ -483,7 +645,7 @@ pub struct TradesHistoryRequest {
 }
 
 /// A request for details of up to 50 trades by ref id.
-#[derive(Debug, Clone, QueryParams, Builder)]
+#[derive(Debug, Clone, QueryParams, Builder, PartialEq, Eq)]
 pub struct TradeInfoRequest {
     #[builder(required)]
     #[query(required, rename = "txid")]
@@ -492,7 +654,7 @@ pub struct TradeInfoRequest {
 }
 
 /// A request for details about an open margin position.
-#[derive(Debug, Clone, QueryParams, Builder)]
+#[derive(Debug, Clone, QueryParams, Builder, PartialEq, Eq)]
 pub struct OpenPositionsRequest {
     #[query(rename = "txid")]
     pub tx_id: Option<String>,
@@ -505,7 +667,7 @@ pub struct OpenPositionsRequest {
 ///
 /// This request is fully paginated by epoch time using the `start` and `end` parameters, in
 /// conjunction with the `offset` parameter.
-#[derive(Debug, Clone, QueryParams, Builder)]
+#[derive(Debug, Clone, QueryParams, Builder, PartialEq, Eq)]
 pub struct LedgersInfoRequest {
     pub asset: Option<StringCSV>,
     #[query(rename = "aclass")]
@@ -520,7 +682,7 @@ pub struct LedgersInfoRequest {
 }
 
 /// A request for details of up to 20 ledger entries by id.
-#[derive(Debug, Clone, QueryParams, Builder)]
+#[derive(Debug, Clone, QueryParams, Builder, PartialEq, Eq)]
 pub struct QueryLedgerRequest {
     #[query(required)]
     #[builder(required)]
@@ -531,13 +693,13 @@ pub struct QueryLedgerRequest {
 /// A request for cumulative 30-day USD trading volume for the account.
 ///
 /// Optionally including fees if a particular pairs are requested.
-#[derive(Debug, Clone, QueryParams, Builder)]
+#[derive(Debug, Clone, QueryParams, Builder, PartialEq, Eq)]
 pub struct TradeVolumeRequest {
     pub pair: Option<StringCSV>,
 }
 
 /// A request for the asynchronous generation of a report of "trades" or "ledgers".
-#[derive(Debug, Clone, QueryParams, Builder)]
+#[derive(Debug, Clone, QueryParams, Builder, PartialEq, Eq)]
 pub struct ExportReportRequest {
     #[builder(required)]
     #[query(required)]
@@ -554,7 +716,7 @@ pub struct ExportReportRequest {
 }
 
 /// A request for the status of a requested export report.
-#[derive(Debug, Clone, QueryParams, Builder)]
+#[derive(Debug, Clone, QueryParams, Builder, PartialEq, Eq)]
 pub struct ExportReportStatusRequest {
     #[builder(required)]
     #[query(required)]
@@ -562,7 +724,7 @@ pub struct ExportReportStatusRequest {
 }
 
 /// A request to retrieve a specific export report by id.
-#[derive(Debug, Clone, QueryParams, Builder)]
+#[derive(Debug, Clone, QueryParams, Builder, PartialEq, Eq)]
 pub struct RetrieveExportReportRequest {
     #[builder(required)]
     #[query(required)]
@@ -570,7 +732,7 @@ pub struct RetrieveExportReportRequest {
 }
 
 /// A request to delete an export report by id.
-#[derive(Debug, Clone, QueryParams, Builder)]
+#[derive(Debug, Clone, QueryParams, Builder, PartialEq, Eq)]
 pub struct DeleteExportRequest {
     #[builder(required)]
     #[query(required)]
@@ -629,9 +791,65 @@ pub struct AddOrderRequest {
     pub validate: Option<bool>,
 }
 
+impl AddOrderRequest {
+    /// Start building a limit order at `price`, which can be further customized before building.
+    pub fn limit(
+        side: BuySell,
+        volume: Decimal,
+        pair: String,
+        price: Decimal,
+    ) -> AddOrderRequestBuilder {
+        let mut builder = AddOrderRequest::builder(OrderType::Limit, side, volume, pair);
+        builder.price(price);
+        builder
+    }
+
+    /// Start building a market order, which can be further customized before building.
+    pub fn market(side: BuySell, volume: Decimal, pair: String) -> AddOrderRequestBuilder {
+        AddOrderRequest::builder(OrderType::Market, side, volume, pair)
+    }
+
+    /// Check the `expire_time` is consistent with the `time_in_force`, see
+    /// [TimeInForce::validate_expire_time].
+    pub fn validate(&self) -> Result<(), ClientError> {
+        match &self.time_in_force {
+            Some(time_in_force) => time_in_force.validate_expire_time(self.expire_time.as_deref()),
+            None => Ok(()),
+        }
+    }
+}
+
+impl AddOrderRequestBuilder {
+    /// Set `start_time` from a [Deadline], see [Deadline::to_order_time].
+    pub fn start_at(&mut self, start: impl Into<Deadline>) -> &mut Self {
+        self.start_time = Some(start.into().to_order_time());
+        self
+    }
+
+    /// Set `expire_time` from a [Deadline], see [Deadline::to_order_time].
+    pub fn expire_at(&mut self, expiry: impl Into<Deadline>) -> &mut Self {
+        self.expire_time = Some(expiry.into().to_order_time());
+        self
+    }
+
+    /// Set the RFC3339 `deadline` from a [Deadline], resolving relative deadlines now.
+    pub fn deadline_at(&mut self, deadline: impl Into<Deadline>) -> Result<&mut Self, ClientError> {
+        self.deadline = Some(deadline.into().to_rfc3339()?);
+        Ok(self)
+    }
+
+    /// Build the order, returning an error if a GTD order has no `expire_time` or an IOC order
+    /// has one.
+    pub fn try_build(&mut self) -> Result<AddOrderRequest, ClientError> {
+        let request = self.build();
+        request.validate()?;
+        Ok(request)
+    }
+}
+
 /// A request to create up to 15 spot orders in a batch.
 #[skip_serializing_none]
-#[derive(Debug, Clone, Serialize, Builder)]
+#[derive(Debug, Clone, Serialize, Builder, PartialEq, Eq)]
 pub struct AddBatchedOrderRequest {
     #[builder(required)]
     pub orders: Vec<BatchedOrderRequest>,
@@ -641,10 +859,55 @@ pub struct AddBatchedOrderRequest {
     pub validate: Option<bool>,
 }
 
+impl AddBatchedOrderRequest {
+    /// The most orders Kraken accepts in a single batch.
+    pub const MAX_ORDERS: usize = 15;
+
+    /// Start building an empty batch for `pair`, adding orders with
+    /// [AddBatchedOrderRequestBuilder::push_order].
+    pub fn for_pair(pair: impl ToString) -> AddBatchedOrderRequestBuilder {
+        AddBatchedOrderRequest::builder(Vec::new(), pair.to_string())
+    }
+
+    /// Check the batch has at least one order and no more than [Self::MAX_ORDERS], and that each
+    /// order is valid, see [BatchedOrderRequest::validate].
+    pub fn validate(&self) -> Result<(), ClientError> {
+        if self.orders.is_empty() {
+            Err(ClientError::InvalidRequest(
+                "batch must contain at least one order",
+            ))
+        } else if self.orders.len() > Self::MAX_ORDERS {
+            Err(ClientError::InvalidRequest(
+                "batch exceeds the maximum of 15 orders",
+            ))
+        } else {
+            self.orders
+                .iter()
+                .try_for_each(BatchedOrderRequest::validate)
+        }
+    }
+}
+
+impl AddBatchedOrderRequestBuilder {
+    /// Add an order to the end of the batch.
+    pub fn push_order(&mut self, order: BatchedOrderRequest) -> &mut Self {
+        self.orders.get_or_insert_with(Vec::new).push(order);
+        self
+    }
+
+    /// Build the batch, returning an error if it's empty, exceeds
+    /// [AddBatchedOrderRequest::MAX_ORDERS] or contains an invalid order.
+    pub fn try_build(&mut self) -> Result<AddBatchedOrderRequest, ClientError> {
+        let request = self.build();
+        request.validate()?;
+        Ok(request)
+    }
+}
+
 /// An individual order request to be placed in a batch.
 #[serde_as]
 #[skip_serializing_none]
-#[derive(Debug, Clone, Builder, Serialize)]
+#[derive(Debug, Clone, Builder, Serialize, PartialEq, Eq)]
 pub struct BatchedOrderRequest {
     #[serde(rename = "userref")]
     pub user_ref: Option<i64>,
@@ -680,7 +943,32 @@ pub struct BatchedOrderRequest {
     pub expire_time: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Builder)]
+impl BatchedOrderRequest {
+    /// Check the `expire_time` is consistent with the `time_in_force`, see
+    /// [TimeInForce::validate_expire_time].
+    pub fn validate(&self) -> Result<(), ClientError> {
+        match &self.time_in_force {
+            Some(time_in_force) => time_in_force.validate_expire_time(self.expire_time.as_deref()),
+            None => Ok(()),
+        }
+    }
+}
+
+impl BatchedOrderRequestBuilder {
+    /// Build the order, returning an error if a GTD order has no `expire_time` or an IOC order
+    /// has one.
+    pub fn try_build(&mut self) -> Result<BatchedOrderRequest, ClientError> {
+        let order = self.build();
+        order.validate()?;
+        Ok(order)
+    }
+}
+
+/// A request to amend an order in place, identified by exactly one of `tx_id` or `client_order_id`.
+///
+/// Use [AmendOrderRequestBuilder::try_build] to check the order is identified correctly.
+#[skip_serializing_none]
+#[derive(Debug, Clone, Serialize, Builder, PartialEq, Eq)]
 pub struct AmendOrderRequest {
     #[serde(rename = "txid")]
     pub tx_id: Option<String>,
@@ -696,8 +984,33 @@ pub struct AmendOrderRequest {
     pub deadline: Option<String>, // RFC-3339
 }
 
+impl AmendOrderRequest {
+    /// Check that the order to amend is identified by exactly one of `tx_id` or `client_order_id`.
+    pub fn validate(&self) -> Result<(), ClientError> {
+        match (&self.tx_id, &self.client_order_id) {
+            (Some(_), None) | (None, Some(_)) => Ok(()),
+            (Some(_), Some(_)) => Err(ClientError::InvalidRequest(
+                "amend must set only one of tx_id or client_order_id",
+            )),
+            (None, None) => Err(ClientError::InvalidRequest(
+                "amend must set one of tx_id or client_order_id",
+            )),
+        }
+    }
+}
+
+impl AmendOrderRequestBuilder {
+    /// Build the request, returning an error unless exactly one of `tx_id` or `client_order_id`
+    /// is set.
+    pub fn try_build(&mut self) -> Result<AmendOrderRequest, ClientError> {
+        let request = self.build();
+        request.validate()?;
+        Ok(request)
+    }
+}
+
 /// A request to edit an existing order.
-#[derive(Debug, Clone, QueryParams, Builder)]
+#[derive(Debug, Clone, QueryParams, Builder, PartialEq, Eq)]
 pub struct EditOrderRequest {
     #[query(rename = "userref")]
     pub user_ref: Option<i64>,
@@ -723,7 +1036,7 @@ pub struct EditOrderRequest {
 }
 
 /// A request to cancel an order by txid (String) or userref (Int).
-#[derive(Debug, Clone, QueryParams, Builder)]
+#[derive(Debug, Clone, QueryParams, Builder, PartialEq, Eq)]
 pub struct CancelOrderRequest {
     #[query(required, rename = "txid")]
     #[builder(required)]
@@ -736,7 +1049,7 @@ pub struct CancelOrderRequest {
 ///
 /// Once set to a timestamp, this must be continually called to prevent all orders from being
 /// cancelled.
-#[derive(Debug, Clone, QueryParams, Builder)]
+#[derive(Debug, Clone, QueryParams, Builder, PartialEq, Eq)]
 pub struct CancelAllOrdersAfterRequest {
     #[builder(required)]
     #[query(required)]
@@ -744,7 +1057,7 @@ pub struct CancelAllOrdersAfterRequest {
 }
 
 /// A request to cancel up to 50 orders in a batch by tx id or user ref.
-#[derive(Debug, Clone, Builder, Serialize)]
+#[derive(Debug, Clone, Builder, Serialize, PartialEq, Eq)]
 pub struct CancelBatchOrdersRequest {
     #[builder(required)]
     pub orders: Vec<IntOrString>,
@@ -776,7 +1089,7 @@ impl CancelBatchOrdersRequest {
 }
 
 /// A request for all available deposit methods for a given asset.
-#[derive(Debug, Clone, Builder, QueryParams)]
+#[derive(Debug, Clone, Builder, QueryParams, PartialEq, Eq)]
 pub struct DepositMethodsRequest {
     #[builder(required)]
     #[query(required)]
@@ -785,7 +1098,7 @@ pub struct DepositMethodsRequest {
 }
 
 /// A request to retrieve or generate a deposit address for a particular asset and method.
-#[derive(Debug, Clone, Builder, QueryParams)]
+#[derive(Debug, Clone, Builder, QueryParams, PartialEq, Eq)]
 pub struct DepositAddressesRequest {
     #[query(required)]
     #[builder(required)]
@@ -799,7 +1112,7 @@ pub struct DepositAddressesRequest {
 }
 
 /// A request for all available withdrawal methods for the user.
-#[derive(Debug, Clone, Builder, QueryParams)]
+#[derive(Debug, Clone, Builder, QueryParams, PartialEq, Eq)]
 pub struct WithdrawalMethodsRequest {
     pub asset: Option<String>,
     #[query(rename = "aclass")]
@@ -808,7 +1121,7 @@ pub struct WithdrawalMethodsRequest {
 }
 
 /// A request to retrieve or generate a withdrawal address for a particular asset and method.
-#[derive(Debug, Clone, Builder, QueryParams)]
+#[derive(Debug, Clone, Builder, QueryParams, PartialEq, Eq)]
 pub struct WithdrawalAddressesRequest {
     pub asset: Option<String>,
     #[query(rename = "aclass")]
@@ -819,7 +1132,7 @@ pub struct WithdrawalAddressesRequest {
 }
 
 /// A sub-type for specifying if paginating (Bool), or providing a cursor for the next page (String).
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Cursor {
     String(String),
     Bool(bool),
@@ -835,7 +1148,7 @@ impl Display for Cursor {
 }
 
 /// A request for the status of a deposit or withdrawal request.
-#[derive(Debug, Clone, Builder, QueryParams)]
+#[derive(Debug, Clone, Builder, QueryParams, PartialEq, Eq)]
 pub struct StatusOfDepositWithdrawRequest {
     pub asset: Option<String>,
     #[query(rename = "aclass")]
@@ -848,7 +1161,7 @@ pub struct StatusOfDepositWithdrawRequest {
 }
 
 /// A request for the limit, amount and fee to withdraw asset.
-#[derive(Debug, Clone, Builder, QueryParams)]
+#[derive(Debug, Clone, Builder, QueryParams, PartialEq, Eq)]
 pub struct WithdrawalInfoRequest {
     #[builder(required)]
     #[query(required)]
@@ -862,7 +1175,7 @@ pub struct WithdrawalInfoRequest {
 }
 
 /// A request to withdraw funds.
-#[derive(Debug, Clone, Builder, QueryParams)]
+#[derive(Debug, Clone, Builder, QueryParams, PartialEq, Eq)]
 pub struct WithdrawFundsRequest {
     #[builder(required)]
     #[query(required)]
@@ -878,7 +1191,7 @@ pub struct WithdrawFundsRequest {
 }
 
 /// A request to cancel an active withdrawal.
-#[derive(Debug, Clone, Builder, QueryParams)]
+#[derive(Debug, Clone, Builder, QueryParams, PartialEq, Eq)]
 pub struct WithdrawCancelRequest {
     #[builder(required)]
     #[query(required)]
@@ -889,7 +1202,7 @@ pub struct WithdrawCancelRequest {
 }
 
 /// A request to transfer from the account's Spot wallet to Future's wallet.
-#[derive(Debug, Clone, Builder, QueryParams)]
+#[derive(Debug, Clone, Builder, QueryParams, PartialEq, Eq)]
 pub struct WalletTransferRequest {
     #[builder(required)]
     #[query(required)]
@@ -906,7 +1219,7 @@ pub struct WalletTransferRequest {
 }
 
 /// A request to create a sub-account for trading.
-#[derive(Debug, Clone, Builder, QueryParams)]
+#[derive(Debug, Clone, Builder, QueryParams, PartialEq, Eq)]
 pub struct CreateSubAccountRequest {
     #[builder(required)]
     #[query(required)]
@@ -917,7 +1230,7 @@ pub struct CreateSubAccountRequest {
 }
 
 /// A request to transfer assets between sub-accounts.
-#[derive(Debug, Clone, Builder, QueryParams)]
+#[derive(Debug, Clone, Builder, QueryParams, PartialEq, Eq)]
 pub struct AccountTransferRequest {
     #[builder(required)]
     #[query(required)]
@@ -934,7 +1247,7 @@ pub struct AccountTransferRequest {
 }
 
 /// A request to allocate funds to a particular Earn strategy.
-#[derive(Debug, Clone, Builder, QueryParams)]
+#[derive(Debug, Clone, Builder, QueryParams, PartialEq, Eq)]
 pub struct AllocateEarnFundsRequest {
     #[builder(required)]
     #[query(required)]
@@ -945,7 +1258,7 @@ pub struct AllocateEarnFundsRequest {
 }
 
 /// A request for the allocation status for a given strategy.
-#[derive(Debug, Clone, Builder, QueryParams)]
+#[derive(Debug, Clone, Builder, QueryParams, PartialEq, Eq)]
 pub struct EarnAllocationStatusRequest {
     #[builder(required)]
     #[query(required)]
@@ -955,7 +1268,7 @@ pub struct EarnAllocationStatusRequest {
 /// A request for all earn strategies.
 ///
 /// Pagination is available via the `cursor` and `limit` parameters.
-#[derive(Debug, Clone, Builder, QueryParams)]
+#[derive(Debug, Clone, Builder, QueryParams, PartialEq, Eq)]
 pub struct ListEarnStrategiesRequest {
     pub ascending: Option<bool>,
     pub asset: Option<String>,
@@ -965,7 +1278,7 @@ pub struct ListEarnStrategiesRequest {
 }
 
 /// A request to list all current earn strategy allocations.
-#[derive(Debug, Clone, Builder, QueryParams)]
+#[derive(Debug, Clone, Builder, QueryParams, PartialEq, Eq)]
 pub struct ListEarnAllocationsRequest {
     pub ascending: Option<bool>,
     pub converted_asset: Option<String>,
@@ -974,8 +1287,78 @@ pub struct ListEarnAllocationsRequest {
 
 #[cfg(test)]
 mod tests {
-    use crate::request_types::{CancelBatchOrdersRequest, IntOrString, OrderFlags, StringCSV};
-    use crate::response_types::OrderFlag;
+    use crate::clients::errors::ClientError;
+    use crate::request_types::{
+        AddBatchedOrderRequest, AddOrderRequest, AmendOrderRequest, BatchedOrderRequest,
+        CancelBatchOrdersRequest, Deadline, EditOrderRequest, IntOrString, OrderFlags, StringCSV,
+        TimeInForce,
+    };
+    use crate::response_types::{BuySell, OrderFlag, OrderType};
+    use rust_decimal_macros::dec;
+    use std::time::Duration;
+    use time::format_description::well_known::Rfc3339;
+    use time::macros::datetime;
+    use time::OffsetDateTime;
+    use to_query_params::ToQueryParams;
+
+    #[test]
+    fn test_add_order_request_limit() {
+        let request = AddOrderRequest::limit(BuySell::Buy, dec!(1.5), "XBTUSD".into(), dec!(90.1))
+            .user_ref(42)
+            .build();
+
+        let expected =
+            AddOrderRequest::builder(OrderType::Limit, BuySell::Buy, dec!(1.5), "XBTUSD".into())
+                .price(dec!(90.1))
+                .user_ref(42)
+                .build();
+
+        assert_eq!(expected, request);
+    }
+
+    #[test]
+    fn test_clone_mutate_and_reserialize() {
+        let template = AddOrderRequest::limit(BuySell::Buy, dec!(1), "XBTUSD".into(), dec!(90))
+            .order_flags(OrderFlag::Post.into())
+            .build();
+
+        let mut order = template.clone();
+        order.price = Some(dec!(91));
+        order.user_ref = Some(7);
+
+        assert_eq!(template, template.clone());
+        assert_ne!(template, order);
+
+        let template_params = template.to_query_params();
+        let order_params = order.to_query_params();
+
+        assert!(template_params.contains(&("price".to_string(), "90".to_string())));
+        assert!(order_params.contains(&("price".to_string(), "91".to_string())));
+        assert!(order_params.contains(&("userref".to_string(), "7".to_string())));
+        assert!(order_params.contains(&("oflags".to_string(), "post".to_string())));
+
+        let edit = EditOrderRequest::builder("OABC".into(), dec!(2), "XBTUSD".into()).build();
+        let mut edited = edit.clone();
+        edited.volume = dec!(3);
+
+        assert!(edited
+            .to_query_params()
+            .contains(&("volume".to_string(), "3".to_string())));
+        assert!(edit
+            .to_query_params()
+            .contains(&("volume".to_string(), "2".to_string())));
+    }
+
+    #[test]
+    fn test_add_order_request_market() {
+        let request = AddOrderRequest::market(BuySell::Sell, dec!(2), "ETHUSD".into()).build();
+
+        assert_eq!(OrderType::Market, request.order_type);
+        assert_eq!(BuySell::Sell, request.side);
+        assert_eq!(dec!(2), request.volume);
+        assert_eq!("ETHUSD", request.pair);
+        assert_eq!(None, request.price);
+    }
 
     #[test]
     fn test_cancel_batch_order_request_ids() {
@@ -1018,6 +1401,212 @@ mod tests {
         assert_eq!(expected_order_flag, order_flags);
     }
 
+    #[test]
+    fn test_order_flags_validation() {
+        let flags =
+            OrderFlags::try_new(vec![OrderFlag::NoMarketPriceProtection, OrderFlag::Post]).unwrap();
+
+        assert!(flags.contains(OrderFlag::Post));
+        assert!(!flags.contains(OrderFlag::FeesInBase));
+        assert_eq!("nompp,post", flags.to_string());
+
+        let post_viqc = OrderFlags::try_new(vec![OrderFlag::Post, OrderFlag::OrderVolumeInQuote]);
+        assert!(matches!(post_viqc, Err(ClientError::InvalidRequest(_))));
+
+        let both_fees = OrderFlags::new(vec![OrderFlag::FeesInQuote, OrderFlag::FeesInBase]);
+        assert!(matches!(
+            both_fees.validate(),
+            Err(ClientError::InvalidRequest(_))
+        ));
+    }
+
+    #[test]
+    fn test_deadline_formatting() {
+        let time = datetime!(2024-05-19 18:32:14.5 +02:00);
+
+        assert_eq!("1716136334", Deadline::from(time).to_order_time());
+        assert_eq!(
+            "2024-05-19T16:32:14.5Z",
+            Deadline::from(time).to_rfc3339().unwrap()
+        );
+        assert_eq!(
+            "+91",
+            Deadline::from_now(Duration::from_millis(90_500)).to_order_time()
+        );
+        assert_eq!(
+            "+1",
+            Deadline::from_now(Duration::from_millis(200)).to_order_time()
+        );
+        assert_eq!(
+            "+90",
+            Deadline::from_now(Duration::from_secs(90)).to_order_time()
+        );
+        assert_eq!(
+            datetime!(2024-05-19 16:33:44.5 UTC),
+            Deadline::from(Duration::from_secs(90))
+                .resolve(time)
+                .unwrap()
+        );
+        assert!(matches!(
+            Deadline::from(Duration::MAX).resolve(time),
+            Err(ClientError::InvalidRequest(_))
+        ));
+        assert!(Deadline::from(Duration::MAX).to_rfc3339().is_err());
+
+        let before = OffsetDateTime::now_utc();
+        let relative = Deadline::from_now(Duration::from_secs(5))
+            .to_rfc3339()
+            .unwrap();
+        let relative = OffsetDateTime::parse(&relative, &Rfc3339).unwrap();
+        assert!(relative >= before + Duration::from_secs(5));
+        assert!(relative < before + Duration::from_secs(10));
+    }
+
+    #[test]
+    fn test_add_order_request_deadlines() {
+        let deadline = datetime!(2024-05-19 16:32:14 UTC);
+        let request = AddOrderRequest::limit(BuySell::Buy, dec!(1), "XBTUSD".to_string(), dec!(90))
+            .start_at(deadline)
+            .expire_at(Duration::from_secs(5))
+            .deadline_at(deadline)
+            .unwrap()
+            .build();
+
+        assert_eq!(Some("1716136334".to_string()), request.start_time);
+        assert_eq!(Some("+5".to_string()), request.expire_time);
+        assert_eq!(Some("2024-05-19T16:32:14Z".to_string()), request.deadline);
+
+        let params = request.to_query_params();
+        assert!(params.contains(&("deadline".to_string(), "2024-05-19T16:32:14Z".to_string())));
+        assert!(params.contains(&("starttm".to_string(), "1716136334".to_string())));
+        assert!(params.contains(&("expiretm".to_string(), "+5".to_string())));
+    }
+
+    #[test]
+    fn test_add_order_request_time_in_force_expiry() {
+        let order = || AddOrderRequest::limit(BuySell::Buy, dec!(1), "XBTUSD".into(), dec!(90));
+
+        assert!(order()
+            .time_in_force(TimeInForce::GTD)
+            .expire_at(Duration::from_secs(60))
+            .try_build()
+            .is_ok());
+        assert!(order().time_in_force(TimeInForce::IOC).try_build().is_ok());
+        assert!(order()
+            .time_in_force(TimeInForce::GTC)
+            .expire_time("+60".to_string())
+            .try_build()
+            .is_ok());
+        assert!(order().expire_time("+60".to_string()).try_build().is_ok());
+
+        for invalid in [
+            order().time_in_force(TimeInForce::GTD).try_build(),
+            order()
+                .time_in_force(TimeInForce::GTD)
+                .expire_time("0".to_string())
+                .try_build(),
+            order()
+                .time_in_force(TimeInForce::IOC)
+                .expire_time("+60".to_string())
+                .try_build(),
+        ] {
+            assert!(matches!(invalid, Err(ClientError::InvalidRequest(_))));
+        }
+
+        let gtd = BatchedOrderRequest::builder(OrderType::Limit, BuySell::Buy, dec!(1))
+            .price(dec!(90))
+            .time_in_force(TimeInForce::GTD)
+            .build();
+        assert!(gtd.validate().is_err());
+
+        let batch = AddBatchedOrderRequest::for_pair("XBTUSD")
+            .push_order(gtd)
+            .try_build();
+        assert!(matches!(batch, Err(ClientError::InvalidRequest(_))));
+    }
+
+    #[test]
+    fn test_add_batched_order_request_builder() {
+        let order = |user_ref: i64| {
+            BatchedOrderRequest::builder(OrderType::Limit, BuySell::Buy, dec!(1))
+                .price(dec!(90))
+                .user_ref(user_ref)
+                .client_order_id(format!("order-{user_ref}"))
+                .build()
+        };
+
+        let request = AddBatchedOrderRequest::for_pair("XBTUSD")
+            .push_order(order(1))
+            .push_order(order(2))
+            .deadline("2024-05-19T16:32:14Z".to_string())
+            .try_build()
+            .unwrap();
+
+        assert_eq!(2, request.orders.len());
+        assert_eq!(
+            Some("order-2".to_string()),
+            request.orders[1].client_order_id
+        );
+        assert_eq!(Some("2024-05-19T16:32:14Z".to_string()), request.deadline);
+    }
+
+    #[test]
+    fn test_add_batched_order_request_max_orders() {
+        let mut builder = AddBatchedOrderRequest::for_pair("XBTUSD");
+        for user_ref in 0..AddBatchedOrderRequest::MAX_ORDERS as i64 {
+            builder.push_order(
+                BatchedOrderRequest::builder(OrderType::Market, BuySell::Sell, dec!(1))
+                    .user_ref(user_ref)
+                    .build(),
+            );
+        }
+
+        let full = builder.try_build().unwrap();
+        assert_eq!(15, full.orders.len());
+
+        let mut orders = full.orders.clone();
+        orders.push(orders[0].clone());
+        let too_many = AddBatchedOrderRequest::builder(orders, "XBTUSD".to_string()).try_build();
+        assert!(matches!(too_many, Err(ClientError::InvalidRequest(_))));
+
+        let empty = AddBatchedOrderRequest::for_pair("XBTUSD").try_build();
+        assert!(matches!(empty, Err(ClientError::InvalidRequest(_))));
+    }
+
+    #[test]
+    fn test_amend_order_request_identifiers() {
+        let by_tx_id = AmendOrderRequest::builder()
+            .tx_id("OK7HDB-KCKIZ-3D5HCN".to_string())
+            .order_quantity(dec!(5.25))
+            .try_build()
+            .unwrap();
+        assert_eq!(
+            r#"{"txid":"OK7HDB-KCKIZ-3D5HCN","order_qty":"5.25"}"#,
+            serde_json::to_string(&by_tx_id).unwrap()
+        );
+
+        let by_client_order_id = AmendOrderRequest::builder()
+            .client_order_id("some-uuid".to_string())
+            .limit_price("0.96".to_string())
+            .try_build()
+            .unwrap();
+        assert_eq!(
+            r#"{"cl_ord_id":"some-uuid","limit_price":"0.96"}"#,
+            serde_json::to_string(&by_client_order_id).unwrap()
+        );
+
+        let both = AmendOrderRequest::builder()
+            .tx_id("OK7HDB-KCKIZ-3D5HCN".to_string())
+            .client_order_id("some-uuid".to_string())
+            .try_build();
+        assert!(matches!(both, Err(ClientError::InvalidRequest(_))));
+
+        let neither = AmendOrderRequest::builder()
+            .order_quantity(dec!(1))
+            .try_build();
+        assert!(matches!(neither, Err(ClientError::InvalidRequest(_))));
+    }
+
     #[test]
     fn test_int_or_string_conversions() {
         let expected_int = IntOrString::Int(42);
@@ -1,7 +1,7 @@
 //! REST response types
 use crate::clients::errors::ClientError;
 use crate::crypto::secrets::Token;
-use crate::request_types::TriggerType;
+use crate::request_types::{RecentTradesRequest, TriggerType};
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 use serde_this_or_that::as_i64;
@@ -12,6 +12,9 @@ use serde_with::{serde_as, DisplayFromStr};
 use std::collections::HashMap;
 use std::fmt::{Debug, Display, Formatter};
 use std::str::FromStr;
+use std::time::Duration;
+use time::OffsetDateTime;
+use tokio::time::Instant;
 
 /// A user's level of KYC verification with Kraken
 ///
@@ -19,6 +22,7 @@ use std::str::FromStr;
 #[derive(Debug, Deserialize, Clone, PartialEq, Eq, Copy)]
 #[serde(rename_all = "snake_case")]
 pub enum VerificationTier {
+    Starter,
     Intermediate,
     Pro,
 }
@@ -31,6 +35,22 @@ pub enum SystemStatus {
     Maintenance,
     CancelOnly,
     PostOnly,
+    LimitOnly,
+}
+
+impl SystemStatus {
+    /// Whether new orders are accepted, though possibly only post-only or limit orders.
+    pub fn can_place_orders(&self) -> bool {
+        matches!(
+            self,
+            SystemStatus::Online | SystemStatus::PostOnly | SystemStatus::LimitOnly
+        )
+    }
+
+    /// Whether open orders can be cancelled, which is the case unless in maintenance.
+    pub fn can_cancel_orders(&self) -> bool {
+        !matches!(self, SystemStatus::Maintenance)
+    }
 }
 
 /// Status of a given asset pair for trading (e.g. BTC-USD, ATOM-USD)
@@ -118,6 +138,51 @@ impl FromStr for OrderFlag {
     }
 }
 
+/// Additional details of an order's state, given in the `misc` field of REST orders.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum OrderMisc {
+    /// Triggered by its stop price
+    Stopped,
+    /// Triggered by its touch price
+    Touched,
+    /// Liquidation
+    Liquidated,
+    /// Partially filled
+    Partial,
+    /// Order parameters were modified
+    Amended,
+    /// Any value not documented by Kraken
+    Other(String),
+}
+
+impl Display for OrderMisc {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OrderMisc::Stopped => write!(f, "stopped"),
+            OrderMisc::Touched => write!(f, "touched"),
+            OrderMisc::Liquidated => write!(f, "liquidated"),
+            OrderMisc::Partial => write!(f, "partial"),
+            OrderMisc::Amended => write!(f, "amended"),
+            OrderMisc::Other(misc) => write!(f, "{}", misc),
+        }
+    }
+}
+
+impl FromStr for OrderMisc {
+    type Err = ClientError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "stopped" => Ok(OrderMisc::Stopped),
+            "touched" => Ok(OrderMisc::Touched),
+            "liquidated" => Ok(OrderMisc::Liquidated),
+            "partial" => Ok(OrderMisc::Partial),
+            "amended" => Ok(OrderMisc::Amended),
+            other => Ok(OrderMisc::Other(other.to_string())),
+        }
+    }
+}
+
 /// Whether a given [BidAsk] is a `Bid` or an `Ask`
 #[derive(Debug, Deserialize, Clone, PartialEq, Eq, Copy)]
 #[serde(rename_all = "lowercase")]
@@ -569,6 +634,24 @@ pub struct BidAsk {
     pub time: i64,
 }
 
+impl BidAsk {
+    /// The time of the last update to this price level, from the unix timestamp in `time`.
+    pub fn timestamp(&self) -> Result<OffsetDateTime, ClientError> {
+        OffsetDateTime::from_unix_timestamp(self.time)
+            .map_err(|_| ClientError::Parse("Invalid orderbook timestamp"))
+    }
+}
+
+/// Drops the timestamp, keeping the price and volume as the WSS book's quantity.
+impl From<BidAsk> for crate::wss::BidAsk {
+    fn from(value: BidAsk) -> Self {
+        crate::wss::BidAsk {
+            price: value.price,
+            quantity: value.volume,
+        }
+    }
+}
+
 /// Orderbook containing some depth of bids and asks
 #[derive(Debug, Deserialize, PartialEq, Clone)]
 pub struct Orderbook {
@@ -602,6 +685,17 @@ pub struct RecentTrades {
     pub trades: HashMap<String, Vec<RecentTrade>>,
 }
 
+impl RecentTrades {
+    /// A copy of `request` continuing from this page's `last` cursor, to request the trades
+    /// that followed.
+    pub fn next_request(&self, request: &RecentTradesRequest) -> RecentTradesRequest {
+        RecentTradesRequest {
+            since: Some(self.last),
+            ..request.clone()
+        }
+    }
+}
+
 /// Bid-ask spread at a given time
 #[derive(Debug, Deserialize_tuple, PartialEq, Clone)]
 pub struct Spread {
@@ -626,6 +720,22 @@ pub type AccountBalances = HashMap<String, Decimal>;
 /// Convenience type for asset: extended balances
 pub type ExtendedBalances = HashMap<String, ExtendedBalance>;
 
+/// Helpers for [ExtendedBalances], which is a type alias and can't have its own methods.
+pub trait ExtendedBalancesExt {
+    /// Only the assets with a nonzero `balance` or `hold_trade`, dropping the zero balances Kraken
+    /// returns for assets held previously.
+    fn non_zero(&self) -> ExtendedBalances;
+}
+
+impl ExtendedBalancesExt for ExtendedBalances {
+    fn non_zero(&self) -> ExtendedBalances {
+        self.iter()
+            .filter(|(_, balance)| !balance.is_zero())
+            .map(|(asset, balance)| (asset.clone(), balance.clone()))
+            .collect()
+    }
+}
+
 /// Detailed balance data, including holds and credit (if available)
 #[derive(Debug, Deserialize, PartialEq, Eq, Clone)]
 pub struct ExtendedBalance {
@@ -635,6 +745,18 @@ pub struct ExtendedBalance {
     pub credit_used: Option<Decimal>,
 }
 
+impl ExtendedBalance {
+    /// Whether both the `balance` and `hold_trade` are zero, regardless of any credit.
+    pub fn is_zero(&self) -> bool {
+        self.balance.is_zero() && self.hold_trade.is_zero()
+    }
+
+    /// The balance not held for open orders, `balance - hold_trade`, excluding any credit.
+    pub fn available(&self) -> Decimal {
+        self.balance - self.hold_trade
+    }
+}
+
 /// Detailed margin balance data
 #[derive(Debug, Deserialize, PartialEq, Clone)]
 pub struct TradeBalances {
@@ -713,7 +835,8 @@ pub struct Order {
     pub limit_price: Decimal,
     pub trigger: Option<TriggerType>,
     pub margin: Option<bool>,
-    pub misc: String,
+    #[serde_as(as = "StringWithSeparator::<CommaSeparator, OrderMisc>")]
+    pub misc: Vec<OrderMisc>,
     pub sender_sub_id: Option<String>,
     #[serde(rename = "oflags")]
     #[serde_as(as = "StringWithSeparator::<CommaSeparator, OrderFlag>")]
@@ -753,7 +876,8 @@ pub struct ClosedOrder {
     pub limit_price: Decimal,
     pub trigger: Option<TriggerType>,
     pub margin: Option<bool>,
-    pub misc: String,
+    #[serde_as(as = "StringWithSeparator::<CommaSeparator, OrderMisc>")]
+    pub misc: Vec<OrderMisc>,
     #[serde(rename = "oflags")]
     #[serde_as(as = "StringWithSeparator::<CommaSeparator, OrderFlag>")]
     pub order_flags: Vec<OrderFlag>,
@@ -828,6 +952,42 @@ pub struct OrderAmend {
     pub timestamp: u64,
 }
 
+/// A single change to an order, comparing an amend to the one before it, see
+/// [OrderAmends::timeline].
+#[derive(Debug, PartialEq, Clone)]
+pub struct OrderAmendEntry {
+    pub amend_id: String,
+    pub amend_type: AmendType,
+    pub timestamp: u64,
+    pub old_limit_price: Decimal,
+    pub new_limit_price: Decimal,
+    pub old_quantity: Decimal,
+    pub new_quantity: Decimal,
+    pub reason: Option<String>,
+}
+
+impl OrderAmends {
+    /// Each amend after the first, oldest first, with the price and quantity it changed from and to.
+    pub fn timeline(&self) -> Vec<OrderAmendEntry> {
+        let mut amends: Vec<&OrderAmend> = self.amends.iter().collect();
+        amends.sort_by_key(|amend| amend.timestamp);
+
+        amends
+            .windows(2)
+            .map(|pair| OrderAmendEntry {
+                amend_id: pair[1].amend_id.clone(),
+                amend_type: pair[1].amend_type,
+                timestamp: pair[1].timestamp,
+                old_limit_price: pair[0].limit_price,
+                new_limit_price: pair[1].limit_price,
+                old_quantity: pair[0].order_quantity,
+                new_quantity: pair[1].order_quantity,
+                reason: pair[1].reason.clone(),
+            })
+            .collect()
+    }
+}
+
 /// Mapping of position id: OpenPosition
 pub type OpenPositions = HashMap<String, OpenPosition>;
 
@@ -966,16 +1126,27 @@ pub struct AddOrderDescription {
 /// Response type for AddOrder
 #[derive(Debug, Deserialize, PartialEq, Clone)]
 pub struct AddOrder {
-    #[serde(rename = "txid")]
+    /// Empty for orders sent with `validate`, which are not placed
+    #[serde(rename = "txid", default)]
     pub tx_id: Vec<String>,
     pub descr: AddOrderDescription,
     pub error: Option<String>,
 }
 
+impl AddOrder {
+    /// The first tx id of the order, which is the id of the order itself if any were returned.
+    ///
+    /// Orders with a conditional close may return further ids after it.
+    pub fn first_txid(&self) -> Option<&str> {
+        self.tx_id.first().map(String::as_str)
+    }
+}
+
 /// Description of an added batch order, including potential error value.
 #[derive(Debug, Deserialize, PartialEq, Clone)]
 pub struct BatchedOrder {
-    #[serde(rename = "txid")]
+    /// Empty for batches sent with `validate`, which are not placed
+    #[serde(rename = "txid", default)]
     pub tx_id: String,
     pub descr: AddOrderDescription,
     pub error: Option<String>,
@@ -1265,16 +1436,173 @@ pub struct EarnAmount {
 }
 
 /// Response type for GetWebSocketsToken
+///
+/// Kraken tokens must be used to establish a connection within `expires` seconds of being issued,
+/// so the time the response was received is recorded to track expiry.
 #[derive(Debug, Deserialize, Clone)]
 pub struct WebsocketToken {
     pub token: Token,
     pub expires: i64,
+    #[serde(skip, default = "Instant::now")]
+    pub received_at: Instant,
+}
+
+impl WebsocketToken {
+    /// The instant after which this token is no longer valid for new connections.
+    pub fn expires_at(&self) -> Instant {
+        self.received_at + Duration::from_secs(self.expires.max(0) as u64)
+    }
+
+    /// True if the token has passed its expiry.
+    pub fn is_expired(&self) -> bool {
+        self.expires_within(Duration::ZERO)
+    }
+
+    /// True if the token expires within `margin` from now, or is already expired.
+    pub fn expires_within(&self, margin: Duration) -> bool {
+        Instant::now() + margin >= self.expires_at()
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::response_types::ExtendedBalance;
+    use crate::clients::http_response_types::ResultErrorResponse;
+    use crate::request_types::RecentTradesRequest;
+    use crate::response_types::{
+        AddOrder, AddOrderBatch, AmendType, BidAsk, BuySellChar, ClosedOrders, ExtendedBalance,
+        ExtendedBalances, ExtendedBalancesExt, MarketLimitChar, Order, OrderAmendEntry,
+        OrderAmends, OrderFlag, OrderMisc, Orderbook, RecentTrade, RecentTrades, WebsocketToken,
+    };
+    use crate::test_data::account_response_json::{
+        get_closed_orders_json, get_extended_balance_json, get_order_amends_json,
+        get_query_order_info_json,
+    };
+    use crate::test_data::public_response_json::{get_orderbook_json, get_recent_trades_json};
+    use crate::test_data::trading_response_json::{
+        get_add_order_batch_json, get_add_order_batch_validate_json,
+        get_add_order_conditional_close_json, get_add_order_json,
+    };
     use rust_decimal_macros::dec;
+    use std::collections::HashMap;
+    use std::time::Duration;
+    use tokio::time::{advance, pause};
+
+    #[test]
+    fn test_order_amends_timeline() {
+        let mut json = get_order_amends_json();
+        let amends: OrderAmends = serde_json::from_value(json["result"].take()).unwrap();
+
+        assert_eq!(2, amends.count);
+        assert_eq!(AmendType::Original, amends.amends[0].amend_type);
+        assert_eq!(dec!(0.95), amends.amends[0].limit_price);
+
+        let expected = OrderAmendEntry {
+            amend_id: "TXH3X2-E4ADJ-CH53N2".to_string(),
+            amend_type: AmendType::User,
+            timestamp: 1728821182969,
+            old_limit_price: dec!(0.95),
+            new_limit_price: dec!(0.96),
+            old_quantity: dec!(5.1234),
+            new_quantity: dec!(5.25),
+            reason: Some("User requested".to_string()),
+        };
+
+        assert_eq!(vec![expected], amends.timeline());
+    }
+
+    #[test]
+    fn test_deserializing_order_misc_and_flags() {
+        let mut json = get_query_order_info_json();
+        let orders: HashMap<String, Order> =
+            serde_json::from_value(json["result"].clone()).unwrap();
+        let order = &orders["P8E9L2-ITMSU-UGBBRE"];
+
+        assert!(order.misc.is_empty());
+        assert_eq!(vec![OrderFlag::FeesInQuote], order.order_flags);
+
+        let entry = &mut json["result"]["P8E9L2-ITMSU-UGBBRE"];
+        entry["misc"] = "partial,amended,touched".into();
+        entry["oflags"] = "fciq,post".into();
+        let orders: HashMap<String, Order> = serde_json::from_value(json["result"].take()).unwrap();
+        let order = &orders["P8E9L2-ITMSU-UGBBRE"];
+
+        assert_eq!(
+            vec![OrderMisc::Partial, OrderMisc::Amended, OrderMisc::Touched],
+            order.misc
+        );
+        assert_eq!(
+            vec![OrderFlag::FeesInQuote, OrderFlag::Post],
+            order.order_flags
+        );
+    }
+
+    #[test]
+    fn test_deserializing_closed_order_misc() {
+        let mut json = get_closed_orders_json();
+        json["result"]["closed"]["1A33O3-W29BC-4FA9UK"]["misc"] = "stopped,liquidated,new".into();
+        let closed: ClosedOrders = serde_json::from_value(json["result"].take()).unwrap();
+
+        assert_eq!(
+            vec![
+                OrderMisc::Stopped,
+                OrderMisc::Liquidated,
+                OrderMisc::Other("new".to_string())
+            ],
+            closed.closed["1A33O3-W29BC-4FA9UK"].misc
+        );
+        assert!(closed.closed["9JTQTA-CLTS8-9W8YOP"].misc.is_empty());
+        assert_eq!(
+            vec![OrderFlag::FeesInQuote],
+            closed.closed["9JTQTA-CLTS8-9W8YOP"].order_flags
+        );
+    }
+
+    #[test]
+    fn test_deserializing_add_order() {
+        let mut json = get_add_order_json();
+        let order: AddOrder = serde_json::from_value(json["result"].take()).unwrap();
+
+        assert_eq!(vec!["AKB9L1-XC5U3-CYCTO1".to_string()], order.tx_id);
+        assert_eq!(Some("AKB9L1-XC5U3-CYCTO1"), order.first_txid());
+        assert_eq!("buy 5.00000000 USDCUSD @ limit 0.9000", order.descr.order);
+        assert_eq!(None, order.descr.close);
+
+        let mut json = get_add_order_conditional_close_json();
+        let order: AddOrder = serde_json::from_value(json["result"].take()).unwrap();
+
+        assert_eq!(2, order.tx_id.len());
+        assert_eq!(Some("OUF4EM-FRGI2-MQMWZD"), order.first_txid());
+        assert_eq!(
+            Some("close position @ stop loss 38000.0 -> limit 36000.0".to_string()),
+            order.descr.close
+        );
+
+        let validated: AddOrder = serde_json::from_value(
+            serde_json::json!({"descr": {"order": "buy 1 XBTUSD @ market"}}),
+        )
+        .unwrap();
+        assert_eq!(None, validated.first_txid());
+    }
+
+    #[test]
+    fn test_deserializing_add_order_batch() {
+        let placed: ResultErrorResponse<AddOrderBatch> =
+            serde_json::from_value(get_add_order_batch_json()).unwrap();
+        let placed = placed.result.unwrap();
+
+        assert_eq!("OZICHZ-FGB63-156I4K", placed.orders[0].tx_id);
+
+        let validated: ResultErrorResponse<AddOrderBatch> =
+            serde_json::from_value(get_add_order_batch_validate_json()).unwrap();
+        let validated = validated.result.unwrap();
+
+        assert_eq!(2, validated.orders.len());
+        assert!(validated.orders.iter().all(|order| order.tx_id.is_empty()));
+        assert_eq!(
+            "buy 5.20000000 USDCUSD @ limit 0.9000",
+            validated.orders[1].descr.order
+        );
+    }
 
     #[test]
     fn test_deserializing_extended_balance_full() {
@@ -1291,6 +1619,25 @@ mod tests {
         assert_eq!(expected_balance, serde_json::from_str(balance).unwrap());
     }
 
+    #[test]
+    fn test_deserializing_extended_balance_number_forms() {
+        for (value, expected) in [
+            ("0", dec!(0)),
+            ("0.0", dec!(0)),
+            (r#""0.00""#, dec!(0)),
+            ("1e-8", dec!(0.00000001)),
+            (r#""1e-8""#, dec!(0.00000001)),
+        ] {
+            let balance =
+                format!(r#"{{"balance": {value}, "hold_trade": {value}, "credit": {value}}}"#);
+            let balance: ExtendedBalance = serde_json::from_str(&balance).unwrap();
+
+            assert_eq!(expected, balance.balance);
+            assert_eq!(expected, balance.hold_trade);
+            assert_eq!(Some(expected), balance.credit);
+        }
+    }
+
     #[test]
     fn test_deserializing_extended_balance_some_none() {
         let balance_missing = r#"{"balance": "0.01", "hold_trade": "0.02"}"#;
@@ -1308,10 +1655,134 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_deserializing_extended_balances() {
+        let balances: ResultErrorResponse<ExtendedBalances> =
+            serde_json::from_value(get_extended_balance_json()).unwrap();
+        let balances = balances.result.unwrap();
+
+        let atom = ExtendedBalance {
+            balance: dec!(14.50385601),
+            hold_trade: dec!(3.92433600),
+            credit: None,
+            credit_used: None,
+        };
+
+        assert_eq!(atom, balances["ATOM"]);
+        assert_eq!(dec!(10.57952001), balances["ATOM"].available());
+        assert_eq!(dec!(12), balances["ARB"].available());
+        assert_eq!(dec!(0), balances["ATOM.S"].available());
+    }
+
+    #[test]
+    fn test_extended_balances_non_zero() {
+        let balances: ResultErrorResponse<ExtendedBalances> =
+            serde_json::from_value(get_extended_balance_json()).unwrap();
+        let balances = balances.result.unwrap();
+        let non_zero = balances.non_zero();
+
+        assert!(balances["ATOM.S"].is_zero());
+        assert!(!non_zero.contains_key("ATOM.S"));
+        assert!(non_zero.values().all(|balance| !balance.is_zero()));
+        assert_eq!(
+            balances
+                .values()
+                .filter(|balance| !balance.is_zero())
+                .count(),
+            non_zero.len()
+        );
+        assert_eq!(balances["GNO"], non_zero["GNO"]);
+
+        let held = ExtendedBalance {
+            balance: dec!(0),
+            hold_trade: dec!(1.5),
+            credit: None,
+            credit_used: None,
+        };
+        assert!(!held.is_zero());
+    }
+
+    #[test]
+    fn test_deserializing_recent_trades() {
+        let trades: ResultErrorResponse<RecentTrades> =
+            serde_json::from_value(get_recent_trades_json()).unwrap();
+        let trades = trades.result.unwrap();
+
+        let expected_first = RecentTrade {
+            price: dec!(37247.00000),
+            volume: dec!(0.00026451),
+            time: 1701019771.0383663,
+            buy_sell: BuySellChar::Buy,
+            market_limit: MarketLimitChar::Limit,
+            misc: "".to_string(),
+            trade_id: 64754585,
+        };
+
+        let xbt = &trades.trades["XXBTZUSD"];
+        assert_eq!(expected_first, xbt[0]);
+        assert_eq!(MarketLimitChar::Market, xbt[2].market_limit);
+        assert_eq!(BuySellChar::Sell, xbt[8].buy_sell);
+        assert_eq!(1701023458860710717, trades.last);
+
+        let request = RecentTradesRequest::builder("XXBTZUSD".to_string())
+            .count(1000)
+            .build();
+        let next = trades.next_request(&request);
+        assert_eq!(Some(trades.last), next.since);
+        assert_eq!(request.count, next.count);
+        assert_eq!(request.pair, next.pair);
+    }
+
     #[test]
     fn test_deserializing_extended_balance_some_gibberish() {
         let gibberish = r#"{"balance": "0.01", "hold_trade": "0.02", "credit": "soNotANumber"}"#;
 
         assert!(serde_json::from_str::<ExtendedBalance>(gibberish).is_err())
     }
+
+    #[tokio::test]
+    async fn test_websocket_token_expiry() {
+        pause();
+        let token: WebsocketToken =
+            serde_json::from_str(r#"{"token": "aToken", "expires": 900}"#).unwrap();
+
+        assert_eq!(
+            token.received_at + Duration::from_secs(900),
+            token.expires_at()
+        );
+        assert!(!token.is_expired());
+        assert!(!token.expires_within(Duration::from_secs(60)));
+
+        advance(Duration::from_secs(841)).await;
+        assert!(!token.is_expired());
+        assert!(token.expires_within(Duration::from_secs(60)));
+
+        advance(Duration::from_secs(60)).await;
+        assert!(token.is_expired());
+    }
+
+    #[test]
+    fn test_deserializing_orderbook_preserves_precision() {
+        let mut json = get_orderbook_json();
+        let book: HashMap<String, Orderbook> =
+            serde_json::from_value(json["result"].take()).unwrap();
+        let book = book.get("XXBTZUSD").unwrap();
+
+        let first_ask = BidAsk {
+            price: dec!(37221.10000),
+            volume: dec!(19.596),
+            time: 1701023454,
+        };
+
+        assert_eq!(first_ask, book.asks[0]);
+        assert_eq!("37221.10000", book.asks[0].price.to_string());
+        assert_eq!(
+            "2023-11-26 18:30:54.0 +00:00:00",
+            book.asks[0].timestamp().unwrap().to_string()
+        );
+
+        let wss_ask: crate::wss::BidAsk = book.asks[0].clone().into();
+        assert_eq!(dec!(37221.10000), wss_ask.price);
+        assert_eq!(dec!(19.596), wss_ask.quantity);
+    }
 }
@@ -4,13 +4,14 @@ mod test_auth;
 mod test_client_impl;
 mod test_client_impl_err;
 mod test_macros;
+mod test_metrics;
 mod wss_messages;
-mod wss_testing;
 
+pub use crate::test_support::{parse_for_test, CallResponseTest, ParseIncomingTest, WssTestState};
 pub use rate_limits::*;
 pub use rest_responses::*;
 pub use test_auth::*;
 pub use test_client_impl::*;
 pub use test_client_impl_err::*;
+pub use test_metrics::*;
 pub use wss_messages::*;
-pub use wss_testing::*;
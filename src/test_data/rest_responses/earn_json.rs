@@ -104,3 +104,28 @@ pub fn get_list_earn_allocations_json() -> Value {
         }
     })
 }
+
+pub fn get_list_earn_strategies_first_page_json() -> Value {
+    json!({
+        "error": [],
+        "result": {
+            "next_cursor": "2",
+            "items":[
+                {"id": "ESQEFMZ-JPPIY-3KXFJ2", "asset": "USD", "lock_type": {"type": "instant", "payout_frequency":302400}, "apr_estimate": {"low": "5.2500", "high": "5.2500"}, "user_min_allocation": "0.01", "allocation_fee": "0.0000", "deallocation_fee": "0.0000", "auto_compound": {"type": "enabled"}, "yield_source": {"type": "opt_in_rewards"}, "can_allocate":false, "can_deallocate":true, "allocation_restriction_info":[]},
+                {"id": "ESSLTY3-TM6GC-G5BWW5", "asset": "EUR", "lock_type": {"type": "instant", "payout_frequency":302400}, "apr_estimate": {"low": "2.5000", "high": "2.5000"}, "user_min_allocation": "0.01", "allocation_fee": "0.0000", "deallocation_fee": "0.0000", "auto_compound": {"type": "enabled"}, "yield_source": {"type": "opt_in_rewards"}, "can_allocate":false, "can_deallocate":true, "allocation_restriction_info":[]}
+            ]
+        }
+    })
+}
+
+pub fn get_list_earn_strategies_last_page_json() -> Value {
+    json!({
+        "error": [],
+        "result": {
+            "next_cursor": null,
+            "items":[
+                {"id": "ES2QQD3-N4GX4-DPB622", "asset": "XBT", "lock_type": {"type": "instant", "payout_frequency":302400}, "apr_estimate": {"low": "0.1500", "high": "0.1500"}, "user_min_allocation": "0.01", "allocation_fee": "0.0000", "deallocation_fee": "0.0000", "auto_compound": {"type": "enabled"}, "yield_source": {"type": "opt_in_rewards"}, "can_allocate":false, "can_deallocate":true, "allocation_restriction_info":[]}
+            ]
+        }
+    })
+}
@@ -12,6 +12,19 @@ pub fn get_add_order_json() -> Value {
     })
 }
 
+pub fn get_add_order_conditional_close_json() -> Value {
+    json!({
+        "error": [],
+        "result": {
+            "txid":["OUF4EM-FRGI2-MQMWZD", "OKRRJ6-MH3UH-DV6IKT"],
+            "descr": {
+                "order":"buy 2.12340000 XBTUSD @ limit 45000.1 with 2:1 leverage",
+                "close":"close position @ stop loss 38000.0 -> limit 36000.0"
+            }
+        }
+    })
+}
+
 pub fn get_add_order_batch_json() -> Value {
     json!({
         "error": [],
@@ -31,6 +44,23 @@ pub fn get_add_order_batch_json() -> Value {
     })
 }
 
+pub fn get_add_order_batch_validate_json() -> Value {
+    json!({
+        "error": [],
+        "result": {
+            "orders": [{
+                "descr": {
+                    "order":"buy 5.10000000 USDCUSD @ limit 0.9000"
+                }
+            },{
+                "descr": {
+                    "order":"buy 5.20000000 USDCUSD @ limit 0.9000"
+                }
+            }]
+        }
+    })
+}
+
 pub fn get_amend_order_json() -> Value {
     json!({"error":[],"result":{"amend_id":"TVB4ER-X5QP3-ADURMW"}})
 }
@@ -7,7 +7,7 @@ const NULL_KEY: &str =
 const NULL_SECRET: &str =
     "kQH5HW/8p1uGOVjbgWA7FunAmGO8lsSUXNsu3eow76sz84Q18fWxnyRzBHCd3pd5nE9qa99HAZtuZuj6F1huXg==";
 
-pub fn get_null_secrets_provider<'a>() -> Box<Arc<Mutex<dyn SecretsProvider>>> {
+pub fn get_null_secrets_provider() -> Box<Arc<Mutex<dyn SecretsProvider>>> {
     Box::new(Arc::new(Mutex::new(StaticSecretsProvider::new(
         NULL_KEY,
         NULL_SECRET,
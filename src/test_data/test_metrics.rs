@@ -0,0 +1,40 @@
+use crate::metrics::Metrics;
+use serde_json::Error as SerdeError;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Records every event reported to it, for asserting on in tests.
+#[derive(Debug, Default)]
+pub struct RecordingMetrics {
+    pub requests: Mutex<Vec<String>>,
+    pub responses: Mutex<Vec<(String, Option<u16>)>>,
+    pub waits: Mutex<Vec<Duration>>,
+    pub reconnects: AtomicUsize,
+    pub deserialize_errors: AtomicUsize,
+}
+
+impl Metrics for RecordingMetrics {
+    fn request_sent(&self, endpoint: &str) {
+        self.requests.lock().unwrap().push(endpoint.to_string());
+    }
+
+    fn response_received(&self, endpoint: &str, status: Option<u16>, _elapsed: Duration) {
+        self.responses
+            .lock()
+            .unwrap()
+            .push((endpoint.to_string(), status));
+    }
+
+    fn rate_limit_wait(&self, waited: Duration) {
+        self.waits.lock().unwrap().push(waited);
+    }
+
+    fn reconnect(&self) {
+        self.reconnects.fetch_add(1, Ordering::SeqCst);
+    }
+
+    fn deserialize_error(&self, _error: &SerdeError) {
+        self.deserialize_errors.fetch_add(1, Ordering::SeqCst);
+    }
+}
@@ -122,6 +122,79 @@ pub fn get_expected_book_message() -> WssMessage {
     }))
 }
 
+pub fn get_book_snapshot() -> String {
+    r#"{
+        "channel":"book",
+        "type":"snapshot",
+        "data":[{
+            "symbol":"BTC/USD",
+            "bids":[
+                {"price":66788.0,"qty":3.21926649},
+                {"price":66787.5,"qty":0.44916298},
+                {"price":66787.4,"qty":0.05992580},
+                {"price":66785.3,"qty":0.01496904},
+                {"price":66785.2,"qty":0.86989511}
+            ],
+            "asks":[
+                {"price":66788.1,"qty":1.67939137},
+                {"price":66788.4,"qty":1.49726637},
+                {"price":66790.0,"qty":1.49723133},
+                {"price":66791.1,"qty":0.01100000},
+                {"price":66792.6,"qty":1.49717197}
+            ],
+            "checksum":2330500275
+        }]
+    }"#
+    .to_string()
+}
+
+pub fn get_expected_ticker_unsubscription() -> Value {
+    json!({"method":"unsubscribe","params":{"channel":"ticker","symbol":["BTC/USD"]},"req_id":43})
+}
+
+pub fn get_ticker_unsubscription_response() -> String {
+    r#"{"method":"unsubscribe","req_id":43,"result":{"channel":"ticker","event_trigger":"trades","symbol":"BTC/USD"},"success":true,"time_in":"2024-05-15T11:25:43.013486Z","time_out":"2024-05-15T11:25:43.013545Z"}"#.to_string()
+}
+
+pub fn get_expected_ticker_unsubscription_message() -> WssMessage {
+    WssMessage::Method(MethodMessage::Unsubscription(ResultResponse {
+        result: Some(SubscriptionResult::Ticker(TickerSubscriptionResponse {
+            symbol: "BTC/USD".to_string(),
+            event_trigger: Some(EventTrigger::Trades),
+            snapshot: None,
+        })),
+        error: None,
+        success: true,
+        req_id: 43,
+        time_in: "2024-05-15T11:25:43.013486Z".to_string(),
+        time_out: "2024-05-15T11:25:43.013545Z".to_string(),
+    }))
+}
+
+pub fn get_expected_book_unsubscription() -> Value {
+    json!({"method":"unsubscribe","params":{"channel":"book","symbol":["BTC/USD"],"depth":10},"req_id":12})
+}
+
+pub fn get_book_unsubscription_response() -> String {
+    r#"{"method":"unsubscribe","req_id":12,"result":{"channel":"book","depth":10,"symbol":"BTC/USD"},"success":true,"time_in":"2024-05-19T16:29:13.694962Z","time_out":"2024-05-19T16:29:13.695006Z"}"#.to_string()
+}
+
+pub fn get_expected_book_unsubscription_message() -> WssMessage {
+    WssMessage::Method(MethodMessage::Unsubscription(ResultResponse {
+        result: Some(SubscriptionResult::Book(BookSubscriptionResponse {
+            symbol: "BTC/USD".to_string(),
+            snapshot: None,
+            depth: Some(10),
+            warnings: None,
+        })),
+        error: None,
+        success: true,
+        req_id: 12,
+        time_in: "2024-05-19T16:29:13.694962Z".to_string(),
+        time_out: "2024-05-19T16:29:13.695006Z".to_string(),
+    }))
+}
+
 pub fn get_expected_l3_subscription() -> Value {
     json!({"method":"subscribe","params":{"channel":"level3","symbol":["BTC/USD"],"snapshot":true,"token":"someToken"},"req_id":99})
 }
@@ -1,3 +1,9 @@
+#[cfg(feature = "test-support")]
 mod logging;
+mod replay;
+mod wss_testing;
 
+#[cfg(feature = "test-support")]
 pub use logging::*;
+pub use replay::*;
+pub use wss_testing::*;
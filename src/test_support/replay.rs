@@ -0,0 +1,111 @@
+use crate::wss::{KrakenMessageStream, KrakenWSSClient, WssMessage};
+use std::path::Path;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::time::sleep;
+use tokio_tungstenite::tungstenite::Message as TungsteniteMessage;
+use ws_mock::ws_mock_server::{WsMock, WsMockServer};
+
+/// Replay a recorded session of newline-delimited JSON messages from `path` through a mock server,
+/// returning a stream connected to it.
+///
+/// Messages are sent in order as fast as they're read, and blank lines are skipped. The stream
+/// stays open after the last message, so consume it with a count or timeout.
+pub async fn replay_session(path: impl AsRef<Path>) -> KrakenMessageStream<WssMessage> {
+    replay_session_with_delay(path, Duration::ZERO).await
+}
+
+/// Replay a recorded session like [replay_session], waiting `delay` before sending each message.
+pub async fn replay_session_with_delay(
+    path: impl AsRef<Path>,
+    delay: Duration,
+) -> KrakenMessageStream<WssMessage> {
+    let session = std::fs::read_to_string(path).expect("failed to read session file!");
+    let messages: Vec<String> = session
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(str::to_string)
+        .collect();
+
+    let server = WsMockServer::start().await;
+    let uri = server.uri().await;
+    let (sender, receiver) = mpsc::channel::<TungsteniteMessage>(32);
+
+    WsMock::new()
+        .forward_from_channel(receiver)
+        .mount(&server)
+        .await;
+
+    let mut client = KrakenWSSClient::new_with_urls(uri.clone(), uri);
+    let stream = client
+        .connect::<WssMessage>()
+        .await
+        .expect("failed to connect to replay server!");
+
+    tokio::spawn(async move {
+        let _server = server;
+
+        for message in messages {
+            if !delay.is_zero() {
+                sleep(delay).await;
+            }
+
+            if sender
+                .send(TungsteniteMessage::Text(message))
+                .await
+                .is_err()
+            {
+                break;
+            }
+        }
+    });
+
+    stream
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wss::ChannelMessage;
+    use tokio::time::timeout;
+    use tokio_stream::StreamExt;
+
+    #[tokio::test]
+    async fn test_replay_session() {
+        let session = [
+            r#"{"channel":"status","data":[{"api_version":"v2","connection_id":18266300427528990701,"system":"online","version":"2.0.4"}],"type":"update"}"#,
+            "",
+            r#"{"channel":"heartbeat"}"#,
+            r#"{"method":"pong","req_id":1,"time_in":"2024-05-19T19:58:40.170724Z","time_out":"2024-05-19T19:58:40.170758Z"}"#,
+        ]
+        .join("\n");
+
+        // unique per process, so concurrent runs of the test suite don't share the file
+        let path = std::env::temp_dir().join(format!(
+            "kraken_async_rs_test_replay_session_{}.jsonl",
+            std::process::id()
+        ));
+        std::fs::write(&path, session).unwrap();
+
+        let mut stream = replay_session_with_delay(&path, Duration::from_millis(5)).await;
+
+        let mut messages = Vec::new();
+        for _ in 0..3 {
+            let message = timeout(Duration::from_secs(1), stream.next())
+                .await
+                .unwrap()
+                .unwrap()
+                .unwrap();
+            messages.push(message);
+        }
+
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(matches!(
+            messages[0],
+            WssMessage::Channel(ChannelMessage::Status(_))
+        ));
+        assert_eq!(WssMessage::Channel(ChannelMessage::Heartbeat), messages[1]);
+        assert!(matches!(messages[2], WssMessage::Method(_)));
+    }
+}
@@ -12,6 +12,7 @@ use tokio_tungstenite::tungstenite::Message as TungsteniteMessage;
 use ws_mock::matchers::JsonExact;
 use ws_mock::ws_mock_server::{WsMock, WsMockServer};
 
+/// A mock websocket server and a [KrakenWSSClient] pointed at it for both public and private urls.
 pub struct WssTestState {
     pub mock_server: WsMockServer,
     pub ws_client: KrakenWSSClient,
@@ -29,6 +30,7 @@ impl WssTestState {
     }
 }
 
+/// Test that each incoming message, forwarded from a mock server, parses to the expected [WssMessage].
 #[derive(Debug)]
 pub struct ParseIncomingTest {
     incoming_messages: Vec<String>,
@@ -94,6 +96,8 @@ impl ParseIncomingTest {
     }
 }
 
+/// Test that sending a message produces exactly the JSON in `match_on`, and that the mock server's
+/// response `respond_with` parses to the expected [WssMessage].
 #[derive(Debug, Builder)]
 pub struct CallResponseTest<T>
 where
@@ -124,7 +128,11 @@ where
             .mount(&test_state.mock_server)
             .await;
 
-        let mut stream = test_state.ws_client.connect::<WssMessage>().await.unwrap();
+        let mut stream = test_state
+            .ws_client
+            .connect_auth::<WssMessage>()
+            .await
+            .unwrap();
 
         stream.send(&self.send.take().unwrap()).await.unwrap();
 
@@ -0,0 +1,147 @@
+//! Helpers for preparing order values
+use crate::response_types::BuySell;
+use rust_decimal::{Decimal, RoundingStrategy};
+
+/// Direction to round a value in when it isn't already a multiple of an increment
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RoundingMode {
+    /// Towards negative infinity
+    Down,
+    /// Towards positive infinity
+    Up,
+    /// To the nearest multiple, with halfway values rounded away from zero
+    Nearest,
+}
+
+impl RoundingMode {
+    /// Round up for buys and down for sells.
+    pub fn for_side(side: BuySell) -> RoundingMode {
+        match side {
+            BuySell::Buy => RoundingMode::Up,
+            BuySell::Sell => RoundingMode::Down,
+        }
+    }
+}
+
+/// Round `value` to a multiple of `increment`, e.g. a price to a pair's tick size or a quantity to
+/// its quantity increment.
+///
+/// Values already on an increment are returned unchanged, as is every value if `increment` is zero.
+/// Returns None if `increment` is negative, or if `value` is too large to count in increments of
+/// `increment` without overflowing a [Decimal].
+pub fn round_to_increment(
+    value: Decimal,
+    increment: Decimal,
+    mode: RoundingMode,
+) -> Option<Decimal> {
+    if increment.is_zero() {
+        return Some(value);
+    }
+
+    if increment < Decimal::ZERO {
+        return None;
+    }
+
+    let increments = value.checked_div(increment)?;
+    let rounded = match mode {
+        RoundingMode::Down => increments.floor(),
+        RoundingMode::Up => increments.ceil(),
+        RoundingMode::Nearest => {
+            increments.round_dp_with_strategy(0, RoundingStrategy::MidpointAwayFromZero)
+        }
+    };
+
+    rounded.checked_mul(increment)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_round_to_increment() {
+        let tick = dec!(0.00001);
+
+        assert_eq!(
+            Some(dec!(0.05321)),
+            round_to_increment(dec!(0.053214), tick, RoundingMode::Down)
+        );
+        assert_eq!(
+            Some(dec!(0.05322)),
+            round_to_increment(dec!(0.053214), tick, RoundingMode::Up)
+        );
+        assert_eq!(
+            Some(dec!(0.05321)),
+            round_to_increment(dec!(0.053214), tick, RoundingMode::Nearest)
+        );
+        assert_eq!(
+            Some(dec!(0.05322)),
+            round_to_increment(dec!(0.053215), tick, RoundingMode::Nearest)
+        );
+    }
+
+    #[test]
+    fn test_round_to_increment_on_boundary() {
+        let tick = dec!(0.00001);
+
+        for mode in [RoundingMode::Down, RoundingMode::Up, RoundingMode::Nearest] {
+            assert_eq!(
+                Some(dec!(0.05321)),
+                round_to_increment(dec!(0.05321), tick, mode)
+            );
+            assert_eq!(
+                Some(dec!(0.05321)),
+                round_to_increment(dec!(0.0532100), tick, mode)
+            );
+        }
+    }
+
+    #[test]
+    fn test_round_to_small_and_zero_increments() {
+        let satoshi = dec!(0.00000001);
+
+        assert_eq!(
+            Some(dec!(1.23456789)),
+            round_to_increment(dec!(1.234567899), satoshi, RoundingMode::Down)
+        );
+        assert_eq!(
+            Some(dec!(1.2345679)),
+            round_to_increment(dec!(1.234567899), satoshi, RoundingMode::Up)
+        );
+        assert_eq!(
+            Some(dec!(1.234567899)),
+            round_to_increment(dec!(1.234567899), Decimal::ZERO, RoundingMode::Up)
+        );
+    }
+
+    #[test]
+    fn test_round_to_increment_overflow() {
+        let satoshi = dec!(0.00000001);
+
+        for mode in [RoundingMode::Down, RoundingMode::Up, RoundingMode::Nearest] {
+            assert_eq!(None, round_to_increment(Decimal::MAX, satoshi, mode));
+            assert_eq!(None, round_to_increment(Decimal::MIN, satoshi, mode));
+        }
+    }
+
+    #[test]
+    fn test_round_to_negative_increment() {
+        let tick = dec!(-0.00001);
+
+        for mode in [RoundingMode::Down, RoundingMode::Up, RoundingMode::Nearest] {
+            assert_eq!(None, round_to_increment(dec!(0.053214), tick, mode));
+        }
+    }
+
+    #[test]
+    fn test_rounding_mode_for_side() {
+        let tick = dec!(0.00001);
+
+        let buy = round_to_increment(dec!(0.053214), tick, RoundingMode::for_side(BuySell::Buy));
+        let sell = round_to_increment(dec!(0.053214), tick, RoundingMode::for_side(BuySell::Sell));
+
+        assert_eq!(Some(dec!(0.05322)), buy);
+        assert_eq!(Some(dec!(0.05321)), sell);
+    }
+}
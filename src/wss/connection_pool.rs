@@ -0,0 +1,391 @@
+//! Subscriptions sharded across several websocket connections
+use crate::wss::errors::WSSError;
+use crate::wss::{KrakenMessageStream, KrakenWSSClient, WssMessage};
+use serde::Serialize;
+use std::fmt::Debug;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::sync::mpsc::{channel, Receiver, Sender};
+use tokio::time::sleep;
+use tokio_stream::{Stream, StreamExt};
+use tracing::warn;
+
+/// The default time a shard of a [WssConnectionPool] waits before reconnecting.
+pub const DEFAULT_RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+/// Subscribes to a public channel for many symbols by splitting them across several connections,
+/// merging the messages of all connections into one [PooledStream].
+///
+/// Each connection (a shard) subscribes to at most `symbols_per_connection` symbols, which is also
+/// its [KrakenMessageStream::set_subscription_limit]. When a shard's connection fails, or stalls if
+/// a stall timeout is set, the error is yielded and the shard reconnects and subscribes again after
+/// the reconnect delay. Messages of all shards are interleaved in the order they arrive.
+///
+/// # Example: Tickers for Hundreds of Pairs
+/// ```ignore
+///let pool = WssConnectionPool::new(KrakenWSSClient::new(), 50)
+///    .with_stall_timeout(Some(Duration::from_secs(10)));
+///let mut tickers = pool.subscribe(&symbols, TickerSubscription::new).await?;
+///
+///while let Some(message) = tickers.next().await {
+///    println!("{:?}", message);
+///}
+/// ```
+#[derive(Debug, Clone)]
+pub struct WssConnectionPool {
+    client: KrakenWSSClient,
+    symbols_per_connection: usize,
+    reconnect_delay: Duration,
+    stall_timeout: Option<Duration>,
+    capacity: usize,
+}
+
+impl WssConnectionPool {
+    /// Create a pool connecting with `client`, subscribing to up to `symbols_per_connection`
+    /// symbols on each connection.
+    ///
+    /// # Panics
+    /// If `symbols_per_connection` is zero.
+    pub fn new(client: KrakenWSSClient, symbols_per_connection: usize) -> Self {
+        assert!(
+            symbols_per_connection > 0,
+            "symbols_per_connection must be positive!"
+        );
+
+        WssConnectionPool {
+            client,
+            symbols_per_connection,
+            reconnect_delay: DEFAULT_RECONNECT_DELAY,
+            stall_timeout: None,
+            capacity: 1024,
+        }
+    }
+
+    /// Wait `delay` before each attempt to reconnect a shard, [DEFAULT_RECONNECT_DELAY] by default.
+    pub fn with_reconnect_delay(mut self, delay: Duration) -> Self {
+        self.reconnect_delay = delay;
+        self
+    }
+
+    /// Set the stall timeout of each connection, see [KrakenMessageStream::set_stall_timeout].
+    ///
+    /// Without one, a shard only reconnects once its connection reports an error, so one is
+    /// recommended to also notice connections that were closed or went quiet.
+    pub fn with_stall_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.stall_timeout = timeout;
+        self
+    }
+
+    /// Buffer up to `capacity` messages from all shards that haven't been read yet, 1024 by
+    /// default. A full buffer applies backpressure to every shard.
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    /// Connect a shard for each `symbols_per_connection` of `symbols`, subscribing each to the
+    /// params `subscription` creates for its symbols, e.g. `TickerSubscription::new` or a closure
+    /// capturing other options of the subscription.
+    ///
+    /// Returns once every shard's subscription is acknowledged, or the first error connecting or
+    /// subscribing, in which case no shards are left running.
+    pub async fn subscribe<P, F>(
+        &self,
+        symbols: &[String],
+        subscription: F,
+    ) -> Result<PooledStream, WSSError>
+    where
+        P: Serialize + Debug + Clone + Send + Sync + 'static,
+        F: Fn(Vec<String>) -> P + Send + Sync + Clone + 'static,
+    {
+        let mut shards = Vec::new();
+
+        for symbols in symbols.chunks(self.symbols_per_connection) {
+            let mut client = self.client.clone();
+            let params = subscription(symbols.to_vec());
+            let stream = self.connect_shard(&mut client, &params).await?;
+            shards.push((client, params, stream));
+        }
+
+        let (sender, receiver) = channel(self.capacity);
+        let shard_count = shards.len();
+
+        for (client, params, stream) in shards {
+            tokio::spawn(
+                self.clone()
+                    .run_shard(client, params, stream, sender.clone()),
+            );
+        }
+
+        Ok(PooledStream {
+            receiver,
+            shards: shard_count,
+        })
+    }
+
+    async fn connect_shard<P>(
+        &self,
+        client: &mut KrakenWSSClient,
+        params: &P,
+    ) -> Result<KrakenMessageStream<WssMessage>, WSSError>
+    where
+        P: Serialize + Debug + Clone,
+    {
+        let mut stream = client.connect::<WssMessage>().await?;
+        stream.set_subscription_limit(Some(self.symbols_per_connection));
+        stream.set_stall_timeout(self.stall_timeout);
+
+        let req_id = stream.next_req_id();
+        stream.subscribe(params.clone(), req_id).await?;
+
+        Ok(stream)
+    }
+
+    /// Forward messages of a shard until the pooled stream is dropped, reconnecting whenever its
+    /// connection fails.
+    async fn run_shard<P>(
+        self,
+        mut client: KrakenWSSClient,
+        params: P,
+        mut stream: KrakenMessageStream<WssMessage>,
+        sender: Sender<Result<WssMessage, WSSError>>,
+    ) where
+        P: Serialize + Debug + Clone,
+    {
+        loop {
+            let message = tokio::select! {
+                message = stream.next() => message,
+                _ = sender.closed() => return,
+            };
+
+            let reconnect = match &message {
+                Some(Err(WSSError::Serde(_))) | Some(Ok(_)) => false,
+                Some(Err(_)) | None => true,
+            };
+
+            if let Some(message) = message {
+                if sender.send(message).await.is_err() {
+                    return;
+                }
+            }
+
+            if reconnect {
+                match self.reconnect(&mut client, &params, &sender).await {
+                    Some(reconnected) => stream = reconnected,
+                    None => return,
+                }
+            }
+        }
+    }
+
+    /// Reconnect a shard until it succeeds, yielding each failure, or None if the pooled stream
+    /// was dropped in the meantime.
+    async fn reconnect<P>(
+        &self,
+        client: &mut KrakenWSSClient,
+        params: &P,
+        sender: &Sender<Result<WssMessage, WSSError>>,
+    ) -> Option<KrakenMessageStream<WssMessage>>
+    where
+        P: Serialize + Debug + Clone,
+    {
+        loop {
+            sleep(self.reconnect_delay).await;
+            if sender.is_closed() {
+                return None;
+            }
+
+            match self.connect_shard(client, params).await {
+                Ok(stream) => {
+                    self.client.metrics().reconnect();
+                    return Some(stream);
+                }
+                Err(err) => {
+                    warn!("Failed to reconnect shard: {err}");
+                    sender.send(Err(err)).await.ok()?;
+                }
+            }
+        }
+    }
+}
+
+/// The merged messages of every shard of a [WssConnectionPool], see
+/// [WssConnectionPool::subscribe].
+///
+/// Dropping it stops all shards and closes their connections.
+pub struct PooledStream {
+    receiver: Receiver<Result<WssMessage, WSSError>>,
+    shards: usize,
+}
+
+impl PooledStream {
+    /// The number of connections the symbols were split across.
+    pub fn shards(&self) -> usize {
+        self.shards
+    }
+}
+
+impl Stream for PooledStream {
+    type Item = Result<WssMessage, WSSError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.receiver.poll_recv(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_data::RecordingMetrics;
+    use crate::wss::{ChannelMessage, TickerSubscription};
+    use futures_util::SinkExt;
+    use serde_json::Value;
+    use std::collections::HashSet;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use tokio::net::TcpListener;
+    use tokio::time::timeout;
+    use tokio_tungstenite::accept_async;
+    use tokio_tungstenite::tungstenite::Message as TungsteniteMessage;
+
+    /// Start a server accepting any number of connections, which acknowledges each subscription
+    /// and sends a ticker per symbol. The first `close_first` connections are closed afterwards.
+    async fn start_server(close_first: usize) -> (String, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        let connections = Arc::new(AtomicUsize::new(0));
+        let counter = connections.clone();
+
+        tokio::spawn(async move {
+            while let Ok((tcp, _)) = listener.accept().await {
+                let index = counter.fetch_add(1, Ordering::SeqCst);
+
+                tokio::spawn(async move {
+                    let mut socket = accept_async(tcp).await.unwrap();
+
+                    while let Some(Ok(message)) = socket.next().await {
+                        let Ok(text) = message.into_text() else {
+                            continue;
+                        };
+                        let Ok(request) = serde_json::from_str::<Value>(&text) else {
+                            continue;
+                        };
+
+                        let symbols: Vec<String> = request["params"]["symbol"]
+                            .as_array()
+                            .unwrap()
+                            .iter()
+                            .map(|symbol| symbol.as_str().unwrap().to_string())
+                            .collect();
+                        let ack = format!(
+                            r#"{{"method":"subscribe","req_id":{},"result":{{"channel":"ticker","symbol":"{}"}},"success":true,"time_in":"2024-05-15T11:20:43.013486Z","time_out":"2024-05-15T11:20:43.013545Z"}}"#,
+                            request["req_id"], symbols[0]
+                        );
+                        socket.send(TungsteniteMessage::Text(ack)).await.unwrap();
+
+                        for symbol in symbols {
+                            let ticker = format!(
+                                r#"{{"channel":"ticker","type":"update","data":[{{"symbol":"{symbol}","bid":1.0,"bid_qty":1.0,"ask":1.0,"ask_qty":1.0,"last":1.0,"volume":1.0,"vwap":1.0,"low":1.0,"high":1.0,"change":1.0,"change_pct":1.0}}]}}"#
+                            );
+                            socket.send(TungsteniteMessage::Text(ticker)).await.unwrap();
+                        }
+
+                        if index < close_first {
+                            let _ = socket.close(None).await;
+                            return;
+                        }
+                    }
+                });
+            }
+        });
+
+        (url, connections)
+    }
+
+    async fn next_symbol(stream: &mut PooledStream) -> Option<String> {
+        loop {
+            match timeout(Duration::from_secs(2), stream.next())
+                .await
+                .unwrap()?
+            {
+                Ok(WssMessage::Channel(ChannelMessage::Ticker(ticker))) => {
+                    return Some(ticker.data.symbol)
+                }
+                _ => continue,
+            }
+        }
+    }
+
+    fn symbols(count: usize) -> Vec<String> {
+        (0..count).map(|i| format!("PAIR{i}/USD")).collect()
+    }
+
+    #[tokio::test]
+    async fn test_connection_pool_shards_symbols() {
+        let (url, connections) = start_server(0).await;
+        let pool = WssConnectionPool::new(KrakenWSSClient::new_with_urls(&url, &url), 2);
+
+        let mut stream = pool
+            .subscribe(&symbols(5), TickerSubscription::new)
+            .await
+            .unwrap();
+
+        assert_eq!(3, stream.shards());
+        assert_eq!(3, connections.load(Ordering::SeqCst));
+
+        let mut received = HashSet::new();
+        for _ in 0..5 {
+            received.insert(next_symbol(&mut stream).await.unwrap());
+        }
+
+        assert_eq!(symbols(5).into_iter().collect::<HashSet<_>>(), received);
+    }
+
+    #[tokio::test]
+    async fn test_connection_pool_subscribes_with_closure() {
+        let (url, connections) = start_server(0).await;
+        let pool = WssConnectionPool::new(KrakenWSSClient::new_with_urls(&url, &url), 2);
+        let snapshot = Some(false);
+
+        let mut stream = pool
+            .subscribe(&symbols(3), move |symbols| TickerSubscription {
+                snapshot,
+                ..TickerSubscription::new(symbols)
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(2, stream.shards());
+        assert_eq!(2, connections.load(Ordering::SeqCst));
+        assert!(next_symbol(&mut stream).await.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_connection_pool_reconnects_shard() {
+        let (url, connections) = start_server(1).await;
+        let metrics = Arc::new(RecordingMetrics::default());
+        let client = KrakenWSSClient::new_with_urls(&url, &url).with_metrics(metrics.clone());
+        let pool = WssConnectionPool::new(client, 2)
+            .with_reconnect_delay(Duration::from_millis(10))
+            .with_stall_timeout(Some(Duration::from_millis(200)));
+
+        let mut stream = pool
+            .subscribe(&symbols(2), TickerSubscription::new)
+            .await
+            .unwrap();
+
+        let mut received = Vec::new();
+        for _ in 0..4 {
+            received.push(next_symbol(&mut stream).await.unwrap());
+        }
+
+        received.sort();
+        assert_eq!(
+            vec!["PAIR0/USD", "PAIR0/USD", "PAIR1/USD", "PAIR1/USD"],
+            received
+        );
+        assert_eq!(2, connections.load(Ordering::SeqCst));
+        assert_eq!(1, metrics.reconnects.load(Ordering::SeqCst));
+    }
+}
@@ -1,8 +1,11 @@
 //! Top level errors produced by [KrakenWSSClient] and [KrakenMessageStream]
 //!
+use crate::clients::errors::ClientError;
 pub use serde_json::Error as SerdeError;
 use std::error::Error;
 use std::fmt::{Display, Formatter};
+use std::time::Duration;
+use tokio_tungstenite::tungstenite::error::CapacityError;
 pub use tokio_tungstenite::tungstenite::Error as TungsteniteError;
 pub use url::ParseError as UrlParseError;
 
@@ -12,6 +15,64 @@ pub enum WSSError {
     Serde(SerdeError),
     WSS(TungsteniteError),
     UrlParse(UrlParseError),
+    /// No messages were received for the given duration, see
+    /// [KrakenMessageStream::set_stall_timeout](crate::wss::KrakenMessageStream::set_stall_timeout)
+    Stalled(Duration),
+    /// Kraken responded to a request with `success: false`
+    Rejected(RejectedRequest),
+    /// A message or frame was larger than the configured maximum, see
+    /// [KrakenWSSClient::with_websocket_config](crate::wss::KrakenWSSClient::with_websocket_config)
+    MessageTooLarge {
+        size: usize,
+        max_size: usize,
+    },
+    /// The method or channel can only be used on a stream from
+    /// [KrakenWSSClient::connect_auth](crate::wss::KrakenWSSClient::connect_auth)
+    RequiresAuthChannel(String),
+    /// A book subscription with a depth Kraken doesn't accept, see
+    /// [BookSubscription::DEPTHS](crate::wss::BookSubscription::DEPTHS)
+    InvalidBookDepth(i32),
+    /// A subscription would exceed the limit of subscriptions for the connection, see
+    /// [KrakenMessageStream::set_subscription_limit](crate::wss::KrakenMessageStream::set_subscription_limit)
+    SubscriptionLimitReached {
+        limit: usize,
+    },
+    /// The server closed the connection with an abnormal close `code`, after which the stream
+    /// ends. A normal close ends the stream without an error.
+    Closed {
+        code: u16,
+        reason: String,
+    },
+    /// A REST request made alongside a websocket failed, see
+    /// [KrakenWSSClient::subscribe_synced_book](crate::wss::KrakenWSSClient::subscribe_synced_book)
+    Client(ClientError),
+}
+
+/// A request that Kraken responded to with `success: false`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RejectedRequest {
+    pub req_id: i64,
+    pub error: String,
+}
+
+impl Display for RejectedRequest {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Request {} was rejected: {}", self.req_id, self.error)
+    }
+}
+
+impl Error for RejectedRequest {}
+
+impl From<RejectedRequest> for WSSError {
+    fn from(value: RejectedRequest) -> Self {
+        Self::Rejected(value)
+    }
+}
+
+impl From<ClientError> for WSSError {
+    fn from(value: ClientError) -> Self {
+        Self::Client(value)
+    }
 }
 
 impl From<SerdeError> for WSSError {
@@ -22,7 +83,12 @@ impl From<SerdeError> for WSSError {
 
 impl From<TungsteniteError> for WSSError {
     fn from(value: TungsteniteError) -> Self {
-        Self::WSS(value)
+        match value {
+            TungsteniteError::Capacity(CapacityError::MessageTooLong { size, max_size }) => {
+                Self::MessageTooLarge { size, max_size }
+            }
+            value => Self::WSS(value),
+        }
     }
 }
 
@@ -38,6 +104,28 @@ impl Display for WSSError {
             WSSError::Serde(err) => write!(f, "{err}"),
             WSSError::WSS(err) => write!(f, "{err}"),
             WSSError::UrlParse(err) => write!(f, "{err}"),
+            WSSError::Stalled(timeout) => write!(f, "No messages received for {timeout:?}"),
+            WSSError::Rejected(err) => write!(f, "{err}"),
+            WSSError::MessageTooLarge { size, max_size } => {
+                write!(
+                    f,
+                    "Message of {size} bytes exceeds the maximum of {max_size}"
+                )
+            }
+            WSSError::RequiresAuthChannel(name) => {
+                write!(f, "{name} requires a connection to the private websocket")
+            }
+            WSSError::InvalidBookDepth(depth) => write!(f, "Invalid book depth: {depth}"),
+            WSSError::SubscriptionLimitReached { limit } => {
+                write!(
+                    f,
+                    "Subscription would exceed the limit of {limit} for this connection"
+                )
+            }
+            WSSError::Closed { code, reason } => {
+                write!(f, "Connection closed with code {code}: {reason}")
+            }
+            WSSError::Client(err) => write!(f, "{err}"),
         }
     }
 }
@@ -48,6 +136,14 @@ impl Error for WSSError {
             WSSError::Serde(e) => Some(e),
             WSSError::WSS(e) => Some(e),
             WSSError::UrlParse(e) => Some(e),
+            WSSError::Rejected(e) => Some(e),
+            WSSError::Stalled(_) => None,
+            WSSError::MessageTooLarge { .. } => None,
+            WSSError::RequiresAuthChannel(_) => None,
+            WSSError::InvalidBookDepth(_) => None,
+            WSSError::SubscriptionLimitReached { .. } => None,
+            WSSError::Closed { .. } => None,
+            WSSError::Client(e) => Some(e),
         }
     }
 }
@@ -57,6 +153,7 @@ mod tests {
     use crate::wss::errors::WSSError;
     use serde::de::Error as DeError;
     use serde_json::Error as SerdeError;
+    use tokio_tungstenite::tungstenite::error::CapacityError;
     use tokio_tungstenite::tungstenite::Error as TungsteniteError;
     use url::ParseError as UrlParseError;
 
@@ -74,5 +171,17 @@ mod tests {
 
         let error = WSSError::from(url_parse_error);
         assert!(matches!(error, WSSError::UrlParse { .. }));
+
+        let error = WSSError::from(TungsteniteError::Capacity(CapacityError::MessageTooLong {
+            size: 100,
+            max_size: 64,
+        }));
+        assert!(matches!(
+            error,
+            WSSError::MessageTooLarge {
+                size: 100,
+                max_size: 64
+            }
+        ));
     }
 }
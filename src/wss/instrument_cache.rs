@@ -0,0 +1,143 @@
+//! Lookup of pair and asset details received from the instrument channel
+use crate::util::{round_to_increment, RoundingMode};
+use crate::wss::{Asset, InstrumentMessage, Instruments, Pair};
+use rust_decimal::Decimal;
+use std::collections::HashMap;
+
+/// The latest asset and pair details from the instrument channel, keyed by asset id and pair symbol.
+///
+/// Feed it every [InstrumentMessage] received to keep it current, then use it to look up pairs or
+/// round prices and quantities to what Kraken will accept for them.
+#[derive(Debug, Default)]
+pub struct InstrumentCache {
+    assets: HashMap<String, Asset>,
+    pairs: HashMap<String, Pair>,
+}
+
+impl InstrumentCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Apply an instrument message, replacing the cache for a snapshot or updating it in place.
+    pub fn apply(&mut self, message: InstrumentMessage) {
+        match message {
+            InstrumentMessage::Snapshot(response) => {
+                self.assets.clear();
+                self.pairs.clear();
+                self.ingest(response.data);
+            }
+            InstrumentMessage::Update(response) => self.ingest(response.data),
+        }
+    }
+
+    /// Add or replace the given assets and pairs, leaving all others as they are.
+    pub fn ingest(&mut self, instruments: Instruments) {
+        for asset in instruments.assets {
+            self.assets.insert(asset.id.clone(), asset);
+        }
+
+        for pair in instruments.pairs {
+            self.pairs.insert(pair.symbol.clone(), pair);
+        }
+    }
+
+    pub fn pair(&self, symbol: &str) -> Option<&Pair> {
+        self.pairs.get(symbol)
+    }
+
+    pub fn asset(&self, id: &str) -> Option<&Asset> {
+        self.assets.get(id)
+    }
+
+    pub fn pairs(&self) -> impl Iterator<Item = &Pair> {
+        self.pairs.values()
+    }
+
+    pub fn assets(&self) -> impl Iterator<Item = &Asset> {
+        self.assets.values()
+    }
+
+    /// Round a price to the nearest `price_increment` of the pair, or None if the pair is unknown or
+    /// the price can't be rounded (see [round_to_increment]).
+    pub fn round_price(&self, symbol: &str, price: Decimal) -> Option<Decimal> {
+        let pair = self.pair(symbol)?;
+        round_to_increment(price, pair.price_increment, RoundingMode::Nearest)
+    }
+
+    /// Round a quantity down to the `quantity_increment` of the pair, so it never exceeds the given
+    /// quantity, or None if the pair is unknown or the quantity can't be rounded (see
+    /// [round_to_increment]).
+    pub fn round_qty(&self, symbol: &str, quantity: Decimal) -> Option<Decimal> {
+        let pair = self.pair(symbol)?;
+        round_to_increment(quantity, pair.quantity_increment, RoundingMode::Down)
+    }
+
+    /// Whether an order meets the pair's minimum quantity and cost, or None if the pair is unknown.
+    pub fn meets_minimums(&self, symbol: &str, quantity: Decimal, price: Decimal) -> Option<bool> {
+        let pair = self.pair(symbol)?;
+        Some(quantity >= pair.quantity_min && quantity * price >= pair.cost_min)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wss::{ChannelMessage, PairStatus, WssMessage};
+    use rust_decimal_macros::dec;
+
+    fn message(kind: &str, pairs: &str) -> InstrumentMessage {
+        let raw =
+            format!(r#"{{"channel":"instrument","type":"{kind}","data":{{"pairs":[{pairs}]}}}}"#);
+
+        match serde_json::from_str(&raw).unwrap() {
+            WssMessage::Channel(ChannelMessage::Instrument(message)) => message,
+            _ => panic!("expected an instrument message"),
+        }
+    }
+
+    fn pair(symbol: &str, status: &str) -> String {
+        format!(
+            r#"{{"symbol":"{symbol}","base":"ETH","quote":"BTC","status":"{status}","qty_precision":8,"qty_increment":0.00000001,"price_precision":5,"cost_precision":10,"marginable":true,"has_index":true,"cost_min":0.00002,"margin_initial":0.2,"position_limit_long":1000,"position_limit_short":600,"tick_size":0.00001,"price_increment":0.00001,"qty_min":0.002}}"#
+        )
+    }
+
+    #[test]
+    fn test_instrument_cache() {
+        let mut cache = InstrumentCache::new();
+        cache.apply(message("snapshot", &pair("ETH/BTC", "online")));
+
+        assert_eq!(
+            dec!(0.05321),
+            cache.round_price("ETH/BTC", dec!(0.053214)).unwrap()
+        );
+        assert_eq!(
+            dec!(0.05322),
+            cache.round_price("ETH/BTC", dec!(0.053216)).unwrap()
+        );
+        assert_eq!(
+            dec!(1.23456789),
+            cache.round_qty("ETH/BTC", dec!(1.234567899)).unwrap()
+        );
+        assert_eq!(
+            Some(true),
+            cache.meets_minimums("ETH/BTC", dec!(0.002), dec!(0.05))
+        );
+        assert_eq!(
+            Some(false),
+            cache.meets_minimums("ETH/BTC", dec!(0.001), dec!(0.05))
+        );
+        assert!(cache.round_price("XBT/USD", dec!(1)).is_none());
+
+        cache.apply(message("update", &pair("ETH/BTC", "cancel_only")));
+        assert_eq!(1, cache.pairs().count());
+        assert_eq!(
+            PairStatus::CancelOnly,
+            cache.pair("ETH/BTC").unwrap().status
+        );
+
+        cache.apply(message("snapshot", &pair("LTC/BTC", "online")));
+        assert!(cache.pair("ETH/BTC").is_none());
+        assert!(cache.pair("LTC/BTC").is_some());
+    }
+}
@@ -1,24 +1,70 @@
 //! Kraken WSS client and message streams
-use crate::wss::errors::WSSError;
-use crate::wss::Message;
-use futures_util::SinkExt;
+use crate::endpoints::Endpoints;
+use crate::metrics::{Metrics, NoopMetrics};
+use crate::wss::errors::{RejectedRequest, TungsteniteError, WSSError};
+use crate::wss::{
+    AddOrderParams, AmendOrderParams, AmendOrderResult, AmendOrdersResponse, BalanceResponse,
+    BalancesSubscription, BookSubscription, ChannelMessage, ExecutionResult, ExecutionSubscription,
+    InstrumentMessage, InstrumentsSubscription, MarketDataResponse, Message, MessageIdGenerator,
+    MethodMessage, Ohlc, OhlcInterval, OhlcSubscription, Response, ResultResponse, SingleResponse,
+    StatusUpdate, SubscriptionResult, Ticker, TickerSubscription, Trade, TradesSubscription,
+    WssMessage, L2, L3,
+};
+use futures_util::future::ready;
+use futures_util::{stream, SinkExt, StreamExt};
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap, VecDeque};
 use std::fmt::Debug;
+use std::future::Future;
 use std::marker::PhantomData;
 use std::pin::Pin;
+use std::sync::Arc;
 use std::task::{Context, Poll};
+use std::time::Duration;
 use tokio::net::TcpStream;
+use tokio::sync::mpsc::{channel, Receiver};
+use tokio::time::{sleep, Instant, Sleep};
 use tokio_stream::Stream;
+use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+use tokio_tungstenite::tungstenite::protocol::CloseFrame;
+pub use tokio_tungstenite::tungstenite::protocol::WebSocketConfig;
 use tokio_tungstenite::tungstenite::Message as TungsteniteMessage;
-use tokio_tungstenite::{connect_async, MaybeTlsStream, WebSocketStream};
-use tracing::trace;
+use tokio_tungstenite::{connect_async_with_config, MaybeTlsStream, WebSocketStream};
+use tracing::{trace, warn};
 use url::Url;
 
 pub const WS_KRAKEN: &str = "wss://ws.kraken.com/v2";
 pub const WS_KRAKEN_AUTH: &str = "wss://ws-auth.kraken.com/v2";
 
+/// Methods that Kraken only accepts on the private websocket.
+pub const PRIVATE_METHODS: [&str; 8] = [
+    "add_order",
+    "amend_order",
+    "edit_order",
+    "cancel_order",
+    "cancel_all",
+    "cancel_all_orders_after",
+    "batch_add",
+    "batch_cancel",
+];
+
+/// Channels that can only be subscribed to on the private websocket.
+pub const PRIVATE_CHANNELS: [&str; 3] = ["executions", "balances", "level3"];
+
 type RawStream = WebSocketStream<MaybeTlsStream<TcpStream>>;
 
+/// A channel subscribed to on a [KrakenMessageStream], see
+/// [KrakenMessageStream::active_subscriptions].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubscriptionSummary {
+    pub channel: String,
+    /// The subscribed symbols, empty for channels without symbols such as executions.
+    pub symbols: Vec<String>,
+    /// Whether the subscription is on the private websocket.
+    pub authenticated: bool,
+}
+
 /// A client for connecting to Kraken websockets via the V2 protocol.
 #[derive(Debug, Clone)]
 pub struct KrakenWSSClient {
@@ -26,6 +72,8 @@ pub struct KrakenWSSClient {
     auth_url: String,
     trace_inbound: bool,
     trace_outbound: bool,
+    websocket_config: Option<WebSocketConfig>,
+    metrics: Arc<dyn Metrics>,
 }
 
 impl Default for KrakenWSSClient {
@@ -47,6 +95,11 @@ impl KrakenWSSClient {
         KrakenWSSClient::new_with_tracing(base_url, auth_url, false, false)
     }
 
+    /// Create a client using the websocket URLs of the given [Endpoints].
+    pub fn new_with_endpoints(endpoints: &Endpoints) -> KrakenWSSClient {
+        KrakenWSSClient::new_with_urls(&endpoints.wss, &endpoints.wss_auth)
+    }
+
     pub fn new_with_tracing(
         base_url: impl ToString,
         auth_url: impl ToString,
@@ -58,16 +111,37 @@ impl KrakenWSSClient {
             auth_url: auth_url.to_string(),
             trace_inbound,
             trace_outbound,
+            websocket_config: None,
+            metrics: Arc::new(NoopMetrics),
         }
     }
 
+    /// Set the [WebSocketConfig] used for all connections, e.g. to cap `max_message_size` and
+    /// `max_frame_size` so oversized messages fail with [WSSError::MessageTooLarge] rather than
+    /// being buffered.
+    pub fn with_websocket_config(mut self, config: WebSocketConfig) -> KrakenWSSClient {
+        self.websocket_config = Some(config);
+        self
+    }
+
+    /// Report events of all streams this client connects to `metrics`, see [Metrics].
+    pub fn with_metrics(mut self, metrics: Arc<dyn Metrics>) -> KrakenWSSClient {
+        self.metrics = metrics;
+        self
+    }
+
+    /// The [Metrics] given to each stream this client connects.
+    pub fn metrics(&self) -> &Arc<dyn Metrics> {
+        &self.metrics
+    }
+
     /// Connect to the Kraken public websocket channel, returning a [`Result`] containing a
     /// [`KrakenMessageStream`] of [`PublicMessage`]s.
     pub async fn connect<T>(&mut self) -> Result<KrakenMessageStream<T>, WSSError>
     where
         T: for<'d> Deserialize<'d>,
     {
-        self._connect(&self.base_url.clone()).await
+        self._connect(&self.base_url.clone(), false).await
     }
 
     /// Connect to the Kraken private websocket channel, returning a [`Result`] containing a
@@ -76,22 +150,235 @@ impl KrakenWSSClient {
     where
         T: for<'d> Deserialize<'d>,
     {
-        self._connect(&self.auth_url.clone()).await
+        self._connect(&self.auth_url.clone(), true).await
+    }
+
+    /// Connect to the public websocket, subscribe to the L2 book and await the acknowledgement,
+    /// returning a stream of only book messages.
+    ///
+    /// Returns [WSSError::InvalidBookDepth] without connecting if the depth is not one of
+    /// [BookSubscription::DEPTHS].
+    pub async fn subscribe_book(
+        &mut self,
+        params: BookSubscription,
+        req_id: i64,
+    ) -> Result<ChannelStream<SingleResponse<L2>>, WSSError> {
+        if let Some(depth) = params.depth.filter(|_| !params.has_valid_depth()) {
+            return Err(WSSError::InvalidBookDepth(depth));
+        }
+
+        let stream = self.connect().await?;
+        stream
+            .subscribe_channel(params, req_id, |message| match message {
+                WssMessage::Channel(ChannelMessage::Orderbook(response)) => Some(response),
+                _ => None,
+            })
+            .await
+    }
+
+    /// Connect to the private websocket, subscribe to the L3 book and await the acknowledgement,
+    /// returning a stream of only L3 book messages.
+    pub async fn subscribe_level3(
+        &mut self,
+        params: BookSubscription,
+        req_id: i64,
+    ) -> Result<ChannelStream<SingleResponse<L3>>, WSSError> {
+        let stream = self.connect_auth().await?;
+        stream
+            .subscribe_channel(params, req_id, |message| match message {
+                WssMessage::Channel(ChannelMessage::L3(response)) => Some(response),
+                _ => None,
+            })
+            .await
+    }
+
+    /// Connect to the public websocket, subscribe to tickers and await the acknowledgement,
+    /// returning a stream of only ticker messages.
+    pub async fn subscribe_ticker(
+        &mut self,
+        params: TickerSubscription,
+        req_id: i64,
+    ) -> Result<ChannelStream<SingleResponse<Ticker>>, WSSError> {
+        let stream = self.connect().await?;
+        stream
+            .subscribe_channel(params, req_id, |message| match message {
+                WssMessage::Channel(ChannelMessage::Ticker(response)) => Some(response),
+                _ => None,
+            })
+            .await
+    }
+
+    /// Connect to the public websocket, subscribe to trades and await the acknowledgement,
+    /// returning a stream of only trade messages.
+    pub async fn subscribe_trades(
+        &mut self,
+        params: TradesSubscription,
+        req_id: i64,
+    ) -> Result<ChannelStream<MarketDataResponse<Vec<Trade>>>, WSSError> {
+        let stream = self.connect().await?;
+        stream
+            .subscribe_channel(params, req_id, |message| match message {
+                WssMessage::Channel(ChannelMessage::Trade(response)) => Some(response),
+                _ => None,
+            })
+            .await
+    }
+
+    /// Connect to the public websocket, subscribe to OHLC candles and await the acknowledgement,
+    /// returning a stream of only OHLC messages.
+    pub async fn subscribe_ohlc(
+        &mut self,
+        params: OhlcSubscription,
+        req_id: i64,
+    ) -> Result<ChannelStream<MarketDataResponse<Vec<Ohlc>>>, WSSError> {
+        let stream = self.connect().await?;
+        stream
+            .subscribe_channel(params, req_id, |message| match message {
+                WssMessage::Channel(ChannelMessage::Ohlc(response)) => Some(response),
+                _ => None,
+            })
+            .await
+    }
+
+    /// Connect to the public websocket and subscribe to OHLC candles of `symbols` once per interval,
+    /// awaiting every acknowledgement, returning a stream of OHLC messages for all intervals.
+    ///
+    /// Kraken accepts a single interval per subscription, so each is sent separately with a
+    /// `req_id` from the stream's [MessageIdGenerator]. All subscriptions are sent before any
+    /// acknowledgement is awaited, and candles received in the meantime (e.g. the snapshot of an
+    /// interval acknowledged first) are buffered and yielded first by the returned stream. Candles
+    /// carry their `interval`, see [ChannelStream::demux_by_interval] to route them.
+    pub async fn subscribe_ohlc_intervals(
+        &mut self,
+        symbols: Vec<String>,
+        intervals: &[OhlcInterval],
+    ) -> Result<ChannelStream<MarketDataResponse<Vec<Ohlc>>>, WSSError> {
+        let mut stream = self.connect().await?;
+        let mut unacknowledged = Vec::with_capacity(intervals.len());
+
+        for interval in intervals {
+            let req_id = stream.next_req_id();
+            let params = OhlcSubscription::new(symbols.clone(), *interval);
+            stream
+                .send(&Message::new_subscription(params, req_id))
+                .await?;
+            unacknowledged.push(req_id);
+        }
+
+        let mut buffered = VecDeque::new();
+
+        while !unacknowledged.is_empty() {
+            let Some(message) = stream.next().await else {
+                return Err(WSSError::WSS(TungsteniteError::ConnectionClosed));
+            };
+
+            match message? {
+                WssMessage::Method(MethodMessage::Subscription(response))
+                    if unacknowledged.contains(&response.req_id) =>
+                {
+                    let req_id = response.req_id;
+                    response
+                        .into_result()?
+                        .ok_or(WSSError::Rejected(RejectedRequest {
+                            req_id,
+                            error: "Missing subscription result".to_string(),
+                        }))?;
+                    unacknowledged.retain(|id| *id != req_id);
+                }
+                WssMessage::Error(response) if unacknowledged.contains(&response.req_id) => {
+                    return Err(WSSError::Rejected(RejectedRequest {
+                        req_id: response.req_id,
+                        error: response.error.unwrap_or_default(),
+                    }));
+                }
+                WssMessage::Channel(ChannelMessage::Ohlc(response)) => buffered.push_back(response),
+                _ => {}
+            }
+        }
+
+        let mut channel = stream.into_channel(|message| match message {
+            WssMessage::Channel(ChannelMessage::Ohlc(response)) => Some(response),
+            _ => None,
+        });
+        channel.buffered = buffered;
+        Ok(channel)
+    }
+
+    /// Connect to the public websocket, subscribe to instruments and await the acknowledgement,
+    /// returning a stream of only instrument messages.
+    pub async fn subscribe_instruments(
+        &mut self,
+        params: InstrumentsSubscription,
+        req_id: i64,
+    ) -> Result<ChannelStream<InstrumentMessage>, WSSError> {
+        let stream = self.connect().await?;
+        stream
+            .subscribe_channel(params, req_id, |message| match message {
+                WssMessage::Channel(ChannelMessage::Instrument(response)) => Some(response),
+                _ => None,
+            })
+            .await
+    }
+
+    /// Connect to the private websocket, subscribe to executions and await the acknowledgement,
+    /// returning a stream of only execution messages.
+    pub async fn subscribe_executions(
+        &mut self,
+        params: ExecutionSubscription,
+        req_id: i64,
+    ) -> Result<ChannelStream<Response<Vec<ExecutionResult>>>, WSSError> {
+        let stream = self.connect_auth().await?;
+        stream
+            .subscribe_channel(params, req_id, |message| match message {
+                WssMessage::Channel(ChannelMessage::Execution(response)) => Some(response),
+                _ => None,
+            })
+            .await
+    }
+
+    /// Connect to the private websocket, subscribe to balances and await the acknowledgement,
+    /// returning a stream of only balance messages.
+    pub async fn subscribe_balances(
+        &mut self,
+        params: BalancesSubscription,
+        req_id: i64,
+    ) -> Result<ChannelStream<Response<BalanceResponse>>, WSSError> {
+        let stream = self.connect_auth().await?;
+        stream
+            .subscribe_channel(params, req_id, |message| match message {
+                WssMessage::Channel(ChannelMessage::Balance(response)) => Some(response),
+                _ => None,
+            })
+            .await
     }
 
     #[tracing::instrument(skip(self))]
-    async fn _connect<T>(&mut self, url: &str) -> Result<KrakenMessageStream<T>, WSSError>
+    async fn _connect<T>(
+        &mut self,
+        url: &str,
+        authenticated: bool,
+    ) -> Result<KrakenMessageStream<T>, WSSError>
     where
         T: for<'d> Deserialize<'d>,
     {
         let url = Url::parse(url)?;
-        let (raw_stream, _response) = connect_async(url.as_str()).await?;
+        let (raw_stream, _response) =
+            connect_async_with_config(url.as_str(), self.websocket_config, false).await?;
 
         Ok(KrakenMessageStream {
             stream: raw_stream,
             phantom: PhantomData,
             trace_inbound: self.trace_inbound,
             trace_outbound: self.trace_outbound,
+            stall_timeout: None,
+            stall_timer: None,
+            server_status: None,
+            req_ids: MessageIdGenerator::new(),
+            authenticated,
+            subscriptions: BTreeMap::new(),
+            subscription_limit: None,
+            metrics: self.metrics.clone(),
+            closed: false,
         })
     }
 }
@@ -99,6 +386,9 @@ impl KrakenWSSClient {
 /// A futures_core::[`Stream`] implementation that returns deserializable messages. Messages can be
 /// retrieved by awaiting `someStream.next()`.
 ///
+/// The stream ends once the server closes the connection. A normal close simply ends it, while any
+/// other close code is first yielded as a [WSSError::Closed].
+///
 /// # Example: Listening to Public Messages
 /// See the full example including subscribing to channels in examples/live_public_wss_listening.rs.
 /// ```ignore
@@ -117,6 +407,15 @@ where
     phantom: PhantomData<T>,
     trace_inbound: bool,
     trace_outbound: bool,
+    stall_timeout: Option<Duration>,
+    stall_timer: Option<Pin<Box<Sleep>>>,
+    server_status: Option<StatusUpdate>,
+    req_ids: MessageIdGenerator,
+    authenticated: bool,
+    subscriptions: BTreeMap<(String, Option<String>), usize>,
+    subscription_limit: Option<usize>,
+    metrics: Arc<dyn Metrics>,
+    closed: bool,
 }
 
 impl<T> Unpin for KrakenMessageStream<T>
@@ -131,7 +430,12 @@ where
     T: for<'a> Deserialize<'a>,
 {
     /// Send an arbitrary serializable message through the stream.
-    #[tracing::instrument(skip(self))]
+    ///
+    /// On a stream from [KrakenWSSClient::connect], any of the [PRIVATE_METHODS] or a subscription to
+    /// one of the [PRIVATE_CHANNELS] returns [WSSError::RequiresAuthChannel] without being sent.
+    /// Subscriptions beyond the [KrakenMessageStream::set_subscription_limit] return
+    /// [WSSError::SubscriptionLimitReached], also without being sent.
+    #[tracing::instrument(skip(self, message), fields(method = %message.method, req_id = message.req_id))]
     pub async fn send<M>(&mut self, message: &Message<M>) -> Result<(), WSSError>
     where
         M: Serialize + Debug,
@@ -139,15 +443,249 @@ where
         self.send_as_str(message).await
     }
 
-    #[tracing::instrument(skip(self))]
+    /// Send a message with its `req_id` replaced by the next id from this stream's
+    /// [MessageIdGenerator], returning the id used so the response can be matched to it.
+    pub async fn send_with_next_id<M>(&mut self, message: &mut Message<M>) -> Result<i64, WSSError>
+    where
+        M: Serialize + Debug,
+    {
+        message.req_id = self.next_req_id();
+        self.send(message).await?;
+        Ok(message.req_id)
+    }
+
+    /// Whether this stream is connected to the private websocket, via [KrakenWSSClient::connect_auth].
+    pub fn is_authenticated(&self) -> bool {
+        self.authenticated
+    }
+
+    /// Take the next `req_id` from this stream's [MessageIdGenerator], e.g. for
+    /// [KrakenMessageStream::subscribe].
+    pub fn next_req_id(&self) -> i64 {
+        self.req_ids.next_id()
+    }
+
+    /// Replace the generator of `req_id`s, e.g. with a clone of one shared by other streams so ids
+    /// are unique across connections.
+    pub fn set_message_id_generator(&mut self, generator: MessageIdGenerator) {
+        self.req_ids = generator;
+    }
+
+    /// Close the connection with a normal close code, waiting for the server to acknowledge it.
+    ///
+    /// Any messages received before the acknowledgement are dropped. Kraken ends all subscriptions
+    /// of a connection when it closes.
+    pub async fn close(mut self) -> Result<(), WSSError> {
+        let frame = CloseFrame {
+            code: CloseCode::Normal,
+            reason: "".into(),
+        };
+        self.stream.close(Some(frame)).await?;
+
+        while let Some(message) = self.stream.next().await {
+            match message {
+                Ok(TungsteniteMessage::Close(_)) | Err(TungsteniteError::ConnectionClosed) => break,
+                Ok(_) => {}
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Yield a [WSSError::Stalled] error whenever no message has been received within `timeout`,
+    /// or disable stall detection with `None` (the default).
+    ///
+    /// Kraken sends a heartbeat roughly every second once subscribed to any channel, so a gap of a
+    /// few seconds is a reliable sign of a dead connection, long before TCP would notice. Receiving
+    /// any message resets the timer, as does yielding a stall error, so the error repeats once per
+    /// `timeout` until messages resume.
+    pub fn set_stall_timeout(&mut self, timeout: Option<Duration>) {
+        self.stall_timeout = timeout;
+        self.stall_timer = timeout.map(|timeout| Box::pin(sleep(timeout)));
+    }
+
+    /// The first status update received on this connection, containing the connection id and API
+    /// version of the server, or None if it has not been received yet.
+    pub fn server_status(&self) -> Option<&StatusUpdate> {
+        self.server_status.as_ref()
+    }
+
+    /// The channels currently subscribed to on this connection, ordered by channel name.
+    ///
+    /// Subscriptions are tracked from the acknowledgements Kraken sends, so they're only current as
+    /// of the last message read from the stream. A subscription is listed until every
+    /// acknowledged subscription to its channel and symbol has been unsubscribed, e.g. both of two
+    /// OHLC intervals.
+    pub fn active_subscriptions(&self) -> Vec<SubscriptionSummary> {
+        let mut summaries: Vec<SubscriptionSummary> = Vec::new();
+
+        for (channel, symbol) in self.subscriptions.keys() {
+            if summaries.last().map(|summary| &summary.channel) != Some(channel) {
+                summaries.push(SubscriptionSummary {
+                    channel: channel.clone(),
+                    symbols: Vec::new(),
+                    authenticated: self.authenticated,
+                });
+            }
+
+            if let (Some(summary), Some(symbol)) = (summaries.last_mut(), symbol) {
+                summary.symbols.push(symbol.clone());
+            }
+        }
+
+        summaries
+    }
+
+    /// Limit the number of subscriptions on this connection, or remove the limit with `None` (the
+    /// default).
+    ///
+    /// Each symbol of a channel counts as one subscription, as do channels without symbols such as
+    /// executions, and resubscribing to a symbol already subscribed to doesn't count again. Once a
+    /// subscription would exceed the limit, it returns [WSSError::SubscriptionLimitReached] without
+    /// being sent, so another connection can be opened for it instead. Only subscriptions
+    /// acknowledged by Kraken count towards the limit, see
+    /// [KrakenMessageStream::active_subscriptions].
+    pub fn set_subscription_limit(&mut self, limit: Option<usize>) {
+        self.subscription_limit = limit;
+    }
+
+    pub fn subscription_limit(&self) -> Option<usize> {
+        self.subscription_limit
+    }
+
+    /// The number of subscriptions the message would add that aren't already active.
+    fn new_subscriptions<M>(&self, message: &Message<M>) -> usize
+    where
+        M: Serialize + Debug,
+    {
+        if message.method != "subscribe" {
+            return 0;
+        }
+
+        let Ok(params) = serde_json::to_value(&message.params) else {
+            return 0;
+        };
+        let Some(channel) = params["channel"].as_str() else {
+            return 0;
+        };
+
+        let symbols: Vec<Option<String>> = match params["symbol"].as_array() {
+            Some(symbols) => symbols
+                .iter()
+                .map(|symbol| symbol.as_str().map(String::from))
+                .collect(),
+            None => vec![None],
+        };
+
+        symbols
+            .into_iter()
+            .filter(|symbol| {
+                !self
+                    .subscriptions
+                    .contains_key(&(channel.to_string(), symbol.clone()))
+            })
+            .count()
+    }
+
+    /// The private method or channel of the message, if only the private websocket accepts it.
+    fn requires_auth<M>(message: &Message<M>) -> Option<String>
+    where
+        M: Serialize + Debug,
+    {
+        if PRIVATE_METHODS.contains(&message.method.as_str()) {
+            return Some(message.method.clone());
+        }
+
+        if message.method != "subscribe" && message.method != "unsubscribe" {
+            return None;
+        }
+
+        let params = serde_json::to_value(&message.params).ok()?;
+        let channel = params.get("channel")?.as_str()?;
+        PRIVATE_CHANNELS
+            .contains(&channel)
+            .then(|| channel.to_string())
+    }
+
+    fn capture_server_status(&mut self, text: &str) {
+        if self.server_status.is_none() && text.contains(r#""channel":"status""#) {
+            if let Ok(ChannelMessage::Status(status)) = serde_json::from_str(text) {
+                self.server_status = Some(status.data);
+            }
+        }
+    }
+
+    fn track_subscription(&mut self, text: &str) {
+        let subscribed = text.contains(r#""method":"subscribe""#);
+        if !subscribed && !text.contains(r#""method":"unsubscribe""#) {
+            return;
+        }
+
+        let Ok(value) = serde_json::from_str::<Value>(text) else {
+            return;
+        };
+        let Some(channel) = value["result"]["channel"].as_str() else {
+            return;
+        };
+        if value["success"].as_bool() != Some(true) {
+            return;
+        }
+
+        let key = (
+            channel.to_string(),
+            value["result"]["symbol"].as_str().map(String::from),
+        );
+
+        if subscribed {
+            *self.subscriptions.entry(key).or_default() += 1;
+        } else if let Some(count) = self.subscriptions.get_mut(&key) {
+            *count -= 1;
+            if *count == 0 {
+                self.subscriptions.remove(&key);
+            }
+        }
+    }
+
+    fn reset_stall_timer(&mut self) {
+        if let (Some(timeout), Some(timer)) = (self.stall_timeout, self.stall_timer.as_mut()) {
+            timer.as_mut().reset(Instant::now() + timeout);
+        }
+    }
+
+    /// Poll the stall timer, returning the stall timeout if it elapsed and resetting it.
+    fn poll_stalled(&mut self, cx: &mut Context<'_>) -> Option<Duration> {
+        let timeout = self.stall_timeout?;
+        let timer = self.stall_timer.as_mut()?;
+
+        if timer.as_mut().poll(cx).is_ready() {
+            timer.as_mut().reset(Instant::now() + timeout);
+            Some(timeout)
+        } else {
+            None
+        }
+    }
+
     async fn send_as_str<M>(&mut self, message: &Message<M>) -> Result<(), WSSError>
     where
         M: Serialize + Debug,
     {
+        if !self.authenticated {
+            if let Some(name) = Self::requires_auth(message) {
+                return Err(WSSError::RequiresAuthChannel(name));
+            }
+        }
+
+        if let Some(limit) = self.subscription_limit {
+            if self.subscriptions.len() + self.new_subscriptions(message) > limit {
+                return Err(WSSError::SubscriptionLimitReached { limit });
+            }
+        }
+
         let message_json = serde_json::to_string(message)?;
 
         if self.trace_outbound {
-            trace!("Sending: {}", message_json);
+            trace!(payload = %message_json, "Sending message");
         }
 
         self.stream
@@ -157,6 +695,282 @@ where
     }
 }
 
+impl KrakenMessageStream<WssMessage> {
+    /// Send a subscription and wait for Kraken to acknowledge it, returning [WSSError::Rejected] if
+    /// the subscription failed.
+    ///
+    /// Any other messages received before the acknowledgement are dropped. Kraken acknowledges
+    /// subscriptions to several symbols once per symbol, and only the first is awaited.
+    #[tracing::instrument(skip(self))]
+    pub async fn subscribe<P>(
+        &mut self,
+        params: P,
+        req_id: i64,
+    ) -> Result<SubscriptionResult, WSSError>
+    where
+        P: Serialize + Debug,
+    {
+        self.send(&Message::new_subscription(params, req_id))
+            .await?;
+
+        while let Some(message) = self.next().await {
+            match message? {
+                WssMessage::Method(MethodMessage::Subscription(response))
+                    if response.req_id == req_id =>
+                {
+                    return response
+                        .into_result()?
+                        .ok_or(WSSError::Rejected(RejectedRequest {
+                            req_id,
+                            error: "Missing subscription result".to_string(),
+                        }));
+                }
+                WssMessage::Error(response) if response.req_id == req_id => {
+                    return Err(WSSError::Rejected(RejectedRequest {
+                        req_id,
+                        error: response.error.unwrap_or_default(),
+                    }));
+                }
+                _ => {}
+            }
+        }
+
+        Err(WSSError::WSS(TungsteniteError::ConnectionClosed))
+    }
+
+    /// Check that Kraken would accept an order without placing it, by sending it with
+    /// `validate = true` and waiting for the response.
+    ///
+    /// Any other messages received before the response are returned in the order received, so
+    /// they aren't lost to the caller.
+    #[tracing::instrument(skip(self, params), fields(symbol = %params.symbol, client_order_id = ?params.client_order_id))]
+    pub async fn dry_run(
+        &mut self,
+        params: AddOrderParams,
+        req_id: i64,
+    ) -> Result<Vec<WssMessage>, WSSError> {
+        let message = Message {
+            method: "add_order".to_string(),
+            params: AddOrderParams {
+                validate: Some(true),
+                ..params
+            },
+            req_id,
+        };
+        self.send(&message).await?;
+
+        let mut other_messages = Vec::new();
+
+        while let Some(message) = self.next().await {
+            match message? {
+                WssMessage::Method(MethodMessage::AddOrder(response))
+                    if response.req_id == req_id =>
+                {
+                    response.into_result()?;
+                    return Ok(other_messages);
+                }
+                WssMessage::Error(response) if response.req_id == req_id => {
+                    return Err(WSSError::Rejected(RejectedRequest {
+                        req_id,
+                        error: response.error.unwrap_or_default(),
+                    }));
+                }
+                other => other_messages.push(other),
+            }
+        }
+
+        Err(WSSError::WSS(TungsteniteError::ConnectionClosed))
+    }
+
+    /// Narrow this stream to only the messages `select` returns `Some` for, see [ChannelStream].
+    pub fn into_channel<R>(self, select: fn(WssMessage) -> Option<R>) -> ChannelStream<R> {
+        ChannelStream {
+            stream: self,
+            select,
+            buffered: VecDeque::new(),
+        }
+    }
+
+    async fn subscribe_channel<P, R>(
+        mut self,
+        params: P,
+        req_id: i64,
+        select: fn(WssMessage) -> Option<R>,
+    ) -> Result<ChannelStream<R>, WSSError>
+    where
+        P: Serialize + Debug,
+    {
+        self.subscribe(params, req_id).await?;
+        Ok(self.into_channel(select))
+    }
+
+    /// Filter the stream to only the messages `select` returns `Some` for, skipping all others.
+    ///
+    /// Errors are always passed through. The `only_*` methods cover the common case of selecting a
+    /// single channel.
+    ///
+    /// # Example: Listening to Executions
+    /// ```ignore
+    ///let mut executions = kraken_stream.filter_messages(|message| match message {
+    ///    WssMessage::Channel(ChannelMessage::Execution(response)) => Some(response),
+    ///    _ => None,
+    ///});
+    ///
+    ///while let Some(response) = executions.next().await {
+    ///    println!("{:?}", response.unwrap().data);
+    ///}
+    /// ```
+    pub fn filter_messages<'a, R, F>(
+        &'a mut self,
+        mut select: F,
+    ) -> impl Stream<Item = Result<R, WSSError>> + 'a
+    where
+        R: 'a,
+        F: FnMut(WssMessage) -> Option<R> + 'a,
+    {
+        self.filter_map(move |message| ready(message.map(&mut select).transpose()))
+    }
+
+    /// Filter the stream to only execution channel messages.
+    pub fn only_executions(
+        &mut self,
+    ) -> impl Stream<Item = Result<Response<Vec<ExecutionResult>>, WSSError>> + '_ {
+        self.filter_messages(|message| match message {
+            WssMessage::Channel(ChannelMessage::Execution(response)) => Some(response),
+            _ => None,
+        })
+    }
+
+    /// Filter the stream to only balance channel messages.
+    pub fn only_balances(
+        &mut self,
+    ) -> impl Stream<Item = Result<Response<BalanceResponse>, WSSError>> + '_ {
+        self.filter_messages(|message| match message {
+            WssMessage::Channel(ChannelMessage::Balance(response)) => Some(response),
+            _ => None,
+        })
+    }
+
+    /// Filter the stream to only trade channel messages.
+    pub fn only_trades(
+        &mut self,
+    ) -> impl Stream<Item = Result<MarketDataResponse<Vec<Trade>>, WSSError>> + '_ {
+        self.filter_messages(|message| match message {
+            WssMessage::Channel(ChannelMessage::Trade(response)) => Some(response),
+            _ => None,
+        })
+    }
+
+    /// Filter the stream to only ticker channel messages.
+    pub fn only_tickers(
+        &mut self,
+    ) -> impl Stream<Item = Result<SingleResponse<Ticker>, WSSError>> + '_ {
+        self.filter_messages(|message| match message {
+            WssMessage::Channel(ChannelMessage::Ticker(response)) => Some(response),
+            _ => None,
+        })
+    }
+
+    /// Filter the stream to only OHLC channel messages.
+    pub fn only_ohlc(
+        &mut self,
+    ) -> impl Stream<Item = Result<MarketDataResponse<Vec<Ohlc>>, WSSError>> + '_ {
+        self.filter_messages(|message| match message {
+            WssMessage::Channel(ChannelMessage::Ohlc(response)) => Some(response),
+            _ => None,
+        })
+    }
+
+    /// Filter the stream to only instrument channel messages.
+    pub fn only_instruments(
+        &mut self,
+    ) -> impl Stream<Item = Result<InstrumentMessage, WSSError>> + '_ {
+        self.filter_messages(|message| match message {
+            WssMessage::Channel(ChannelMessage::Instrument(response)) => Some(response),
+            _ => None,
+        })
+    }
+
+    /// Filter the stream to only L2 book channel messages.
+    pub fn only_books(&mut self) -> impl Stream<Item = Result<SingleResponse<L2>, WSSError>> + '_ {
+        self.filter_messages(|message| match message {
+            WssMessage::Channel(ChannelMessage::Orderbook(response)) => Some(response),
+            _ => None,
+        })
+    }
+
+    /// Filter the stream to only L3 book channel messages.
+    pub fn only_level3(&mut self) -> impl Stream<Item = Result<SingleResponse<L3>, WSSError>> + '_ {
+        self.filter_messages(|message| match message {
+            WssMessage::Channel(ChannelMessage::L3(response)) => Some(response),
+            _ => None,
+        })
+    }
+
+    /// Amend several orders with one call by pipelining an `amend_order` message per order.
+    ///
+    /// Amends are sent with consecutive request ids starting at `first_req_id`, then the stream is
+    /// read until every amend has been answered. Responses are returned in the order of `amends`,
+    /// and any unrelated messages and errors received in the meantime are returned alongside them.
+    ///
+    /// Messages that fail to deserialize are collected and skipped, while any other error stops
+    /// waiting like the stream ending does. Responses collected so far are kept either way, with
+    /// None for each amend left unanswered. Only failing to send an amend returns an error.
+    pub async fn amend_orders(
+        &mut self,
+        amends: Vec<AmendOrderParams>,
+        first_req_id: i64,
+    ) -> Result<AmendOrdersResponse, WSSError> {
+        let n_amends = amends.len();
+
+        for (req_id, params) in (first_req_id..).zip(amends) {
+            let message = Message {
+                method: "amend_order".to_string(),
+                params,
+                req_id,
+            };
+            self.send(&message).await?;
+        }
+
+        let req_ids = first_req_id..first_req_id + n_amends as i64;
+        let mut responses: Vec<Option<ResultResponse<AmendOrderResult>>> =
+            (0..n_amends).map(|_| None).collect();
+        let mut other_messages = Vec::new();
+        let mut errors = Vec::new();
+        let mut n_received = 0;
+
+        while n_received < n_amends {
+            let Some(message) = self.next().await else {
+                break;
+            };
+
+            match message {
+                Ok(WssMessage::Method(MethodMessage::AmendOrder(response)))
+                    if req_ids.contains(&response.req_id) =>
+                {
+                    let i = (response.req_id - first_req_id) as usize;
+                    if responses[i].is_none() {
+                        n_received += 1;
+                    }
+                    responses[i] = Some(response);
+                }
+                Ok(other) => other_messages.push(other),
+                Err(err @ WSSError::Serde(_)) => errors.push(err),
+                Err(err) => {
+                    errors.push(err);
+                    break;
+                }
+            }
+        }
+
+        Ok(AmendOrdersResponse {
+            responses,
+            other_messages,
+            errors,
+        })
+    }
+}
+
 impl<T> Stream for KrakenMessageStream<T>
 where
     T: for<'a> Deserialize<'a>,
@@ -164,65 +978,293 @@ where
     type Item = Result<T, WSSError>;
 
     /// returns Poll:Ready with a message if available, otherwise Poll:Pending
+    ///
+    /// The stream ends (returning Poll:Ready(None)) once the connection is closed, whether with a
+    /// close frame or by the underlying connection ending without one.
+    ///
+    /// Binary frames are decoded as UTF-8 JSON like text frames, yielding a
+    /// [TungsteniteError::Utf8] if they aren't valid UTF-8. Control frames are skipped rather than
+    /// parsed. Tungstenite queues a pong for each ping it
+    /// reads, which is flushed here so it's sent without waiting for the next outbound message.
     fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-        if let Poll::Ready(Some(message)) = Pin::new(&mut self.stream).poll_next(cx)? {
+        if self.closed {
+            return Poll::Ready(None);
+        }
+
+        loop {
+            let message = match Pin::new(&mut self.stream).poll_next(cx) {
+                Poll::Ready(Some(message)) => message,
+                Poll::Ready(None) => {
+                    self.closed = true;
+                    return Poll::Ready(None);
+                }
+                Poll::Pending => break,
+            };
+            self.reset_stall_timer();
+
+            let text = match message? {
+                TungsteniteMessage::Ping(_) => {
+                    let _ = self.stream.poll_flush_unpin(cx);
+                    continue;
+                }
+                TungsteniteMessage::Close(frame) => {
+                    self.closed = true;
+                    return Poll::Ready(closed_error(frame).map(Err));
+                }
+                TungsteniteMessage::Pong(_) | TungsteniteMessage::Frame(_) => continue,
+                message => message.into_text()?,
+            };
+
             if self.trace_inbound {
-                trace!("Received: {}", message.to_string());
+                trace!(payload = %text, "Received message");
             }
-            let parsed: T = serde_json::from_str(message.to_text()?)?;
-            Poll::Ready(Some(Ok(parsed)))
+            self.capture_server_status(&text);
+            self.track_subscription(&text);
+            let parsed = serde_json::from_str::<T>(&text).inspect_err(|err| {
+                self.metrics.deserialize_error(err);
+            });
+            return Poll::Ready(Some(parsed.map_err(WSSError::from)));
+        }
+
+        if let Some(timeout) = self.poll_stalled(cx) {
+            Poll::Ready(Some(Err(WSSError::Stalled(timeout))))
         } else {
             Poll::Pending
         }
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::crypto::secrets::Token;
-    use crate::request_types::{TimeInForce, TimeInForceV2, TriggerType};
-    use crate::response_types::{BuySell, OrderStatusV2, OrderType, SystemStatus};
-    use crate::test_data::{
-        get_balances_subscription_response, get_book_subscription_response,
-        get_execution_subscription_response, get_expected_balances_message,
-        get_expected_balances_subscription, get_expected_book_message,
-        get_expected_book_subscription, get_expected_execution_message,
-        get_expected_execution_subscription, get_expected_instruments_message,
-        get_expected_instruments_subscription, get_expected_l3_message,
-        get_expected_l3_subscription, get_expected_ohlc_message, get_expected_ohlc_subscription,
-        get_expected_ping, get_expected_pong_message, get_expected_ticker_message,
-        get_expected_ticker_subscription, get_expected_trade_message,
-        get_expected_trade_subscription, get_instruments_subscription_response,
-        get_l3_subscription_response, get_ohlc_subscription_response, get_pong,
-        get_ticker_subscription_response, get_trade_subscription_response, parse_for_test,
-        CallResponseTest, ParseIncomingTest,
-    };
-    use crate::wss::ChannelMessage::{Heartbeat, Status};
-    use crate::wss::MethodMessage::{AddOrder, AmendOrder, CancelOrder, EditOrder};
-    use crate::wss::{
-        AddOrderParams, AddOrderResult, AmendOrderParams, AmendOrderResult, Asset, AssetStatus,
-        Balance, BalanceResponse, BalancesSubscription, BatchCancelParams, BatchCancelResponse,
-        BatchOrder, BatchOrderParams, BidAsk, BookSubscription, CancelAllOrdersParams,
-        CancelAllOrdersResult, CancelOnDisconnectParams, CancelOnDisconnectResult,
-        CancelOrderParams, CancelOrderResult, ChannelMessage, EditOrderParams, EditOrderResult,
-        ExecutionResult, ExecutionSubscription, ExecutionType, Fee, FeePreference, Instruments,
-        InstrumentsSubscription, L3BidAsk, L3BidAskUpdate, L3Orderbook, L3OrderbookUpdate,
-        LedgerCategory, LedgerEntryTypeV2, LedgerUpdate, MakerTaker, MarketDataResponse,
-        MarketLimit, MethodMessage, Ohlc, OhlcSubscription, Orderbook, OrderbookEvent,
-        OrderbookUpdate, Pair, PairStatus, PriceType, Response, ResultResponse, SingleResponse,
-        StatusUpdate, Ticker, TickerSubscription, Trade, TradesSubscription, TriggerDescription,
-        TriggerStatus, Wallet, WalletId, WalletType, WssMessage, L2, L3,
+/// The error for a close frame received from the server, or None if it closed normally.
+fn closed_error(frame: Option<CloseFrame>) -> Option<WSSError> {
+    match frame {
+        Some(frame) if frame.code != CloseCode::Normal => Some(WSSError::Closed {
+            code: frame.code.into(),
+            reason: frame.reason.into_owned(),
+        }),
+        _ => None,
+    }
+}
+
+/// A [KrakenMessageStream] narrowed to the messages of a single channel, as returned by the
+/// `subscribe_*` methods of [KrakenWSSClient].
+///
+/// Messages not selected (heartbeats, status updates, other channels) are skipped, while errors
+/// are always passed through. The underlying stream remains available for sending further
+/// messages, e.g. to unsubscribe.
+pub struct ChannelStream<R> {
+    stream: KrakenMessageStream<WssMessage>,
+    select: fn(WssMessage) -> Option<R>,
+    /// Messages received while subscribing, yielded before any read from `stream`
+    buffered: VecDeque<R>,
+}
+
+impl<R> ChannelStream<R> {
+    /// Mutable access to the underlying stream, e.g. for sending messages.
+    pub fn inner_mut(&mut self) -> &mut KrakenMessageStream<WssMessage> {
+        &mut self.stream
+    }
+
+    /// Return the underlying stream of all messages, dropping any buffered messages not yet yielded.
+    pub fn into_inner(self) -> KrakenMessageStream<WssMessage> {
+        self.stream
+    }
+}
+
+impl<T> ChannelStream<Response<Vec<T>>> {
+    /// Yield each item of every message individually, e.g. each execution of a large executions
+    /// snapshot rather than the whole snapshot at once.
+    ///
+    /// Each message is still deserialized in full before its items are yielded. Errors are passed
+    /// through, and the `sequence` of each message is dropped.
+    pub fn into_item_stream(self) -> impl Stream<Item = Result<T, WSSError>> {
+        self.flat_map(|message| match message {
+            Ok(response) => stream::iter(response.into_items().map(Ok)).left_stream(),
+            Err(err) => stream::once(ready(Err(err))).right_stream(),
+        })
+    }
+}
+
+impl ChannelStream<SingleResponse<Ticker>> {
+    /// Split a multi-symbol ticker stream into a bounded channel per symbol, spawning a task that
+    /// routes each ticker by its `symbol`.
+    ///
+    /// Tickers for symbols not in `symbols` are dropped. A full channel applies backpressure to all
+    /// symbols, so each receiver should be read promptly. Errors, e.g. a message that failed to
+    /// deserialize or a stall, are logged and skipped, and the routing task ends only when the
+    /// stream ends or every receiver is dropped, closing all remaining channels.
+    pub fn demux_by_symbol(
+        mut self,
+        symbols: &[String],
+        capacity: usize,
+    ) -> HashMap<String, Receiver<Ticker>> {
+        let mut senders = HashMap::new();
+        let mut receivers = HashMap::new();
+
+        for symbol in symbols {
+            let (sender, receiver) = channel(capacity);
+            senders.insert(symbol.clone(), sender);
+            receivers.insert(symbol.clone(), receiver);
+        }
+
+        tokio::spawn(async move {
+            while let Some(message) = self.next().await {
+                let ticker = match message {
+                    Ok(response) => response.data,
+                    Err(err) => {
+                        warn!("Skipping ticker demux error: {err}");
+                        continue;
+                    }
+                };
+
+                if let Some(sender) = senders.get(&ticker.symbol) {
+                    let symbol = ticker.symbol.clone();
+                    if sender.send(ticker).await.is_err() {
+                        senders.remove(&symbol);
+                    }
+                }
+
+                if senders.is_empty() {
+                    break;
+                }
+            }
+        });
+
+        receivers
+    }
+}
+
+impl ChannelStream<MarketDataResponse<Vec<Ohlc>>> {
+    /// Split an OHLC stream of several intervals into a bounded channel per interval, spawning a
+    /// task that routes each candle by its `interval`.
+    ///
+    /// Candles for intervals not in `intervals` are dropped. A full channel applies backpressure to
+    /// all intervals, so each receiver should be read promptly. Errors, e.g. a message that failed
+    /// to deserialize or a stall, are logged and skipped, and the routing task ends only when the
+    /// stream ends or every receiver is dropped, closing all remaining channels.
+    pub fn demux_by_interval(
+        mut self,
+        intervals: &[OhlcInterval],
+        capacity: usize,
+    ) -> HashMap<OhlcInterval, Receiver<Ohlc>> {
+        let mut senders = HashMap::new();
+        let mut receivers = HashMap::new();
+
+        for interval in intervals {
+            let (sender, receiver) = channel(capacity);
+            senders.insert(*interval, sender);
+            receivers.insert(*interval, receiver);
+        }
+
+        tokio::spawn(async move {
+            while let Some(message) = self.next().await {
+                let candles = match message {
+                    Ok(response) => response.data,
+                    Err(err) => {
+                        warn!("Skipping OHLC demux error: {err}");
+                        continue;
+                    }
+                };
+
+                for candle in candles {
+                    let Some(interval) = OhlcInterval::from_minutes(candle.interval) else {
+                        continue;
+                    };
+
+                    if let Some(sender) = senders.get(&interval) {
+                        if sender.send(candle).await.is_err() {
+                            senders.remove(&interval);
+                        }
+                    }
+                }
+
+                if senders.is_empty() {
+                    break;
+                }
+            }
+        });
+
+        receivers
+    }
+}
+
+impl<R> Unpin for ChannelStream<R> {}
+
+impl<R> Stream for ChannelStream<R> {
+    type Item = Result<R, WSSError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        if let Some(buffered) = self.buffered.pop_front() {
+            return Poll::Ready(Some(Ok(buffered)));
+        }
+
+        loop {
+            match Pin::new(&mut self.stream).poll_next(cx) {
+                Poll::Ready(Some(Ok(message))) => {
+                    if let Some(selected) = (self.select)(message) {
+                        return Poll::Ready(Some(Ok(selected)));
+                    }
+                }
+                Poll::Ready(Some(Err(err))) => return Poll::Ready(Some(Err(err))),
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::secrets::Token;
+    use crate::request_types::{TimeInForce, TimeInForceV2, TriggerType};
+    use crate::response_types::{BuySell, OrderStatusV2, OrderType, SystemStatus};
+    use crate::test_data::{
+        get_balances_subscription_response, get_book_snapshot, get_book_subscription_response,
+        get_book_unsubscription_response, get_execution_subscription_response,
+        get_expected_balances_message, get_expected_balances_subscription,
+        get_expected_book_message, get_expected_book_subscription,
+        get_expected_book_unsubscription, get_expected_book_unsubscription_message,
+        get_expected_execution_message, get_expected_execution_subscription,
+        get_expected_instruments_message, get_expected_instruments_subscription,
+        get_expected_l3_message, get_expected_l3_subscription, get_expected_ohlc_message,
+        get_expected_ohlc_subscription, get_expected_ping, get_expected_pong_message,
+        get_expected_ticker_message, get_expected_ticker_subscription,
+        get_expected_ticker_unsubscription, get_expected_ticker_unsubscription_message,
+        get_expected_trade_message, get_expected_trade_subscription,
+        get_instruments_subscription_response, get_l3_subscription_response,
+        get_ohlc_subscription_response, get_pong, get_ticker_subscription_response,
+        get_ticker_unsubscription_response, get_trade_subscription_response, parse_for_test,
+        CallResponseTest, ParseIncomingTest, RecordingMetrics, WssTestState,
+    };
+    use crate::wss::ChannelMessage::{Heartbeat, Status};
+    use crate::wss::MethodMessage::{AddOrder, AmendOrder, CancelOrder, EditOrder};
+    use crate::wss::{
+        AddOrderParams, AddOrderResult, AmendOrderParams, AmendOrderResult, Asset, AssetStatus,
+        Balance, BalanceResponse, BalancesSubscription, BatchCancelParams, BatchCancelResponse,
+        BatchOrder, BatchOrderParams, BidAsk, BookSubscription, CancelAllOrdersParams,
+        CancelAllOrdersResult, CancelOnDisconnectParams, CancelOnDisconnectResult,
+        CancelOrderParams, CancelOrderResult, ChannelMessage, EditOrderParams, EditOrderResult,
+        ExecutionResult, ExecutionSubscription, ExecutionType, Fee, FeePreference, Instruments,
+        InstrumentsSubscription, L3BidAsk, L3BidAskUpdate, L3Orderbook, L3OrderbookUpdate,
+        LedgerCategory, LedgerEntryTypeV2, LedgerUpdate, MakerTaker, MarketDataResponse,
+        MarketLimit, MethodMessage, Ohlc, OhlcSubscription, Orderbook, OrderbookEvent,
+        OrderbookUpdate, Pair, PairStatus, PriceType, Response, ResultResponse, SingleResponse,
+        StatusUpdate, Ticker, TickerSubscription, Trade, TradesSubscription, TriggerDescription,
+        TriggerStatus, Wallet, WalletId, WalletType, WssMessage, L2, L3,
     };
     use rust_decimal_macros::dec;
     use serde_json::{json, Number};
     use std::str::FromStr;
+    use std::sync::atomic::Ordering;
     use std::time::Duration;
+    use tokio::net::TcpListener;
+    use tokio::sync::mpsc;
     use tokio::time::timeout;
     use tokio_stream::StreamExt;
+    use tokio_tungstenite::accept_async;
     use tokio_tungstenite::tungstenite::Message as TungsteniteMessage;
     use tracing_test::traced_test;
-    use ws_mock::matchers::Any;
+    use ws_mock::matchers::{Any, JsonExact};
     use ws_mock::ws_mock_server::{WsMock, WsMockServer};
 
     #[test]
@@ -232,6 +1274,18 @@ mod tests {
         assert_eq!(WS_KRAKEN_AUTH, client.auth_url);
     }
 
+    #[test]
+    fn test_wss_client_creates_with_endpoints() {
+        let endpoints = Endpoints::new("https://proxy", "wss://proxy/v2", "wss://proxy-auth/v2");
+        let client = KrakenWSSClient::new_with_endpoints(&endpoints);
+        assert_eq!("wss://proxy/v2", client.base_url);
+        assert_eq!("wss://proxy-auth/v2", client.auth_url);
+
+        let client = KrakenWSSClient::new_with_endpoints(&Endpoints::default());
+        assert_eq!(WS_KRAKEN, client.base_url);
+        assert_eq!(WS_KRAKEN_AUTH, client.auth_url);
+    }
+
     #[test]
     fn test_wss_default_creates_client() {
         let client = KrakenWSSClient::default();
@@ -249,79 +1303,1177 @@ mod tests {
         assert_eq!(mock_auth_url, client.auth_url);
     }
 
-    #[traced_test]
+    #[traced_test]
+    #[tokio::test]
+    async fn test_tracing_flags_disabled_by_default() {
+        let mock_server = WsMockServer::start().await;
+        let uri = mock_server.uri().await;
+        let mut client = KrakenWSSClient::new_with_urls(uri.clone(), uri);
+
+        WsMock::new()
+            .matcher(Any::new())
+            .respond_with(TungsteniteMessage::text("response"))
+            .mount(&mock_server)
+            .await;
+
+        let mut stream = client.connect::<String>().await.unwrap();
+
+        stream.send(&Message::new_subscription(0, 0)).await.unwrap();
+
+        let _message = timeout(Duration::from_secs(1), stream.next())
+            .await
+            .unwrap();
+
+        assert!(!logs_contain("Sending message"));
+        assert!(!logs_contain("Received message"));
+    }
+
+    #[traced_test]
+    #[tokio::test]
+    async fn test_tracing_flags_enabled() {
+        let mock_server = WsMockServer::start().await;
+        let uri = mock_server.uri().await;
+        let mut client = KrakenWSSClient::new_with_tracing(&uri, &uri, true, true);
+
+        WsMock::new()
+            .matcher(Any::new())
+            .respond_with(TungsteniteMessage::text("response"))
+            .mount(&mock_server)
+            .await;
+
+        let mut stream = client.connect::<String>().await.unwrap();
+
+        stream.send(&Message::new_subscription(0, 0)).await.unwrap();
+
+        let _message = timeout(Duration::from_secs(1), stream.next())
+            .await
+            .unwrap();
+
+        assert!(logs_contain(
+            r#"Sending message payload={"method":"subscribe","params":0,"req_id":0}"#
+        ));
+        assert!(logs_contain("method=subscribe req_id=0"));
+        assert!(logs_contain("Received message payload=response"));
+    }
+
+    #[tokio::test]
+    async fn test_admin_messages() {
+        let heartbeat = r#"{"channel":"heartbeat"}"#.to_string();
+        let status_update = r#"{"channel":"status","data":[{"api_version":"v2","connection_id":12393906104898154338,"system":"online","version":"2.0.4"}],"type":"update"}"#.to_string();
+
+        let status_message = WssMessage::Channel(Status(SingleResponse {
+            data: StatusUpdate {
+                api_version: "v2".to_string(),
+                connection_id: Some(Number::from_str("12393906104898154338").unwrap()),
+                system: SystemStatus::Online,
+                version: "2.0.4".to_string(),
+            },
+        }));
+
+        ParseIncomingTest::new()
+            .with_incoming(heartbeat)
+            .expect_message(WssMessage::Channel(Heartbeat))
+            .with_incoming(status_update)
+            .expect_message(status_message)
+            .test()
+            .await;
+    }
+
+    #[tokio::test]
+    async fn test_only_executions() {
+        let heartbeat = r#"{"channel":"heartbeat"}"#;
+        let status_update = r#"{"channel":"status","data":[{"api_version":"v2","connection_id":12393906104898154338,"system":"online","version":"2.0.4"}],"type":"update"}"#;
+        let execution = r#"{"channel":"executions","type":"update","data":[{"timestamp":"2024-05-18T11:00:37.240691Z","order_status":"new","exec_type":"new","order_userref":0,"order_id":"OLADEP-E5D5S-IKEHMF"}],"sequence":20}"#;
+        let not_json = r#"CloudFlare WebSocket proxy restarting"#;
+
+        let mut test_state = WssTestState::new().await;
+        let (mpsc_send, mpsc_recv) = mpsc::channel::<TungsteniteMessage>(8);
+
+        WsMock::new()
+            .forward_from_channel(mpsc_recv)
+            .mount(&test_state.mock_server)
+            .await;
+
+        let mut stream = test_state.ws_client.connect::<WssMessage>().await.unwrap();
+
+        for message in [heartbeat, status_update, execution, not_json] {
+            mpsc_send
+                .send(TungsteniteMessage::Text(message.to_string()))
+                .await
+                .unwrap();
+        }
+
+        let mut executions = stream.only_executions();
+
+        let response = timeout(Duration::from_secs(1), executions.next())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(20, response.sequence);
+        assert_eq!(1, response.data.len());
+        assert_eq!("OLADEP-E5D5S-IKEHMF", response.data[0].order_id);
+
+        // errors are passed through rather than filtered
+        let error = timeout(Duration::from_secs(1), executions.next())
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert!(matches!(error, Err(WSSError::Serde(..))));
+    }
+
+    #[tokio::test]
+    async fn test_deserialize_error_metrics() {
+        let test_state = WssTestState::new().await;
+        let (mpsc_send, mpsc_recv) = mpsc::channel::<TungsteniteMessage>(8);
+        let metrics = Arc::new(RecordingMetrics::default());
+
+        WsMock::new()
+            .forward_from_channel(mpsc_recv)
+            .mount(&test_state.mock_server)
+            .await;
+
+        let mut client = test_state.ws_client.clone().with_metrics(metrics.clone());
+        let mut stream = client.connect::<WssMessage>().await.unwrap();
+
+        for message in [r#"{"channel":"heartbeat"}"#, "not json"] {
+            mpsc_send
+                .send(TungsteniteMessage::Text(message.to_string()))
+                .await
+                .unwrap();
+        }
+
+        let heartbeat = timeout(Duration::from_secs(1), stream.next())
+            .await
+            .unwrap();
+        assert!(matches!(
+            heartbeat,
+            Some(Ok(WssMessage::Channel(Heartbeat)))
+        ));
+        assert_eq!(0, metrics.deserialize_errors.load(Ordering::SeqCst));
+
+        let error = timeout(Duration::from_secs(1), stream.next())
+            .await
+            .unwrap();
+        assert!(matches!(error, Some(Err(WSSError::Serde(..)))));
+        assert_eq!(1, metrics.deserialize_errors.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn test_server_status() {
+        let heartbeat = r#"{"channel":"heartbeat"}"#;
+        let status = r#"{"channel":"status","data":[{"api_version":"v2","connection_id":18266300427528990701,"system":"online","version":"2.0.4"}],"type":"update"}"#;
+        let maintenance = r#"{"channel":"status","data":[{"api_version":"v2","system":"maintenance","version":"2.0.6"}],"type":"update"}"#;
+
+        let mut test_state = WssTestState::new().await;
+        let (mpsc_send, mpsc_recv) = mpsc::channel::<TungsteniteMessage>(8);
+
+        WsMock::new()
+            .forward_from_channel(mpsc_recv)
+            .mount(&test_state.mock_server)
+            .await;
+
+        let mut stream = test_state.ws_client.connect::<WssMessage>().await.unwrap();
+        assert!(stream.server_status().is_none());
+
+        for message in [heartbeat, status, maintenance] {
+            mpsc_send
+                .send(TungsteniteMessage::Text(message.to_string()))
+                .await
+                .unwrap();
+
+            timeout(Duration::from_secs(1), stream.next())
+                .await
+                .unwrap()
+                .unwrap()
+                .unwrap();
+        }
+
+        let expected = StatusUpdate {
+            api_version: "v2".to_string(),
+            connection_id: Some(Number::from_str("18266300427528990701").unwrap()),
+            system: SystemStatus::Online,
+            version: "2.0.4".to_string(),
+        };
+
+        assert_eq!(Some(&expected), stream.server_status());
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_ticker() {
+        let heartbeat = r#"{"channel":"heartbeat"}"#;
+        let ticker = r#"{"channel":"ticker","type":"snapshot","data":[{"symbol":"BTC/USD","bid":65972.8,"bid_qty":0.10000000,"ask":65972.9,"ask_qty":39.67506683,"last":65972.9,"volume":4216.61829370,"vwap":64275.2,"low":61325.4,"high":66450.0,"change":4412.1,"change_pct":7.17}]}"#;
+
+        let mut test_state = WssTestState::new().await;
+        let (mpsc_send, mpsc_recv) = mpsc::channel::<TungsteniteMessage>(8);
+
+        WsMock::new()
+            .matcher(JsonExact::new(get_expected_ticker_subscription()))
+            .expect(1)
+            .respond_with(TungsteniteMessage::Text(get_ticker_subscription_response()))
+            .mount(&test_state.mock_server)
+            .await;
+
+        WsMock::new()
+            .forward_from_channel(mpsc_recv)
+            .mount(&test_state.mock_server)
+            .await;
+
+        let params = TickerSubscription::new(vec!["BTC/USD".into()]);
+        let mut tickers = test_state
+            .ws_client
+            .subscribe_ticker(params, 42)
+            .await
+            .unwrap();
+
+        for message in [heartbeat, ticker] {
+            mpsc_send
+                .send(TungsteniteMessage::Text(message.to_string()))
+                .await
+                .unwrap();
+        }
+
+        let response = timeout(Duration::from_secs(1), tickers.next())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+
+        assert_eq!("BTC/USD", response.data.symbol);
+        assert_eq!(dec!(65972.9), response.data.last);
+
+        test_state.mock_server.verify().await;
+    }
+
+    #[tokio::test]
+    async fn test_demux_tickers_by_symbol() {
+        let ticker = |symbol: &str, last: &str| {
+            format!(
+                r#"{{"channel":"ticker","type":"update","data":[{{"symbol":"{symbol}","bid":1.0,"bid_qty":1.0,"ask":1.0,"ask_qty":1.0,"last":{last},"volume":1.0,"vwap":1.0,"low":1.0,"high":1.0,"change":1.0,"change_pct":1.0}}]}}"#
+            )
+        };
+
+        let mut test_state = WssTestState::new().await;
+        let (mpsc_send, mpsc_recv) = mpsc::channel::<TungsteniteMessage>(8);
+
+        WsMock::new()
+            .matcher(JsonExact::new(json!({"method":"subscribe","params":{"channel":"ticker","symbol":["BTC/USD","ETH/USD"]},"req_id":42})))
+            .expect(1)
+            .respond_with(TungsteniteMessage::Text(get_ticker_subscription_response()))
+            .mount(&test_state.mock_server)
+            .await;
+
+        WsMock::new()
+            .forward_from_channel(mpsc_recv)
+            .mount(&test_state.mock_server)
+            .await;
+
+        let symbols = vec!["BTC/USD".to_string(), "ETH/USD".to_string()];
+        let params = TickerSubscription::new(symbols.clone());
+        let tickers = test_state
+            .ws_client
+            .subscribe_ticker(params, 42)
+            .await
+            .unwrap();
+
+        let mut receivers = tickers.demux_by_symbol(&symbols, 4);
+        assert_eq!(2, receivers.len());
+
+        for (symbol, last) in [
+            ("BTC/USD", "100.0"),
+            ("SOL/USD", "3.0"),
+            ("ETH/USD", "20.0"),
+        ] {
+            mpsc_send
+                .send(TungsteniteMessage::Text(ticker(symbol, last)))
+                .await
+                .unwrap();
+        }
+
+        // a message that fails to deserialize is skipped rather than ending the demux
+        mpsc_send
+            .send(TungsteniteMessage::Text(
+                r#"{"channel":"ticker","type":"update","data":"invalid"}"#.into(),
+            ))
+            .await
+            .unwrap();
+        mpsc_send
+            .send(TungsteniteMessage::Text(ticker("BTC/USD", "101.0")))
+            .await
+            .unwrap();
+
+        let btc = receivers.get_mut("BTC/USD").unwrap();
+        for expected in [dec!(100.0), dec!(101.0)] {
+            let ticker = timeout(Duration::from_secs(1), btc.recv())
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!("BTC/USD", ticker.symbol);
+            assert_eq!(expected, ticker.last);
+        }
+
+        let eth = receivers.get_mut("ETH/USD").unwrap();
+        let ticker = timeout(Duration::from_secs(1), eth.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(dec!(20.0), ticker.last);
+        assert!(eth.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_execution_item_stream() {
+        let execution = |order_id: &str| {
+            format!(
+                r#"{{"order_id":"{order_id}","exec_type":"new","order_status":"new","timestamp":"2024-05-18T11:00:37.240691Z"}}"#
+            )
+        };
+        let snapshot = format!(
+            r#"{{"channel":"executions","type":"snapshot","data":[{},{},{}],"sequence":1}}"#,
+            execution("first"),
+            execution("second"),
+            execution("third")
+        );
+
+        let mut test_state = WssTestState::new().await;
+        let (mpsc_send, mpsc_recv) = mpsc::channel::<TungsteniteMessage>(8);
+
+        WsMock::new()
+            .forward_from_channel(mpsc_recv)
+            .mount(&test_state.mock_server)
+            .await;
+
+        let stream = test_state.ws_client.connect::<WssMessage>().await.unwrap();
+        let mut executions = Box::pin(
+            stream
+                .into_channel(|message| match message {
+                    WssMessage::Channel(ChannelMessage::Execution(response)) => Some(response),
+                    _ => None,
+                })
+                .into_item_stream(),
+        );
+
+        mpsc_send
+            .send(TungsteniteMessage::Text(snapshot))
+            .await
+            .unwrap();
+
+        for expected in ["first", "second", "third"] {
+            let execution = timeout(Duration::from_secs(1), executions.next())
+                .await
+                .unwrap()
+                .unwrap()
+                .unwrap();
+            assert_eq!(expected, execution.order_id);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_demux_ohlc_by_interval() {
+        let subscription_response = |interval: i32, req_id: i64| {
+            format!(
+                r#"{{"method":"subscribe","req_id":{req_id},"result":{{"channel":"ohlc","interval":{interval},"snapshot":true,"symbol":"ETH/USD"}},"success":true,"time_in":"2024-05-19T19:06:57.002983Z","time_out":"2024-05-19T19:06:57.003037Z"}}"#
+            )
+        };
+        let candle = |interval: i32, close: &str| {
+            format!(
+                r#"{{"channel":"ohlc","type":"update","data":[{{"symbol":"ETH/USD","open":3000.0,"high":3010.0,"low":2990.0,"close":{close},"trades":5,"volume":1.5,"vwap":3001.0,"interval_begin":"2024-05-19T19:00:00.000000000Z","interval":{interval}}}]}}"#
+            )
+        };
+
+        let mut test_state = WssTestState::new().await;
+        let (mpsc_send, mpsc_recv) = mpsc::channel::<TungsteniteMessage>(8);
+
+        for (interval, req_id) in [(1, 1), (60, 2)] {
+            WsMock::new()
+                .matcher(JsonExact::new(json!({"method":"subscribe","params":{"channel":"ohlc","symbol":["ETH/USD"],"interval":interval},"req_id":req_id})))
+                .expect(1)
+                .respond_with(TungsteniteMessage::Text(subscription_response(
+                    interval, req_id,
+                )))
+                .mount(&test_state.mock_server)
+                .await;
+        }
+
+        WsMock::new()
+            .forward_from_channel(mpsc_recv)
+            .mount(&test_state.mock_server)
+            .await;
+
+        let candles = test_state
+            .ws_client
+            .subscribe_ohlc_intervals(
+                vec!["ETH/USD".to_string()],
+                &[OhlcInterval::Minute, OhlcInterval::Hour],
+            )
+            .await
+            .unwrap();
+
+        let mut receivers =
+            candles.demux_by_interval(&[OhlcInterval::Minute, OhlcInterval::Hour], 4);
+
+        for (interval, close) in [(1, "3001.0"), (60, "3005.0"), (5, "1.0")] {
+            mpsc_send
+                .send(TungsteniteMessage::Text(candle(interval, close)))
+                .await
+                .unwrap();
+        }
+
+        // a message that fails to deserialize is skipped rather than ending the demux
+        mpsc_send
+            .send(TungsteniteMessage::Text(
+                r#"{"channel":"ohlc","type":"update","data":"invalid"}"#.into(),
+            ))
+            .await
+            .unwrap();
+        mpsc_send
+            .send(TungsteniteMessage::Text(candle(1, "3002.0")))
+            .await
+            .unwrap();
+
+        let minutes = receivers.get_mut(&OhlcInterval::Minute).unwrap();
+        for expected in [dec!(3001.0), dec!(3002.0)] {
+            let candle = timeout(Duration::from_secs(1), minutes.recv())
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(1, candle.interval);
+            assert_eq!(expected, candle.close);
+        }
+
+        let hours = receivers.get_mut(&OhlcInterval::Hour).unwrap();
+        let candle = timeout(Duration::from_secs(1), hours.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(dec!(3005.0), candle.close);
+        assert!(hours.try_recv().is_err());
+
+        test_state.mock_server.verify().await;
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_ohlc_intervals_keeps_snapshots_between_acks() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+
+        // acknowledges each interval followed by its snapshot, only after both subscriptions
+        tokio::spawn(async move {
+            let (tcp, _) = listener.accept().await.unwrap();
+            let mut socket = accept_async(tcp).await.unwrap();
+
+            for _ in 0..2 {
+                socket.next().await.unwrap().unwrap();
+            }
+
+            for (interval, req_id) in [(1, 1), (60, 2)] {
+                let ack = format!(
+                    r#"{{"method":"subscribe","req_id":{req_id},"result":{{"channel":"ohlc","interval":{interval},"snapshot":true,"symbol":"ETH/USD"}},"success":true,"time_in":"2024-05-19T19:06:57.002983Z","time_out":"2024-05-19T19:06:57.003037Z"}}"#
+                );
+                let snapshot = format!(
+                    r#"{{"channel":"ohlc","type":"snapshot","data":[{{"symbol":"ETH/USD","open":3000.0,"high":3010.0,"low":2990.0,"close":3001.0,"trades":5,"volume":1.5,"vwap":3001.0,"interval_begin":"2024-05-19T19:00:00.000000000Z","interval":{interval}}}]}}"#
+                );
+                socket.send(TungsteniteMessage::Text(ack)).await.unwrap();
+                socket
+                    .send(TungsteniteMessage::Text(snapshot))
+                    .await
+                    .unwrap();
+            }
+
+            sleep(Duration::from_secs(5)).await;
+        });
+
+        let mut client = KrakenWSSClient::new_with_urls(&url, &url);
+        let mut candles = timeout(
+            Duration::from_secs(1),
+            client.subscribe_ohlc_intervals(
+                vec!["ETH/USD".to_string()],
+                &[OhlcInterval::Minute, OhlcInterval::Hour],
+            ),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+
+        for expected in [1, 60] {
+            let response = timeout(Duration::from_secs(1), candles.next())
+                .await
+                .unwrap()
+                .unwrap()
+                .unwrap();
+            assert_eq!(expected, response.data[0].interval);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_throttled_books() {
+        let snapshot = r#"{"channel":"book","type":"snapshot","data":[{"symbol":"BTC/USD","bids":[{"price":100.0,"qty":1.0}],"asks":[{"price":101.0,"qty":1.0}],"checksum":1}]}"#;
+        let update = |price: &str, checksum: u32| {
+            format!(
+                r#"{{"channel":"book","type":"update","data":[{{"symbol":"BTC/USD","bids":[{{"price":{price},"qty":2.0}}],"asks":[],"checksum":{checksum},"timestamp":"2024-05-19T16:32:14.446587Z"}}]}}"#
+            )
+        };
+
+        let mut test_state = WssTestState::new().await;
+        let (mpsc_send, mpsc_recv) = mpsc::channel::<TungsteniteMessage>(8);
+
+        WsMock::new()
+            .matcher(JsonExact::new(get_expected_book_subscription()))
+            .expect(1)
+            .respond_with(TungsteniteMessage::Text(get_book_subscription_response()))
+            .mount(&test_state.mock_server)
+            .await;
+
+        WsMock::new()
+            .forward_from_channel(mpsc_recv)
+            .mount(&test_state.mock_server)
+            .await;
+
+        let mut params = BookSubscription::new(vec!["BTC/USD".into()]);
+        params.depth = Some(10);
+        params.snapshot = Some(true);
+
+        let mut books = test_state
+            .ws_client
+            .subscribe_book(params, 11)
+            .await
+            .unwrap()
+            .throttle(10, Duration::from_millis(200));
+
+        let messages = [snapshot.to_string(), update("99.0", 2), update("100.5", 3)];
+        for message in messages {
+            mpsc_send
+                .send(TungsteniteMessage::Text(message))
+                .await
+                .unwrap();
+        }
+
+        let book = timeout(Duration::from_secs(1), books.next())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+
+        assert_eq!("BTC/USD", book.symbol());
+        assert_eq!(3, book.checksum());
+        assert_eq!(3, book.bids().len());
+        assert_eq!(dec!(100.5), book.best_bid().unwrap().price);
+
+        // an update that doesn't change any level doesn't emit the book again
+        let unchanged = r#"{"channel":"book","type":"update","data":[{"symbol":"BTC/USD","bids":[{"price":50.0,"qty":0.0}],"asks":[],"checksum":4,"timestamp":"2024-05-19T16:32:14.446587Z"}]}"#;
+        mpsc_send
+            .send(TungsteniteMessage::Text(unchanged.to_string()))
+            .await
+            .unwrap();
+
+        assert!(timeout(Duration::from_millis(500), books.next())
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_rejected() {
+        let rejected = r#"{"error":"Currency pair not supported","method":"subscribe","req_id":42,"success":false,"time_in":"2024-05-15T11:20:43.013486Z","time_out":"2024-05-15T11:20:43.013545Z"}"#;
+
+        let mut test_state = WssTestState::new().await;
+
+        WsMock::new()
+            .matcher(JsonExact::new(get_expected_ticker_subscription()))
+            .expect(1)
+            .respond_with(TungsteniteMessage::Text(rejected.to_string()))
+            .mount(&test_state.mock_server)
+            .await;
+
+        let params = TickerSubscription::new(vec!["BTC/USD".into()]);
+        let result = timeout(
+            Duration::from_secs(1),
+            test_state.ws_client.subscribe_ticker(params, 42),
+        )
+        .await
+        .unwrap();
+
+        match result {
+            Err(WSSError::Rejected(rejected)) => {
+                assert_eq!(42, rejected.req_id);
+                assert_eq!("Currency pair not supported", rejected.error);
+            }
+            Err(err) => panic!("expected a rejected subscription, got {err:?}"),
+            Ok(_) => panic!("expected a rejected subscription"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_message_too_large() {
+        let mock_server = WsMockServer::start().await;
+        let uri = mock_server.uri().await;
+        let config = WebSocketConfig {
+            max_message_size: Some(64),
+            max_frame_size: Some(64),
+            ..Default::default()
+        };
+        let mut client = KrakenWSSClient::new_with_urls(&uri, &uri).with_websocket_config(config);
+
+        let (mpsc_send, mpsc_recv) = mpsc::channel::<TungsteniteMessage>(8);
+
+        WsMock::new()
+            .forward_from_channel(mpsc_recv)
+            .mount(&mock_server)
+            .await;
+
+        let mut stream = client.connect::<WssMessage>().await.unwrap();
+
+        mpsc_send
+            .send(TungsteniteMessage::Text(
+                r#"{"channel":"heartbeat"}"#.to_string(),
+            ))
+            .await
+            .unwrap();
+        mpsc_send
+            .send(TungsteniteMessage::Text(format!(
+                r#"{{"channel":"heartbeat","padding":"{}"}}"#,
+                "x".repeat(100)
+            )))
+            .await
+            .unwrap();
+
+        let heartbeat = timeout(Duration::from_secs(1), stream.next())
+            .await
+            .unwrap();
+        assert!(matches!(heartbeat, Some(Ok(_))));
+
+        let too_large = timeout(Duration::from_secs(1), stream.next())
+            .await
+            .unwrap();
+        assert!(matches!(
+            too_large,
+            Some(Err(WSSError::MessageTooLarge { max_size: 64, .. }))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_private_messages_require_auth_channel() {
+        let mut test_state = WssTestState::new().await;
+        let token = || Token::new("someToken".to_string());
+
+        let mut public = test_state.ws_client.connect::<WssMessage>().await.unwrap();
+        assert!(!public.is_authenticated());
+
+        let subscription = Message::new_subscription(ExecutionSubscription::new(token()), 1);
+        let result = public.send(&subscription).await;
+        assert!(
+            matches!(result, Err(WSSError::RequiresAuthChannel(channel)) if channel == "executions")
+        );
+
+        let cancel_all = Message {
+            method: "cancel_all".to_string(),
+            params: CancelAllOrdersParams { token: token() },
+            req_id: 2,
+        };
+        let result = public.send(&cancel_all).await;
+        assert!(
+            matches!(result, Err(WSSError::RequiresAuthChannel(method)) if method == "cancel_all")
+        );
+
+        let ticker = Message::new_subscription(TickerSubscription::new(vec!["BTC/USD".into()]), 3);
+        assert!(public.send(&ticker).await.is_ok());
+
+        let mut test_state = WssTestState::new().await;
+        let mut private = test_state
+            .ws_client
+            .connect_auth::<WssMessage>()
+            .await
+            .unwrap();
+        assert!(private.is_authenticated());
+        assert!(private.send(&subscription).await.is_ok());
+        assert!(private.send(&cancel_all).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_active_subscriptions() {
+        let mut test_state = WssTestState::new().await;
+        let (sender, receiver) = mpsc::channel::<TungsteniteMessage>(16);
+
+        WsMock::new()
+            .forward_from_channel(receiver)
+            .mount(&test_state.mock_server)
+            .await;
+
+        let mut stream = test_state.ws_client.connect::<WssMessage>().await.unwrap();
+        assert!(stream.active_subscriptions().is_empty());
+
+        let ack = |method: &str, result: &str, success: bool| {
+            format!(
+                r#"{{"method":"{method}","req_id":1,"result":{result},"success":{success},"time_in":"2024-05-19T16:27:13.694962Z","time_out":"2024-05-19T16:27:13.695006Z"}}"#
+            )
+        };
+        let acks = [
+            ack(
+                "subscribe",
+                r#"{"channel":"ticker","symbol":"BTC/USD"}"#,
+                true,
+            ),
+            ack(
+                "subscribe",
+                r#"{"channel":"ticker","symbol":"ETH/USD"}"#,
+                true,
+            ),
+            ack(
+                "subscribe",
+                r#"{"channel":"ohlc","interval":1,"symbol":"BTC/USD"}"#,
+                true,
+            ),
+            ack(
+                "subscribe",
+                r#"{"channel":"ohlc","interval":5,"symbol":"BTC/USD"}"#,
+                true,
+            ),
+            ack(
+                "subscribe",
+                r#"{"channel":"trade","symbol":"BTC/USD"}"#,
+                false,
+            ),
+            ack(
+                "unsubscribe",
+                r#"{"channel":"ohlc","interval":1,"symbol":"BTC/USD"}"#,
+                true,
+            ),
+            ack(
+                "unsubscribe",
+                r#"{"channel":"ticker","symbol":"ETH/USD"}"#,
+                true,
+            ),
+        ];
+
+        for ack in acks.iter() {
+            sender
+                .send(TungsteniteMessage::Text(ack.clone()))
+                .await
+                .unwrap();
+            timeout(Duration::from_secs(1), stream.next())
+                .await
+                .unwrap()
+                .unwrap()
+                .unwrap();
+        }
+
+        let summary = |channel: &str| SubscriptionSummary {
+            channel: channel.to_string(),
+            symbols: vec!["BTC/USD".to_string()],
+            authenticated: false,
+        };
+
+        assert_eq!(
+            vec![summary("ohlc"), summary("ticker")],
+            stream.active_subscriptions()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_subscription_limit() {
+        let mut test_state = WssTestState::new().await;
+        let (sender, receiver) = mpsc::channel::<TungsteniteMessage>(8);
+
+        WsMock::new()
+            .forward_from_channel(receiver)
+            .mount(&test_state.mock_server)
+            .await;
+
+        let mut stream = test_state.ws_client.connect::<WssMessage>().await.unwrap();
+        assert_eq!(None, stream.subscription_limit());
+        stream.set_subscription_limit(Some(2));
+
+        let ticker = |symbols: &[&str]| {
+            Message::new_subscription(
+                TickerSubscription::new(symbols.iter().map(|s| s.to_string()).collect()),
+                1,
+            )
+        };
+
+        let result = stream
+            .send(&ticker(&["BTC/USD", "ETH/USD", "SOL/USD"]))
+            .await;
+        assert!(matches!(
+            result,
+            Err(WSSError::SubscriptionLimitReached { limit: 2 })
+        ));
+
+        sender
+            .send(TungsteniteMessage::Text(
+                r#"{"method":"subscribe","req_id":1,"result":{"channel":"ticker","symbol":"BTC/USD"},"success":true,"time_in":"2024-05-19T16:27:13.694962Z","time_out":"2024-05-19T16:27:13.695006Z"}"#.to_string(),
+            ))
+            .await
+            .unwrap();
+        timeout(Duration::from_secs(1), stream.next())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+
+        assert!(stream.send(&ticker(&["BTC/USD", "ETH/USD"])).await.is_ok());
+        assert!(stream.send(&ticker(&["ETH/USD", "SOL/USD"])).await.is_err());
+
+        let unsubscription =
+            Message::new_unsubscription(TickerSubscription::new(vec!["SOL/USD".into()]), 2);
+        assert!(stream.send(&unsubscription).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_control_frames_are_skipped() {
+        let mut test_state = WssTestState::new().await;
+        let (sender, receiver) = mpsc::channel::<TungsteniteMessage>(8);
+
+        WsMock::new()
+            .forward_from_channel(receiver)
+            .mount(&test_state.mock_server)
+            .await;
+
+        let mut stream = test_state.ws_client.connect::<WssMessage>().await.unwrap();
+
+        for message in [
+            TungsteniteMessage::Ping(vec![1, 2, 3]),
+            TungsteniteMessage::Pong(b"not json".to_vec()),
+            TungsteniteMessage::Text(r#"{"channel":"heartbeat"}"#.to_string()),
+        ] {
+            sender.send(message).await.unwrap();
+        }
+
+        let message = timeout(Duration::from_secs(1), stream.next())
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert!(matches!(
+            message,
+            Ok(WssMessage::Channel(ChannelMessage::Heartbeat))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_normal_close_ends_stream() {
+        let mut test_state = WssTestState::new().await;
+        let (sender, receiver) = mpsc::channel::<TungsteniteMessage>(8);
+
+        WsMock::new()
+            .forward_from_channel(receiver)
+            .mount(&test_state.mock_server)
+            .await;
+
+        let mut stream = test_state.ws_client.connect::<WssMessage>().await.unwrap();
+
+        let frame = CloseFrame {
+            code: CloseCode::Normal,
+            reason: "".into(),
+        };
+        sender
+            .send(TungsteniteMessage::Close(Some(frame)))
+            .await
+            .unwrap();
+
+        let message = timeout(Duration::from_secs(1), stream.next())
+            .await
+            .unwrap();
+        assert!(message.is_none());
+        assert!(stream.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_dropped_connection_ends_stream() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+
+        // sends two heartbeats, then drops the connection without a close frame
+        tokio::spawn(async move {
+            let (tcp, _) = listener.accept().await.unwrap();
+            let mut socket = accept_async(tcp).await.unwrap();
+            for _ in 0..2 {
+                let heartbeat = TungsteniteMessage::Text(r#"{"channel":"heartbeat"}"#.into());
+                socket.send(heartbeat).await.unwrap();
+            }
+        });
+
+        let mut client = KrakenWSSClient::new_with_urls(&url, &url);
+        let stream = client.connect::<WssMessage>().await.unwrap();
+
+        let messages: Vec<Result<WssMessage, WSSError>> =
+            timeout(Duration::from_secs(1), stream.collect())
+                .await
+                .unwrap();
+
+        assert!(messages.len() >= 2);
+        assert!(matches!(messages[0], Ok(WssMessage::Channel(Heartbeat))));
+        assert!(matches!(messages[1], Ok(WssMessage::Channel(Heartbeat))));
+        assert!(messages[2..].iter().all(|message| message.is_err()));
+    }
+
+    #[tokio::test]
+    async fn test_abnormal_close_yields_closed_error() {
+        let mut test_state = WssTestState::new().await;
+        let (sender, receiver) = mpsc::channel::<TungsteniteMessage>(8);
+
+        WsMock::new()
+            .forward_from_channel(receiver)
+            .mount(&test_state.mock_server)
+            .await;
+
+        let mut stream = test_state.ws_client.connect::<WssMessage>().await.unwrap();
+
+        let frame = CloseFrame {
+            code: CloseCode::Again,
+            reason: "maintenance".into(),
+        };
+        sender
+            .send(TungsteniteMessage::Close(Some(frame)))
+            .await
+            .unwrap();
+
+        let message = timeout(Duration::from_secs(1), stream.next())
+            .await
+            .unwrap();
+        assert!(matches!(
+            message,
+            Some(Err(WSSError::Closed { code: 1013, ref reason })) if reason == "maintenance"
+        ));
+        assert!(stream.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_binary_frames_are_decoded() {
+        let mut test_state = WssTestState::new().await;
+        let (sender, receiver) = mpsc::channel::<TungsteniteMessage>(8);
+
+        WsMock::new()
+            .forward_from_channel(receiver)
+            .mount(&test_state.mock_server)
+            .await;
+
+        let mut stream = test_state.ws_client.connect::<WssMessage>().await.unwrap();
+
+        for message in [
+            TungsteniteMessage::Binary(br#"{"channel":"heartbeat"}"#.to_vec()),
+            TungsteniteMessage::Binary(vec![0xff, 0xfe]),
+            TungsteniteMessage::Text(r#"{"channel":"heartbeat"}"#.to_string()),
+        ] {
+            sender.send(message).await.unwrap();
+        }
+
+        let mut messages = Vec::new();
+        for _ in 0..3 {
+            let message = timeout(Duration::from_secs(1), stream.next())
+                .await
+                .unwrap()
+                .unwrap();
+            messages.push(message);
+        }
+
+        assert!(matches!(
+            messages[0],
+            Ok(WssMessage::Channel(ChannelMessage::Heartbeat))
+        ));
+        assert!(matches!(
+            messages[1],
+            Err(WSSError::WSS(TungsteniteError::Utf8))
+        ));
+        assert!(matches!(
+            messages[2],
+            Ok(WssMessage::Channel(ChannelMessage::Heartbeat))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_close() {
+        let test_state = WssTestState::new().await;
+        let mut client = test_state.ws_client.clone();
+        let stream = client.connect::<WssMessage>().await.unwrap();
+
+        timeout(Duration::from_secs(1), stream.close())
+            .await
+            .unwrap()
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_send_with_next_id() {
+        let mut test_state = WssTestState::new().await;
+
+        for req_id in [7, 8] {
+            WsMock::new()
+                .matcher(JsonExact::new(json!({"method":"ping","req_id":req_id})))
+                .expect(1)
+                .mount(&test_state.mock_server)
+                .await;
+        }
+
+        let mut stream = test_state.ws_client.connect::<WssMessage>().await.unwrap();
+        stream.set_message_id_generator(MessageIdGenerator::starting_at(7));
+
+        let mut ping = Message {
+            method: "ping".to_string(),
+            params: None::<()>,
+            req_id: 0,
+        };
+
+        assert_eq!(7, stream.send_with_next_id(&mut ping).await.unwrap());
+        assert_eq!(8, stream.send_with_next_id(&mut ping).await.unwrap());
+        assert_eq!(9, stream.next_req_id());
+
+        sleep(Duration::from_millis(100)).await;
+        test_state.mock_server.verify().await;
+    }
+
+    #[tokio::test]
+    async fn test_dry_run() {
+        let expected_request = json!({"method":"add_order","params":{"order_type":"limit","side":"buy","symbol":"USDC/USD","limit_price":0.95,"order_qty":5.0,"token":"aToken","validate":true},"req_id":3});
+        let response = r#"{"method":"add_order","req_id":3,"result":{"order_id":"OPS23M-VS41G-DDE5Z2"},"success":true,"time_in":"2024-05-18T12:05:50.293682Z","time_out":"2024-05-18T12:05:50.300542Z"}"#;
+
+        let mut test_state = WssTestState::new().await;
+        let (mpsc_send, mpsc_recv) = mpsc::channel::<TungsteniteMessage>(8);
+
+        WsMock::new()
+            .matcher(JsonExact::new(expected_request))
+            .expect(1)
+            .respond_with(TungsteniteMessage::Text(response.to_string()))
+            .mount(&test_state.mock_server)
+            .await;
+
+        WsMock::new()
+            .forward_from_channel(mpsc_recv)
+            .mount(&test_state.mock_server)
+            .await;
+
+        let mut stream = test_state
+            .ws_client
+            .connect_auth::<WssMessage>()
+            .await
+            .unwrap();
+
+        // received before the response, so returned alongside it
+        mpsc_send
+            .send(TungsteniteMessage::Text(
+                r#"{"channel":"heartbeat"}"#.into(),
+            ))
+            .await
+            .unwrap();
+        sleep(Duration::from_millis(50)).await;
+
+        let params = AddOrderParams::limit(
+            BuySell::Buy,
+            "USDC/USD",
+            dec!(5.0),
+            dec!(0.95),
+            Token::new("aToken".to_string()),
+        );
+
+        let other_messages = timeout(Duration::from_secs(1), stream.dry_run(params, 3))
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(vec![WssMessage::Channel(Heartbeat)], other_messages);
+        test_state.mock_server.verify().await;
+    }
+
     #[tokio::test]
-    async fn test_tracing_flags_disabled_by_default() {
-        let mock_server = WsMockServer::start().await;
-        let uri = mock_server.uri().await;
-        let mut client = KrakenWSSClient::new_with_urls(uri.clone(), uri);
+    async fn test_dry_run_rejected() {
+        let response = r#"{"error":"EOrder:Insufficient funds","method":"add_order","req_id":3,"success":false,"time_in":"2024-05-18T12:05:50.293682Z","time_out":"2024-05-18T12:05:50.300542Z"}"#;
+
+        let mut test_state = WssTestState::new().await;
 
         WsMock::new()
             .matcher(Any::new())
-            .respond_with(TungsteniteMessage::text("response"))
-            .mount(&mock_server)
+            .expect(1)
+            .respond_with(TungsteniteMessage::Text(response.to_string()))
+            .mount(&test_state.mock_server)
             .await;
 
-        let mut stream = client.connect::<String>().await.unwrap();
-
-        stream.send(&Message::new_subscription(0, 0)).await.unwrap();
-
-        let _message = timeout(Duration::from_secs(1), stream.next())
+        let mut stream = test_state
+            .ws_client
+            .connect_auth::<WssMessage>()
+            .await
+            .unwrap();
+        let params = AddOrderParams::market(
+            BuySell::Buy,
+            "USDC/USD",
+            dec!(5.0),
+            Token::new("aToken".to_string()),
+        );
+
+        let result = timeout(Duration::from_secs(1), stream.dry_run(params, 3))
             .await
             .unwrap();
 
-        assert!(!logs_contain("Sending:"));
-        assert!(!logs_contain("Received: response"));
+        match result {
+            Err(WSSError::Rejected(rejected)) => {
+                assert_eq!("EOrder:Insufficient funds", rejected.error)
+            }
+            other => panic!("expected a rejected order, got {other:?}"),
+        }
     }
 
-    #[traced_test]
     #[tokio::test]
-    async fn test_tracing_flags_enabled() {
-        let mock_server = WsMockServer::start().await;
-        let uri = mock_server.uri().await;
-        let mut client = KrakenWSSClient::new_with_tracing(&uri, &uri, true, true);
+    async fn test_stall_timeout() {
+        let heartbeat = r#"{"channel":"heartbeat"}"#;
+
+        let mut test_state = WssTestState::new().await;
+        let (mpsc_send, mpsc_recv) = mpsc::channel::<TungsteniteMessage>(8);
 
         WsMock::new()
-            .matcher(Any::new())
-            .respond_with(TungsteniteMessage::text("response"))
-            .mount(&mock_server)
+            .forward_from_channel(mpsc_recv)
+            .mount(&test_state.mock_server)
             .await;
 
-        let mut stream = client.connect::<String>().await.unwrap();
+        let mut stream = test_state.ws_client.connect::<WssMessage>().await.unwrap();
+        stream.set_stall_timeout(Some(Duration::from_millis(200)));
 
-        stream.send(&Message::new_subscription(0, 0)).await.unwrap();
+        mpsc_send
+            .send(TungsteniteMessage::Text(heartbeat.to_string()))
+            .await
+            .unwrap();
 
-        let _message = timeout(Duration::from_secs(1), stream.next())
+        let message = timeout(Duration::from_secs(1), stream.next())
             .await
             .unwrap();
+        assert_eq!(WssMessage::Channel(Heartbeat), message.unwrap().unwrap());
 
-        assert!(logs_contain(
-            r#"Sending: {"method":"subscribe","params":0,"req_id":0}"#
-        ));
-        assert!(logs_contain("Received: response"));
+        // nothing else is sent, so the stream reports a stall, repeatedly
+        for _ in 0..2 {
+            let start = Instant::now();
+            let stalled = timeout(Duration::from_secs(1), stream.next())
+                .await
+                .unwrap();
+
+            assert!(matches!(stalled, Some(Err(WSSError::Stalled(..)))));
+            assert!(Instant::now() - start >= Duration::from_millis(150));
+        }
+
+        // messages resume as usual after a stall
+        mpsc_send
+            .send(TungsteniteMessage::Text(heartbeat.to_string()))
+            .await
+            .unwrap();
+
+        let message = timeout(Duration::from_secs(1), stream.next())
+            .await
+            .unwrap();
+        assert_eq!(WssMessage::Channel(Heartbeat), message.unwrap().unwrap());
     }
 
     #[tokio::test]
-    async fn test_admin_messages() {
-        let heartbeat = r#"{"channel":"heartbeat"}"#.to_string();
-        let status_update = r#"{"channel":"status","data":[{"api_version":"v2","connection_id":12393906104898154338,"system":"online","version":"2.0.4"}],"type":"update"}"#.to_string();
-
-        let status_message = WssMessage::Channel(Status(SingleResponse {
-            data: StatusUpdate {
-                api_version: "v2".to_string(),
-                connection_id: Some(Number::from_str("12393906104898154338").unwrap()),
-                system: SystemStatus::Online,
-                version: "2.0.4".to_string(),
-            },
-        }));
+    async fn test_no_stall_timeout_by_default() {
+        let mut test_state = WssTestState::new().await;
+        let (_mpsc_send, mpsc_recv) = mpsc::channel::<TungsteniteMessage>(8);
 
-        ParseIncomingTest::new()
-            .with_incoming(heartbeat)
-            .expect_message(WssMessage::Channel(Heartbeat))
-            .with_incoming(status_update)
-            .expect_message(status_message)
-            .test()
+        WsMock::new()
+            .forward_from_channel(mpsc_recv)
+            .mount(&test_state.mock_server)
             .await;
+
+        let mut stream = test_state.ws_client.connect::<WssMessage>().await.unwrap();
+
+        let result = timeout(Duration::from_millis(300), stream.next()).await;
+
+        assert!(result.is_err());
     }
 
     #[tokio::test]
@@ -537,29 +2689,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_book_snapshot() {
-        let book_snapshot = r#"{
-        "channel":"book",
-        "type":"snapshot",
-        "data":[{
-            "symbol":"BTC/USD",
-            "bids":[
-                {"price":66788.0,"qty":3.21926649},
-                {"price":66787.5,"qty":0.44916298},
-                {"price":66787.4,"qty":0.05992580},
-                {"price":66785.3,"qty":0.01496904},
-                {"price":66785.2,"qty":0.86989511}
-            ],
-            "asks":[
-                {"price":66788.1,"qty":1.67939137},
-                {"price":66788.4,"qty":1.49726637},
-                {"price":66790.0,"qty":1.49723133},
-                {"price":66791.1,"qty":0.01100000},
-                {"price":66792.6,"qty":1.49717197}
-            ],
-            "checksum":2330500275
-        }]
-    }"#
-        .to_string();
+        let book_snapshot = get_book_snapshot();
 
         let expected_snapshot = WssMessage::Channel(ChannelMessage::Orderbook(SingleResponse {
             data: L2::Orderbook(Orderbook {
@@ -958,8 +3088,8 @@ mod tests {
         }
     }"#.to_string();
 
-        let expected_snapshot =
-            WssMessage::Channel(ChannelMessage::Instrument(MarketDataResponse {
+        let expected_snapshot = WssMessage::Channel(ChannelMessage::Instrument(
+            InstrumentMessage::Snapshot(MarketDataResponse {
                 data: Instruments {
                     assets: vec![
                         Asset {
@@ -1029,7 +3159,8 @@ mod tests {
                         },
                     ],
                 },
-            }));
+            }),
+        ));
 
         ParseIncomingTest::new()
             .with_incoming(instrument_snapshot)
@@ -1038,6 +3169,51 @@ mod tests {
             .await;
     }
 
+    #[tokio::test]
+    async fn test_instruments_update() {
+        let instrument_update = r#"{
+        "channel":"instrument",
+        "type":"update",
+        "data":{
+            "pairs": [
+                {"symbol":"EUR/USD","base":"EUR","quote":"USD","status":"cancel_only","qty_precision":8,"qty_increment":0.00000001,"price_precision":5,"cost_precision":5,"marginable":false,"has_index":true,"cost_min":0.50,"tick_size":0.00001,"price_increment":0.00001,"qty_min":0.50000000}
+            ]
+        }
+    }"#.to_string();
+
+        let expected_update = WssMessage::Channel(ChannelMessage::Instrument(
+            InstrumentMessage::Update(MarketDataResponse {
+                data: Instruments {
+                    assets: vec![],
+                    pairs: vec![Pair {
+                        base: "EUR".to_string(),
+                        quote: "USD".to_string(),
+                        cost_min: dec!(0.50),
+                        cost_precision: 5,
+                        has_index: true,
+                        margin_initial: None,
+                        marginable: false,
+                        position_limit_long: None,
+                        position_limit_short: None,
+                        price_increment: dec!(0.00001),
+                        price_precision: 5,
+                        quantity_increment: dec!(0.00000001),
+                        quantity_min: dec!(0.50),
+                        quantity_precision: 8,
+                        status: PairStatus::CancelOnly,
+                        symbol: "EUR/USD".to_string(),
+                    }],
+                },
+            }),
+        ));
+
+        ParseIncomingTest::new()
+            .with_incoming(instrument_update)
+            .expect_message(expected_update)
+            .test()
+            .await;
+    }
+
     #[tokio::test]
     async fn test_execution_subscription() {
         let mut execution_params = ExecutionSubscription::new(Token::new("someToken".to_string()));
@@ -1089,6 +3265,21 @@ mod tests {
             .await;
     }
 
+    #[tokio::test]
+    async fn test_ticker_unsubscription() {
+        let mut ticker_params = TickerSubscription::new(vec!["BTC/USD".into()]);
+        ticker_params.snapshot = Some(true);
+
+        CallResponseTest::builder()
+            .match_on(get_expected_ticker_unsubscription())
+            .respond_with(get_ticker_unsubscription_response())
+            .send(ticker_params.unsubscription(43))
+            .expect(get_expected_ticker_unsubscription_message())
+            .build()
+            .test()
+            .await;
+    }
+
     #[tokio::test]
     async fn test_book_subscription() {
         let mut book_params = BookSubscription::new(vec!["BTC/USD".into()]);
@@ -1107,6 +3298,33 @@ mod tests {
             .await;
     }
 
+    #[tokio::test]
+    async fn test_subscribe_book_invalid_depth() {
+        let mut params = BookSubscription::top_of_book(vec!["BTC/USD".into()]);
+        params.depth = Some(1);
+
+        let mut client = KrakenWSSClient::new_with_urls("ws://127.0.0.1:1", "ws://127.0.0.1:1");
+        let result = client.subscribe_book(params, 1).await;
+
+        assert!(matches!(result, Err(WSSError::InvalidBookDepth(1))));
+    }
+
+    #[tokio::test]
+    async fn test_book_unsubscription() {
+        let mut book_params = BookSubscription::new(vec!["BTC/USD".into()]);
+        book_params.depth = Some(10);
+        book_params.snapshot = Some(true);
+
+        CallResponseTest::builder()
+            .match_on(get_expected_book_unsubscription())
+            .respond_with(get_book_unsubscription_response())
+            .send(book_params.unsubscription(12))
+            .expect(get_expected_book_unsubscription_message())
+            .build()
+            .test()
+            .await;
+    }
+
     #[tokio::test]
     async fn test_l3_subscription() {
         let mut book_params =
@@ -1127,7 +3345,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_ohlc_subscription() {
-        let ohlc_params = OhlcSubscription::new(vec!["ETH/USD".into()], 60);
+        let ohlc_params = OhlcSubscription::new(vec!["ETH/USD".into()], OhlcInterval::Hour);
 
         let subscription = Message::new_subscription(ohlc_params, 121);
 
@@ -1303,6 +3521,149 @@ mod tests {
             .await;
     }
 
+    #[tokio::test]
+    async fn test_amend_orders() {
+        let params = |order_id: &str, quantity| AmendOrderParams {
+            order_id: Some(order_id.to_string()),
+            client_order_id: None,
+            order_quantity: quantity,
+            display_quantity: None,
+            limit_price: None,
+            limit_price_type: None,
+            post_only: None,
+            trigger_price: None,
+            trigger_price_type: None,
+            deadline: None,
+            token: Token::new("aToken".to_string()),
+        };
+
+        let first_request = json!({"method":"amend_order","params":{"order_id":"BQS60L-EGW18-UPAK9U","order_qty":5.1,"token":"aToken"},"req_id":7});
+        let second_request = json!({"method":"amend_order","params":{"order_id":"OFGKYQ-FHPCQ-HUQFEK","order_qty":2.0,"token":"aToken"},"req_id":8});
+        let first_response = r#"{"method":"amend_order","req_id":7,"result":{"amend_id":"1M2JV8-OEJZD-G5GSBF","order_id":"BQS60L-EGW18-UPAK9U"},"success":true,"time_in":"2024-10-11T12:12:21.003873Z","time_out":"2024-10-11T12:12:21.005064Z"}"#;
+        let second_response = r#"{"error":"Order not found","method":"amend_order","req_id":8,"success":false,"time_in":"2024-10-11T12:12:21.006128Z","time_out":"2024-10-11T12:12:21.006201Z"}"#;
+
+        let mock_server = WsMockServer::start().await;
+
+        WsMock::new()
+            .matcher(JsonExact::new(first_request))
+            .expect(1)
+            .respond_with(TungsteniteMessage::Text(first_response.to_string()))
+            .mount(&mock_server)
+            .await;
+
+        WsMock::new()
+            .matcher(JsonExact::new(second_request))
+            .expect(1)
+            .respond_with(TungsteniteMessage::Text(second_response.to_string()))
+            .mount(&mock_server)
+            .await;
+
+        let uri = mock_server.uri().await;
+        let mut client = KrakenWSSClient::new_with_urls(uri.clone(), uri);
+        let mut stream = client.connect_auth::<WssMessage>().await.unwrap();
+
+        let amends = vec![
+            params("BQS60L-EGW18-UPAK9U", dec!(5.1)),
+            params("OFGKYQ-FHPCQ-HUQFEK", dec!(2.0)),
+        ];
+
+        let result = timeout(Duration::from_secs(3), stream.amend_orders(amends, 7))
+            .await
+            .unwrap()
+            .unwrap();
+
+        mock_server.verify().await;
+
+        let expected = vec![
+            Some(ResultResponse {
+                result: Some(AmendOrderResult {
+                    amend_id: "1M2JV8-OEJZD-G5GSBF".to_string(),
+                    order_id: Some("BQS60L-EGW18-UPAK9U".to_string()),
+                    client_order_id: None,
+                    warnings: None,
+                }),
+                error: None,
+                success: true,
+                req_id: 7,
+                time_in: "2024-10-11T12:12:21.003873Z".to_string(),
+                time_out: "2024-10-11T12:12:21.005064Z".to_string(),
+            }),
+            Some(ResultResponse {
+                result: None,
+                error: Some("Order not found".to_string()),
+                success: false,
+                req_id: 8,
+                time_in: "2024-10-11T12:12:21.006128Z".to_string(),
+                time_out: "2024-10-11T12:12:21.006201Z".to_string(),
+            }),
+        ];
+
+        assert_eq!(expected, result.responses);
+        assert!(result.other_messages.is_empty());
+        assert!(result.errors.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_amend_orders_keeps_responses_when_stream_ends_early() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+
+        // answers only the first amend after an unparseable message, then drops the connection
+        tokio::spawn(async move {
+            let (tcp, _) = listener.accept().await.unwrap();
+            let mut socket = accept_async(tcp).await.unwrap();
+            for _ in 0..2 {
+                socket.next().await.unwrap().unwrap();
+            }
+
+            let response = r#"{"method":"amend_order","req_id":1,"result":{"amend_id":"1M2JV8-OEJZD-G5GSBF","order_id":"BQS60L-EGW18-UPAK9U"},"success":true,"time_in":"2024-10-11T12:12:21.003873Z","time_out":"2024-10-11T12:12:21.005064Z"}"#;
+            for message in [r#"{"channel":"ohlc","data":"invalid"}"#, response] {
+                socket
+                    .send(TungsteniteMessage::Text(message.into()))
+                    .await
+                    .unwrap();
+            }
+        });
+
+        let params = |order_id: &str| AmendOrderParams {
+            order_id: Some(order_id.to_string()),
+            client_order_id: None,
+            order_quantity: dec!(1.0),
+            display_quantity: None,
+            limit_price: None,
+            limit_price_type: None,
+            post_only: None,
+            trigger_price: None,
+            trigger_price_type: None,
+            deadline: None,
+            token: Token::new("aToken".to_string()),
+        };
+        let amends = vec![params("BQS60L-EGW18-UPAK9U"), params("OFGKYQ-FHPCQ-HUQFEK")];
+
+        let mut client = KrakenWSSClient::new_with_urls(&url, &url);
+        let mut stream = client.connect_auth::<WssMessage>().await.unwrap();
+
+        let result = timeout(Duration::from_secs(1), stream.amend_orders(amends, 1))
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(2, result.responses.len());
+        assert_eq!(
+            Some("1M2JV8-OEJZD-G5GSBF".to_string()),
+            result.responses[0]
+                .as_ref()
+                .and_then(|response| response.result.as_ref())
+                .map(|result| result.amend_id.clone())
+        );
+        assert!(result.responses[1].is_none());
+        assert!(!result.errors.is_empty());
+        assert!(result
+            .errors
+            .iter()
+            .all(|err| matches!(err, WSSError::Serde(_) | WSSError::WSS(_))));
+    }
+
     #[tokio::test]
     async fn test_edit_order() {
         let expected_request = json!({"method":"edit_order","params":{"limit_price":0.93,"order_id":"K1FF7H-A13AR-Q1S9Z6","order_qty":6.1,"symbol":"USDC/USD","token":"someToken"},"req_id":0});
@@ -1643,6 +4004,42 @@ mod tests {
             .await;
     }
 
+    #[tokio::test]
+    async fn test_batch_cancel_by_client_order_ids() {
+        let expected_request = json!({"method":"batch_cancel","params":{"cl_ord_id":["first-order","second-order"],"token":"theirToken"},"req_id":0});
+        let response = r#"{"method":"batch_cancel","orders_cancelled":2,"req_id":0,"success":true,"time_in":"2024-05-19T19:29:58.063754Z","time_out":"2024-05-19T19:29:58.071569Z","cl_ord_id":["first-order","second-order"]}"#.to_string();
+        let expected_response =
+            WssMessage::Method(MethodMessage::BatchCancel(BatchCancelResponse {
+                orders_cancelled: 2,
+                error: None,
+                success: true,
+                req_id: 0,
+                time_in: "2024-05-19T19:29:58.063754Z".to_string(),
+                time_out: "2024-05-19T19:29:58.071569Z".to_string(),
+                client_order_id: Some(vec!["first-order".to_string(), "second-order".to_string()]),
+            }));
+
+        let batch_cancel = BatchCancelParams::from_client_order_ids(
+            vec!["first-order".to_string(), "second-order".to_string()],
+            Token::new("theirToken".to_string()),
+        );
+
+        let message = Message {
+            method: "batch_cancel".to_string(),
+            params: batch_cancel,
+            req_id: 0,
+        };
+
+        CallResponseTest::builder()
+            .match_on(expected_request)
+            .respond_with(response)
+            .send(message)
+            .expect(expected_response)
+            .build()
+            .test()
+            .await;
+    }
+
     #[tokio::test]
     async fn test_execution_trades_snapshot() {
         let trades_snapshot = r#"{
@@ -2363,4 +4760,83 @@ mod tests {
             .test()
             .await;
     }
+
+    #[tokio::test]
+    async fn test_balances_update_with_multiple_entries() {
+        let trade_update = r#"{
+        "channel":"balances",
+        "type":"update",
+        "data":[{
+            "ledger_id":"DATKX6-PEHL1-HZKND8",
+            "ref_id":"LKAKN2-N0N12-VKQNLN",
+            "timestamp":"2024-05-24T14:01:53.526524Z",
+            "type":"trade",
+            "asset":"USD",
+            "asset_class":"currency",
+            "category":"trade",
+            "wallet_type":"spot",
+            "wallet_id":"main",
+            "amount":-19.9743,
+            "fee":0.0499,
+            "balance":118.0677
+        },{
+            "ledger_id":"9K6IR4-X9PQJ-OMBG73",
+            "ref_id":"LKAKN2-N0N12-VKQNLN",
+            "timestamp":"2024-05-24T14:01:53.526524Z",
+            "type":"trade",
+            "asset":"ADX",
+            "asset_class":"currency",
+            "category":"trade",
+            "wallet_type":"spot",
+            "wallet_id":"main",
+            "amount":111.0857412800,
+            "fee":0,
+            "balance":147.1906006900
+        }],
+        "sequence":6
+    }"#
+        .to_string();
+
+        let ledger_update = |asset: &str, ledger_id: &str, amount, fee, balance| LedgerUpdate {
+            asset: asset.to_string(),
+            amount,
+            balance,
+            fee,
+            ledger_id: ledger_id.to_string(),
+            ref_id: "LKAKN2-N0N12-VKQNLN".to_string(),
+            timestamp: "2024-05-24T14:01:53.526524Z".to_string(),
+            asset_class: "currency".to_string(),
+            ledger_type: LedgerEntryTypeV2::Trade,
+            sub_type: None,
+            category: LedgerCategory::Trade,
+            wallet_type: WalletType::Spot,
+            wallet_id: WalletId::Main,
+        };
+
+        let expected_update = WssMessage::Channel(ChannelMessage::Balance(Response {
+            data: BalanceResponse::Update(vec![
+                ledger_update(
+                    "USD",
+                    "DATKX6-PEHL1-HZKND8",
+                    dec!(-19.9743),
+                    dec!(0.0499),
+                    dec!(118.0677),
+                ),
+                ledger_update(
+                    "ADX",
+                    "9K6IR4-X9PQJ-OMBG73",
+                    dec!(111.0857412800),
+                    dec!(0),
+                    dec!(147.1906006900),
+                ),
+            ]),
+            sequence: 6,
+        }));
+
+        ParseIncomingTest::new()
+            .with_incoming(trade_update)
+            .expect_message(expected_update)
+            .test()
+            .await;
+    }
 }
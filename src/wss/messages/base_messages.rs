@@ -1,23 +1,148 @@
+use crate::wss::errors::RejectedRequest;
 use crate::wss::StatusUpdate;
 use crate::wss::{
     AddOrderResult, AmendOrderResult, BatchCancelResponse, CancelAllOrdersResult,
     CancelOnDisconnectResult, CancelOrderResult, EditOrderResult,
 };
 use crate::wss::{BalanceResponse, ExecutionResult, SubscriptionResult};
-use crate::wss::{Instruments, Ohlc, Ticker, Trade, L2, L3};
+use crate::wss::{InstrumentMessage, Ohlc, Ticker, Trade, L2, L3};
 use serde::{de, Deserialize, Deserializer, Serialize};
 use serde_json::Value::Null;
+use serde_json::{Map, Value};
 use std::collections::VecDeque;
 use std::fmt::Debug;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Arc;
 
-#[derive(Debug, Deserialize, PartialEq)]
-#[serde(untagged)]
+/// Any message received from Kraken.
+///
+/// Messages are dispatched on their `channel` field to [ChannelMessage], or their `method` field to
+/// [MethodMessage] (falling back to [ErrorResponse]), so failures name the message that could not
+/// be parsed and why.
+#[derive(Debug, PartialEq)]
 pub enum WssMessage {
     Channel(ChannelMessage),
     Method(MethodMessage),
     Error(ErrorResponse),
 }
 
+impl<'de> Deserialize<'de> for WssMessage {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let fields = Map::deserialize(deserializer)?;
+
+        if let Some(channel) = fields.get("channel") {
+            let channel = channel.as_str().unwrap_or_default().to_string();
+            let kind = match fields.get("type").and_then(Value::as_str) {
+                Some(kind) => kind.to_string(),
+                None => "message".to_string(),
+            };
+
+            return ChannelMessage::deserialize(Value::Object(fields))
+                .map(WssMessage::Channel)
+                .map_err(|err| {
+                    de::Error::custom(format!("failed to parse `{channel}` {kind}: {err}"))
+                });
+        }
+
+        let Some(method) = fields.get("method") else {
+            return Err(de::Error::custom(
+                "message has neither a `channel` nor a `method` field",
+            ));
+        };
+        let method = method.as_str().unwrap_or_default().to_string();
+        let value = Value::Object(fields);
+
+        match MethodMessage::deserialize(&value) {
+            Ok(message) => Ok(WssMessage::Method(message)),
+            Err(method_err) => ErrorResponse::deserialize(&value)
+                .map(WssMessage::Error)
+                .map_err(|_| {
+                    de::Error::custom(format!("failed to parse `{method}` response: {method_err}"))
+                }),
+        }
+    }
+}
+
+impl WssMessage {
+    /// The error message if this is a rejected subscription, either as a failed subscribe response
+    /// or a top-level error for a subscribe request.
+    pub fn as_subscription_error(&self) -> Option<&str> {
+        match self {
+            WssMessage::Method(MethodMessage::Subscription(response)) => response.error_message(),
+            WssMessage::Error(response) if response.method == "subscribe" => {
+                response.error_message()
+            }
+            _ => None,
+        }
+    }
+
+    /// The error message if this is a rejected unsubscription, either as a failed unsubscribe
+    /// response or a top-level error for an unsubscribe request.
+    pub fn as_unsubscription_error(&self) -> Option<&str> {
+        match self {
+            WssMessage::Method(MethodMessage::Unsubscription(response)) => response.error_message(),
+            WssMessage::Error(response) if response.method == "unsubscribe" => {
+                response.error_message()
+            }
+            _ => None,
+        }
+    }
+}
+
+/// The channel of a message, as read by [peek_channel] without parsing the rest of the message.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum Channel {
+    Heartbeat,
+    Status,
+    Executions,
+    Balances,
+    Trade,
+    Ticker,
+    Ohlc,
+    Instrument,
+    Book,
+    Level3,
+}
+
+/// Whether a channel message is a snapshot or an incremental update.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum ChannelMessageType {
+    Snapshot,
+    Update,
+}
+
+#[derive(Deserialize)]
+struct ChannelDiscriminator {
+    channel: Option<Channel>,
+    #[serde(rename = "type")]
+    message_type: Option<ChannelMessageType>,
+}
+
+/// Read only the `channel` of a raw message, returning None for method responses, errors and any
+/// message that isn't valid JSON.
+///
+/// Other fields are skipped without being parsed into values, so this is much cheaper than fully
+/// parsing a [WssMessage], e.g. to discard unwanted book updates before parsing them.
+pub fn peek_channel(raw: &str) -> Option<Channel> {
+    peek_channel_and_type(raw).map(|(channel, _)| channel)
+}
+
+/// Read only the `channel` and `type` of a raw message, see [peek_channel].
+///
+/// The type is None for channels without snapshots or updates, like heartbeats.
+pub fn peek_channel_and_type(raw: &str) -> Option<(Channel, Option<ChannelMessageType>)> {
+    let discriminator: ChannelDiscriminator = serde_json::from_str(raw).ok()?;
+
+    discriminator
+        .channel
+        .map(|channel| (channel, discriminator.message_type))
+}
+
 #[derive(Debug, Deserialize, PartialEq)]
 #[serde(tag = "method")]
 pub enum MethodMessage {
@@ -39,6 +164,8 @@ pub enum MethodMessage {
     BatchCancel(BatchCancelResponse),
     #[serde(rename = "subscribe")]
     Subscription(ResultResponse<SubscriptionResult>),
+    #[serde(rename = "unsubscribe")]
+    Unsubscription(ResultResponse<SubscriptionResult>),
     #[serde(alias = "ping")]
     Ping(ResultResponse<Option<()>>),
     #[serde(rename = "pong")]
@@ -63,7 +190,7 @@ pub enum ChannelMessage {
     #[serde(rename = "ohlc")]
     Ohlc(MarketDataResponse<Vec<Ohlc>>),
     #[serde(rename = "instrument")]
-    Instrument(MarketDataResponse<Instruments>),
+    Instrument(InstrumentMessage),
     #[serde(rename = "book")]
     Orderbook(SingleResponse<L2>),
     #[serde(rename = "level3")]
@@ -92,6 +219,47 @@ where
             req_id,
         }
     }
+
+    pub fn new_unsubscription(params: T, req_id: i64) -> Self {
+        Message {
+            method: "unsubscribe".to_string(),
+            params,
+            req_id,
+        }
+    }
+}
+
+/// A shared counter of `req_id`s, so every message sent with it can be correlated with its response.
+///
+/// Clones share the same counter, so one generator can stamp ids across several streams without
+/// collisions.
+#[derive(Debug, Clone)]
+pub struct MessageIdGenerator {
+    next: Arc<AtomicI64>,
+}
+
+impl Default for MessageIdGenerator {
+    fn default() -> Self {
+        MessageIdGenerator::starting_at(1)
+    }
+}
+
+impl MessageIdGenerator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a generator whose first id is `first`, e.g. to stay clear of manually assigned ids.
+    pub fn starting_at(first: i64) -> Self {
+        MessageIdGenerator {
+            next: Arc::new(AtomicI64::new(first)),
+        }
+    }
+
+    /// Take the next id, incrementing the counter.
+    pub fn next_id(&self) -> i64 {
+        self.next.fetch_add(1, Ordering::Relaxed)
+    }
 }
 
 // this is required to not serialize None for generic type parameters
@@ -111,11 +279,25 @@ pub struct Response<T> {
     pub sequence: i64,
 }
 
+impl<T> Response<Vec<T>> {
+    /// Consume the response, returning its items, e.g. each execution of a snapshot.
+    pub fn into_items(self) -> std::vec::IntoIter<T> {
+        self.data.into_iter()
+    }
+}
+
 #[derive(Debug, Deserialize, PartialEq)]
 pub struct MarketDataResponse<T> {
     pub data: T,
 }
 
+impl<T> MarketDataResponse<Vec<T>> {
+    /// Consume the response, returning its items, e.g. each trade or candle.
+    pub fn into_items(self) -> std::vec::IntoIter<T> {
+        self.data.into_iter()
+    }
+}
+
 #[derive(Debug, Deserialize, PartialEq)]
 pub struct SingleResponse<T>
 where
@@ -146,6 +328,25 @@ pub struct ResultResponse<T> {
     pub time_out: String,
 }
 
+impl<T> ResultResponse<T> {
+    /// The error message if the request was not successful, empty if Kraken gave no reason.
+    pub fn error_message(&self) -> Option<&str> {
+        (!self.success).then(|| self.error.as_deref().unwrap_or_default())
+    }
+
+    /// Convert the response into its result if successful, or a [RejectedRequest] if not.
+    pub fn into_result(self) -> Result<Option<T>, RejectedRequest> {
+        if self.success {
+            Ok(self.result)
+        } else {
+            Err(RejectedRequest {
+                req_id: self.req_id,
+                error: self.error.unwrap_or_default(),
+            })
+        }
+    }
+}
+
 #[derive(Debug, Deserialize, PartialEq)]
 #[serde(deny_unknown_fields)]
 pub struct ErrorResponse {
@@ -158,6 +359,13 @@ pub struct ErrorResponse {
     pub time_out: String,
 }
 
+impl ErrorResponse {
+    /// The error message if the request was not successful, empty if Kraken gave no reason.
+    pub fn error_message(&self) -> Option<&str> {
+        (!self.success).then(|| self.error.as_deref().unwrap_or_default())
+    }
+}
+
 #[derive(Debug, Deserialize, PartialEq)]
 #[serde(deny_unknown_fields)]
 pub struct PongResponse {
@@ -170,11 +378,69 @@ pub struct PongResponse {
 #[cfg(test)]
 mod tests {
     use crate::response_types::SystemStatus;
+    use crate::wss::errors::RejectedRequest;
     use crate::wss::StatusUpdate;
-    use crate::wss::{ChannelMessage, ErrorResponse, SingleResponse, WssMessage};
+    use crate::wss::{peek_channel, peek_channel_and_type, Channel, ChannelMessageType};
+    use crate::wss::{
+        ChannelMessage, ErrorResponse, MessageIdGenerator, MethodMessage, ResultResponse,
+        SingleResponse, SubscriptionResult, WssMessage,
+    };
     use serde_json::Number;
     use std::str::FromStr;
 
+    #[test]
+    fn test_message_id_generator() {
+        let generator = MessageIdGenerator::new();
+        let shared = generator.clone();
+
+        assert_eq!(1, generator.next_id());
+        assert_eq!(2, shared.next_id());
+        assert_eq!(3, generator.next_id());
+
+        assert_eq!(100, MessageIdGenerator::starting_at(100).next_id());
+    }
+
+    #[test]
+    fn test_wss_message_parse_errors() {
+        let executions = r#"{"channel":"executions","type":"snapshot","data":[{"exec_type":"new"}],"sequence":1}"#;
+        let err = serde_json::from_str::<WssMessage>(executions).unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("failed to parse `executions` snapshot: missing field `order_id`"));
+
+        let add_order = r#"{"method":"add_order","req_id":1,"success":true}"#;
+        let err = serde_json::from_str::<WssMessage>(add_order).unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("failed to parse `add_order` response: missing field `time_in`"));
+
+        let neither = r#"{"data":[]}"#;
+        let err = serde_json::from_str::<WssMessage>(neither).unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("message has neither a `channel` nor a `method` field"));
+    }
+
+    #[test]
+    fn test_peek_channel() {
+        let book = r#"{"channel":"book","type":"update","data":[{"symbol":"BTC/USD","bids":[],"asks":[{"price":66000.0,"qty":0.5}],"checksum":2578702794,"timestamp":"2024-05-19T16:32:14.446587Z"}]}"#;
+        let heartbeat = r#"{"channel":"heartbeat"}"#;
+        let subscribe = r#"{"method":"subscribe","req_id":42,"success":true,"time_in":"2024-05-15T11:20:43.013486Z","time_out":"2024-05-15T11:20:43.013545Z"}"#;
+
+        assert_eq!(Some(Channel::Book), peek_channel(book));
+        assert_eq!(
+            Some((Channel::Book, Some(ChannelMessageType::Update))),
+            peek_channel_and_type(book)
+        );
+        assert_eq!(
+            Some((Channel::Heartbeat, None)),
+            peek_channel_and_type(heartbeat)
+        );
+        assert_eq!(None, peek_channel(subscribe));
+        assert_eq!(None, peek_channel(r#"{"channel":"unknown"}"#));
+        assert_eq!(None, peek_channel("not json"));
+    }
+
     #[test]
     fn test_deserializing_status_update() {
         let message = r#"{"channel":"status","data":[{"api_version":"v2","connection_id":18266300427528990701,"system":"online","version":"2.0.4"}],"type":"update"}"#;
@@ -232,4 +498,66 @@ mod tests {
         let parsed = serde_json::from_str::<WssMessage>(raw).unwrap();
         assert_eq!(expected, parsed);
     }
+
+    #[test]
+    fn test_as_subscription_error() {
+        let rejected = r#"{"error":"Currency pair not supported BTC/XYZ","method":"subscribe","req_id":3,"success":false,"time_in":"2024-05-19T20:02:10.316562Z","time_out":"2024-05-19T20:02:10.316592Z"}"#;
+        let session_error = r#"{"error":"ESession:Invalid session","method":"subscribe","req_id":42,"status":"error","success":false,"time_in":"2023-04-19T12:04:41.320119Z","time_out":"2023-04-19T12:04:41.980119Z"}"#;
+        let accepted = r#"{"method":"subscribe","req_id":0,"result":{"channel":"instrument","snapshot":true,"warnings":["tick_size is deprecated, use price_increment"]},"success":true,"time_in":"2024-05-19T19:44:43.264430Z","time_out":"2024-05-19T19:44:43.264464Z"}"#;
+
+        let rejected = serde_json::from_str::<WssMessage>(rejected).unwrap();
+        let session_error = serde_json::from_str::<WssMessage>(session_error).unwrap();
+        let accepted = serde_json::from_str::<WssMessage>(accepted).unwrap();
+
+        assert_eq!(
+            Some("Currency pair not supported BTC/XYZ"),
+            rejected.as_subscription_error()
+        );
+        assert_eq!(
+            Some("ESession:Invalid session"),
+            session_error.as_subscription_error()
+        );
+        assert_eq!(None, accepted.as_subscription_error());
+    }
+
+    #[test]
+    fn test_result_response_into_result() {
+        let rejected: ResultResponse<SubscriptionResult> = ResultResponse {
+            result: None,
+            error: Some("Unsupported event".to_string()),
+            success: false,
+            req_id: 7,
+            time_in: "2024-05-19T20:02:10.316562Z".to_string(),
+            time_out: "2024-05-19T20:02:10.316592Z".to_string(),
+        };
+
+        assert!(matches!(
+            rejected.into_result(),
+            Err(RejectedRequest { req_id: 7, error }) if error == "Unsupported event"
+        ));
+
+        let accepted: ResultResponse<Option<()>> = ResultResponse {
+            result: None,
+            error: None,
+            success: true,
+            req_id: 8,
+            time_in: "2024-05-19T20:02:10.316562Z".to_string(),
+            time_out: "2024-05-19T20:02:10.316592Z".to_string(),
+        };
+
+        assert!(matches!(accepted.into_result(), Ok(None)));
+    }
+
+    #[test]
+    fn test_method_message_subscription_error_only_for_subscriptions() {
+        let add_order_failure = r#"{"error":"EGeneral:Permission denied","method":"add_order","req_id":0,"success":false,"time_in":"2024-05-18T12:03:43.466650Z","time_out":"2024-05-18T12:03:43.471987Z"}"#;
+
+        let parsed = serde_json::from_str::<WssMessage>(add_order_failure).unwrap();
+
+        assert!(matches!(
+            parsed,
+            WssMessage::Method(MethodMessage::AddOrder(_))
+        ));
+        assert_eq!(None, parsed.as_subscription_error());
+    }
 }
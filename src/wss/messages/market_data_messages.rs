@@ -1,5 +1,7 @@
 use crate::crypto::secrets::Token;
+use crate::request_types::CandlestickInterval;
 use crate::response_types::BuySell;
+use crate::wss::{MarketDataResponse, Message};
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 use serde_with::{serde_as, skip_serializing_none};
@@ -69,6 +71,16 @@ impl TickerSubscription {
             snapshot: None,
         }
     }
+
+    /// Create the message to unsubscribe from this subscription, omitting snapshot options.
+    pub fn unsubscription(&self, req_id: i64) -> Message<Self> {
+        let params = TickerSubscription {
+            snapshot: None,
+            ..self.clone()
+        };
+
+        Message::new_unsubscription(params, req_id)
+    }
 }
 
 #[derive(Debug, Deserialize, PartialEq)]
@@ -102,6 +114,9 @@ pub struct BookSubscription {
 }
 
 impl BookSubscription {
+    /// The depths Kraken accepts for the book channel, where 10 is the default.
+    pub const DEPTHS: [i32; 5] = [10, 25, 100, 500, 1000];
+
     pub fn new(symbol: Vec<String>) -> Self {
         BookSubscription {
             channel: "book".to_string(),
@@ -112,6 +127,24 @@ impl BookSubscription {
         }
     }
 
+    /// Subscribe to the shallowest book Kraken offers, for users who only need the best bid and ask.
+    ///
+    /// Kraken has no depth-1 book, so this requests [BookSubscription::DEPTHS]'s minimum of 10
+    /// levels. Keep a [LocalOrderBook](crate::wss::LocalOrderBook) of the same depth and read its
+    /// `best_bid` and `best_ask`, which unlike the ticker channel have full price and quantity
+    /// precision.
+    pub fn top_of_book(symbol: Vec<String>) -> Self {
+        BookSubscription {
+            depth: Some(Self::DEPTHS[0]),
+            ..Self::new(symbol)
+        }
+    }
+
+    /// Whether the depth is unset or one of [BookSubscription::DEPTHS].
+    pub fn has_valid_depth(&self) -> bool {
+        self.depth.is_none_or(|depth| Self::DEPTHS.contains(&depth))
+    }
+
     pub fn new_l3(symbol: Vec<String>, token: Token) -> Self {
         BookSubscription {
             channel: "level3".to_string(),
@@ -121,6 +154,16 @@ impl BookSubscription {
             token: Some(token),
         }
     }
+
+    /// Create the message to unsubscribe from this subscription, omitting snapshot options.
+    pub fn unsubscription(&self, req_id: i64) -> Message<Self> {
+        let params = BookSubscription {
+            snapshot: None,
+            ..self.clone()
+        };
+
+        Message::new_unsubscription(params, req_id)
+    }
 }
 
 #[derive(Debug, Deserialize, PartialEq)]
@@ -130,13 +173,28 @@ pub enum L2 {
     Update(OrderbookUpdate),
 }
 
-#[derive(Debug, Deserialize, PartialEq)]
+/// A price level of the L2 book.
+///
+/// Levels are ordered by price, then by quantity, so bids and asks can be sorted or merged
+/// directly, e.g. `bids.sort_by(|a, b| b.cmp(a))` for best bids first.
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
 pub struct BidAsk {
     pub price: Decimal,
     #[serde(rename = "qty")]
     pub quantity: Decimal,
 }
 
+impl BidAsk {
+    /// The value of the level in the quote currency, `price * quantity`.
+    pub fn notional(&self) -> Decimal {
+        self.price * self.quantity
+    }
+}
+
+/// A snapshot of the L2 book, with the best bids and asks first.
+///
+/// Spread and imbalance metrics are computed from the snapshot's levels, each returning None if the
+/// side(s) it needs are empty.
 #[derive(Debug, Deserialize, PartialEq)]
 #[serde(deny_unknown_fields)]
 pub struct Orderbook {
@@ -146,6 +204,50 @@ pub struct Orderbook {
     pub asks: Vec<BidAsk>,
 }
 
+impl Orderbook {
+    /// The midpoint of the best bid and ask.
+    pub fn mid_price(&self) -> Option<Decimal> {
+        let (bid, ask) = self.best_levels()?;
+        Some((bid.price + ask.price) / Decimal::TWO)
+    }
+
+    /// The best ask less the best bid.
+    pub fn spread(&self) -> Option<Decimal> {
+        let (bid, ask) = self.best_levels()?;
+        Some(ask.price - bid.price)
+    }
+
+    /// The spread in basis points of the mid price, e.g. 10 for a spread of 0.1%.
+    pub fn spread_bps(&self) -> Option<Decimal> {
+        let mid_price = self.mid_price().filter(|mid_price| !mid_price.is_zero())?;
+        Some(self.spread()? / mid_price * Decimal::from(10_000))
+    }
+
+    /// The imbalance of the bid and ask quantity of the top `depth` levels of each side, from -1
+    /// (only asks) to 1 (only bids), i.e. `(bids - asks) / (bids + asks)`.
+    ///
+    /// Returns None if there's no quantity on either side within `depth`.
+    pub fn imbalance(&self, depth: usize) -> Option<Decimal> {
+        let bids = Self::quantity(&self.bids, depth);
+        let asks = Self::quantity(&self.asks, depth);
+        let total = bids + asks;
+
+        if total.is_zero() {
+            None
+        } else {
+            Some((bids - asks) / total)
+        }
+    }
+
+    fn best_levels(&self) -> Option<(&BidAsk, &BidAsk)> {
+        Some((self.bids.first()?, self.asks.first()?))
+    }
+
+    fn quantity(levels: &[BidAsk], depth: usize) -> Decimal {
+        levels.iter().take(depth).map(|level| level.quantity).sum()
+    }
+}
+
 #[derive(Debug, Deserialize, PartialEq)]
 pub struct OrderbookUpdate {
     pub symbol: String,
@@ -178,6 +280,54 @@ pub struct L3OrderbookUpdate {
     pub checksum: u32,
 }
 
+impl L3Orderbook {
+    /// The position of an order in the queue at its price level, or None if it's not in the book.
+    ///
+    /// Kraken lists orders at each price level in time priority, so this can track where your own
+    /// resting orders are, using the `order_id` returned when they were added.
+    pub fn queue_position(&self, order_id: &str) -> Option<QueuePosition> {
+        [(BuySell::Buy, &self.bids), (BuySell::Sell, &self.asks)]
+            .into_iter()
+            .find_map(|(side, orders)| {
+                let index = orders.iter().position(|order| order.order_id == order_id)?;
+                let price = orders[index].limit_price;
+                let ahead = orders[..index]
+                    .iter()
+                    .filter(|order| order.limit_price == price);
+
+                Some(QueuePosition {
+                    side,
+                    limit_price: price,
+                    orders_ahead: ahead.clone().count(),
+                    quantity_ahead: ahead.map(|order| order.order_quantity).sum(),
+                })
+            })
+    }
+}
+
+impl L3OrderbookUpdate {
+    /// All events in this update for the given `order_id`, with the side of the book they're on.
+    pub fn events_for_order(&self, order_id: &str) -> Vec<(BuySell, &L3BidAskUpdate)> {
+        let bids = self.bids.iter().map(|event| (BuySell::Buy, event));
+        let asks = self.asks.iter().map(|event| (BuySell::Sell, event));
+
+        bids.chain(asks)
+            .filter(|(_, event)| event.order_id == order_id)
+            .collect()
+    }
+}
+
+/// Where an order sits in the queue at its price level, see [L3Orderbook::queue_position].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QueuePosition {
+    pub side: BuySell,
+    pub limit_price: Decimal,
+    /// Orders at the same price with higher time priority
+    pub orders_ahead: usize,
+    /// Total quantity of the orders ahead
+    pub quantity_ahead: Decimal,
+}
+
 #[derive(Debug, Deserialize, PartialEq)]
 #[serde(deny_unknown_fields)]
 pub struct L3BidAsk {
@@ -188,6 +338,13 @@ pub struct L3BidAsk {
     pub timestamp: String,
 }
 
+impl L3BidAsk {
+    /// The value of the order in the quote currency, `limit_price * order_quantity`.
+    pub fn notional(&self) -> Decimal {
+        self.limit_price * self.order_quantity
+    }
+}
+
 #[derive(Debug, Deserialize, PartialEq)]
 pub struct L3BidAskUpdate {
     pub event: OrderbookEvent,
@@ -198,18 +355,121 @@ pub struct L3BidAskUpdate {
     pub timestamp: String,
 }
 
+impl L3BidAskUpdate {
+    /// The value of the order in the quote currency, `limit_price * order_quantity`.
+    pub fn notional(&self) -> Decimal {
+        self.limit_price * self.order_quantity
+    }
+}
+
+/// All intervals supported by the OHLC channel, serialized as their number of minutes.
+///
+/// The websocket equivalent of [CandlestickInterval], which converts to and from it.
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(into = "i32")]
+pub enum OhlcInterval {
+    Minute,
+    Minutes5,
+    Minutes15,
+    Minutes30,
+    Hour,
+    Hours4,
+    Day,
+    Week,
+    Days15,
+}
+
+impl OhlcInterval {
+    pub const ALL: [OhlcInterval; 9] = [
+        OhlcInterval::Minute,
+        OhlcInterval::Minutes5,
+        OhlcInterval::Minutes15,
+        OhlcInterval::Minutes30,
+        OhlcInterval::Hour,
+        OhlcInterval::Hours4,
+        OhlcInterval::Day,
+        OhlcInterval::Week,
+        OhlcInterval::Days15,
+    ];
+
+    pub fn minutes(&self) -> i32 {
+        match self {
+            OhlcInterval::Minute => 1,
+            OhlcInterval::Minutes5 => 5,
+            OhlcInterval::Minutes15 => 15,
+            OhlcInterval::Minutes30 => 30,
+            OhlcInterval::Hour => 60,
+            OhlcInterval::Hours4 => 240,
+            OhlcInterval::Day => 1440,
+            OhlcInterval::Week => 10080,
+            OhlcInterval::Days15 => 21600,
+        }
+    }
+
+    /// The interval of the given length, or None if Kraken doesn't support it, e.g. for
+    /// matching [Ohlc::interval] of received candles.
+    pub fn from_minutes(minutes: i32) -> Option<OhlcInterval> {
+        OhlcInterval::ALL
+            .into_iter()
+            .find(|interval| interval.minutes() == minutes)
+    }
+}
+
+impl From<OhlcInterval> for i32 {
+    fn from(interval: OhlcInterval) -> Self {
+        interval.minutes()
+    }
+}
+
+impl From<CandlestickInterval> for OhlcInterval {
+    fn from(interval: CandlestickInterval) -> Self {
+        match interval {
+            CandlestickInterval::Minute => OhlcInterval::Minute,
+            CandlestickInterval::Minutes5 => OhlcInterval::Minutes5,
+            CandlestickInterval::Minutes15 => OhlcInterval::Minutes15,
+            CandlestickInterval::Minutes30 => OhlcInterval::Minutes30,
+            CandlestickInterval::Hour => OhlcInterval::Hour,
+            CandlestickInterval::Hours4 => OhlcInterval::Hours4,
+            CandlestickInterval::Day => OhlcInterval::Day,
+            CandlestickInterval::Week => OhlcInterval::Week,
+            CandlestickInterval::Days15 => OhlcInterval::Days15,
+        }
+    }
+}
+
+impl From<OhlcInterval> for CandlestickInterval {
+    fn from(interval: OhlcInterval) -> Self {
+        match interval {
+            OhlcInterval::Minute => CandlestickInterval::Minute,
+            OhlcInterval::Minutes5 => CandlestickInterval::Minutes5,
+            OhlcInterval::Minutes15 => CandlestickInterval::Minutes15,
+            OhlcInterval::Minutes30 => CandlestickInterval::Minutes30,
+            OhlcInterval::Hour => CandlestickInterval::Hour,
+            OhlcInterval::Hours4 => CandlestickInterval::Hours4,
+            OhlcInterval::Day => CandlestickInterval::Day,
+            OhlcInterval::Week => CandlestickInterval::Week,
+            OhlcInterval::Days15 => CandlestickInterval::Days15,
+        }
+    }
+}
+
+/// A subscription to OHLC candles of a single interval.
+///
+/// Kraken accepts one interval per subscription; to receive several intervals on one connection,
+/// subscribe once per interval with distinct `req_id`s and route candles by [Ohlc::interval], see
+/// [KrakenWSSClient::subscribe_ohlc_intervals](crate::wss::KrakenWSSClient::subscribe_ohlc_intervals).
 #[serde_as]
 #[skip_serializing_none]
 #[derive(Debug, Serialize, Clone)]
 pub struct OhlcSubscription {
     pub channel: String,
     pub symbol: Vec<String>,
-    pub interval: i32,
+    pub interval: OhlcInterval,
     pub snapshot: Option<bool>,
 }
 
 impl OhlcSubscription {
-    pub fn new(symbols: Vec<String>, interval: i32) -> Self {
+    pub fn new(symbols: Vec<String>, interval: OhlcInterval) -> Self {
         OhlcSubscription {
             channel: "ohlc".to_string(),
             symbol: symbols,
@@ -217,6 +477,16 @@ impl OhlcSubscription {
             snapshot: None,
         }
     }
+
+    /// Create the message to unsubscribe from this subscription, omitting snapshot options.
+    pub fn unsubscription(&self, req_id: i64) -> Message<Self> {
+        let params = OhlcSubscription {
+            snapshot: None,
+            ..self.clone()
+        };
+
+        Message::new_unsubscription(params, req_id)
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -288,6 +558,16 @@ impl TradesSubscription {
             snapshot: None,
         }
     }
+
+    /// Create the message to unsubscribe from this subscription, omitting snapshot options.
+    pub fn unsubscription(&self, req_id: i64) -> Message<Self> {
+        let params = TradesSubscription {
+            snapshot: None,
+            ..self.clone()
+        };
+
+        Message::new_unsubscription(params, req_id)
+    }
 }
 
 #[derive(Debug, Deserialize, PartialEq)]
@@ -356,16 +636,245 @@ pub struct Pair {
 
 #[derive(Debug, Deserialize, PartialEq)]
 pub struct Instruments {
+    #[serde(default)]
     pub assets: Vec<Asset>,
+    #[serde(default)]
     pub pairs: Vec<Pair>,
 }
 
+/// An instrument channel message, either a snapshot of all assets and pairs, or an update
+/// containing only those that changed (e.g. a pair moving to [PairStatus::CancelOnly]).
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum InstrumentMessage {
+    Snapshot(MarketDataResponse<Instruments>),
+    Update(MarketDataResponse<Instruments>),
+}
+
+impl InstrumentMessage {
+    /// The assets and pairs contained in this message, regardless of its type.
+    pub fn data(&self) -> &Instruments {
+        match self {
+            InstrumentMessage::Snapshot(response) | InstrumentMessage::Update(response) => {
+                &response.data
+            }
+        }
+    }
+
+    pub fn is_snapshot(&self) -> bool {
+        matches!(self, InstrumentMessage::Snapshot(_))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_data::get_book_snapshot;
+    use crate::wss::{ChannelMessage, SingleResponse, WssMessage};
 
     use rust_decimal_macros::dec;
 
+    #[test]
+    fn test_ohlc_interval() {
+        let subscription = OhlcSubscription::new(vec!["ETH/USD".into()], OhlcInterval::Hours4);
+        assert_eq!(
+            r#"{"channel":"ohlc","symbol":["ETH/USD"],"interval":240}"#,
+            serde_json::to_string(&subscription).unwrap()
+        );
+
+        for interval in OhlcInterval::ALL {
+            let candlestick = CandlestickInterval::from(interval);
+            assert_eq!(interval.minutes().to_string(), candlestick.to_string());
+            assert_eq!(interval, OhlcInterval::from(candlestick));
+            assert_eq!(
+                Some(interval),
+                OhlcInterval::from_minutes(interval.minutes())
+            );
+        }
+
+        assert_eq!(None, OhlcInterval::from_minutes(7));
+    }
+
+    #[test]
+    fn test_deserializing_scientific_notation() {
+        for (value, expected) in [
+            ("1.5e-7", dec!(0.00000015)),
+            ("1.5E-7", dec!(0.00000015)),
+            ("2e3", dec!(2000)),
+            (r#""1.5e-7""#, dec!(0.00000015)),
+            ("0.00000015", dec!(0.00000015)),
+        ] {
+            let level: BidAsk =
+                serde_json::from_str(&format!(r#"{{"price":{value},"qty":{value}}}"#)).unwrap();
+            assert_eq!(expected, level.price);
+            assert_eq!(expected, level.quantity);
+
+            let trade: Trade = serde_json::from_str(&format!(
+                r#"{{"symbol":"SHIB/USD","side":"buy","price":{value},"qty":{value},"ord_type":"market","trade_id":1,"timestamp":"2024-05-19T16:31:14.1Z"}}"#
+            ))
+            .unwrap();
+            assert_eq!(expected, trade.price);
+            assert_eq!(expected, trade.quantity);
+        }
+    }
+
+    #[test]
+    fn test_bid_ask_ordering_and_notional() {
+        let level = |price, quantity| BidAsk { price, quantity };
+
+        let mut levels = vec![
+            level(dec!(101.5), dec!(1)),
+            level(dec!(99.0), dec!(3)),
+            level(dec!(101.5), dec!(0.5)),
+            level(dec!(100.25), dec!(2)),
+        ];
+        levels.sort();
+
+        assert_eq!(
+            vec![
+                level(dec!(99.0), dec!(3)),
+                level(dec!(100.25), dec!(2)),
+                level(dec!(101.5), dec!(0.5)),
+                level(dec!(101.5), dec!(1)),
+            ],
+            levels
+        );
+        assert_eq!(Some(&level(dec!(101.5), dec!(1))), levels.iter().max());
+        assert_eq!(dec!(200.5), levels[1].notional());
+
+        let order = L3BidAsk {
+            order_id: "O7SO4Y-RHRAK-GGAHJE".to_string(),
+            limit_price: dec!(2000.5),
+            order_quantity: dec!(0.25),
+            timestamp: "2024-05-19T16:31:14.1Z".to_string(),
+        };
+        assert_eq!(dec!(500.125), order.notional());
+
+        let update = L3BidAskUpdate {
+            event: OrderbookEvent::Add,
+            order_id: order.order_id.clone(),
+            limit_price: order.limit_price,
+            order_quantity: order.order_quantity,
+            timestamp: order.timestamp.clone(),
+        };
+        assert_eq!(order.notional(), update.notional());
+    }
+
+    #[test]
+    fn test_orderbook_metrics() {
+        let message: WssMessage = serde_json::from_str(&get_book_snapshot()).unwrap();
+        let WssMessage::Channel(ChannelMessage::Orderbook(SingleResponse {
+            data: L2::Orderbook(book),
+        })) = message
+        else {
+            panic!("expected a book snapshot, got {message:?}");
+        };
+
+        assert_eq!(Some(dec!(66788.05)), book.mid_price());
+        assert_eq!(Some(dec!(0.1)), book.spread());
+        assert_eq!(
+            Some(dec!(0.014973)),
+            book.spread_bps().map(|bps| bps.round_dp(6))
+        );
+        assert_eq!(
+            Some(dec!(0.071843)),
+            book.imbalance(2).map(|imbalance| imbalance.round_dp(6))
+        );
+        assert_eq!(
+            Some(dec!(-0.145327)),
+            book.imbalance(5).map(|imbalance| imbalance.round_dp(6))
+        );
+        assert_eq!(None, book.imbalance(0));
+
+        let one_sided = Orderbook {
+            asks: vec![],
+            ..book
+        };
+        assert_eq!(None, one_sided.mid_price());
+        assert_eq!(None, one_sided.spread());
+        assert_eq!(None, one_sided.spread_bps());
+        assert_eq!(Some(dec!(1)), one_sided.imbalance(10));
+
+        let empty = Orderbook {
+            bids: vec![],
+            ..one_sided
+        };
+        assert_eq!(None, empty.imbalance(10));
+    }
+
+    #[test]
+    fn test_top_of_book_subscription() {
+        let subscription = BookSubscription::top_of_book(vec!["BTC/USD".into()]);
+        assert!(subscription.has_valid_depth());
+        assert_eq!(
+            r#"{"channel":"book","symbol":["BTC/USD"],"depth":10}"#,
+            serde_json::to_string(&subscription).unwrap()
+        );
+
+        let mut subscription = BookSubscription::new(vec!["BTC/USD".into()]);
+        assert!(subscription.has_valid_depth());
+
+        subscription.depth = Some(1);
+        assert!(!subscription.has_valid_depth());
+    }
+
+    fn l3_order(order_id: &str, limit_price: Decimal, order_quantity: Decimal) -> L3BidAsk {
+        L3BidAsk {
+            order_id: order_id.to_string(),
+            limit_price,
+            order_quantity,
+            timestamp: "2024-05-19T16:32:14.446587Z".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_l3_queue_position() {
+        let book = L3Orderbook {
+            symbol: "BTC/USD".to_string(),
+            bids: vec![
+                l3_order("O1", dec!(100), dec!(1)),
+                l3_order("O2", dec!(100), dec!(2.5)),
+                l3_order("O3", dec!(99), dec!(4)),
+                l3_order("MINE", dec!(99), dec!(1)),
+            ],
+            asks: vec![l3_order("O4", dec!(101), dec!(1))],
+            checksum: 0,
+        };
+
+        let expected = QueuePosition {
+            side: BuySell::Buy,
+            limit_price: dec!(99),
+            orders_ahead: 1,
+            quantity_ahead: dec!(4),
+        };
+
+        assert_eq!(Some(expected), book.queue_position("MINE"));
+        assert_eq!(0, book.queue_position("O4").unwrap().orders_ahead);
+        assert_eq!(BuySell::Sell, book.queue_position("O4").unwrap().side);
+        assert!(book.queue_position("MISSING").is_none());
+    }
+
+    #[test]
+    fn test_l3_events_for_order() {
+        let raw = r#"{"symbol":"BTC/USD","checksum":1,"bids":[{"event":"modify","order_id":"MINE","limit_price":99,"order_qty":0.5,"timestamp":"2024-05-19T16:32:14.446587Z"},{"event":"add","order_id":"O5","limit_price":98,"order_qty":1,"timestamp":"2024-05-19T16:32:14.446587Z"}],"asks":[{"event":"delete","order_id":"MINE","limit_price":101,"order_qty":1,"timestamp":"2024-05-19T16:32:14.446587Z"}]}"#;
+        let update: L3OrderbookUpdate = serde_json::from_str(raw).unwrap();
+
+        let events: Vec<(BuySell, OrderbookEvent)> = update
+            .events_for_order("MINE")
+            .into_iter()
+            .map(|(side, event)| (side, event.event))
+            .collect();
+
+        assert_eq!(
+            vec![
+                (BuySell::Buy, OrderbookEvent::Modify),
+                (BuySell::Sell, OrderbookEvent::Delete)
+            ],
+            events
+        );
+        assert!(update.events_for_order("MISSING").is_empty());
+    }
+
     #[test]
     fn test_deserialize_asset() {
         let raw = r#"{"id":"XLM","status":"enabled","precision":8,"precision_display":5,"borrowable":true,"collateral_value":0.00,"margin_rate":0.020000}"#;
@@ -410,4 +919,43 @@ mod tests {
 
         assert_eq!(expected, deserialized);
     }
+
+    #[test]
+    fn test_deserialize_pair_statuses() {
+        let statuses = [
+            ("cancel_only", PairStatus::CancelOnly),
+            ("delisted", PairStatus::Delisted),
+            ("limit_only", PairStatus::LimitOnly),
+            ("maintenance", PairStatus::Maintenance),
+            ("online", PairStatus::Online),
+            ("post_only", PairStatus::PostOnly),
+            ("reduce_only", PairStatus::ReduceOnly),
+            ("work_in_progress", PairStatus::WorkInProgress),
+        ];
+
+        for (raw, expected) in statuses {
+            let deserialized = serde_json::from_str::<PairStatus>(&format!(r#""{raw}""#)).unwrap();
+            assert_eq!(expected, deserialized);
+        }
+    }
+
+    #[test]
+    fn test_deserialize_asset_statuses() {
+        let statuses = [
+            ("depositonly", AssetStatus::DepositOnly),
+            ("disabled", AssetStatus::Disabled),
+            ("enabled", AssetStatus::Enabled),
+            (
+                "fundingtemporarilydisabled",
+                AssetStatus::FundingTemporarilyDisabled,
+            ),
+            ("withdrawalonly", AssetStatus::WithdrawalOnly),
+            ("workinprogress", AssetStatus::WorkInProgress),
+        ];
+
+        for (raw, expected) in statuses {
+            let deserialized = serde_json::from_str::<AssetStatus>(&format!(r#""{raw}""#)).unwrap();
+            assert_eq!(expected, deserialized);
+        }
+    }
 }
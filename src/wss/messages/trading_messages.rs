@@ -1,11 +1,19 @@
+use crate::clients::errors::ClientError;
 use crate::crypto::secrets::Token;
-use crate::request_types::{IntOrString, SelfTradePrevention, TimeInForceV2, TriggerType};
-use crate::response_types::{BuySell, OrderType};
+use crate::request_types::{
+    AddOrderRequest, Deadline, IntOrString, OrderFlags, SelfTradePrevention, TimeInForceV2,
+    TriggerType,
+};
+use crate::response_types::{BuySell, OrderFlag, OrderType};
+use crate::wss::errors::WSSError;
+use crate::wss::{ResultResponse, WssMessage};
 use rust_decimal::serde::{float, float_option};
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 use serde_with::skip_serializing_none;
 use simple_builder::Builder;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
 
 #[derive(Debug, Eq, PartialEq, Clone, Deserialize)]
 pub enum AddOrderStatus {
@@ -16,9 +24,9 @@ pub enum AddOrderStatus {
 #[derive(Debug, Eq, PartialEq, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum FeePreference {
-    #[serde(rename(deserialize = "fcib"))]
+    #[serde(rename(deserialize = "fcib"), alias = "base")]
     Base,
-    #[serde(rename(deserialize = "fciq"))]
+    #[serde(rename(deserialize = "fciq"), alias = "quote")]
     Quote,
 }
 
@@ -36,18 +44,42 @@ pub enum PriceType {
     Quote,
 }
 
+/// Trigger of a primary stop-loss, take-profit or trailing-stop order.
+#[skip_serializing_none]
 #[derive(Debug, Serialize, Deserialize)]
 pub struct TriggerParams {
+    #[serde(with = "float")]
     pub price: Decimal,
     pub price_type: Option<PriceType>,
+    /// Whether the trigger follows the last traded price or the index price
     pub reference: Option<TriggerType>,
 }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+/// The secondary order of a one-triggers-other (OTO) order, placed once the primary order fills.
+///
+/// The secondary order is triggered by the last traded price, since Kraken has no
+/// `trigger_reference` for conditional orders. Its trigger price is only required for
+/// stop-loss and take-profit order types.
+///
+/// # Example: Stop-Loss-Limit Attached to a Limit Buy
+/// ```
+/// # use kraken_async_rs::response_types::OrderType;
+/// # use kraken_async_rs::wss::ConditionalParams;
+/// # use rust_decimal_macros::dec;
+/// let stop_loss = ConditionalParams::builder()
+///     .order_type(OrderType::StopLossLimit)
+///     .trigger_price(dec!(28410))
+///     .limit_price(dec!(28400))
+///     .build();
+/// ```
+#[skip_serializing_none]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Builder)]
 pub struct ConditionalParams {
     pub order_type: Option<OrderType>,
+    #[serde(default, with = "float_option")]
     pub limit_price: Option<Decimal>,
     pub limit_price_type: Option<PriceType>,
+    #[serde(default, with = "float_option")]
     pub trigger_price: Option<Decimal>,
     pub trigger_price_type: Option<PriceType>,
 }
@@ -92,6 +124,154 @@ pub struct AddOrderParams {
     pub token: Token,
 }
 
+impl AddOrderParams {
+    /// Create an order with only the required fields set, leaving every optional field as `None`.
+    pub fn new(
+        order_type: OrderType,
+        side: BuySell,
+        symbol: impl ToString,
+        order_quantity: Decimal,
+        token: Token,
+    ) -> Self {
+        AddOrderParams {
+            order_type,
+            side,
+            symbol: symbol.to_string(),
+            limit_price: None,
+            limit_price_type: None,
+            triggers: None,
+            time_in_force: None,
+            order_quantity,
+            margin: None,
+            post_only: None,
+            reduce_only: None,
+            expire_time: None,
+            deadline: None,
+            effective_time: None,
+            client_order_id: None,
+            order_user_ref: None,
+            conditional: None,
+            display_quantity: None,
+            fee_preference: None,
+            no_market_price_protection: None,
+            stp_type: None,
+            cash_order_quantity: None,
+            validate: None,
+            sender_sub_id: None,
+            token,
+        }
+    }
+
+    /// Create a limit order at `limit_price`, leaving every optional field as `None`.
+    pub fn limit(
+        side: BuySell,
+        symbol: impl ToString,
+        order_quantity: Decimal,
+        limit_price: Decimal,
+        token: Token,
+    ) -> Self {
+        AddOrderParams {
+            limit_price: Some(limit_price),
+            ..AddOrderParams::new(OrderType::Limit, side, symbol, order_quantity, token)
+        }
+    }
+
+    /// Create a market order, leaving every optional field as `None`.
+    pub fn market(
+        side: BuySell,
+        symbol: impl ToString,
+        order_quantity: Decimal,
+        token: Token,
+    ) -> Self {
+        AddOrderParams::new(OrderType::Market, side, symbol, order_quantity, token)
+    }
+
+    /// Check the `expire_time` is consistent with the `time_in_force`, see
+    /// [TimeInForceV2::validate_expire_time].
+    pub fn validate(&self) -> Result<(), ClientError> {
+        match &self.time_in_force {
+            Some(time_in_force) => time_in_force.validate_expire_time(self.expire_time.as_deref()),
+            None => Ok(()),
+        }
+    }
+
+    /// Set the RFC3339 `effective_time` from a [Deadline], resolving relative times now.
+    pub fn effective_at(mut self, effective: impl Into<Deadline>) -> Result<Self, ClientError> {
+        self.effective_time = Some(effective.into().to_rfc3339()?);
+        Ok(self)
+    }
+
+    /// Set the RFC3339 `expire_time` from a [Deadline], resolving relative times now.
+    pub fn expire_at(mut self, expiry: impl Into<Deadline>) -> Result<Self, ClientError> {
+        self.expire_time = Some(expiry.into().to_rfc3339()?);
+        Ok(self)
+    }
+
+    /// Set the RFC3339 `deadline` from a [Deadline], resolving relative deadlines now.
+    pub fn deadline_at(mut self, deadline: impl Into<Deadline>) -> Result<Self, ClientError> {
+        self.deadline = Some(deadline.into().to_rfc3339()?);
+        Ok(self)
+    }
+}
+
+/// A warning Kraken attached to the result of a request, parsed from its message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OrderWarning {
+    /// A field that Kraken no longer supports, with its replacement if one was named, e.g. from
+    /// "stop_price is deprecated, use triggers.price"
+    Deprecated {
+        field: String,
+        replacement: Option<String>,
+    },
+    /// Any other warning, with its message as given
+    Other(String),
+}
+
+impl From<&str> for OrderWarning {
+    fn from(value: &str) -> Self {
+        let Some((field, rest)) = value.split_once(" is deprecated") else {
+            return OrderWarning::Other(value.to_string());
+        };
+
+        let replacement = rest
+            .trim_start_matches(',')
+            .trim()
+            .strip_prefix("use ")
+            .map(|replacement| replacement.trim().to_string());
+
+        OrderWarning::Deprecated {
+            field: field.trim().to_string(),
+            replacement,
+        }
+    }
+}
+
+/// Results of trading requests that can carry warnings, see [ResultResponse::warnings].
+pub trait Warnings {
+    /// The warnings as Kraken sent them, empty if there were none.
+    fn warning_messages(&self) -> &[String];
+}
+
+impl<T> ResultResponse<T>
+where
+    T: Warnings,
+{
+    /// The warnings of the result, parsed as [OrderWarning]s, empty if there were none or the
+    /// request was not successful.
+    pub fn warnings(&self) -> Vec<OrderWarning> {
+        self.result
+            .as_ref()
+            .map(|result| {
+                result
+                    .warning_messages()
+                    .iter()
+                    .map(|warning| OrderWarning::from(warning.as_str()))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
 #[derive(Debug, Deserialize, PartialEq)]
 pub struct AddOrderResult {
     pub order_id: String,
@@ -102,6 +282,12 @@ pub struct AddOrderResult {
     pub client_order_id: Option<String>,
 }
 
+impl Warnings for AddOrderResult {
+    fn warning_messages(&self) -> &[String] {
+        self.warning.as_deref().unwrap_or_default()
+    }
+}
+
 #[skip_serializing_none]
 #[derive(Debug, Serialize, Deserialize, Builder)]
 pub struct AmendOrderParams {
@@ -135,6 +321,27 @@ pub struct AmendOrderResult {
     pub warnings: Option<Vec<String>>,
 }
 
+impl Warnings for AmendOrderResult {
+    fn warning_messages(&self) -> &[String] {
+        self.warnings.as_deref().unwrap_or_default()
+    }
+}
+
+/// Responses collected when amending several orders at once with
+/// [KrakenMessageStream::amend_orders](crate::wss::KrakenMessageStream::amend_orders).
+///
+/// Kraken has no batch amend method, so each amend is sent and answered individually.
+#[derive(Debug)]
+pub struct AmendOrdersResponse {
+    /// One response per amend, in the order the amends were given, or None for amends that weren't
+    /// answered before the stream ended or failed
+    pub responses: Vec<Option<ResultResponse<AmendOrderResult>>>,
+    /// Any other messages received while waiting for the amend responses, in the order received
+    pub other_messages: Vec<WssMessage>,
+    /// Any errors received while waiting for the amend responses, in the order received
+    pub errors: Vec<WSSError>,
+}
+
 #[skip_serializing_none]
 #[derive(Debug, Serialize, Deserialize)]
 pub struct EditOrderParams {
@@ -168,6 +375,12 @@ pub struct EditOrderResult {
     pub warning: Option<Vec<String>>,
 }
 
+impl Warnings for EditOrderResult {
+    fn warning_messages(&self) -> &[String] {
+        self.warning.as_deref().unwrap_or_default()
+    }
+}
+
 #[skip_serializing_none]
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CancelOrderParams {
@@ -179,6 +392,44 @@ pub struct CancelOrderParams {
     pub token: Token,
 }
 
+impl CancelOrderParams {
+    /// Cancel a single order by tx id or user ref, matching the REST `cancel_order` semantics.
+    pub fn new(id: IntOrString, token: Token) -> Self {
+        match id {
+            IntOrString::Int(user_ref) => Self::by_user_ref(user_ref, token),
+            IntOrString::String(order_id) => Self::by_order_id(order_id, token),
+        }
+    }
+
+    pub fn by_order_id(order_id: impl ToString, token: Token) -> Self {
+        CancelOrderParams {
+            order_id: Some(vec![order_id.to_string()]),
+            client_order_id: None,
+            order_user_ref: None,
+            token,
+        }
+    }
+
+    pub fn by_client_order_id(client_order_id: impl ToString, token: Token) -> Self {
+        CancelOrderParams {
+            order_id: None,
+            client_order_id: Some(vec![client_order_id.to_string()]),
+            order_user_ref: None,
+            token,
+        }
+    }
+
+    /// Cancel all orders placed with the given user ref.
+    pub fn by_user_ref(user_ref: i64, token: Token) -> Self {
+        CancelOrderParams {
+            order_id: None,
+            client_order_id: None,
+            order_user_ref: Some(vec![user_ref]),
+            token,
+        }
+    }
+}
+
 #[derive(Debug, Deserialize, PartialEq)]
 pub struct CancelOrderResult {
     pub order_id: Option<String>,
@@ -187,6 +438,12 @@ pub struct CancelOrderResult {
     pub client_order_id: Option<String>,
 }
 
+impl Warnings for CancelOrderResult {
+    fn warning_messages(&self) -> &[String] {
+        self.warning.as_deref().unwrap_or_default()
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CancelAllOrdersParams {
     pub token: Token,
@@ -198,6 +455,12 @@ pub struct CancelAllOrdersResult {
     pub warning: Option<Vec<String>>,
 }
 
+impl Warnings for CancelAllOrdersResult {
+    fn warning_messages(&self) -> &[String] {
+        self.warning.as_deref().unwrap_or_default()
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CancelOnDisconnectParams {
     pub timeout: i64,
@@ -213,6 +476,12 @@ pub struct CancelOnDisconnectResult {
     pub warning: Option<Vec<String>>,
 }
 
+impl Warnings for CancelOnDisconnectResult {
+    fn warning_messages(&self) -> &[String] {
+        self.warning.as_deref().unwrap_or_default()
+    }
+}
+
 #[skip_serializing_none]
 #[derive(Debug, Serialize, Deserialize)]
 pub struct BatchOrder {
@@ -248,6 +517,17 @@ pub struct BatchOrder {
     pub client_order_id: Option<String>,
 }
 
+impl BatchOrder {
+    /// Check the `expire_time` is consistent with the `time_in_force`, see
+    /// [TimeInForceV2::validate_expire_time].
+    pub fn validate(&self) -> Result<(), ClientError> {
+        match &self.time_in_force {
+            Some(time_in_force) => time_in_force.validate_expire_time(self.expire_time.as_deref()),
+            None => Ok(()),
+        }
+    }
+}
+
 #[skip_serializing_none]
 #[derive(Debug, Serialize, Deserialize)]
 pub struct BatchOrderParams {
@@ -258,21 +538,65 @@ pub struct BatchOrderParams {
     pub orders: Vec<BatchOrder>,
 }
 
+impl BatchOrderParams {
+    /// Check each order of the batch, see [BatchOrder::validate].
+    pub fn validate(&self) -> Result<(), ClientError> {
+        self.orders.iter().try_for_each(BatchOrder::validate)
+    }
+}
+
+/// Cancel orders in a batch by tx id or user ref (`orders`), client order id, or both.
+///
+/// `orders` is omitted from the request when empty, so a batch can be cancelled purely by client
+/// order ids.
 #[skip_serializing_none]
 #[derive(Debug, Serialize)]
 pub struct BatchCancelParams {
+    #[serde(skip_serializing_if = "Vec::is_empty")]
     pub orders: Vec<IntOrString>,
     pub token: Token,
     #[serde(rename = "cl_ord_id")]
     pub client_order_id: Option<Vec<String>>,
 }
 
+impl BatchCancelParams {
+    pub fn from_user_refs(refs: Vec<i64>, token: Token) -> Self {
+        BatchCancelParams {
+            orders: refs.into_iter().map(IntOrString::Int).collect(),
+            token,
+            client_order_id: None,
+        }
+    }
+
+    pub fn from_tx_ids(ids: Vec<String>, token: Token) -> Self {
+        BatchCancelParams {
+            orders: ids.into_iter().map(IntOrString::String).collect(),
+            token,
+            client_order_id: None,
+        }
+    }
+
+    pub fn from_client_order_ids(ids: Vec<String>, token: Token) -> Self {
+        BatchCancelParams {
+            orders: vec![],
+            token,
+            client_order_id: Some(ids),
+        }
+    }
+}
+
 #[derive(Debug, Deserialize, PartialEq)]
 pub struct BatchCancelResult {
     pub count: i32,
     pub warning: Option<Vec<String>>,
 }
 
+impl Warnings for BatchCancelResult {
+    fn warning_messages(&self) -> &[String] {
+        self.warning.as_deref().unwrap_or_default()
+    }
+}
+
 #[derive(Debug, Deserialize, PartialEq)]
 pub struct BatchCancelResponse {
     pub orders_cancelled: i64,
@@ -284,3 +608,577 @@ pub struct BatchCancelResponse {
     #[serde(rename = "cl_ord_id")]
     pub client_order_id: Option<Vec<String>>,
 }
+
+/// Convert a REST order to a websocket order, placed with `token`.
+///
+/// The `pair` becomes the `symbol` as is, so it must already be the websocket name of the pair,
+/// e.g. `BTC/USD` rather than `XBTUSD`. Order flags become `post_only`, `fee_preference` and
+/// `no_mpp`, unix start and expire times become RFC3339, and the REST `req_id` is dropped.
+///
+/// Orders that can't be represented exactly return [ClientError::InvalidRequest]: trailing stops,
+/// whose REST prices are relative offsets, orders with `leverage` or volume in quote (`viqc`), and
+/// start or expire times given as offsets like `+60`.
+impl TryFrom<(AddOrderRequest, Token)> for AddOrderParams {
+    type Error = ClientError;
+
+    fn try_from((request, token): (AddOrderRequest, Token)) -> Result<Self, Self::Error> {
+        if request.leverage.is_some() {
+            return Err(ClientError::InvalidRequest(
+                "leverage has no equivalent for websocket orders",
+            ));
+        }
+
+        let flags = request
+            .order_flags
+            .as_ref()
+            .map(OrderFlags::flags)
+            .unwrap_or_default();
+
+        if flags.contains(&OrderFlag::OrderVolumeInQuote) {
+            return Err(ClientError::InvalidRequest(
+                "volume in quote (viqc) has no equivalent for websocket orders",
+            ));
+        }
+
+        let fee_preference = if flags.contains(&OrderFlag::FeesInBase) {
+            Some(FeePreference::Base)
+        } else if flags.contains(&OrderFlag::FeesInQuote) {
+            Some(FeePreference::Quote)
+        } else {
+            None
+        };
+
+        let conditional = match request.close_order_type {
+            Some(close_order_type) => {
+                let order_type = serde_json::from_value(close_order_type.into())
+                    .map_err(|_| ClientError::InvalidRequest("unknown close order type"))?;
+                let (limit_price, trigger_price) =
+                    split_prices(order_type, request.close_price, request.close_price_2)?;
+
+                Some(ConditionalParams {
+                    order_type: Some(order_type),
+                    limit_price,
+                    limit_price_type: None,
+                    trigger_price,
+                    trigger_price_type: None,
+                })
+            }
+            None => None,
+        };
+
+        let (limit_price, trigger_price) =
+            split_prices(request.order_type, request.price, request.price_2)?;
+
+        Ok(AddOrderParams {
+            limit_price,
+            triggers: trigger_price.map(|price| TriggerParams {
+                price,
+                price_type: None,
+                reference: request.trigger,
+            }),
+            time_in_force: request.time_in_force.map(TimeInForceV2::from),
+            post_only: flags.contains(&OrderFlag::Post).then_some(true),
+            reduce_only: request.reduce_only,
+            expire_time: unix_to_rfc3339(request.expire_time)?,
+            deadline: request.deadline,
+            effective_time: unix_to_rfc3339(request.start_time)?,
+            client_order_id: request.client_order_id,
+            order_user_ref: request.user_ref,
+            conditional,
+            display_quantity: request.display_volume,
+            fee_preference,
+            no_market_price_protection: flags
+                .contains(&OrderFlag::NoMarketPriceProtection)
+                .then_some(true),
+            stp_type: request.stp_type,
+            validate: request.validate,
+            ..AddOrderParams::new(
+                request.order_type,
+                request.side,
+                request.pair,
+                request.volume,
+                token,
+            )
+        })
+    }
+}
+
+/// Convert a websocket order to a REST order, dropping its token.
+///
+/// The `symbol` becomes the `pair` as is, and otherwise this is the reverse of converting a
+/// REST order. Orders that can't be represented exactly return [ClientError::InvalidRequest]:
+/// trailing stops, prices relative to the last trade, margin orders (which need a leverage over
+/// REST), and orders with a `cash_order_qty` or `sender_sub_id`.
+impl TryFrom<AddOrderParams> for AddOrderRequest {
+    type Error = ClientError;
+
+    fn try_from(params: AddOrderParams) -> Result<Self, Self::Error> {
+        if params.margin == Some(true) {
+            return Err(ClientError::InvalidRequest(
+                "margin orders need a leverage for REST orders",
+            ));
+        }
+
+        if params.cash_order_quantity.is_some() || params.sender_sub_id.is_some() {
+            return Err(ClientError::InvalidRequest(
+                "cash_order_qty and sender_sub_id have no equivalent for REST orders",
+            ));
+        }
+
+        let price_types = [
+            params.limit_price_type.as_ref(),
+            params.triggers.as_ref().and_then(|t| t.price_type.as_ref()),
+            params
+                .conditional
+                .as_ref()
+                .and_then(|c| c.limit_price_type.as_ref()),
+            params
+                .conditional
+                .as_ref()
+                .and_then(|c| c.trigger_price_type.as_ref()),
+        ];
+        if price_types
+            .into_iter()
+            .any(|price_type| price_type.is_some_and(|p| *p != PriceType::Static))
+        {
+            return Err(ClientError::InvalidRequest(
+                "relative prices have no equivalent for REST orders",
+            ));
+        }
+
+        let mut flags = Vec::new();
+        if params.post_only == Some(true) {
+            flags.push(OrderFlag::Post);
+        }
+        match params.fee_preference {
+            Some(FeePreference::Base) => flags.push(OrderFlag::FeesInBase),
+            Some(FeePreference::Quote) => flags.push(OrderFlag::FeesInQuote),
+            None => {}
+        }
+        if params.no_market_price_protection == Some(true) {
+            flags.push(OrderFlag::NoMarketPriceProtection);
+        }
+
+        let (close_order_type, close_price, close_price_2) = match params.conditional {
+            Some(ConditionalParams {
+                order_type: Some(order_type),
+                limit_price,
+                trigger_price,
+                ..
+            }) => {
+                let (price, price_2) = join_prices(order_type, limit_price, trigger_price)?;
+                (Some(order_type.to_string()), price, price_2)
+            }
+            _ => (None, None, None),
+        };
+
+        let trigger = params.triggers.as_ref().and_then(|t| t.reference);
+        let (price, price_2) = join_prices(
+            params.order_type,
+            params.limit_price,
+            params.triggers.map(|t| t.price),
+        )?;
+
+        Ok(AddOrderRequest {
+            user_ref: params.order_user_ref,
+            client_order_id: params.client_order_id,
+            order_type: params.order_type,
+            side: params.side,
+            volume: params.order_quantity,
+            display_volume: params.display_quantity,
+            pair: params.symbol,
+            req_id: None,
+            price,
+            price_2,
+            trigger,
+            leverage: None,
+            reduce_only: params.reduce_only,
+            stp_type: params.stp_type,
+            order_flags: (!flags.is_empty()).then(|| OrderFlags::new(flags)),
+            time_in_force: params.time_in_force.map(Into::into),
+            start_time: rfc3339_to_unix(params.effective_time)?,
+            expire_time: rfc3339_to_unix(params.expire_time)?,
+            close_order_type,
+            close_price,
+            close_price_2,
+            deadline: params.deadline,
+            validate: params.validate,
+        })
+    }
+}
+
+/// Split REST `price` and `price2` into the limit and trigger price of the order type.
+fn split_prices(
+    order_type: OrderType,
+    price: Option<Decimal>,
+    price_2: Option<Decimal>,
+) -> Result<(Option<Decimal>, Option<Decimal>), ClientError> {
+    match order_type {
+        OrderType::Limit | OrderType::Iceberg => Ok((price, None)),
+        OrderType::Market | OrderType::SettlePosition => Ok((None, None)),
+        OrderType::StopLoss | OrderType::TakeProfit => Ok((None, price)),
+        OrderType::StopLossLimit | OrderType::TakeProfitLimit => Ok((price_2, price)),
+        OrderType::TrailingStop | OrderType::TrailingStopLimit => Err(ClientError::InvalidRequest(
+            "trailing stop offsets can't be converted between REST and websocket orders",
+        )),
+    }
+}
+
+/// Join the limit and trigger price of the order type into REST `price` and `price2`.
+fn join_prices(
+    order_type: OrderType,
+    limit_price: Option<Decimal>,
+    trigger_price: Option<Decimal>,
+) -> Result<(Option<Decimal>, Option<Decimal>), ClientError> {
+    match order_type {
+        OrderType::Limit | OrderType::Iceberg => Ok((limit_price, None)),
+        OrderType::Market | OrderType::SettlePosition => Ok((None, None)),
+        OrderType::StopLoss | OrderType::TakeProfit => Ok((trigger_price, None)),
+        OrderType::StopLossLimit | OrderType::TakeProfitLimit => Ok((trigger_price, limit_price)),
+        OrderType::TrailingStop | OrderType::TrailingStopLimit => Err(ClientError::InvalidRequest(
+            "trailing stop offsets can't be converted between REST and websocket orders",
+        )),
+    }
+}
+
+/// Convert a REST time of `0` (now) or a unix timestamp to RFC3339.
+fn unix_to_rfc3339(time: Option<String>) -> Result<Option<String>, ClientError> {
+    const INVALID: ClientError =
+        ClientError::InvalidRequest("REST order times must be 0 or a unix timestamp to convert");
+
+    match time.as_deref() {
+        None | Some("0") => Ok(None),
+        Some(time) if time.starts_with('+') => Err(INVALID),
+        Some(time) => {
+            let seconds: i64 = time.parse().map_err(|_| INVALID)?;
+            OffsetDateTime::from_unix_timestamp(seconds)
+                .ok()
+                .and_then(|time| time.format(&Rfc3339).ok())
+                .map(Some)
+                .ok_or(INVALID)
+        }
+    }
+}
+
+/// Convert an RFC3339 time to a unix timestamp for REST orders.
+fn rfc3339_to_unix(time: Option<String>) -> Result<Option<String>, ClientError> {
+    time.map(|time| {
+        OffsetDateTime::parse(&time, &Rfc3339)
+            .map(|time| time.unix_timestamp().to_string())
+            .map_err(|_| ClientError::InvalidRequest("websocket order times must be RFC3339"))
+    })
+    .transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::request_types::TimeInForce;
+    use rust_decimal_macros::dec;
+    use serde_json::json;
+    use std::time::Duration;
+    use time::macros::datetime;
+
+    #[test]
+    fn test_cancel_order_params_round_trip() {
+        let token = || Token::new("someToken".to_string());
+
+        for (params, expected) in [
+            (
+                CancelOrderParams::by_user_ref(42, token()),
+                json!({"order_userref":[42],"token":"someToken"}),
+            ),
+            (
+                CancelOrderParams::new(IntOrString::Int(42), token()),
+                json!({"order_userref":[42],"token":"someToken"}),
+            ),
+            (
+                CancelOrderParams::new("OUMUGK-6BLIN-T6USAU".into(), token()),
+                json!({"order_id":["OUMUGK-6BLIN-T6USAU"],"token":"someToken"}),
+            ),
+            (
+                CancelOrderParams::by_client_order_id("some-uuid", token()),
+                json!({"cl_ord_id":["some-uuid"],"token":"someToken"}),
+            ),
+        ] {
+            let serialized = serde_json::to_value(&params).unwrap();
+            assert_eq!(expected, serialized);
+
+            let deserialized: CancelOrderParams = serde_json::from_value(serialized).unwrap();
+            assert_eq!(expected, serde_json::to_value(&deserialized).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_order_warnings() {
+        let raw = r#"{"req_id":7,"result":{"order_id":"OPS23M-VS41G-DDE5Z2","warning":["stop_price is deprecated, use triggers.price","trigger is deprecated use triggers.reference","cancel_reason is deprecated","Order price adjusted"]},"success":true,"time_in":"2024-05-19T19:30:36.343170Z","time_out":"2024-05-19T19:30:36.350083Z"}"#;
+        let response: ResultResponse<AddOrderResult> = serde_json::from_str(raw).unwrap();
+
+        let deprecated = |field: &str, replacement: Option<&str>| OrderWarning::Deprecated {
+            field: field.to_string(),
+            replacement: replacement.map(String::from),
+        };
+
+        assert_eq!(
+            vec![
+                deprecated("stop_price", Some("triggers.price")),
+                deprecated("trigger", Some("triggers.reference")),
+                deprecated("cancel_reason", None),
+                OrderWarning::Other("Order price adjusted".to_string()),
+            ],
+            response.warnings()
+        );
+
+        let no_warnings = r#"{"req_id":8,"result":{"order_id":"OPS23M-VS41G-DDE5Z2"},"success":true,"time_in":"2024-05-19T19:30:36.343170Z","time_out":"2024-05-19T19:30:36.350083Z"}"#;
+        let response: ResultResponse<CancelOrderResult> =
+            serde_json::from_str(no_warnings).unwrap();
+        assert!(response.warnings().is_empty());
+    }
+
+    #[test]
+    fn test_fee_preference_round_trip() {
+        for (preference, sent, received) in [
+            (FeePreference::Base, "base", "fcib"),
+            (FeePreference::Quote, "quote", "fciq"),
+        ] {
+            assert_eq!(json!(sent), serde_json::to_value(&preference).unwrap());
+
+            let from_execution: FeePreference = serde_json::from_value(json!(received)).unwrap();
+            let from_request: FeePreference = serde_json::from_value(json!(sent)).unwrap();
+            assert_eq!(preference, from_execution);
+            assert_eq!(preference, from_request);
+        }
+    }
+
+    #[test]
+    fn test_add_order_params_conditional_stop_loss_limit() {
+        let mut params = AddOrderParams::limit(
+            BuySell::Buy,
+            "BTC/USD",
+            dec!(1.2),
+            dec!(28440),
+            Token::new("aToken".to_string()),
+        );
+        params.conditional = Some(
+            ConditionalParams::builder()
+                .order_type(OrderType::StopLossLimit)
+                .trigger_price(dec!(28410))
+                .limit_price(dec!(28400))
+                .build(),
+        );
+
+        let expected = json!({
+            "order_type": "limit",
+            "side": "buy",
+            "order_qty": 1.2,
+            "symbol": "BTC/USD",
+            "limit_price": 28440.0,
+            "conditional": {
+                "order_type": "stop-loss-limit",
+                "trigger_price": 28410.0,
+                "limit_price": 28400.0
+            },
+            "token": "aToken"
+        });
+
+        assert_eq!(expected, serde_json::to_value(&params).unwrap());
+    }
+
+    #[test]
+    fn test_add_order_params_triggers() {
+        let mut params = AddOrderParams::new(
+            OrderType::StopLoss,
+            BuySell::Sell,
+            "BTC/USD",
+            dec!(0.5),
+            Token::new("aToken".to_string()),
+        );
+        params.triggers = Some(TriggerParams {
+            price: dec!(-2.5),
+            price_type: Some(PriceType::Percent),
+            reference: Some(TriggerType::Index),
+        });
+
+        let expected = json!({
+            "order_type": "stop-loss",
+            "side": "sell",
+            "order_qty": 0.5,
+            "symbol": "BTC/USD",
+            "triggers": {"price": -2.5, "price_type": "pct", "reference": "index"},
+            "token": "aToken"
+        });
+
+        assert_eq!(expected, serde_json::to_value(&params).unwrap());
+    }
+
+    #[test]
+    fn test_add_order_params_time_in_force_expiry() {
+        let mut params = AddOrderParams::limit(
+            BuySell::Buy,
+            "BTC/USD",
+            dec!(1),
+            dec!(65000),
+            Token::new("aToken".to_string()),
+        );
+        assert!(params.validate().is_ok());
+
+        params.time_in_force = Some(TimeInForceV2::GTD);
+        assert!(matches!(
+            params.validate(),
+            Err(ClientError::InvalidRequest(_))
+        ));
+
+        params.expire_time = Some("2024-05-19T16:32:14Z".to_string());
+        assert!(params.validate().is_ok());
+
+        params.time_in_force = Some(TimeInForceV2::IOC);
+        assert!(matches!(
+            params.validate(),
+            Err(ClientError::InvalidRequest(_))
+        ));
+    }
+
+    #[test]
+    fn test_add_order_params_deadlines() {
+        let time = datetime!(2024-05-19 16:32:14 UTC);
+        let params = AddOrderParams::limit(
+            BuySell::Buy,
+            "BTC/USD",
+            dec!(1),
+            dec!(65000),
+            Token::new("aToken".to_string()),
+        )
+        .effective_at(time)
+        .and_then(|params| params.expire_at(Duration::from_secs(60)))
+        .and_then(|params| params.deadline_at(time))
+        .unwrap();
+
+        assert_eq!(
+            Some("2024-05-19T16:32:14Z"),
+            params.effective_time.as_deref()
+        );
+        assert_eq!(Some("2024-05-19T16:32:14Z"), params.deadline.as_deref());
+
+        let expiry = OffsetDateTime::parse(params.expire_time.as_deref().unwrap(), &Rfc3339);
+        assert!(expiry.unwrap() > OffsetDateTime::now_utc());
+
+        let serialized = serde_json::to_value(&params).unwrap();
+        assert_eq!(json!("2024-05-19T16:32:14Z"), serialized["deadline"]);
+        assert_eq!(json!("2024-05-19T16:32:14Z"), serialized["effective_time"]);
+    }
+
+    #[test]
+    fn test_add_order_conversions_limit() {
+        let request = AddOrderRequest::limit(
+            BuySell::Buy,
+            dec!(0.5),
+            "BTC/USD".to_string(),
+            dec!(65123.1),
+        )
+        .client_order_id("some-uuid".to_string())
+        .order_flags(OrderFlags::new(vec![
+            OrderFlag::Post,
+            OrderFlag::FeesInBase,
+        ]))
+        .time_in_force(TimeInForce::GTD)
+        .expire_time("1716134400".to_string())
+        .build();
+
+        let params =
+            AddOrderParams::try_from((request.clone(), Token::new("aToken".to_string()))).unwrap();
+
+        let expected = json!({"order_type":"limit","side":"buy","symbol":"BTC/USD","limit_price":65123.1,"time_in_force":"gtd","order_qty":0.5,"post_only":true,"expire_time":"2024-05-19T16:00:00Z","cl_ord_id":"some-uuid","fee_preference":"base","token":"aToken"});
+        assert_eq!(expected, serde_json::to_value(&params).unwrap());
+
+        assert_eq!(request, AddOrderRequest::try_from(params).unwrap());
+    }
+
+    #[test]
+    fn test_add_order_conversions_market_and_triggers() {
+        let mut market = AddOrderRequest::market(BuySell::Sell, dec!(2), "ETH/USD".to_string());
+        market.order_flags(OrderFlag::FeesInQuote.into());
+        let market = market.build();
+
+        let params =
+            AddOrderParams::try_from((market.clone(), Token::new("aToken".to_string()))).unwrap();
+        let expected = json!({"order_type":"market","side":"sell","symbol":"ETH/USD","order_qty":2.0,"fee_preference":"quote","token":"aToken"});
+        assert_eq!(expected, serde_json::to_value(&params).unwrap());
+        assert_eq!(market, AddOrderRequest::try_from(params).unwrap());
+
+        let stop_loss_limit = AddOrderRequest::builder(
+            OrderType::StopLossLimit,
+            BuySell::Sell,
+            dec!(1),
+            "BTC/USD".to_string(),
+        )
+        .price(dec!(60000))
+        .price_2(dec!(59900))
+        .trigger(TriggerType::Index)
+        .build();
+
+        let params =
+            AddOrderParams::try_from((stop_loss_limit.clone(), Token::new("aToken".to_string())))
+                .unwrap();
+        let expected = json!({"order_type":"stop-loss-limit","side":"sell","symbol":"BTC/USD","limit_price":59900.0,"triggers":{"price":60000.0,"reference":"index"},"order_qty":1.0,"token":"aToken"});
+        assert_eq!(expected, serde_json::to_value(&params).unwrap());
+        assert_eq!(stop_loss_limit, AddOrderRequest::try_from(params).unwrap());
+    }
+
+    #[test]
+    fn test_add_order_conversions_rejected() {
+        let token = || Token::new("aToken".to_string());
+
+        let mut leveraged = AddOrderRequest::market(BuySell::Buy, dec!(1), "BTC/USD".into());
+        leveraged.leverage(2);
+        assert!(AddOrderParams::try_from((leveraged.build(), token())).is_err());
+
+        let trailing = AddOrderRequest::builder(
+            OrderType::TrailingStop,
+            BuySell::Sell,
+            dec!(1),
+            "BTC/USD".to_string(),
+        )
+        .price(dec!(100))
+        .build();
+        assert!(AddOrderParams::try_from((trailing, token())).is_err());
+
+        let mut offset = AddOrderRequest::market(BuySell::Buy, dec!(1), "BTC/USD".into());
+        offset.start_time("+60".to_string());
+        assert!(AddOrderParams::try_from((offset.build(), token())).is_err());
+
+        let mut relative =
+            AddOrderParams::limit(BuySell::Buy, "BTC/USD", dec!(1), dec!(-5), token());
+        relative.limit_price_type = Some(PriceType::Percent);
+        assert!(AddOrderRequest::try_from(relative).is_err());
+    }
+
+    #[test]
+    fn test_add_order_params_limit() {
+        let params = AddOrderParams::limit(
+            BuySell::Buy,
+            "BTC/USD",
+            dec!(0.5),
+            dec!(65123.1),
+            Token::new("aToken".to_string()),
+        );
+
+        let expected = json!({"order_type":"limit","side":"buy","symbol":"BTC/USD","limit_price":65123.1,"order_qty":0.5,"token":"aToken"});
+
+        assert_eq!(expected, serde_json::to_value(&params).unwrap());
+    }
+
+    #[test]
+    fn test_add_order_params_market() {
+        let mut params = AddOrderParams::market(
+            BuySell::Sell,
+            "ETH/USD",
+            dec!(1.25),
+            Token::new("aToken".to_string()),
+        );
+        params.client_order_id = Some("my-order".to_string());
+
+        let expected = json!({"order_type":"market","side":"sell","symbol":"ETH/USD","order_qty":1.25,"cl_ord_id":"my-order","token":"aToken"});
+
+        assert_eq!(expected, serde_json::to_value(&params).unwrap());
+    }
+}
@@ -5,10 +5,11 @@ use crate::wss::{
     BookSubscriptionResponse, OhlcSubscriptionResponse, TickerSubscriptionResponse,
     TradeSubscriptionResponse,
 };
-use crate::wss::{ConditionalParams, FeePreference, PriceType};
+use crate::wss::{ConditionalParams, FeePreference, Message, PriceType, Response};
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 use serde_with::skip_serializing_none;
+use std::collections::HashMap;
 
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
@@ -19,9 +20,9 @@ pub enum ExecutionResponseType {
 
 #[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
 pub enum MakerTaker {
-    #[serde(rename = "m")]
+    #[serde(rename = "m", alias = "maker")]
     Maker,
-    #[serde(rename = "t")]
+    #[serde(rename = "t", alias = "taker")]
     Taker,
 }
 
@@ -47,7 +48,10 @@ pub enum LedgerEntryTypeV2 {
     CreatorFee,
 }
 
-#[derive(Debug, Deserialize, Clone, PartialEq, Eq, Copy)]
+/// Sub-type of a ledger entry, e.g. for transfers between spot, futures and staking wallets.
+///
+/// Sub-types not listed here are kept as `Other`, so new values don't fail the balances channel.
+#[derive(Debug, Deserialize, Clone, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
 pub enum LedgerEntrySubType {
     SpotFromFutures,
@@ -56,6 +60,8 @@ pub enum LedgerEntrySubType {
     SpotFromStaking,
     StakingToSpot,
     SpotToStaking,
+    #[serde(untagged)]
+    Other(String),
 }
 
 #[derive(Debug, Deserialize, Clone, PartialEq, Eq, Copy)]
@@ -107,6 +113,7 @@ pub enum ExecutionType {
     Amended,
     Restated,
     Status,
+    TradeCorrection,
 }
 
 #[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
@@ -145,6 +152,18 @@ impl ExecutionSubscription {
             rate_counter: None,
         }
     }
+
+    /// Create the message to unsubscribe from this subscription, omitting snapshot options.
+    pub fn unsubscription(&self, req_id: i64) -> Message<Self> {
+        let params = ExecutionSubscription {
+            snapshot_trades: None,
+            snapshot_orders: None,
+            rate_counter: None,
+            ..self.clone()
+        };
+
+        Message::new_unsubscription(params, req_id)
+    }
 }
 
 #[derive(Debug, Deserialize, PartialEq)]
@@ -279,6 +298,51 @@ pub struct ExecutionResult {
     pub triggers: Option<TriggerDescription>,
 }
 
+impl ExecutionResult {
+    /// Total the fees of this execution by asset, returning an empty map if there are no fees.
+    pub fn fees_by_asset(&self) -> HashMap<String, Decimal> {
+        let mut totals: HashMap<String, Decimal> = HashMap::new();
+
+        for fee in self.fees.iter().flatten() {
+            *totals.entry(fee.asset.clone()).or_default() += fee.quantity;
+        }
+
+        totals
+    }
+}
+
+impl Response<Vec<ExecutionResult>> {
+    /// Find the first execution with the given `client_order_id`, if any.
+    pub fn find_by_client_order_id(&self, client_order_id: &str) -> Option<&ExecutionResult> {
+        self.data
+            .iter()
+            .find(|execution| execution.client_order_id.as_deref() == Some(client_order_id))
+    }
+
+    /// Find all executions with the given `order_user_ref`, which Kraken does not require to be
+    /// unique.
+    pub fn find_by_order_user_ref(&self, order_user_ref: i64) -> Vec<&ExecutionResult> {
+        self.data
+            .iter()
+            .filter(|execution| execution.order_user_ref == Some(order_user_ref))
+            .collect()
+    }
+
+    /// Group executions by `order_id`, preserving the order they were received in for each order.
+    pub fn group_by_order_id(&self) -> HashMap<String, Vec<&ExecutionResult>> {
+        let mut groups: HashMap<String, Vec<&ExecutionResult>> = HashMap::new();
+
+        for execution in &self.data {
+            groups
+                .entry(execution.order_id.clone())
+                .or_default()
+                .push(execution);
+        }
+
+        groups
+    }
+}
+
 #[skip_serializing_none]
 #[derive(Debug, Serialize, Clone)]
 pub struct BalancesSubscription {
@@ -295,6 +359,16 @@ impl BalancesSubscription {
             snapshot: None,
         }
     }
+
+    /// Create the message to unsubscribe from this subscription, omitting snapshot options.
+    pub fn unsubscription(&self, req_id: i64) -> Message<Self> {
+        let params = BalancesSubscription {
+            snapshot: None,
+            ..self.clone()
+        };
+
+        Message::new_unsubscription(params, req_id)
+    }
 }
 
 #[derive(Debug, Deserialize, PartialEq)]
@@ -340,8 +414,35 @@ pub struct LedgerUpdate {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::wss::{ChannelMessage, WssMessage};
     use rust_decimal_macros::dec;
 
+    #[test]
+    fn test_execution_lookups() {
+        let snapshot = r#"[
+            {"order_id":"OA","exec_type":"new","cl_ord_id":"first","order_userref":7,"order_status":"new","timestamp":"2024-05-18T05:41:33.480251Z"},
+            {"order_id":"OB","exec_type":"new","cl_ord_id":"second","order_userref":7,"order_status":"new","timestamp":"2024-05-18T05:41:34.480251Z"},
+            {"order_id":"OA","exec_type":"filled","order_status":"filled","timestamp":"2024-05-18T05:41:35.480251Z"}
+        ]"#;
+        let response = Response {
+            data: serde_json::from_str::<Vec<ExecutionResult>>(snapshot).unwrap(),
+            sequence: 1,
+        };
+
+        let second = response.find_by_client_order_id("second").unwrap();
+        assert_eq!("OB", second.order_id);
+        assert!(response.find_by_client_order_id("missing").is_none());
+
+        assert_eq!(2, response.find_by_order_user_ref(7).len());
+        assert!(response.find_by_order_user_ref(8).is_empty());
+
+        let groups = response.group_by_order_id();
+        assert_eq!(2, groups.len());
+        assert_eq!(ExecutionType::New, groups["OA"][0].execution_type);
+        assert_eq!(ExecutionType::Filled, groups["OA"][1].execution_type);
+        assert_eq!(1, groups["OB"].len());
+    }
+
     #[test]
     fn test_deserializing_execution_trade() {
         let message = r#"{"order_id":"O7IBL5-O2V6X-EEXY4U","exec_id":"TJE7HC-DKBTI-5BFVKE","exec_type":"trade","ext_ord_id":"some-uuid","ext_exec_id":"another-uuid","trade_id":365573,"symbol":"KAR/USD","side":"buy","last_qty":105.02014889,"last_price":0.121,"liquidity_ind":"t","cost":12.70744,"order_status":"filled","order_type":"limit","timestamp":"2024-05-18T05:41:33.480251Z","fee_usd_equiv":0.05083,"fees":[{"asset":"USD","qty":0.05083}]}"#;
@@ -399,6 +500,89 @@ mod tests {
         assert_eq!(expected, parsed);
     }
 
+    #[test]
+    fn test_deserializing_execution_position_status() {
+        let snapshot = r#"{"channel":"executions","type":"snapshot","data":[
+            {"order_id":"OK4GJX-KSTLS-7DZZO5","symbol":"BTC/USD","order_qty":0.001,"cum_cost":60.3,"time_in_force":"GTC","exec_type":"filled","side":"buy","order_type":"market","order_userref":0,"avg_price":60300.0,"cum_qty":0.001,"margin":true,"position_status":"opened","order_status":"filled","timestamp":"2024-05-18T05:41:33.480251Z"},
+            {"order_id":"OQCLML-BW3P3-BUCMWZ","symbol":"BTC/USD","order_qty":0.001,"exec_type":"new","side":"sell","order_type":"limit","limit_price":61000.0,"margin":true,"position_status":"closing","order_status":"new","timestamp":"2024-05-18T05:42:33.480251Z"},
+            {"order_id":"OMMDB2-FSB6Z-DNVLRH","symbol":"BTC/USD","order_qty":0.001,"exec_type":"filled","side":"sell","order_type":"limit","limit_price":61000.0,"margin":true,"position_status":"closed","order_status":"filled","timestamp":"2024-05-18T05:43:33.480251Z"}
+        ],"sequence":1}"#;
+
+        let message: WssMessage = serde_json::from_str(snapshot).unwrap();
+        let WssMessage::Channel(ChannelMessage::Execution(response)) = message else {
+            panic!("expected an executions message");
+        };
+
+        let statuses: Vec<Option<PositionStatusV2>> = response
+            .data
+            .iter()
+            .map(|execution| execution.position_status)
+            .collect();
+
+        assert_eq!(
+            vec![
+                Some(PositionStatusV2::Opened),
+                Some(PositionStatusV2::Closing),
+                Some(PositionStatusV2::Closed)
+            ],
+            statuses
+        );
+    }
+
+    #[test]
+    fn test_deserializing_execution_types() {
+        let execution = |exec_type: &str| {
+            format!(
+                r#"{{"order_id":"OK4GJX-KSTLS-7DZZO5","exec_type":"{exec_type}","order_status":"filled","timestamp":"2024-05-18T05:41:33.480251Z"}}"#
+            )
+        };
+
+        let expected = [
+            ("expired", ExecutionType::Expired),
+            ("filled", ExecutionType::Filled),
+            ("restated", ExecutionType::Restated),
+            ("status", ExecutionType::Status),
+            ("trade_correction", ExecutionType::TradeCorrection),
+        ];
+
+        for (raw, exec_type) in expected {
+            let parsed: ExecutionResult = serde_json::from_str(&execution(raw)).unwrap();
+            assert_eq!(exec_type, parsed.execution_type);
+        }
+    }
+
+    #[test]
+    fn test_deserializing_liquidity_indicator() {
+        for (raw, expected) in [
+            (r#""m""#, MakerTaker::Maker),
+            (r#""maker""#, MakerTaker::Maker),
+            (r#""t""#, MakerTaker::Taker),
+            (r#""taker""#, MakerTaker::Taker),
+        ] {
+            assert_eq!(expected, serde_json::from_str::<MakerTaker>(raw).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_fees_by_asset() {
+        let message = r#"{"order_id":"O7IBL5-O2V6X-EEXY4U","exec_type":"trade","order_status":"filled","timestamp":"2024-05-18T05:41:33.480251Z","fees":[{"asset":"USD","qty":0.04051},{"asset":"KAR","qty":0.5},{"asset":"USD","qty":0.01}]}"#;
+        let parsed: ExecutionResult = serde_json::from_str(message).unwrap();
+
+        let fees = parsed.fees_by_asset();
+
+        assert_eq!(2, fees.len());
+        assert_eq!(Some(&dec!(0.05051)), fees.get("USD"));
+        assert_eq!(Some(&dec!(0.5)), fees.get("KAR"));
+    }
+
+    #[test]
+    fn test_fees_by_asset_no_fees() {
+        let message = r#"{"timestamp":"2024-05-18T11:00:37.240691Z","order_status":"new","exec_type":"new","order_id":"OLADEP-E5D5S-IKEHMF"}"#;
+        let parsed: ExecutionResult = serde_json::from_str(message).unwrap();
+
+        assert!(parsed.fees_by_asset().is_empty());
+    }
+
     #[test]
     fn test_deserializing_execution_new_update() {
         let message = r#"{"timestamp":"2024-05-18T11:00:37.240691Z","order_status":"new","exec_type":"new","order_userref":0,"order_id":"OLADEP-E5D5S-IKEHMF"}"#;
@@ -452,4 +636,80 @@ mod tests {
 
         assert_eq!(expected, parsed);
     }
+
+    #[test]
+    fn test_deserializing_ledger_sub_types() {
+        let ledger = |sub_type: &str| {
+            format!(
+                r#"{{"ledger_id":"9K6IR4-X9PQJ-OMBG73","ref_id":"WLINKJ-1TZZW-M3HCOY","timestamp":"2024-05-12T12:11:57.525134Z","type":"transfer","sub_type":"{sub_type}","asset":"DOT","asset_class":"currency","category":"staking-rewards","wallet_type":"earn","wallet_id":"flex","amount":1.5,"fee":0,"balance":3.0}}"#
+            )
+        };
+
+        let known: LedgerUpdate = serde_json::from_str(&ledger("spottostaking")).unwrap();
+        assert_eq!(Some(LedgerEntrySubType::SpotToStaking), known.sub_type);
+        assert_eq!(LedgerEntryTypeV2::Transfer, known.ledger_type);
+        assert_eq!(LedgerCategory::StakingRewards, known.category);
+
+        let unknown: LedgerUpdate = serde_json::from_str(&ledger("autoallocation")).unwrap();
+        assert_eq!(
+            Some(LedgerEntrySubType::Other("autoallocation".to_string())),
+            unknown.sub_type
+        );
+    }
+
+    #[test]
+    fn test_deserializing_execution_fee_fields() {
+        let message = |fee_ccy_pref: &str, fee_usd_equiv: &str| {
+            format!(
+                r#"{{"order_id":"OLADEP-E5D5S-IKEHMF","exec_type":"pending_new","order_status":"pending_new","timestamp":"2024-05-18T11:00:37.240691Z","fee_ccy_pref":"{fee_ccy_pref}","fee_usd_equiv":{fee_usd_equiv}}}"#
+            )
+        };
+
+        let base: ExecutionResult = serde_json::from_str(&message("fcib", "0")).unwrap();
+        assert_eq!(Some(FeePreference::Base), base.fee_preference);
+        assert_eq!(Some(dec!(0)), base.fee_usd_equivalent);
+
+        let quote: ExecutionResult = serde_json::from_str(&message("fciq", "0.000000")).unwrap();
+        assert_eq!(Some(FeePreference::Quote), quote.fee_preference);
+        assert_eq!(Some(dec!(0)), quote.fee_usd_equivalent);
+
+        let integer: ExecutionResult = serde_json::from_str(&message("fciq", "12")).unwrap();
+        assert_eq!(Some(dec!(12)), integer.fee_usd_equivalent);
+    }
+
+    #[test]
+    fn test_deserializing_execution_number_forms() {
+        for (value, expected) in [
+            ("0", dec!(0)),
+            ("0.0", dec!(0)),
+            (r#""0.00""#, dec!(0)),
+            ("1e-8", dec!(0.00000001)),
+            ("1.5e-7", dec!(0.00000015)),
+            (r#""1.5e-7""#, dec!(0.00000015)),
+        ] {
+            let message = format!(
+                r#"{{"order_id":"OLADEP-E5D5S-IKEHMF","exec_type":"trade","order_status":"filled","timestamp":"2024-05-18T11:00:37.240691Z","last_qty":{value},"cum_cost":{value},"fee_usd_equiv":{value}}}"#
+            );
+            let execution: ExecutionResult = serde_json::from_str(&message).unwrap();
+
+            assert_eq!(Some(expected), execution.last_quantity);
+            assert_eq!(Some(expected), execution.cumulative_cost);
+            assert_eq!(Some(expected), execution.fee_usd_equivalent);
+        }
+    }
+
+    #[test]
+    fn test_execution_unsubscription() {
+        let mut subscription = ExecutionSubscription::new(Token::new("someToken"));
+        subscription.snapshot_trades = Some(true);
+        subscription.snapshot_orders = Some(true);
+        subscription.rate_counter = Some(true);
+
+        let expected = serde_json::json!({"method":"unsubscribe","params":{"channel":"executions","token":"someToken"},"req_id":5});
+
+        assert_eq!(
+            expected,
+            serde_json::to_value(subscription.unsubscription(5)).unwrap()
+        );
+    }
 }
@@ -3,9 +3,26 @@
 //! Connect to public or private messages using [KrakenWSSClient], and send/receive messages using
 //! [KrakenMessageStream].
 //!
+mod connection_pool;
 pub mod errors;
+mod instrument_cache;
 mod kraken_wss_client;
 mod messages;
+mod ohlc_aggregator;
+mod order_book;
+#[cfg(feature = "raw-value")]
+mod raw;
+mod system_status;
 
-pub use kraken_wss_client::{KrakenMessageStream, KrakenWSSClient, WS_KRAKEN, WS_KRAKEN_AUTH};
+pub use connection_pool::{PooledStream, WssConnectionPool, DEFAULT_RECONNECT_DELAY};
+pub use instrument_cache::InstrumentCache;
+pub use kraken_wss_client::{
+    ChannelStream, KrakenMessageStream, KrakenWSSClient, SubscriptionSummary, WebSocketConfig,
+    PRIVATE_CHANNELS, PRIVATE_METHODS, WS_KRAKEN, WS_KRAKEN_AUTH,
+};
 pub use messages::*;
+pub use ohlc_aggregator::OhlcAggregator;
+pub use order_book::{LocalOrderBook, SyncedBooks, ThrottledBooks};
+#[cfg(feature = "raw-value")]
+pub use raw::RawMessage;
+pub use system_status::SystemStatusTracker;
@@ -0,0 +1,316 @@
+//! Candles built from the public trade channel
+use crate::wss::errors::WSSError;
+use crate::wss::{ChannelStream, MarketDataResponse, Ohlc, Trade};
+use futures_util::{stream, StreamExt};
+use rust_decimal::Decimal;
+use std::collections::HashMap;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+use tokio_stream::Stream;
+use tracing::warn;
+
+/// Builds [Ohlc] bars of `interval` minutes per symbol from public trades.
+///
+/// Intervals are aligned to UTC, so 5-minute bars begin at :00, :05, etc. A bar is emitted once a
+/// trade arrives for a later interval, along with a bar for each interval that had no trades in
+/// between, which carries forward the previous close with no volume. Trades older than the current
+/// bar of their symbol are ignored, since its earlier bars have already been emitted.
+#[derive(Debug, Clone)]
+pub struct OhlcAggregator {
+    interval: i32,
+    bars: HashMap<String, Bar>,
+}
+
+#[derive(Debug, Clone)]
+struct Bar {
+    begin: i64,
+    open: Decimal,
+    high: Decimal,
+    low: Decimal,
+    close: Decimal,
+    volume: Decimal,
+    notional: Decimal,
+    trades: i64,
+}
+
+impl Bar {
+    fn new(begin: i64, price: Decimal) -> Self {
+        Bar {
+            begin,
+            open: price,
+            high: price,
+            low: price,
+            close: price,
+            volume: Decimal::ZERO,
+            notional: Decimal::ZERO,
+            trades: 0,
+        }
+    }
+
+    fn add(&mut self, trade: &Trade) {
+        self.high = self.high.max(trade.price);
+        self.low = self.low.min(trade.price);
+        self.close = trade.price;
+        self.volume += trade.quantity;
+        self.notional += trade.price * trade.quantity;
+        self.trades += 1;
+    }
+
+    fn to_ohlc(&self, symbol: &str, interval: i32) -> Ohlc {
+        let vwap = if self.volume.is_zero() {
+            self.close
+        } else {
+            self.notional / self.volume
+        };
+
+        Ohlc {
+            symbol: symbol.to_string(),
+            open: self.open,
+            high: self.high,
+            low: self.low,
+            close: self.close,
+            vwap,
+            trades: self.trades,
+            volume: self.volume,
+            interval_begin: format_begin(self.begin),
+            interval,
+        }
+    }
+}
+
+impl OhlcAggregator {
+    /// Create an aggregator of `interval`-minute bars, e.g. 1, 5 or 60.
+    ///
+    /// # Panics
+    /// If `interval` is not positive.
+    pub fn new(interval: i32) -> Self {
+        assert!(interval > 0, "interval must be positive!");
+
+        OhlcAggregator {
+            interval,
+            bars: HashMap::new(),
+        }
+    }
+
+    pub fn interval(&self) -> i32 {
+        self.interval
+    }
+
+    /// Add a trade, returning any bars of its symbol completed by it, oldest first.
+    pub fn update(&mut self, trade: &Trade) -> Vec<Ohlc> {
+        let Some(begin) = self.interval_begin(&trade.timestamp) else {
+            warn!("Ignoring trade with invalid timestamp: {}", trade.timestamp);
+            return Vec::new();
+        };
+
+        let interval = self.interval;
+        let step = interval as i64 * 60;
+        let mut completed = Vec::new();
+
+        let bar = self
+            .bars
+            .entry(trade.symbol.clone())
+            .or_insert_with(|| Bar::new(begin, trade.price));
+
+        if begin < bar.begin {
+            return completed;
+        }
+
+        while bar.begin < begin {
+            completed.push(bar.to_ohlc(&trade.symbol, interval));
+            *bar = Bar::new(bar.begin + step, bar.close);
+        }
+
+        if bar.trades == 0 {
+            bar.open = trade.price;
+            bar.high = trade.price;
+            bar.low = trade.price;
+        }
+
+        bar.add(trade);
+        completed
+    }
+
+    /// The bar in progress for `symbol`, if any trades have been seen for it.
+    pub fn current(&self, symbol: &str) -> Option<Ohlc> {
+        self.bars
+            .get(symbol)
+            .map(|bar| bar.to_ohlc(symbol, self.interval))
+    }
+
+    /// Remove and return the bars in progress for all symbols, e.g. when shutting down.
+    pub fn flush(&mut self) -> Vec<Ohlc> {
+        let interval = self.interval;
+        self.bars
+            .drain()
+            .map(|(symbol, bar)| bar.to_ohlc(&symbol, interval))
+            .collect()
+    }
+
+    fn interval_begin(&self, timestamp: &str) -> Option<i64> {
+        let step = self.interval as i64 * 60;
+        let seconds = OffsetDateTime::parse(timestamp, &Rfc3339)
+            .ok()?
+            .unix_timestamp();
+
+        Some(seconds.div_euclid(step) * step)
+    }
+}
+
+fn format_begin(begin: i64) -> String {
+    OffsetDateTime::from_unix_timestamp(begin)
+        .ok()
+        .and_then(|begin| begin.format(&Rfc3339).ok())
+        .unwrap_or_default()
+}
+
+impl ChannelStream<MarketDataResponse<Vec<Trade>>> {
+    /// Aggregate trades into `interval`-minute bars with an [OhlcAggregator], yielding each bar
+    /// once it's complete.
+    ///
+    /// Errors are passed through immediately. Bars in progress when the stream ends are dropped.
+    pub fn aggregate_ohlc(self, interval: i32) -> impl Stream<Item = Result<Ohlc, WSSError>> {
+        let mut aggregator = OhlcAggregator::new(interval);
+
+        self.flat_map(move |message| {
+            let bars: Vec<Result<Ohlc, WSSError>> = match message {
+                Ok(response) => response
+                    .data
+                    .iter()
+                    .flat_map(|trade| aggregator.update(trade))
+                    .map(Ok)
+                    .collect(),
+                Err(err) => vec![Err(err)],
+            };
+
+            stream::iter(bars)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::response_types::BuySell;
+    use crate::test_data::WssTestState;
+    use crate::wss::{ChannelMessage, MarketLimit, WssMessage};
+    use rust_decimal_macros::dec;
+    use std::time::Duration;
+    use tokio::sync::mpsc;
+    use tokio::time::timeout;
+    use tokio_tungstenite::tungstenite::Message as TungsteniteMessage;
+    use ws_mock::ws_mock_server::WsMock;
+
+    fn trade(timestamp: &str, price: Decimal, quantity: Decimal) -> Trade {
+        Trade {
+            symbol: "BTC/USD".to_string(),
+            side: BuySell::Buy,
+            quantity,
+            price,
+            order_type: MarketLimit::Market,
+            trade_id: 1,
+            timestamp: timestamp.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_ohlc_aggregator() {
+        let mut aggregator = OhlcAggregator::new(5);
+
+        assert!(aggregator
+            .update(&trade("2024-05-19T16:31:14.1Z", dec!(100), dec!(1)))
+            .is_empty());
+        assert!(aggregator
+            .update(&trade("2024-05-19T16:33:00Z", dec!(104), dec!(1)))
+            .is_empty());
+        assert!(aggregator
+            .update(&trade("2024-05-19T16:34:59.9Z", dec!(98), dec!(2)))
+            .is_empty());
+
+        let completed = aggregator.update(&trade("2024-05-19T16:46:01Z", dec!(99), dec!(1)));
+
+        let expected = Ohlc {
+            symbol: "BTC/USD".to_string(),
+            open: dec!(100),
+            high: dec!(104),
+            low: dec!(98),
+            close: dec!(98),
+            vwap: dec!(100),
+            trades: 3,
+            volume: dec!(4),
+            interval_begin: "2024-05-19T16:30:00Z".to_string(),
+            interval: 5,
+        };
+        let empty = Ohlc {
+            symbol: "BTC/USD".to_string(),
+            open: dec!(98),
+            high: dec!(98),
+            low: dec!(98),
+            close: dec!(98),
+            vwap: dec!(98),
+            trades: 0,
+            volume: dec!(0),
+            interval_begin: "2024-05-19T16:35:00Z".to_string(),
+            interval: 5,
+        };
+
+        assert_eq!(3, completed.len());
+        assert_eq!(expected, completed[0]);
+        assert_eq!(empty, completed[1]);
+        assert_eq!("2024-05-19T16:40:00Z", completed[2].interval_begin);
+
+        let current = aggregator.current("BTC/USD").unwrap();
+        assert_eq!("2024-05-19T16:45:00Z", current.interval_begin);
+        assert_eq!(dec!(99), current.open);
+        assert_eq!(1, current.trades);
+
+        assert!(aggregator
+            .update(&trade("2024-05-19T16:31:00Z", dec!(1), dec!(1)))
+            .is_empty());
+        assert_eq!(1, aggregator.flush().len());
+        assert!(aggregator.current("BTC/USD").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_aggregate_ohlc_stream() {
+        let mut test_state = WssTestState::new().await;
+        let (sender, receiver) = mpsc::channel::<TungsteniteMessage>(8);
+
+        WsMock::new()
+            .forward_from_channel(receiver)
+            .mount(&test_state.mock_server)
+            .await;
+
+        let stream = test_state.ws_client.connect::<WssMessage>().await.unwrap();
+        let mut bars = Box::pin(
+            stream
+                .into_channel(|message| match message {
+                    WssMessage::Channel(ChannelMessage::Trade(response)) => Some(response),
+                    _ => None,
+                })
+                .aggregate_ohlc(1),
+        );
+
+        for (price, timestamp) in [
+            ("100.1", "2024-05-19T16:31:14.1Z"),
+            ("100.3", "2024-05-19T16:31:50Z"),
+            ("100.2", "2024-05-19T16:32:01Z"),
+        ] {
+            let trade = format!(
+                r#"{{"channel":"trade","type":"update","data":[{{"symbol":"BTC/USD","side":"buy","price":{price},"qty":1,"ord_type":"market","trade_id":1,"timestamp":"{timestamp}"}}]}}"#
+            );
+            sender.send(TungsteniteMessage::Text(trade)).await.unwrap();
+        }
+
+        let bar = timeout(Duration::from_secs(1), bars.next())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+
+        assert_eq!("2024-05-19T16:31:00Z", bar.interval_begin);
+        assert_eq!(dec!(100.3), bar.high);
+        assert_eq!(dec!(100.2), bar.vwap);
+        assert_eq!(2, bar.trades);
+    }
+}
@@ -0,0 +1,619 @@
+//! Local L2 order books maintained from the book channel
+use crate::clients::errors::ClientError;
+use crate::clients::kraken_client::KrakenClient;
+use crate::request_types::OrderbookRequest;
+use crate::response_types::Orderbook as RestOrderbook;
+use crate::wss::errors::WSSError;
+use crate::wss::{
+    BidAsk, BookSubscription, ChannelStream, KrakenWSSClient, OrderbookUpdate, SingleResponse, L2,
+};
+use rust_decimal::Decimal;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+use tokio::time::{interval_at, Instant, Interval, MissedTickBehavior};
+use tokio_stream::Stream;
+
+/// An L2 order book for a single symbol, built by applying book snapshots and updates in order.
+///
+/// Levels with a quantity of zero are removed, and each side is truncated to `depth` levels after
+/// every update, matching the book Kraken maintains for the subscribed depth. `depth` should be the
+/// subscribed depth, since Kraken only sends the level that replaces a deleted one if it falls
+/// within that depth: a shallower book empties as its levels are deleted.
+///
+/// The checksum Kraken sends with each message is stored as-is, and not verified against the
+/// book. Verifying it requires formatting each level with the instrument's price and quantity
+/// precision, e.g. from an [InstrumentCache](crate::wss::InstrumentCache).
+#[derive(Debug, Clone, PartialEq)]
+pub struct LocalOrderBook {
+    symbol: String,
+    depth: usize,
+    bids: BTreeMap<Decimal, Decimal>,
+    asks: BTreeMap<Decimal, Decimal>,
+    checksum: u32,
+    timestamp: Option<String>,
+}
+
+impl LocalOrderBook {
+    pub fn new(symbol: impl ToString, depth: usize) -> Self {
+        LocalOrderBook {
+            symbol: symbol.to_string(),
+            depth,
+            bids: BTreeMap::new(),
+            asks: BTreeMap::new(),
+            checksum: 0,
+            timestamp: None,
+        }
+    }
+
+    /// Create a book from a REST snapshot, e.g. from [KrakenClient::get_orderbook].
+    pub fn from_rest(symbol: impl ToString, depth: usize, snapshot: &RestOrderbook) -> Self {
+        let mut book = LocalOrderBook::new(symbol, depth);
+
+        for (side, levels) in [
+            (&mut book.bids, &snapshot.bids),
+            (&mut book.asks, &snapshot.asks),
+        ] {
+            for level in levels {
+                if !level.volume.is_zero() {
+                    side.insert(level.price, level.volume);
+                }
+            }
+        }
+
+        book.truncate();
+        book
+    }
+
+    /// Apply a snapshot, replacing the whole book, or an update, changing only the given levels,
+    /// returning whether any level of the book changed.
+    ///
+    /// Messages for other symbols are ignored. A snapshot always counts as a change, while an
+    /// update only does if it removed a level, or added or changed one that's within `depth`.
+    pub fn apply(&mut self, book: &L2) -> bool {
+        match book {
+            L2::Orderbook(snapshot) if snapshot.symbol == self.symbol => {
+                self.bids.clear();
+                self.asks.clear();
+                Self::apply_levels(&mut self.bids, &snapshot.bids);
+                Self::apply_levels(&mut self.asks, &snapshot.asks);
+                self.checksum = snapshot.checksum;
+                self.timestamp = None;
+                self.truncate();
+
+                true
+            }
+            L2::Update(update) if update.symbol == self.symbol => {
+                let (bids_removed, bids_set) = Self::apply_levels(&mut self.bids, &update.bids);
+                let (asks_removed, asks_set) = Self::apply_levels(&mut self.asks, &update.asks);
+                self.checksum = update.checksum;
+                self.timestamp = Some(update.timestamp.clone());
+                self.truncate();
+
+                bids_removed
+                    || asks_removed
+                    || bids_set.iter().any(|price| self.bids.contains_key(price))
+                    || asks_set.iter().any(|price| self.asks.contains_key(price))
+            }
+            _ => false,
+        }
+    }
+
+    pub fn symbol(&self) -> &str {
+        &self.symbol
+    }
+
+    /// The checksum Kraken sent with the most recently applied message, which isn't verified.
+    pub fn checksum(&self) -> u32 {
+        self.checksum
+    }
+
+    /// The timestamp of the most recently applied update, or None if only a snapshot was applied.
+    pub fn timestamp(&self) -> Option<&str> {
+        self.timestamp.as_deref()
+    }
+
+    /// Bids from the highest price down.
+    pub fn bids(&self) -> Vec<BidAsk> {
+        self.bids.iter().rev().map(Self::level).collect()
+    }
+
+    /// Asks from the lowest price up.
+    pub fn asks(&self) -> Vec<BidAsk> {
+        self.asks.iter().map(Self::level).collect()
+    }
+
+    /// The highest bid, or None if there are no bids.
+    pub fn best_bid(&self) -> Option<BidAsk> {
+        self.bids.iter().next_back().map(Self::level)
+    }
+
+    /// The lowest ask, or None if there are no asks.
+    pub fn best_ask(&self) -> Option<BidAsk> {
+        self.asks.iter().next().map(Self::level)
+    }
+
+    fn level((price, quantity): (&Decimal, &Decimal)) -> BidAsk {
+        BidAsk {
+            price: *price,
+            quantity: *quantity,
+        }
+    }
+
+    /// Apply levels to one side, returning whether any existing level was removed, and the prices
+    /// of levels that were added or given a new quantity.
+    fn apply_levels(
+        side: &mut BTreeMap<Decimal, Decimal>,
+        levels: &[BidAsk],
+    ) -> (bool, Vec<Decimal>) {
+        let mut removed = false;
+        let mut set = Vec::new();
+
+        for level in levels {
+            if level.quantity.is_zero() {
+                removed |= side.remove(&level.price).is_some();
+            } else if side.insert(level.price, level.quantity) != Some(level.quantity) {
+                set.push(level.price);
+            }
+        }
+
+        (removed, set)
+    }
+
+    fn truncate(&mut self) {
+        while self.bids.len() > self.depth {
+            self.bids.pop_first();
+        }
+
+        while self.asks.len() > self.depth {
+            self.asks.pop_last();
+        }
+    }
+}
+
+impl ChannelStream<SingleResponse<L2>> {
+    /// Maintain a [LocalOrderBook] of `depth` levels per symbol, yielding a copy of each book
+    /// changed since the last tick once every `period`.
+    ///
+    /// Every message is applied as it arrives, so books stay consistent with Kraken's; only
+    /// emission is throttled, with intermediate states dropped. Errors are passed through
+    /// immediately, and changed books are flushed once more when the stream ends.
+    pub fn throttle(self, depth: usize, period: Duration) -> ThrottledBooks {
+        let mut ticks = interval_at(Instant::now() + period, period);
+        ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        ThrottledBooks {
+            stream: self,
+            depth,
+            ticks,
+            books: HashMap::new(),
+            changed: Vec::new(),
+            pending: VecDeque::new(),
+            ended: false,
+        }
+    }
+}
+
+/// Stream of coalesced order books, see [ChannelStream::throttle].
+pub struct ThrottledBooks {
+    stream: ChannelStream<SingleResponse<L2>>,
+    depth: usize,
+    ticks: Interval,
+    books: HashMap<String, LocalOrderBook>,
+    changed: Vec<String>,
+    pending: VecDeque<LocalOrderBook>,
+    ended: bool,
+}
+
+impl ThrottledBooks {
+    /// The current book for `symbol`, including any changes not yet emitted.
+    pub fn book(&self, symbol: &str) -> Option<&LocalOrderBook> {
+        self.books.get(symbol)
+    }
+
+    fn apply(&mut self, book: L2) {
+        let symbol = match &book {
+            L2::Orderbook(snapshot) => &snapshot.symbol,
+            L2::Update(update) => &update.symbol,
+        };
+
+        let changed = self
+            .books
+            .entry(symbol.clone())
+            .or_insert_with(|| LocalOrderBook::new(symbol, self.depth))
+            .apply(&book);
+
+        if changed && !self.changed.contains(symbol) {
+            self.changed.push(symbol.clone());
+        }
+    }
+
+    fn flush(&mut self) {
+        for symbol in self.changed.drain(..) {
+            if let Some(book) = self.books.get(&symbol) {
+                self.pending.push_back(book.clone());
+            }
+        }
+    }
+}
+
+impl Unpin for ThrottledBooks {}
+
+impl Stream for ThrottledBooks {
+    type Item = Result<LocalOrderBook, WSSError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        while !self.ended {
+            match Pin::new(&mut self.stream).poll_next(cx) {
+                Poll::Ready(Some(Ok(response))) => self.apply(response.data),
+                Poll::Ready(Some(Err(err))) => return Poll::Ready(Some(Err(err))),
+                Poll::Ready(None) => {
+                    self.ended = true;
+                    self.flush();
+                }
+                Poll::Pending => break,
+            }
+        }
+
+        if self.pending.is_empty() && !self.ended && self.ticks.poll_tick(cx).is_ready() {
+            self.flush();
+        }
+
+        match self.pending.pop_front() {
+            Some(book) => Poll::Ready(Some(Ok(book))),
+            None if self.ended => Poll::Ready(None),
+            None => Poll::Pending,
+        }
+    }
+}
+
+impl KrakenWSSClient {
+    /// Subscribe to the L2 book without a websocket snapshot, and start each symbol's book from a
+    /// REST snapshot fetched with `client` instead, applying the websocket updates that follow it.
+    ///
+    /// Snapshots are fetched after the subscription is acknowledged, so updates sent in the
+    /// meantime wait on the connection rather than being missed. Since REST levels only have
+    /// second resolution, updates from before the second of a snapshot's most recent level are
+    /// dropped as already included, while later updates are applied. Websocket symbols are used
+    /// for the REST requests as-is, e.g. `BTC/USD`.
+    ///
+    /// Returns [WSSError::InvalidBookDepth] without connecting if the depth is not one of
+    /// [BookSubscription::DEPTHS], or [WSSError::Client] if a snapshot can't be fetched.
+    pub async fn subscribe_synced_book<C>(
+        &mut self,
+        client: &mut C,
+        params: BookSubscription,
+        req_id: i64,
+    ) -> Result<SyncedBooks, WSSError>
+    where
+        C: KrakenClient,
+    {
+        let depth = params.depth.unwrap_or(BookSubscription::DEPTHS[0]);
+        let symbols = params.symbol.clone();
+        let params = BookSubscription {
+            snapshot: Some(false),
+            ..params
+        };
+
+        let stream = self.subscribe_book(params, req_id).await?;
+
+        let mut books = HashMap::new();
+        let mut snapshot_times = HashMap::new();
+        for symbol in symbols {
+            let request = OrderbookRequest::builder(symbol.clone())
+                .count(depth as i64)
+                .build();
+            let snapshot = client
+                .get_orderbook(&request)
+                .await?
+                .result
+                .and_then(|books| books.into_values().next())
+                .ok_or(ClientError::Parse("Missing result for orderbook"))?;
+
+            let newest = snapshot
+                .bids
+                .iter()
+                .chain(&snapshot.asks)
+                .map(|level| level.time)
+                .max();
+            if let Some(newest) = newest {
+                snapshot_times.insert(symbol.clone(), newest);
+            }
+
+            let book = LocalOrderBook::from_rest(&symbol, depth as usize, &snapshot);
+            books.insert(symbol, book);
+        }
+
+        Ok(SyncedBooks {
+            stream,
+            depth: depth as usize,
+            books,
+            snapshot_times,
+        })
+    }
+}
+
+/// Stream of order books started from REST snapshots, yielding a copy of a book each time an update
+/// is applied to it, see [KrakenWSSClient::subscribe_synced_book].
+pub struct SyncedBooks {
+    stream: ChannelStream<SingleResponse<L2>>,
+    depth: usize,
+    books: HashMap<String, LocalOrderBook>,
+    snapshot_times: HashMap<String, i64>,
+}
+
+impl SyncedBooks {
+    /// The current book for `symbol`, available from the REST snapshot before any updates.
+    pub fn book(&self, symbol: &str) -> Option<&LocalOrderBook> {
+        self.books.get(symbol)
+    }
+
+    /// Return the underlying stream of book messages, e.g. to unsubscribe.
+    pub fn into_inner(self) -> ChannelStream<SingleResponse<L2>> {
+        self.stream
+    }
+
+    fn apply(&mut self, book: L2) -> Option<LocalOrderBook> {
+        let symbol = match &book {
+            L2::Orderbook(snapshot) => &snapshot.symbol,
+            L2::Update(update) if self.is_stale(update) => return None,
+            L2::Update(update) => &update.symbol,
+        };
+
+        self.snapshot_times.remove(symbol);
+
+        let local = self
+            .books
+            .entry(symbol.clone())
+            .or_insert_with(|| LocalOrderBook::new(symbol, self.depth));
+        local.apply(&book);
+
+        Some(local.clone())
+    }
+
+    fn is_stale(&self, update: &OrderbookUpdate) -> bool {
+        let Some(snapshot_time) = self.snapshot_times.get(&update.symbol) else {
+            return false;
+        };
+
+        OffsetDateTime::parse(&update.timestamp, &Rfc3339)
+            .is_ok_and(|timestamp| timestamp.unix_timestamp() < *snapshot_time)
+    }
+}
+
+impl Unpin for SyncedBooks {}
+
+impl Stream for SyncedBooks {
+    type Item = Result<LocalOrderBook, WSSError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            match Pin::new(&mut self.stream).poll_next(cx) {
+                Poll::Ready(Some(Ok(response))) => {
+                    if let Some(book) = self.apply(response.data) {
+                        return Poll::Ready(Some(Ok(book)));
+                    }
+                }
+                Poll::Ready(Some(Err(err))) => return Poll::Ready(Some(Err(err))),
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clients::core_kraken_client::CoreKrakenClient;
+    use crate::crypto::nonce_provider::{IncreasingNonceProvider, NonceProvider};
+    use crate::test_data::{get_null_secrets_provider, WssTestState};
+    use crate::wss::Orderbook;
+    use rust_decimal_macros::dec;
+    use serde_json::json;
+    use std::sync::Arc;
+    use tokio::sync::{mpsc, Mutex};
+    use tokio::time::timeout;
+    use tokio_stream::StreamExt;
+    use tokio_tungstenite::tungstenite::Message as TungsteniteMessage;
+    use wiremock::matchers::{method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+    use ws_mock::ws_mock_server::WsMock;
+
+    fn level(price: Decimal, quantity: Decimal) -> BidAsk {
+        BidAsk { price, quantity }
+    }
+
+    fn snapshot() -> L2 {
+        L2::Orderbook(Orderbook {
+            symbol: "BTC/USD".to_string(),
+            checksum: 1,
+            bids: vec![level(dec!(100), dec!(1)), level(dec!(99), dec!(2))],
+            asks: vec![level(dec!(101), dec!(1)), level(dec!(102), dec!(2))],
+        })
+    }
+
+    #[test]
+    fn test_local_order_book() {
+        let mut book = LocalOrderBook::new("BTC/USD", 2);
+        book.apply(&snapshot());
+
+        book.apply(&L2::Update(OrderbookUpdate {
+            symbol: "BTC/USD".to_string(),
+            checksum: 2,
+            timestamp: "2024-05-19T16:32:14.446587Z".to_string(),
+            bids: vec![level(dec!(100.5), dec!(3)), level(dec!(100), dec!(0))],
+            asks: vec![level(dec!(100.8), dec!(4))],
+        }));
+
+        assert_eq!(
+            vec![level(dec!(100.5), dec!(3)), level(dec!(99), dec!(2))],
+            book.bids()
+        );
+        assert_eq!(
+            vec![level(dec!(100.8), dec!(4)), level(dec!(101), dec!(1))],
+            book.asks()
+        );
+        assert_eq!(Some(level(dec!(100.5), dec!(3))), book.best_bid());
+        assert_eq!(Some(level(dec!(100.8), dec!(4))), book.best_ask());
+        assert_eq!(2, book.checksum());
+        assert_eq!(Some("2024-05-19T16:32:14.446587Z"), book.timestamp());
+    }
+
+    #[test]
+    fn test_local_order_book_apply_reports_changes() {
+        let mut book = LocalOrderBook::new("BTC/USD", 2);
+        assert!(book.apply(&snapshot()));
+
+        let update = |bids: Vec<BidAsk>| {
+            L2::Update(OrderbookUpdate {
+                symbol: "BTC/USD".to_string(),
+                checksum: 2,
+                timestamp: "2024-05-19T16:32:14.446587Z".to_string(),
+                bids,
+                asks: vec![],
+            })
+        };
+
+        // deleting a missing level, repeating a quantity, or adding a level beyond the depth
+        assert!(!book.apply(&update(vec![level(dec!(98), dec!(0))])));
+        assert!(!book.apply(&update(vec![level(dec!(100), dec!(1))])));
+        assert!(!book.apply(&update(vec![level(dec!(97), dec!(5))])));
+
+        assert!(book.apply(&update(vec![level(dec!(100), dec!(2))])));
+        assert!(book.apply(&update(vec![level(dec!(99), dec!(0))])));
+        assert!(!book.apply(&L2::Orderbook(Orderbook {
+            symbol: "ETH/USD".to_string(),
+            checksum: 1,
+            bids: vec![],
+            asks: vec![],
+        })));
+    }
+
+    #[test]
+    fn test_local_order_book_top_of_book() {
+        let depth = BookSubscription::top_of_book(vec!["BTC/USD".into()])
+            .depth
+            .unwrap();
+        let mut book = LocalOrderBook::new("BTC/USD", depth as usize);
+        book.apply(&snapshot());
+
+        assert_eq!(Some(level(dec!(100), dec!(1))), book.best_bid());
+        assert_eq!(Some(level(dec!(101), dec!(1))), book.best_ask());
+
+        // a plain delete of the best bid, without a replacement level
+        book.apply(&L2::Update(OrderbookUpdate {
+            symbol: "BTC/USD".to_string(),
+            checksum: 2,
+            timestamp: "2024-05-19T16:32:14.446587Z".to_string(),
+            bids: vec![level(dec!(100), dec!(0))],
+            asks: vec![],
+        }));
+
+        assert_eq!(Some(level(dec!(99), dec!(2))), book.best_bid());
+        assert_eq!(Some(level(dec!(101), dec!(1))), book.best_ask());
+        assert_eq!(2, book.checksum());
+    }
+
+    #[test]
+    fn test_local_order_book_from_rest() {
+        let snapshot: RestOrderbook = serde_json::from_value(json!({
+            "asks": [["101.0", "1.0", 1716136000], ["102.0", "2.0", 1716136000]],
+            "bids": [["100.0", "2.0", 1716136000], ["99.0", "0.0", 1716136000]]
+        }))
+        .unwrap();
+
+        let book = LocalOrderBook::from_rest("BTC/USD", 1, &snapshot);
+
+        assert_eq!("BTC/USD", book.symbol());
+        assert_eq!(vec![level(dec!(100), dec!(2))], book.bids());
+        assert_eq!(vec![level(dec!(101), dec!(1))], book.asks());
+        assert!(book.timestamp().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_synced_book() {
+        let rest_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/0/public/Depth"))
+            .and(query_param("pair", "BTC/USD"))
+            .and(query_param("count", "10"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "error": [],
+                "result": {
+                    "XXBTZUSD": {
+                        "asks": [["101.0", "1.0", 1716136000]],
+                        "bids": [["100.0", "2.0", 1716135990]]
+                    }
+                }
+            })))
+            .expect(1)
+            .mount(&rest_server)
+            .await;
+
+        let nonce_provider: Box<Arc<Mutex<dyn NonceProvider>>> =
+            Box::new(Arc::new(Mutex::new(IncreasingNonceProvider::new())));
+        let mut rest_client = CoreKrakenClient::new_with_url(
+            get_null_secrets_provider(),
+            nonce_provider,
+            rest_server.uri(),
+        );
+
+        let mut test_state = WssTestState::new().await;
+        let (sender, receiver) = mpsc::channel::<TungsteniteMessage>(8);
+
+        WsMock::new()
+            .forward_from_channel(receiver)
+            .mount(&test_state.mock_server)
+            .await;
+
+        let update = |timestamp: &str, bid: &str| {
+            format!(
+                r#"{{"channel":"book","type":"update","data":[{{"symbol":"BTC/USD","bids":[{{"price":{bid},"qty":1.0}}],"asks":[],"checksum":7,"timestamp":"{timestamp}"}}]}}"#
+            )
+        };
+        for message in [
+            r#"{"method":"subscribe","req_id":11,"result":{"channel":"book","depth":10,"snapshot":false,"symbol":"BTC/USD"},"success":true,"time_in":"2024-05-19T16:27:13.694962Z","time_out":"2024-05-19T16:27:13.695006Z"}"#.to_string(),
+            update("2024-05-19T16:26:39.5Z", "99.5"),
+            update("2024-05-19T16:26:40.2Z", "100.5"),
+        ] {
+            sender.send(TungsteniteMessage::Text(message)).await.unwrap();
+        }
+
+        let params = BookSubscription::new(vec!["BTC/USD".into()]);
+        let mut books = test_state
+            .ws_client
+            .subscribe_synced_book(&mut rest_client, params, 11)
+            .await
+            .unwrap();
+
+        let snapshot = books.book("BTC/USD").unwrap();
+        assert_eq!(vec![level(dec!(100), dec!(2))], snapshot.bids());
+
+        let book = timeout(Duration::from_secs(1), books.next())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(
+            vec![level(dec!(100.5), dec!(1)), level(dec!(100), dec!(2))],
+            book.bids()
+        );
+        assert_eq!(vec![level(dec!(101), dec!(1))], book.asks());
+        assert_eq!(7, book.checksum());
+        assert_eq!(Some("2024-05-19T16:26:40.2Z"), book.timestamp());
+    }
+
+    #[test]
+    fn test_local_order_book_ignores_other_symbols() {
+        let mut book = LocalOrderBook::new("ETH/USD", 10);
+        book.apply(&snapshot());
+
+        assert!(book.bids().is_empty());
+        assert!(book.best_ask().is_none());
+    }
+}
@@ -0,0 +1,64 @@
+//! Raw JSON passthrough for forwarding messages without re-serializing them
+use serde::de::{DeserializeOwned, Error};
+use serde::{Deserialize, Deserializer};
+use serde_json::value::RawValue;
+
+/// A parsed message alongside the raw JSON it was parsed from.
+///
+/// Stream these with `connect::<RawMessage<WssMessage>>()` to route on the parsed message while
+/// forwarding `raw` as-is. For raw JSON alone, connect with `connect::<Box<RawValue>>()`.
+#[derive(Debug)]
+pub struct RawMessage<T> {
+    pub raw: Box<RawValue>,
+    pub message: T,
+}
+
+impl<T> RawMessage<T> {
+    /// The raw JSON of the message, exactly as received.
+    pub fn json(&self) -> &str {
+        self.raw.get()
+    }
+}
+
+impl<'de, T> Deserialize<'de> for RawMessage<T>
+where
+    T: DeserializeOwned,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let raw = Box::<RawValue>::deserialize(deserializer)?;
+        let message = serde_json::from_str(raw.get()).map_err(D::Error::custom)?;
+
+        Ok(RawMessage { raw, message })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wss::{ChannelMessage, WssMessage};
+
+    #[test]
+    fn test_raw_message() {
+        let heartbeat = r#"{"channel":"heartbeat"}"#;
+
+        let parsed: RawMessage<WssMessage> = serde_json::from_str(heartbeat).unwrap();
+
+        assert_eq!(heartbeat, parsed.json());
+        assert_eq!(
+            WssMessage::Channel(ChannelMessage::Heartbeat),
+            parsed.message
+        );
+    }
+
+    #[test]
+    fn test_raw_message_parse_error() {
+        let unknown = r#"{"channel":"unknown"}"#;
+
+        let parsed = serde_json::from_str::<RawMessage<WssMessage>>(unknown);
+
+        assert!(parsed.is_err());
+    }
+}
@@ -0,0 +1,102 @@
+//! Tracking of the exchange's status from the status channel or REST API
+use crate::response_types::{SystemStatus, SystemStatusInfo};
+use crate::wss::{ChannelMessage, StatusUpdate, WssMessage};
+
+/// The latest [SystemStatus] of the exchange, for pausing trading while it's in maintenance or
+/// otherwise not accepting orders.
+///
+/// Feed it status updates from the websocket, which Kraken sends on connecting and whenever the
+/// status changes, or the result of
+/// [KrakenClient::get_system_status](crate::clients::kraken_client::KrakenClient::get_system_status).
+/// Until the first status is received, the status is unknown and no trading is allowed.
+#[derive(Debug, Clone, Default)]
+pub struct SystemStatusTracker {
+    status: Option<SystemStatus>,
+}
+
+impl SystemStatusTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Apply a status update from the websocket.
+    pub fn apply(&mut self, update: &StatusUpdate) {
+        self.status = Some(update.system);
+    }
+
+    /// Apply the message if it's a status update, returning whether it was one.
+    pub fn apply_message(&mut self, message: &WssMessage) -> bool {
+        match message {
+            WssMessage::Channel(ChannelMessage::Status(response)) => {
+                self.apply(&response.data);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Apply the system status from the REST API.
+    pub fn apply_rest(&mut self, info: &SystemStatusInfo) {
+        self.status = Some(info.status);
+    }
+
+    /// The latest status, or None if no status has been applied yet.
+    pub fn status(&self) -> Option<SystemStatus> {
+        self.status
+    }
+
+    /// Whether new orders are accepted, see [SystemStatus::can_place_orders].
+    pub fn can_place_orders(&self) -> bool {
+        self.status.is_some_and(|status| status.can_place_orders())
+    }
+
+    /// Whether open orders can be cancelled, see [SystemStatus::can_cancel_orders].
+    pub fn can_cancel_orders(&self) -> bool {
+        self.status.is_some_and(|status| status.can_cancel_orders())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn status_message(system: &str) -> WssMessage {
+        let raw = format!(
+            r#"{{"channel":"status","data":[{{"api_version":"v2","system":"{system}","version":"2.0.6"}}],"type":"update"}}"#
+        );
+        serde_json::from_str(&raw).unwrap()
+    }
+
+    #[test]
+    fn test_system_status_tracker() {
+        let mut tracker = SystemStatusTracker::new();
+        assert_eq!(None, tracker.status());
+        assert!(!tracker.can_place_orders());
+        assert!(!tracker.can_cancel_orders());
+
+        assert!(tracker.apply_message(&status_message("online")));
+        assert!(tracker.can_place_orders());
+
+        assert!(tracker.apply_message(&status_message("maintenance")));
+        assert_eq!(Some(SystemStatus::Maintenance), tracker.status());
+        assert!(!tracker.can_place_orders());
+        assert!(!tracker.can_cancel_orders());
+
+        assert!(tracker.apply_message(&status_message("cancel_only")));
+        assert!(!tracker.can_place_orders());
+        assert!(tracker.can_cancel_orders());
+
+        assert!(tracker.apply_message(&status_message("limit_only")));
+        assert!(tracker.can_place_orders());
+
+        let heartbeat: WssMessage = serde_json::from_str(r#"{"channel":"heartbeat"}"#).unwrap();
+        assert!(!tracker.apply_message(&heartbeat));
+        assert_eq!(Some(SystemStatus::LimitOnly), tracker.status());
+
+        tracker.apply_rest(&SystemStatusInfo {
+            status: SystemStatus::PostOnly,
+            timestamp: "2024-05-19T16:00:00Z".to_string(),
+        });
+        assert!(tracker.can_place_orders());
+    }
+}
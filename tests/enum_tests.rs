@@ -1,5 +1,5 @@
 use crate::resources::test_serde::test_display_output;
-use kraken_async_rs::clients::errors::KrakenError;
+use kraken_async_rs::clients::errors::{ClientError, KrakenError};
 use kraken_async_rs::request_types::{
     AssetPairInfo, CandlestickInterval, CloseTime, Cursor, DeleteExportType, LockType,
     ReportFormatType, SelfTradePrevention, TimeInForce, TradeType, TriggerType,
@@ -166,29 +166,91 @@ fn test_cursor_display() {
 
 #[test]
 fn test_kraken_error_display() {
-    test_display_output(KrakenError::PermissionDenied, "PermissionDenied");
-    test_display_output(KrakenError::InvalidKey, "InvalidKey");
-    test_display_output(KrakenError::UnknownAssetPair, "UnknownAssetPair");
+    test_display_output(
+        KrakenError::PermissionDenied,
+        "PermissionDenied: the API key does not have permission for this request, check the key's permissions",
+    );
+    test_display_output(
+        KrakenError::InvalidKey,
+        "InvalidKey: the API key was not recognized, check it was copied correctly and has not been revoked",
+    );
+    test_display_output(
+        KrakenError::UnknownAssetPair,
+        "UnknownAssetPair: the asset pair was not recognized, check the pair name against AssetPairs",
+    );
     test_display_output(
         KrakenError::InvalidArguments("InvalidArguments:type".to_string()),
-        "InvalidArguments:type",
+        "InvalidArguments: check the request parameters, Kraken sent InvalidArguments:type",
+    );
+    test_display_output(
+        KrakenError::InvalidSignature,
+        "InvalidSignature: the request signature was rejected, check the API secret matches the key",
+    );
+    test_display_output(
+        KrakenError::InvalidNonce,
+        "InvalidNonce: the nonce was not greater than the last one used, ensure your NonceProvider is monotonic across restarts and clients sharing a key",
+    );
+    test_display_output(
+        KrakenError::InvalidSession,
+        "InvalidSession: the session is invalid, request a new websockets token",
+    );
+    test_display_output(
+        KrakenError::BadRequest,
+        "BadRequest: the request was malformed, check the endpoint and its parameters",
+    );
+    test_display_output(
+        KrakenError::UnknownMethod,
+        "UnknownMethod: the endpoint was not recognized by Kraken",
+    );
+    test_display_output(
+        KrakenError::RateLimitExceeded,
+        "RateLimitExceeded: the API rate limit was exceeded, reduce request frequency or use a RateLimitedKrakenClient",
     );
-    test_display_output(KrakenError::InvalidSignature, "InvalidSignature");
-    test_display_output(KrakenError::InvalidNonce, "InvalidNonce");
-    test_display_output(KrakenError::InvalidSession, "InvalidSession");
-    test_display_output(KrakenError::BadRequest, "BadRequest");
-    test_display_output(KrakenError::UnknownMethod, "UnknownMethod");
-    test_display_output(KrakenError::RateLimitExceeded, "RateLimitExceeded");
     test_display_output(
         KrakenError::TradingRateLimitExceeded,
-        "TradingRateLimitExceeded",
-    );
-    test_display_output(KrakenError::TemporaryLockout, "TemporaryLockout");
-    test_display_output(KrakenError::ServiceUnavailable, "ServiceUnavailable");
-    test_display_output(KrakenError::ServiceBusy, "ServiceBusy");
-    test_display_output(KrakenError::InternalError, "InternalError");
-    test_display_output(KrakenError::TradeLocked, "TradeLocked");
-    test_display_output(KrakenError::FeatureDisabled, "FeatureDisabled");
+        "TradingRateLimitExceeded: the trading rate limit was exceeded, reduce order placement, edit and cancel frequency",
+    );
+    test_display_output(
+        KrakenError::TemporaryLockout,
+        "TemporaryLockout: the API key is temporarily locked out after exceeding rate limits, wait before retrying",
+    );
+    test_display_output(
+        KrakenError::ServiceUnavailable,
+        "ServiceUnavailable: Kraken is unavailable, retry later",
+    );
+    test_display_output(
+        KrakenError::ServiceBusy,
+        "ServiceBusy: Kraken is busy, retry with backoff",
+    );
+    test_display_output(
+        KrakenError::InternalError,
+        "InternalError: Kraken encountered an internal error, retry later",
+    );
+    test_display_output(
+        KrakenError::TradeLocked,
+        "TradeLocked: trading is locked for this account, contact Kraken support",
+    );
+    test_display_output(
+        KrakenError::FeatureDisabled,
+        "FeatureDisabled: the feature is disabled for this account or API key",
+    );
+    test_display_output(
+        KrakenError::Unknown("EFake:Made up error".to_string()),
+        "Unknown: Kraken sent EFake:Made up error",
+    );
+}
+
+#[test]
+fn test_client_error_display() {
+    test_display_output(
+        ClientError::Kraken(KrakenError::ServiceBusy),
+        "Kraken error: ServiceBusy: Kraken is busy, retry with backoff",
+    );
+    test_display_output(
+        ClientError::HttpStatus("body".to_string()),
+        "Non-successful status with body: body",
+    );
+    test_display_output(ClientError::Parse("Missing result"), "Missing result");
 }
 
 #[test]